@@ -0,0 +1,48 @@
+use anyhow::Result;
+use scylla::bulk_writer::BulkWriter;
+use scylla::{Session, SessionBuilder};
+use std::env;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+
+    println!("Connecting to {} ...", uri);
+
+    let session: Session = SessionBuilder::new().known_node(uri).build().await?;
+
+    session.query("CREATE KEYSPACE IF NOT EXISTS ks WITH REPLICATION = {'class' : 'SimpleStrategy', 'replication_factor' : 1}", &[]).await?;
+
+    session
+        .query(
+            "CREATE TABLE IF NOT EXISTS ks.t2 (a int, b int, c text, primary key (a, b))",
+            &[],
+        )
+        .await?;
+
+    let prepared = session
+        .prepare("INSERT INTO ks.t2 (a, b, c) VALUES (?, ?, 'abc')")
+        .await?;
+
+    let mut writer = BulkWriter::new(prepared);
+    writer.set_concurrency(256);
+    // Rows of the same partition are sent in batches of up to 16 rows
+    writer.set_max_batch_size(16);
+
+    let rows = futures::stream::iter((0..100_000).map(|i: i32| (i % 1000, i)));
+    let report = writer.write(&session, rows).await;
+
+    println!(
+        "Written {} rows in {:?} ({:.0} rows/s) using {} requests, {} of them batches",
+        report.rows_written,
+        report.elapsed,
+        report.rows_per_second(),
+        report.requests,
+        report.batches
+    );
+    for failure in &report.failures {
+        println!("Row {} failed: {}", failure.row, failure.error);
+    }
+
+    Ok(())
+}
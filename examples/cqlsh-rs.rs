@@ -185,7 +185,7 @@ fn print_result(result: &QueryResult) {
                 " {:16}",
                 match column {
                     None => "null".to_owned(),
-                    Some(value) => format!("{:?}", value),
+                    Some(value) => value.to_string(),
                 }
             );
         }
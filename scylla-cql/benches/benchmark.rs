@@ -3,7 +3,7 @@ use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use scylla_cql::frame::request::Request;
 use scylla_cql::frame::value::SerializedValues;
 use scylla_cql::frame::value::ValueList;
-use scylla_cql::frame::{request::query, Compression, SerializedRequest};
+use scylla_cql::frame::{request::query, Compression, ProtocolVersion, SerializedRequest};
 
 fn make_query<'a>(contents: &'a str, values: &'a SerializedValues) -> query::Query<'a> {
     query::Query {
@@ -15,6 +15,8 @@ fn make_query<'a>(contents: &'a str, values: &'a SerializedValues) -> query::Que
             page_size: None,
             paging_state: None,
             timestamp: None,
+            keyspace: None,
+            now_in_seconds: None,
         },
     }
 }
@@ -32,7 +34,7 @@ fn serialized_request_make_bench(c: &mut Criterion) {
     let queries = query_args.map(|(q, v)| make_query(q, v));
 
     for query in queries {
-        let query_size = query.to_bytes().unwrap().len();
+        let query_size = query.to_bytes(ProtocolVersion::V4).unwrap().len();
         group.bench_with_input(
             BenchmarkId::new("SerializedRequest::make", query_size),
             &query,
@@ -40,8 +42,10 @@ fn serialized_request_make_bench(c: &mut Criterion) {
                 b.iter(|| {
                     let _ = criterion::black_box(SerializedRequest::make(
                         query,
+                        ProtocolVersion::V4,
                         Some(Compression::Lz4),
                         false,
+                        None,
                     ));
                 })
             },
@@ -2,8 +2,9 @@
 
 use crate::frame::frame_errors::{FrameError, ParseError};
 use crate::frame::protocol_features::ProtocolFeatures;
+use crate::frame::response::deserialize::DeserializationError;
 use crate::frame::types::LegacyConsistency;
-use crate::frame::value::SerializeValuesError;
+use crate::frame::value::{SerializeValuesError, ValueTypeCheckError};
 use bytes::Bytes;
 use std::io::ErrorKind;
 use std::sync::Arc;
@@ -11,6 +12,7 @@ use thiserror::Error;
 
 /// Error that occurred during query execution
 #[derive(Error, Debug, Clone)]
+#[non_exhaustive]
 pub enum QueryError {
     /// Database sent a response containing some error with a message
     #[error("Database returned an error: {0}, Error message: {1}")]
@@ -45,6 +47,18 @@ pub enum QueryError {
     /// Client timeout occurred before any response arrived
     #[error("Request timeout: {0}")]
     RequestTimeout(String),
+
+    /// The session was shut down before or while the request was running
+    #[error("The session has been shut down")]
+    SessionShutDown,
+
+    /// The request exceeded the concurrency limits and the queue of waiting requests was full
+    #[error("Concurrency limits exceeded and the request queue is full")]
+    RequestQueueFull,
+
+    /// The request waited in the queue for longer than the queue timeout
+    #[error("Request waited in the queue for too long")]
+    RequestQueueTimeout,
 }
 
 /// An error sent from the database in response to a query
@@ -327,6 +341,10 @@ pub enum BadQuery {
     #[error("Serializing values failed: {0} ")]
     SerializeValuesError(#[from] SerializeValuesError),
 
+    /// Values bound to a prepared statement don't match its bind markers
+    #[error("Values don't match the prepared statement: {0}")]
+    ValueTypeCheckError(#[from] ValueTypeCheckError),
+
     /// Serialized values are too long to compute partition key
     #[error("Serialized values are too long to compute partition key! Length: {0}, Max allowed length: {1}")]
     ValuesTooLongForKey(usize, usize),
@@ -342,6 +360,7 @@ pub enum BadQuery {
 
 /// Error that occurred during session creation
 #[derive(Error, Debug, Clone)]
+#[non_exhaustive]
 pub enum NewSessionError {
     /// Failed to resolve hostname passed in Session creation
     #[error("Couldn't resolve address: {0}")]
@@ -386,6 +405,19 @@ pub enum NewSessionError {
     /// during `Session` creation.
     #[error("Client timeout: {0}")]
     RequestTimeout(String),
+
+    /// The session was shut down while it was being created
+    #[error("The session has been shut down")]
+    SessionShutDown,
+
+    /// A request made during `Session` creation exceeded the concurrency limits
+    /// and the queue of waiting requests was full
+    #[error("Concurrency limits exceeded and the request queue is full")]
+    RequestQueueFull,
+
+    /// A request made during `Session` creation waited in the queue for too long
+    #[error("Request waited in the queue for too long")]
+    RequestQueueTimeout,
 }
 
 /// Invalid keyspace name given to `Session::use_keyspace()`
@@ -428,6 +460,12 @@ impl From<ParseError> for QueryError {
     }
 }
 
+impl From<DeserializationError> for QueryError {
+    fn from(deser_error: DeserializationError) -> QueryError {
+        QueryError::InvalidMessage(format!("Error deserializing rows: {}", deser_error))
+    }
+}
+
 impl From<FrameError> for QueryError {
     fn from(frame_error: FrameError) -> QueryError {
         QueryError::InvalidMessage(format!("Frame error: {}", frame_error))
@@ -460,6 +498,9 @@ impl From<QueryError> for NewSessionError {
             }
             QueryError::UnableToAllocStreamId => NewSessionError::UnableToAllocStreamId,
             QueryError::RequestTimeout(msg) => NewSessionError::RequestTimeout(msg),
+            QueryError::SessionShutDown => NewSessionError::SessionShutDown,
+            QueryError::RequestQueueFull => NewSessionError::RequestQueueFull,
+            QueryError::RequestQueueTimeout => NewSessionError::RequestQueueTimeout,
         }
     }
 }
@@ -13,7 +13,7 @@ pub enum FrameError {
     FrameFromClient,
     #[error("Received frame marked as coming from the server")]
     FrameFromServer,
-    #[error("Received a frame from version {0}, but only 4 is supported")]
+    #[error("Received a frame from version {0}, but only 4 and 5 are supported")]
     VersionNotSupported(u8),
     #[error("Connection was closed before body was read: missing {0} out of {1}")]
     ConnectionClosed(usize, usize),
@@ -29,6 +29,16 @@ pub enum FrameError {
     Lz4CompressError(#[from] lz4_flex::block::CompressError),
     #[error("Error decompressing lz4 data {0}")]
     Lz4DecompressError(#[from] lz4_flex::block::DecompressError),
+    #[error(
+        "Segment header checksum mismatch: computed {computed:#08x}, received {received:#08x}"
+    )]
+    SegmentHeaderChecksumMismatch { computed: u32, received: u32 },
+    #[error(
+        "Segment payload checksum mismatch: computed {computed:#010x}, received {received:#010x}"
+    )]
+    SegmentPayloadChecksumMismatch { computed: u32, received: u32 },
+    #[error("Segment payload has {0} bytes, but a segment can hold at most {1}")]
+    SegmentTooLarge(usize, usize),
 }
 
 #[derive(Error, Debug)]
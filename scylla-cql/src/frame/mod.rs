@@ -2,7 +2,9 @@ pub mod frame_errors;
 pub mod protocol_features;
 pub mod request;
 pub mod response;
+pub mod segment;
 pub mod server_event_type;
+pub mod type_markers;
 pub mod types;
 pub mod value;
 
@@ -14,6 +16,7 @@ use bytes::{Buf, BufMut, Bytes};
 use tokio::io::{AsyncRead, AsyncReadExt};
 use uuid::Uuid;
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
 
 use request::Request;
@@ -27,6 +30,65 @@ pub const FLAG_TRACING: u8 = 0x02;
 pub const FLAG_CUSTOM_PAYLOAD: u8 = 0x04;
 pub const FLAG_WARNING: u8 = 0x08;
 
+/// Version of the CQL native protocol spoken on a connection.
+///
+/// The default is v5. Connections start with the configured version
+/// and fall back to older ones if the server rejects it, so nodes
+/// supporting only v4 (e.g. Scylla) are still reachable.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+#[non_exhaustive]
+pub enum ProtocolVersion {
+    /// Protocol v4, supported by all Scylla versions and Cassandra 2.2+.
+    V4,
+    /// Protocol v5, which wraps frames in checksummed segments after STARTUP.
+    /// Supported by Cassandra 4.0+.
+    #[default]
+    V5,
+}
+
+impl ProtocolVersion {
+    /// Returns the number sent in the version byte of the frame header.
+    pub fn as_u8(self) -> u8 {
+        match self {
+            ProtocolVersion::V4 => 0x04,
+            ProtocolVersion::V5 => 0x05,
+        }
+    }
+
+    /// Returns true if frames must be wrapped in segments once the
+    /// connection is ready, which is the case since protocol v5.
+    pub fn uses_segments(self) -> bool {
+        self >= ProtocolVersion::V5
+    }
+
+    /// Returns the version to try next if the server rejects this one,
+    /// or `None` if there is no older version supported by the driver.
+    pub fn fallback(self) -> Option<ProtocolVersion> {
+        match self {
+            ProtocolVersion::V4 => None,
+            ProtocolVersion::V5 => Some(ProtocolVersion::V4),
+        }
+    }
+}
+
+impl TryFrom<u8> for ProtocolVersion {
+    type Error = FrameError;
+
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        match version {
+            0x04 => Ok(ProtocolVersion::V4),
+            0x05 => Ok(ProtocolVersion::V5),
+            v => Err(FrameError::VersionNotSupported(v)),
+        }
+    }
+}
+
+impl std::fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}", self.as_u8())
+    }
+}
+
 // All of the Authenticators supported by Scylla
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Authenticator {
@@ -60,27 +122,47 @@ pub struct SerializedRequest {
 }
 
 impl SerializedRequest {
+    /// Serializes the request into a frame of the given protocol version.
+    ///
+    /// Since protocol v5 compression is applied to whole segments instead
+    /// of single frames, so `compression` must be `None` for such versions.
+    ///
+    /// If `custom_payload` is given, it is sent in front of the request body
+    /// and the frame is marked with [`FLAG_CUSTOM_PAYLOAD`].
     pub fn make<R: Request>(
         req: &R,
+        version: ProtocolVersion,
         compression: Option<Compression>,
         tracing: bool,
+        custom_payload: Option<&HashMap<String, Vec<u8>>>,
     ) -> Result<SerializedRequest, FrameError> {
         let mut flags = 0;
         let mut data = vec![0; HEADER_SIZE];
 
+        if custom_payload.is_some() {
+            flags |= FLAG_CUSTOM_PAYLOAD;
+        }
+
         if let Some(compression) = compression {
             flags |= FLAG_COMPRESSION;
-            let body = req.to_bytes()?;
+            let mut body = Vec::new();
+            if let Some(custom_payload) = custom_payload {
+                types::write_bytes_map(custom_payload, &mut body)?;
+            }
+            req.serialize(&mut body, version)?;
             compress_append(&body, compression, &mut data)?;
         } else {
-            req.serialize(&mut data)?;
+            if let Some(custom_payload) = custom_payload {
+                types::write_bytes_map(custom_payload, &mut data)?;
+            }
+            req.serialize(&mut data, version)?;
         }
 
         if tracing {
             flags |= FLAG_TRACING;
         }
 
-        data[0] = 4; // We only support version 4 for now
+        data[0] = version.as_u8();
         data[1] = flags;
         // Leave space for the stream number
         data[4] = R::OPCODE as u8;
@@ -108,6 +190,13 @@ pub struct FrameParams {
     pub stream: i16,
 }
 
+impl FrameParams {
+    /// Returns the protocol version of the frame, ignoring the direction bit.
+    pub fn protocol_version(&self) -> Result<ProtocolVersion, FrameError> {
+        ProtocolVersion::try_from(self.version & 0x7F)
+    }
+}
+
 impl Default for FrameParams {
     fn default() -> Self {
         Self {
@@ -118,22 +207,18 @@ impl Default for FrameParams {
     }
 }
 
-pub async fn read_response_frame(
-    reader: &mut (impl AsyncRead + Unpin),
-) -> Result<(FrameParams, ResponseOpcode, Bytes), FrameError> {
-    let mut raw_header = [0u8; HEADER_SIZE];
-    reader.read_exact(&mut raw_header[..]).await?;
-
+// Parses the header of a response frame, returning its parameters, opcode
+// and the length of the body which follows it.
+pub(crate) fn parse_response_header(
+    raw_header: &[u8; HEADER_SIZE],
+) -> Result<(FrameParams, ResponseOpcode, usize), FrameError> {
     let mut buf = &raw_header[..];
 
-    // TODO: Validate version
     let version = buf.get_u8();
     if version & 0x80 != 0x80 {
         return Err(FrameError::FrameFromClient);
     }
-    if version & 0x7F != 0x04 {
-        return Err(FrameError::VersionNotSupported(version & 0x7f));
-    }
+    ProtocolVersion::try_from(version & 0x7F)?;
 
     let flags = buf.get_u8();
     let stream = buf.get_i16();
@@ -149,6 +234,17 @@ pub async fn read_response_frame(
     // TODO: Guard from frames that are too large
     let length = buf.get_u32() as usize;
 
+    Ok((frame_params, opcode, length))
+}
+
+pub async fn read_response_frame(
+    reader: &mut (impl AsyncRead + Unpin),
+) -> Result<(FrameParams, ResponseOpcode, Bytes), FrameError> {
+    let mut raw_header = [0u8; HEADER_SIZE];
+    reader.read_exact(&mut raw_header[..]).await?;
+
+    let (frame_params, opcode, length) = parse_response_header(&raw_header)?;
+
     let mut raw_body = Vec::with_capacity(length).limit(length);
     while raw_body.has_remaining_mut() {
         let n = reader.read_buf(&mut raw_body).await?;
@@ -167,6 +263,7 @@ pub async fn read_response_frame(
 pub struct ResponseBodyWithExtensions {
     pub trace_id: Option<Uuid>,
     pub warnings: Vec<String>,
+    pub custom_payload: Option<HashMap<String, Vec<u8>>>,
     pub body: Bytes,
 }
 
@@ -203,19 +300,21 @@ pub fn parse_response_body_extensions(
         Vec::new()
     };
 
-    if flags & FLAG_CUSTOM_PAYLOAD != 0 {
-        // TODO: Do something useful with the custom payload map
-        // For now, just skip it
+    let custom_payload = if flags & FLAG_CUSTOM_PAYLOAD != 0 {
         let body_len = body.len();
         let buf = &mut &*body;
-        types::read_bytes_map(buf)?;
+        let custom_payload = types::read_bytes_map(buf)?;
         let buf_len = buf.len();
         body.advance(body_len - buf_len);
-    }
+        Some(custom_payload)
+    } else {
+        None
+    };
 
     Ok(ResponseBodyWithExtensions {
         trace_id,
         warnings,
+        custom_payload,
         body,
     })
 }
@@ -286,4 +385,29 @@ mod test {
         assert_eq!(32, comp_body.len());
         assert_eq!(uncomp_body.as_bytes(), result);
     }
+
+    #[test]
+    fn test_custom_payload_roundtrip() {
+        let mut custom_payload = HashMap::new();
+        custom_payload.insert("audit".to_owned(), vec![1, 2, 3]);
+
+        let mut body = Vec::new();
+        types::write_bytes_map(&custom_payload, &mut body).unwrap();
+        body.extend_from_slice(b"rest of the body");
+
+        let parsed =
+            parse_response_body_extensions(FLAG_CUSTOM_PAYLOAD, None, body.into()).unwrap();
+        assert_eq!(parsed.custom_payload, Some(custom_payload));
+        assert_eq!(&parsed.body[..], b"rest of the body");
+
+        let request = SerializedRequest::make(
+            &request::Options,
+            ProtocolVersion::V4,
+            None,
+            false,
+            parsed.custom_payload.as_ref(),
+        )
+        .unwrap();
+        assert_eq!(request.get_data()[1], FLAG_CUSTOM_PAYLOAD);
+    }
 }
@@ -1,4 +1,5 @@
 use crate::frame::frame_errors::ParseError;
+use crate::frame::ProtocolVersion;
 use bytes::BufMut;
 
 use crate::frame::request::{Request, RequestOpcode};
@@ -12,7 +13,11 @@ pub struct AuthResponse {
 impl Request for AuthResponse {
     const OPCODE: RequestOpcode = RequestOpcode::AuthResponse;
 
-    fn serialize(&self, buf: &mut impl BufMut) -> Result<(), ParseError> {
+    fn serialize(
+        &self,
+        buf: &mut impl BufMut,
+        _version: ProtocolVersion,
+    ) -> Result<(), ParseError> {
         write_bytes_opt(self.response.as_ref(), buf)
     }
 }
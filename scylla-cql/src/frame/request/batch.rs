@@ -3,14 +3,18 @@ use bytes::{BufMut, Bytes};
 use std::convert::TryInto;
 
 use crate::frame::{
-    request::{Request, RequestOpcode},
+    request::{query::write_flags, Request, RequestOpcode},
     types,
     value::BatchValues,
+    ProtocolVersion,
 };
 
 // Batch flags
-const FLAG_WITH_SERIAL_CONSISTENCY: u8 = 0x10;
-const FLAG_WITH_DEFAULT_TIMESTAMP: u8 = 0x20;
+const FLAG_WITH_SERIAL_CONSISTENCY: u32 = 0x10;
+const FLAG_WITH_DEFAULT_TIMESTAMP: u32 = 0x20;
+// Available since protocol v5
+const FLAG_WITH_KEYSPACE: u32 = 0x80;
+const FLAG_WITH_NOW_IN_SECONDS: u32 = 0x100;
 
 pub struct Batch<'a, StatementsIter, Values>
 where
@@ -24,6 +28,12 @@ where
     pub serial_consistency: Option<types::SerialConsistency>,
     pub timestamp: Option<i64>,
     pub values: Values,
+    /// Keyspace in which the batch is executed, instead of the keyspace
+    /// set on the connection. Requires protocol v5.
+    pub keyspace: Option<&'a str>,
+    /// Current time in seconds used by the server to evaluate TTLs.
+    /// Requires protocol v5.
+    pub now_in_seconds: Option<i32>,
 }
 
 /// The type of a batch.
@@ -47,7 +57,7 @@ where
 {
     const OPCODE: RequestOpcode = RequestOpcode::Batch;
 
-    fn serialize(&self, buf: &mut impl BufMut) -> Result<(), ParseError> {
+    fn serialize(&self, buf: &mut impl BufMut, version: ProtocolVersion) -> Result<(), ParseError> {
         // Serializing type of batch
         buf.put_u8(self.batch_type as u8);
 
@@ -96,8 +106,14 @@ where
         if self.timestamp.is_some() {
             flags |= FLAG_WITH_DEFAULT_TIMESTAMP;
         }
+        if self.keyspace.is_some() {
+            flags |= FLAG_WITH_KEYSPACE;
+        }
+        if self.now_in_seconds.is_some() {
+            flags |= FLAG_WITH_NOW_IN_SECONDS;
+        }
 
-        buf.put_u8(flags);
+        write_flags(flags, buf, version)?;
 
         if let Some(serial_consistency) = self.serial_consistency {
             types::write_serial_consistency(serial_consistency, buf);
@@ -105,6 +121,12 @@ where
         if let Some(timestamp) = self.timestamp {
             types::write_long(timestamp, buf);
         }
+        if let Some(keyspace) = self.keyspace {
+            types::write_string(keyspace, buf)?;
+        }
+        if let Some(now_in_seconds) = self.now_in_seconds {
+            types::write_int(now_in_seconds, buf);
+        }
 
         Ok(())
     }
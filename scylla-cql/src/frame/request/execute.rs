@@ -4,22 +4,36 @@ use bytes::{BufMut, Bytes};
 use crate::{
     frame::request::{query, Request, RequestOpcode},
     frame::types,
+    frame::ProtocolVersion,
 };
 
 pub struct Execute<'a> {
     pub id: Bytes,
+    /// Id of the result metadata returned when preparing the statement.
+    /// Sent only since protocol v5, where it is mandatory.
+    pub result_metadata_id: Option<Bytes>,
     pub parameters: query::QueryParameters<'a>,
 }
 
 impl Request for Execute<'_> {
     const OPCODE: RequestOpcode = RequestOpcode::Execute;
 
-    fn serialize(&self, buf: &mut impl BufMut) -> Result<(), ParseError> {
+    fn serialize(&self, buf: &mut impl BufMut, version: ProtocolVersion) -> Result<(), ParseError> {
         // Serializing statement id
         types::write_short_bytes(&self.id[..], buf)?;
 
+        // Serializing result metadata id
+        if version >= ProtocolVersion::V5 {
+            let result_metadata_id = self.result_metadata_id.as_ref().ok_or_else(|| {
+                ParseError::BadDataToSerialize(
+                    "EXECUTE requires a result metadata id since protocol v5".to_string(),
+                )
+            })?;
+            types::write_short_bytes(&result_metadata_id[..], buf)?;
+        }
+
         // Serializing params
-        self.parameters.serialize(buf)?;
+        self.parameters.serialize(buf, version)?;
         Ok(())
     }
 }
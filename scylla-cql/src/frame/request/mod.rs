@@ -8,6 +8,7 @@ pub mod register;
 pub mod startup;
 
 use crate::frame::frame_errors::ParseError;
+use crate::frame::ProtocolVersion;
 use bytes::{BufMut, Bytes};
 use num_enum::TryFromPrimitive;
 
@@ -34,11 +35,11 @@ pub enum RequestOpcode {
 pub trait Request {
     const OPCODE: RequestOpcode;
 
-    fn serialize(&self, buf: &mut impl BufMut) -> Result<(), ParseError>;
+    fn serialize(&self, buf: &mut impl BufMut, version: ProtocolVersion) -> Result<(), ParseError>;
 
-    fn to_bytes(&self) -> Result<Bytes, ParseError> {
+    fn to_bytes(&self, version: ProtocolVersion) -> Result<Bytes, ParseError> {
         let mut v = Vec::new();
-        self.serialize(&mut v)?;
+        self.serialize(&mut v, version)?;
         Ok(v.into())
     }
 }
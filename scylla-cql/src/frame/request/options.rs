@@ -1,4 +1,5 @@
 use crate::frame::frame_errors::ParseError;
+use crate::frame::ProtocolVersion;
 use bytes::BufMut;
 
 use crate::frame::request::{Request, RequestOpcode};
@@ -8,7 +9,11 @@ pub struct Options;
 impl Request for Options {
     const OPCODE: RequestOpcode = RequestOpcode::Options;
 
-    fn serialize(&self, _buf: &mut impl BufMut) -> Result<(), ParseError> {
+    fn serialize(
+        &self,
+        _buf: &mut impl BufMut,
+        _version: ProtocolVersion,
+    ) -> Result<(), ParseError> {
         Ok(())
     }
 }
@@ -4,17 +4,41 @@ use bytes::BufMut;
 use crate::{
     frame::request::{Request, RequestOpcode},
     frame::types,
+    frame::ProtocolVersion,
 };
 
+// Prepare flags, available since protocol v5
+const FLAG_WITH_KEYSPACE: i32 = 0x01;
+
 pub struct Prepare<'a> {
     pub query: &'a str,
+    /// Keyspace in which the statement is prepared, instead of the keyspace
+    /// set on the connection. Requires protocol v5.
+    pub keyspace: Option<&'a str>,
 }
 
 impl<'a> Request for Prepare<'a> {
     const OPCODE: RequestOpcode = RequestOpcode::Prepare;
 
-    fn serialize(&self, buf: &mut impl BufMut) -> Result<(), ParseError> {
+    fn serialize(&self, buf: &mut impl BufMut, version: ProtocolVersion) -> Result<(), ParseError> {
         types::write_long_string(self.query, buf)?;
+
+        if version >= ProtocolVersion::V5 {
+            let flags = if self.keyspace.is_some() {
+                FLAG_WITH_KEYSPACE
+            } else {
+                0
+            };
+            types::write_int(flags, buf);
+            if let Some(keyspace) = self.keyspace {
+                types::write_string(keyspace, buf)?;
+            }
+        } else if self.keyspace.is_some() {
+            return Err(ParseError::BadDataToSerialize(
+                "Setting a per-statement keyspace requires protocol v5".to_string(),
+            ));
+        }
+
         Ok(())
     }
 }
@@ -5,17 +5,21 @@ use crate::{
     frame::request::{Request, RequestOpcode},
     frame::types,
     frame::value::SerializedValues,
+    frame::ProtocolVersion,
 };
 
 // Query flags
 // Unused flags are commented out so that they don't trigger warnings
-const FLAG_VALUES: u8 = 0x01;
-// const FLAG_SKIP_METADATA: u8 = 0x02;
-const FLAG_PAGE_SIZE: u8 = 0x04;
-const FLAG_WITH_PAGING_STATE: u8 = 0x08;
-const FLAG_WITH_SERIAL_CONSISTENCY: u8 = 0x10;
-const FLAG_WITH_DEFAULT_TIMESTAMP: u8 = 0x20;
-const FLAG_WITH_NAMES_FOR_VALUES: u8 = 0x40;
+const FLAG_VALUES: u32 = 0x01;
+// const FLAG_SKIP_METADATA: u32 = 0x02;
+const FLAG_PAGE_SIZE: u32 = 0x04;
+const FLAG_WITH_PAGING_STATE: u32 = 0x08;
+const FLAG_WITH_SERIAL_CONSISTENCY: u32 = 0x10;
+const FLAG_WITH_DEFAULT_TIMESTAMP: u32 = 0x20;
+const FLAG_WITH_NAMES_FOR_VALUES: u32 = 0x40;
+// Available since protocol v5
+const FLAG_WITH_KEYSPACE: u32 = 0x80;
+const FLAG_WITH_NOW_IN_SECONDS: u32 = 0x100;
 
 pub struct Query<'a> {
     pub contents: &'a str,
@@ -25,9 +29,9 @@ pub struct Query<'a> {
 impl Request for Query<'_> {
     const OPCODE: RequestOpcode = RequestOpcode::Query;
 
-    fn serialize(&self, buf: &mut impl BufMut) -> Result<(), ParseError> {
+    fn serialize(&self, buf: &mut impl BufMut, version: ProtocolVersion) -> Result<(), ParseError> {
         types::write_long_string(self.contents, buf)?;
-        self.parameters.serialize(buf)?;
+        self.parameters.serialize(buf, version)?;
         Ok(())
     }
 }
@@ -39,6 +43,12 @@ pub struct QueryParameters<'a> {
     pub page_size: Option<i32>,
     pub paging_state: Option<Bytes>,
     pub values: &'a SerializedValues,
+    /// Keyspace in which the statement is executed, instead of the keyspace
+    /// set on the connection. Requires protocol v5.
+    pub keyspace: Option<&'a str>,
+    /// Current time in seconds used by the server to evaluate TTLs.
+    /// Requires protocol v5.
+    pub now_in_seconds: Option<i32>,
 }
 
 impl Default for QueryParameters<'_> {
@@ -50,12 +60,18 @@ impl Default for QueryParameters<'_> {
             page_size: None,
             paging_state: None,
             values: SerializedValues::EMPTY,
+            keyspace: None,
+            now_in_seconds: None,
         }
     }
 }
 
 impl QueryParameters<'_> {
-    pub fn serialize(&self, buf: &mut impl BufMut) -> Result<(), ParseError> {
+    pub fn serialize(
+        &self,
+        buf: &mut impl BufMut,
+        version: ProtocolVersion,
+    ) -> Result<(), ParseError> {
         types::write_consistency(self.consistency, buf);
 
         let mut flags = 0;
@@ -83,7 +99,15 @@ impl QueryParameters<'_> {
             flags |= FLAG_WITH_NAMES_FOR_VALUES;
         }
 
-        buf.put_u8(flags);
+        if self.keyspace.is_some() {
+            flags |= FLAG_WITH_KEYSPACE;
+        }
+
+        if self.now_in_seconds.is_some() {
+            flags |= FLAG_WITH_NOW_IN_SECONDS;
+        }
+
+        write_flags(flags, buf, version)?;
 
         if !self.values.is_empty() {
             self.values.write_to_request(buf);
@@ -105,6 +129,40 @@ impl QueryParameters<'_> {
             types::write_long(timestamp, buf);
         }
 
+        if let Some(keyspace) = self.keyspace {
+            types::write_string(keyspace, buf)?;
+        }
+
+        if let Some(now_in_seconds) = self.now_in_seconds {
+            types::write_int(now_in_seconds, buf);
+        }
+
         Ok(())
     }
 }
+
+// Writes query or batch flags, which are a [byte] in protocol v4 and an [int] since v5.
+// Fails if a flag which can't be expressed in the given version is set.
+pub(crate) fn write_flags(
+    flags: u32,
+    buf: &mut impl BufMut,
+    version: ProtocolVersion,
+) -> Result<(), ParseError> {
+    if version >= ProtocolVersion::V5 {
+        types::write_int(flags as i32, buf);
+        return Ok(());
+    }
+
+    if flags & FLAG_WITH_KEYSPACE != 0 {
+        return Err(ParseError::BadDataToSerialize(
+            "Setting a per-statement keyspace requires protocol v5".to_string(),
+        ));
+    }
+    if flags & FLAG_WITH_NOW_IN_SECONDS != 0 {
+        return Err(ParseError::BadDataToSerialize(
+            "Setting now_in_seconds requires protocol v5".to_string(),
+        ));
+    }
+    buf.put_u8(flags as u8);
+    Ok(())
+}
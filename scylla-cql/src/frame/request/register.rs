@@ -4,7 +4,7 @@ use crate::frame::{
     frame_errors::ParseError,
     request::{Request, RequestOpcode},
     server_event_type::EventType,
-    types,
+    types, ProtocolVersion,
 };
 
 pub struct Register {
@@ -14,7 +14,11 @@ pub struct Register {
 impl Request for Register {
     const OPCODE: RequestOpcode = RequestOpcode::Register;
 
-    fn serialize(&self, buf: &mut impl BufMut) -> Result<(), ParseError> {
+    fn serialize(
+        &self,
+        buf: &mut impl BufMut,
+        _version: ProtocolVersion,
+    ) -> Result<(), ParseError> {
         let event_types_list = self
             .event_types_to_register_for
             .iter()
@@ -1,4 +1,5 @@
 use crate::frame::frame_errors::ParseError;
+use crate::frame::ProtocolVersion;
 use bytes::BufMut;
 
 use std::collections::HashMap;
@@ -15,7 +16,11 @@ pub struct Startup {
 impl Request for Startup {
     const OPCODE: RequestOpcode = RequestOpcode::Startup;
 
-    fn serialize(&self, buf: &mut impl BufMut) -> Result<(), ParseError> {
+    fn serialize(
+        &self,
+        buf: &mut impl BufMut,
+        _version: ProtocolVersion,
+    ) -> Result<(), ParseError> {
         types::write_string_map(&self.options, buf)?;
         Ok(())
     }
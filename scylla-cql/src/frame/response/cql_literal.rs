@@ -0,0 +1,1064 @@
+//! Conversion between [`CqlValue`] and CQL literals.
+//!
+//! `CqlValue` implements [`Display`](fmt::Display), printing the value as a literal which
+//! can be pasted into a CQL statement, e.g. `{'a': [1, 2], 'b': []}` for a `map<text, list<int>>`.
+//! [`CqlValue::from_cql_literal`] reads such a literal back, given the type of the value.
+//!
+//! A few values have no exact literal form:
+//! * [`CqlValue::Empty`] is printed as `null`
+//! * timestamps, dates and times outside of the range supported by `chrono`
+//!   are printed as their raw integer representation, which CQL also accepts
+//!
+//! [`quote_identifier`] prints names of keyspaces, tables, columns and fields of user defined types.
+
+use super::result::{ColumnType, CqlValue};
+use crate::frame::value::{Counter, CqlDecimal, CqlDuration, CqlVarint};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::borrow::Cow;
+use std::fmt::{self, Display, Write};
+use std::net::IpAddr;
+use std::str::FromStr;
+use thiserror::Error;
+use uuid::Uuid;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const NANOS_PER_DAY: i64 = 24 * 60 * 60 * NANOS_PER_SECOND;
+
+// Duration units, from the largest, as (unit, months, days, nanoseconds)
+const DURATION_UNITS: [(&str, i64, i64, i64); 10] = [
+    ("y", 12, 0, 0),
+    ("mo", 1, 0, 0),
+    ("w", 0, 7, 0),
+    ("d", 0, 1, 0),
+    ("h", 0, 0, 60 * 60 * NANOS_PER_SECOND),
+    ("m", 0, 0, 60 * NANOS_PER_SECOND),
+    ("s", 0, 0, NANOS_PER_SECOND),
+    ("ms", 0, 0, 1_000_000),
+    ("us", 0, 0, 1_000),
+    ("ns", 0, 0, 1),
+];
+
+/// Error returned when a CQL literal can't be parsed as a value of the given type
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid CQL literal at position {position}: {reason}")]
+pub struct ParseLiteralError {
+    /// Byte offset in the parsed text at which the error was found
+    pub position: usize,
+    pub reason: String,
+}
+
+impl Display for CqlValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CqlValue::Ascii(s) | CqlValue::Text(s) => write_string(f, s),
+            CqlValue::Boolean(b) => write!(f, "{}", b),
+            CqlValue::Blob(bytes) => {
+                f.write_str("0x")?;
+                bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+            CqlValue::Counter(Counter(c)) => write!(f, "{}", c),
+            CqlValue::Decimal(d) => write!(f, "{}", d),
+            CqlValue::Date(days) => match self.as_date() {
+                Some(date) if (0..=9999).contains(&chrono::Datelike::year(&date)) => {
+                    write!(f, "'{}'", date.format("%Y-%m-%d"))
+                }
+                _ => write!(f, "'{}'", days),
+            },
+            CqlValue::Double(d) => write_float(f, *d),
+            CqlValue::Duration(d) => write_duration(f, d),
+            CqlValue::Empty => f.write_str("null"),
+            CqlValue::Float(fl) => write_float(f, *fl),
+            CqlValue::Int(i) => write!(f, "{}", i),
+            CqlValue::BigInt(i) => write!(f, "{}", i),
+            CqlValue::Timestamp(t) => {
+                let millis = t.num_milliseconds();
+                match Utc.timestamp_millis_opt(millis).single() {
+                    Some(dt) if (0..=9999).contains(&chrono::Datelike::year(&dt)) => {
+                        write!(f, "'{}'", dt.format("%Y-%m-%d %H:%M:%S%.3f%z"))
+                    }
+                    _ => write!(f, "{}", millis),
+                }
+            }
+            CqlValue::Inet(addr) => write!(f, "'{}'", addr),
+            CqlValue::List(elements) | CqlValue::Vector(elements) => {
+                write_separated(f, "[", elements.iter(), "]", |f, e| e.fmt(f))
+            }
+            CqlValue::Set(elements) => {
+                write_separated(f, "{", elements.iter(), "}", |f, e| e.fmt(f))
+            }
+            CqlValue::Map(entries) => write_separated(f, "{", entries.iter(), "}", |f, (k, v)| {
+                write!(f, "{}: {}", k, v)
+            }),
+            CqlValue::UserDefinedType { fields, .. } => {
+                write_separated(f, "{", fields.iter(), "}", |f, (name, value)| {
+                    f.write_str(&quote_identifier(name))?;
+                    f.write_str(": ")?;
+                    write_optional(f, value.as_ref())
+                })
+            }
+            CqlValue::SmallInt(i) => write!(f, "{}", i),
+            CqlValue::TinyInt(i) => write!(f, "{}", i),
+            CqlValue::Time(t) => match t.num_nanoseconds() {
+                Some(nanos) if (0..NANOS_PER_DAY).contains(&nanos) => {
+                    let seconds = nanos / NANOS_PER_SECOND;
+                    write!(
+                        f,
+                        "'{:02}:{:02}:{:02}.{:09}'",
+                        seconds / 3600,
+                        seconds / 60 % 60,
+                        seconds % 60,
+                        nanos % NANOS_PER_SECOND
+                    )
+                }
+                Some(nanos) => write!(f, "'{}'", nanos),
+                None => write!(f, "'{}'", t),
+            },
+            CqlValue::Timeuuid(timeuuid) => write!(f, "{}", timeuuid),
+            CqlValue::Uuid(uuid) => write!(f, "{}", uuid),
+            CqlValue::Tuple(elements) => write_separated(f, "(", elements.iter(), ")", |f, e| {
+                write_optional(f, e.as_ref())
+            }),
+            CqlValue::Varint(i) => write!(f, "{}", i),
+        }
+    }
+}
+
+fn write_separated<T>(
+    f: &mut fmt::Formatter<'_>,
+    open: &str,
+    items: impl Iterator<Item = T>,
+    close: &str,
+    mut write_item: impl FnMut(&mut fmt::Formatter<'_>, T) -> fmt::Result,
+) -> fmt::Result {
+    f.write_str(open)?;
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write_item(f, item)?;
+    }
+    f.write_str(close)
+}
+
+fn write_optional(f: &mut fmt::Formatter<'_>, value: Option<&CqlValue>) -> fmt::Result {
+    match value {
+        Some(value) => value.fmt(f),
+        None => f.write_str("null"),
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('\'')?;
+    for c in s.chars() {
+        if c == '\'' {
+            f.write_char('\'')?;
+        }
+        f.write_char(c)?;
+    }
+    f.write_char('\'')
+}
+
+// Keywords which can't be used as unquoted identifiers.
+// Quoting a name is always correct, so the list includes keywords reserved in any version
+// of ScyllaDB or Cassandra, and `key` which some statements don't accept unquoted.
+const RESERVED_KEYWORDS: &[&str] = &[
+    "add",
+    "allow",
+    "alter",
+    "and",
+    "apply",
+    "asc",
+    "authorize",
+    "batch",
+    "begin",
+    "by",
+    "columnfamily",
+    "create",
+    "default",
+    "delete",
+    "desc",
+    "describe",
+    "drop",
+    "entries",
+    "execute",
+    "from",
+    "full",
+    "grant",
+    "if",
+    "in",
+    "index",
+    "infinity",
+    "insert",
+    "into",
+    "is",
+    "key",
+    "keyspace",
+    "limit",
+    "materialized",
+    "mbean",
+    "mbeans",
+    "modify",
+    "nan",
+    "norecursive",
+    "not",
+    "null",
+    "of",
+    "on",
+    "or",
+    "order",
+    "primary",
+    "rename",
+    "replace",
+    "revoke",
+    "schema",
+    "select",
+    "set",
+    "table",
+    "to",
+    "token",
+    "truncate",
+    "unlogged",
+    "unset",
+    "update",
+    "use",
+    "using",
+    "view",
+    "where",
+    "with",
+];
+
+/// Quotes a name unless it's a lowercase identifier and not a reserved keyword.\
+/// Unquoted identifiers are case insensitive, so anything but lowercase names has to be quoted.
+///
+/// ```
+/// # use scylla_cql::frame::response::cql_literal::quote_identifier;
+/// assert_eq!(quote_identifier("user_id"), "user_id");
+/// assert_eq!(quote_identifier("order"), "\"order\"");
+/// assert_eq!(quote_identifier("Name"), "\"Name\"");
+/// assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+/// ```
+pub fn quote_identifier(name: &str) -> Cow<'_, str> {
+    let mut chars = name.chars();
+    let is_plain = matches!(chars.next(), Some(c) if c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !RESERVED_KEYWORDS.contains(&name);
+    if is_plain {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("\"{}\"", name.replace('"', "\"\"")))
+    }
+}
+
+fn write_float<F: Into<f64> + fmt::Debug + Copy>(
+    f: &mut fmt::Formatter<'_>,
+    value: F,
+) -> fmt::Result {
+    let as_f64: f64 = value.into();
+    if as_f64.is_nan() {
+        f.write_str("NaN")
+    } else if as_f64.is_infinite() {
+        f.write_str(if as_f64 > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        })
+    } else {
+        // Debug keeps the decimal point, so that the literal isn't read as an integer
+        write!(f, "{:?}", value)
+    }
+}
+
+fn write_duration(f: &mut fmt::Formatter<'_>, duration: &CqlDuration) -> fmt::Result {
+    // All components of a valid duration have the same sign
+    if duration.months < 0 || duration.days < 0 || duration.nanoseconds < 0 {
+        f.write_char('-')?;
+    }
+    let mut months = duration.months.unsigned_abs() as u64;
+    let mut days = duration.days.unsigned_abs() as u64;
+    let mut nanos = duration.nanoseconds.unsigned_abs();
+    if months == 0 && days == 0 && nanos == 0 {
+        return f.write_str("0s");
+    }
+    for (unit, unit_months, unit_days, unit_nanos) in DURATION_UNITS {
+        // Weeks are printed as days
+        let count = if unit == "w" {
+            0
+        } else if unit_months > 0 {
+            let count = months / unit_months as u64;
+            months %= unit_months as u64;
+            count
+        } else if unit_days > 0 {
+            let count = days / unit_days as u64;
+            days %= unit_days as u64;
+            count
+        } else {
+            let count = nanos / unit_nanos as u64;
+            nanos %= unit_nanos as u64;
+            count
+        };
+        if count > 0 {
+            write!(f, "{}{}", count, unit)?;
+        }
+    }
+    Ok(())
+}
+
+impl CqlValue {
+    /// Parses a CQL literal, as printed by the `Display` implementation of `CqlValue`,
+    /// into a value of the given type.
+    ///
+    /// Besides the forms produced by `Display`, the parser accepts `$$`-quoted strings,
+    /// timestamps given as milliseconds since the epoch or in the other common
+    /// `yyyy-mm-dd[ |T]hh:mm[:ss[.fff]][Z|+hhmm]` forms, and durations using any of the
+    /// `y`, `mo`, `w`, `d`, `h`, `m`, `s`, `ms`, `us`/`µs` and `ns` units.
+    ///
+    /// Timestamps without a time zone are interpreted as UTC.
+    ///
+    /// ```
+    /// # use scylla_cql::frame::response::result::{ColumnType, CqlValue};
+    /// let typ = ColumnType::List(Box::new(ColumnType::Text));
+    /// let value = CqlValue::from_cql_literal("['a', 'it''s']", &typ).unwrap();
+    /// assert_eq!(
+    ///     value,
+    ///     CqlValue::List(vec![
+    ///         CqlValue::Text("a".to_string()),
+    ///         CqlValue::Text("it's".to_string()),
+    ///     ])
+    /// );
+    /// assert_eq!(value.to_string(), "['a', 'it''s']");
+    /// ```
+    pub fn from_cql_literal(text: &str, typ: &ColumnType) -> Result<CqlValue, ParseLiteralError> {
+        let mut parser = LiteralParser { text, position: 0 };
+        let value = parser.parse_value(typ)?;
+        parser.skip_whitespace();
+        if parser.position < text.len() {
+            return Err(parser.error("unexpected characters after the literal"));
+        }
+        Ok(value)
+    }
+}
+
+struct LiteralParser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> LiteralParser<'a> {
+    fn error(&self, reason: impl Into<String>) -> ParseLiteralError {
+        ParseLiteralError {
+            position: self.position,
+            reason: reason.into(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn try_consume(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseLiteralError> {
+        if self.try_consume(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}'", c)))
+        }
+    }
+
+    // Reads an unquoted token, e.g. a number, a uuid or a duration
+    fn word(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || ",:[]{}()'\"".contains(c))
+            .unwrap_or(rest.len());
+        self.position += len;
+        &rest[..len]
+    }
+
+    fn quoted_string(&mut self) -> Result<String, ParseLiteralError> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        if let Some(body) = rest.strip_prefix("$$") {
+            let end = body
+                .find("$$")
+                .ok_or_else(|| self.error("unterminated $$ string"))?;
+            self.position += end + 4;
+            return Ok(body[..end].to_string());
+        }
+        if !rest.starts_with('\'') {
+            return Err(self.error("expected a quoted string"));
+        }
+        let mut result = String::new();
+        let mut chars = rest.char_indices().skip(1).peekable();
+        while let Some((i, c)) = chars.next() {
+            if c == '\'' {
+                if chars.peek().map(|&(_, c)| c) == Some('\'') {
+                    chars.next();
+                } else {
+                    self.position += i + 1;
+                    return Ok(result);
+                }
+            }
+            result.push(c);
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    // Reads either a quoted string or an unquoted token
+    fn string_or_word(&mut self) -> Result<String, ParseLiteralError> {
+        match self.peek() {
+            Some('\'') | Some('$') => self.quoted_string(),
+            _ => Ok(self.word().to_string()),
+        }
+    }
+
+    fn parse_word<T: FromStr>(&mut self, type_name: &str) -> Result<T, ParseLiteralError> {
+        let start = self.position;
+        let word = self.word();
+        word.parse().map_err(|_| ParseLiteralError {
+            position: start,
+            reason: format!("'{}' is not a valid {}", word, type_name),
+        })
+    }
+
+    fn parse_optional_value(
+        &mut self,
+        typ: &ColumnType,
+    ) -> Result<Option<CqlValue>, ParseLiteralError> {
+        let start = self.position;
+        if self.word().eq_ignore_ascii_case("null") {
+            return Ok(None);
+        }
+        self.position = start;
+        self.parse_value(typ).map(Some)
+    }
+
+    fn parse_value(&mut self, typ: &ColumnType) -> Result<CqlValue, ParseLiteralError> {
+        self.skip_whitespace();
+        let start = self.position;
+        let value = match typ {
+            ColumnType::Custom(name) => {
+                return Err(self.error(format!("custom type {} is not supported", name)))
+            }
+            ColumnType::Ascii => {
+                let s = self.quoted_string()?;
+                if !s.is_ascii() {
+                    return Err(ParseLiteralError {
+                        position: start,
+                        reason: "ascii string contains non-ASCII characters".to_string(),
+                    });
+                }
+                CqlValue::Ascii(s)
+            }
+            ColumnType::Text => CqlValue::Text(self.quoted_string()?),
+            ColumnType::Boolean => match self.word().to_ascii_lowercase().as_str() {
+                "true" => CqlValue::Boolean(true),
+                "false" => CqlValue::Boolean(false),
+                _ => return Err(self.error_at(start, "expected true or false")),
+            },
+            ColumnType::Blob => CqlValue::Blob(self.parse_blob()?),
+            ColumnType::Counter => CqlValue::Counter(Counter(self.parse_word("counter")?)),
+            ColumnType::Date => CqlValue::Date(self.parse_date()?),
+            ColumnType::Decimal => CqlValue::Decimal(self.parse_word::<CqlDecimal>("decimal")?),
+            ColumnType::Double => CqlValue::Double(self.parse_word("double")?),
+            ColumnType::Duration => CqlValue::Duration(self.parse_duration()?),
+            ColumnType::Float => CqlValue::Float(self.parse_word("float")?),
+            ColumnType::Int => CqlValue::Int(self.parse_word("int")?),
+            ColumnType::BigInt => CqlValue::BigInt(self.parse_word("bigint")?),
+            ColumnType::Timestamp => CqlValue::Timestamp(self.parse_timestamp()?),
+            ColumnType::Inet => {
+                let s = self.string_or_word()?;
+                CqlValue::Inet(IpAddr::from_str(&s).map_err(|_| {
+                    self.error_at(start, format!("'{}' is not a valid inet address", s))
+                })?)
+            }
+            ColumnType::List(elem) => CqlValue::List(self.parse_elements('[', ']', elem)?),
+            ColumnType::Set(elem) => CqlValue::Set(self.parse_elements('{', '}', elem)?),
+            ColumnType::Vector {
+                element,
+                dimensions,
+            } => {
+                let elements = self.parse_elements('[', ']', element)?;
+                if elements.len() != *dimensions as usize {
+                    return Err(self.error_at(
+                        start,
+                        format!(
+                            "expected a vector of {} elements, got {}",
+                            dimensions,
+                            elements.len()
+                        ),
+                    ));
+                }
+                CqlValue::Vector(elements)
+            }
+            ColumnType::Map(key_type, value_type) => {
+                let mut entries = Vec::new();
+                self.expect('{')?;
+                if !self.try_consume('}') {
+                    loop {
+                        let key = self.parse_value(key_type)?;
+                        self.expect(':')?;
+                        let value = self.parse_value(value_type)?;
+                        entries.push((key, value));
+                        if self.try_consume('}') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                CqlValue::Map(entries)
+            }
+            ColumnType::UserDefinedType {
+                type_name,
+                keyspace,
+                field_types,
+            } => {
+                let mut fields: Vec<(String, Option<CqlValue>)> = field_types
+                    .iter()
+                    .map(|(name, _)| (name.clone(), None))
+                    .collect();
+                self.expect('{')?;
+                if !self.try_consume('}') {
+                    loop {
+                        self.skip_whitespace();
+                        let name_position = self.position;
+                        let name = self.parse_identifier()?;
+                        let index = field_types
+                            .iter()
+                            .position(|(field_name, _)| *field_name == name)
+                            .ok_or_else(|| {
+                                self.error_at(
+                                    name_position,
+                                    format!("type {} has no field {}", type_name, name),
+                                )
+                            })?;
+                        self.expect(':')?;
+                        fields[index].1 = self.parse_optional_value(&field_types[index].1)?;
+                        if self.try_consume('}') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                CqlValue::UserDefinedType {
+                    keyspace: keyspace.clone(),
+                    type_name: type_name.clone(),
+                    fields,
+                }
+            }
+            ColumnType::SmallInt => CqlValue::SmallInt(self.parse_word("smallint")?),
+            ColumnType::TinyInt => CqlValue::TinyInt(self.parse_word("tinyint")?),
+            ColumnType::Time => CqlValue::Time(self.parse_time()?),
+            ColumnType::Timeuuid => {
+                let uuid: Uuid = self.parse_word("timeuuid")?;
+                if uuid.get_version_num() != 1 {
+                    return Err(self.error_at(start, format!("{} is not a timeuuid", uuid)));
+                }
+                CqlValue::Timeuuid(uuid.into())
+            }
+            ColumnType::Tuple(element_types) => {
+                let mut elements = Vec::with_capacity(element_types.len());
+                self.expect('(')?;
+                for (i, element_type) in element_types.iter().enumerate() {
+                    if i > 0 {
+                        self.expect(',')?;
+                    }
+                    elements.push(self.parse_optional_value(element_type)?);
+                }
+                self.expect(')')?;
+                CqlValue::Tuple(elements)
+            }
+            ColumnType::Uuid => CqlValue::Uuid(self.parse_word("uuid")?),
+            ColumnType::Varint => CqlValue::Varint(self.parse_word::<CqlVarint>("varint")?),
+        };
+        Ok(value)
+    }
+
+    fn error_at(&self, position: usize, reason: impl Into<String>) -> ParseLiteralError {
+        ParseLiteralError {
+            position,
+            reason: reason.into(),
+        }
+    }
+
+    fn parse_elements(
+        &mut self,
+        open: char,
+        close: char,
+        typ: &ColumnType,
+    ) -> Result<Vec<CqlValue>, ParseLiteralError> {
+        let mut elements = Vec::new();
+        self.expect(open)?;
+        if self.try_consume(close) {
+            return Ok(elements);
+        }
+        loop {
+            elements.push(self.parse_value(typ)?);
+            if self.try_consume(close) {
+                return Ok(elements);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn parse_identifier(&mut self) -> Result<String, ParseLiteralError> {
+        if self.peek() != Some('"') {
+            let word = self.word();
+            if word.is_empty() {
+                return Err(self.error("expected a field name"));
+            }
+            return Ok(word.to_lowercase());
+        }
+        let rest = self.rest();
+        let mut result = String::new();
+        let mut chars = rest.char_indices().skip(1).peekable();
+        while let Some((i, c)) = chars.next() {
+            if c == '"' {
+                if chars.peek().map(|&(_, c)| c) == Some('"') {
+                    chars.next();
+                } else {
+                    self.position += i + 1;
+                    return Ok(result);
+                }
+            }
+            result.push(c);
+        }
+        Err(self.error("unterminated quoted identifier"))
+    }
+
+    fn parse_blob(&mut self) -> Result<Vec<u8>, ParseLiteralError> {
+        let start = self.position;
+        let word = self.word();
+        let hex = word
+            .strip_prefix("0x")
+            .or_else(|| word.strip_prefix("0X"))
+            .filter(|hex| hex.len() % 2 == 0)
+            .ok_or_else(|| self.error_at(start, format!("'{}' is not a valid blob", word)))?;
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                u8::from_str_radix(hex.get(i..i + 2).unwrap_or_default(), 16)
+                    .map_err(|_| self.error_at(start, format!("'{}' is not a valid blob", word)))
+            })
+            .collect()
+    }
+
+    fn parse_date(&mut self) -> Result<u32, ParseLiteralError> {
+        let start = self.position;
+        let s = self.string_or_word()?;
+        let invalid = || self.error_at(start, format!("'{}' is not a valid date", s));
+        if let Ok(days) = s.parse::<u32>() {
+            return Ok(days);
+        }
+        let date = NaiveDate::parse_from_str(&s, "%Y-%m-%d").map_err(|_| invalid())?;
+        let days_since_epoch = date
+            .signed_duration_since(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .num_days();
+        u32::try_from(days_since_epoch + (1 << 31)).map_err(|_| invalid())
+    }
+
+    fn parse_time(&mut self) -> Result<chrono::Duration, ParseLiteralError> {
+        let start = self.position;
+        let s = self.string_or_word()?;
+        let invalid = || self.error_at(start, format!("'{}' is not a valid time", s));
+        if let Ok(nanos) = s.parse::<i64>() {
+            if !(0..NANOS_PER_DAY).contains(&nanos) {
+                return Err(invalid());
+            }
+            return Ok(chrono::Duration::nanoseconds(nanos));
+        }
+        let (hms, fraction) = s.split_once('.').unwrap_or((&s, ""));
+        let mut parts = hms.split(':').map(|part| part.parse::<i64>().ok());
+        let (hours, minutes, seconds) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(Some(h)), Some(Some(m)), Some(Some(s))) if parts.next().is_none() => (h, m, s),
+            _ => return Err(invalid()),
+        };
+        if !(0..24).contains(&hours)
+            || !(0..60).contains(&minutes)
+            || !(0..60).contains(&seconds)
+            || fraction.len() > 9
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let fraction_nanos = format!("{:0<9}", fraction).parse::<i64>().unwrap_or(0);
+        Ok(chrono::Duration::nanoseconds(
+            ((hours * 60 + minutes) * 60 + seconds) * NANOS_PER_SECOND + fraction_nanos,
+        ))
+    }
+
+    fn parse_timestamp(&mut self) -> Result<chrono::Duration, ParseLiteralError> {
+        let start = self.position;
+        let s = self.string_or_word()?;
+        if let Ok(millis) = s.parse::<i64>() {
+            return Ok(chrono::Duration::milliseconds(millis));
+        }
+        let with_offset = match s.strip_suffix('Z') {
+            Some(utc) => format!("{}+0000", utc),
+            None => s.clone(),
+        };
+        const OFFSET_FORMATS: [&str; 4] = [
+            "%Y-%m-%d %H:%M:%S%.f%z",
+            "%Y-%m-%dT%H:%M:%S%.f%z",
+            "%Y-%m-%d %H:%M%z",
+            "%Y-%m-%dT%H:%M%z",
+        ];
+        const NAIVE_FORMATS: [&str; 4] = [
+            "%Y-%m-%d %H:%M:%S%.f",
+            "%Y-%m-%dT%H:%M:%S%.f",
+            "%Y-%m-%d %H:%M",
+            "%Y-%m-%dT%H:%M",
+        ];
+        let millis = OFFSET_FORMATS
+            .iter()
+            .find_map(|format| DateTime::parse_from_str(&with_offset, format).ok())
+            .map(|dt| dt.timestamp_millis())
+            .or_else(|| {
+                NAIVE_FORMATS
+                    .iter()
+                    .find_map(|format| NaiveDateTime::parse_from_str(&s, format).ok())
+                    .or_else(|| {
+                        NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+                            .ok()
+                            .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    })
+                    .map(|dt| Utc.from_utc_datetime(&dt).timestamp_millis())
+            })
+            .ok_or_else(|| self.error_at(start, format!("'{}' is not a valid timestamp", s)))?;
+        Ok(chrono::Duration::milliseconds(millis))
+    }
+
+    fn parse_duration(&mut self) -> Result<CqlDuration, ParseLiteralError> {
+        let start = self.position;
+        let word = self.word();
+        let invalid = || self.error_at(start, format!("'{}' is not a valid duration", word));
+        let (negative, mut rest) = match word.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, word),
+        };
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+        let (mut months, mut days, mut nanos) = (0i64, 0i64, 0i64);
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let count: i64 = rest[..digits].parse().map_err(|_| invalid())?;
+            rest = &rest[digits..];
+            let unit_len = rest
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let unit = rest[..unit_len].to_ascii_lowercase().replace('µ', "u");
+            rest = &rest[unit_len..];
+            let (_, unit_months, unit_days, unit_nanos) = DURATION_UNITS
+                .iter()
+                .find(|(name, ..)| *name == unit)
+                .ok_or_else(invalid)?;
+            let add = |total: i64, per_unit: i64| {
+                count
+                    .checked_mul(per_unit)
+                    .and_then(|value| total.checked_add(value))
+                    .ok_or_else(invalid)
+            };
+            months = add(months, *unit_months)?;
+            days = add(days, *unit_days)?;
+            nanos = add(nanos, *unit_nanos)?;
+        }
+        let sign = if negative { -1 } else { 1 };
+        Ok(CqlDuration {
+            months: i32::try_from(months * sign).map_err(|_| invalid())?,
+            days: i32::try_from(days * sign).map_err(|_| invalid())?,
+            nanoseconds: nanos * sign,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::result::{ColumnType, CqlValue};
+    use crate::frame::value::{Counter, CqlDecimal, CqlDuration, CqlVarint};
+    use std::str::FromStr;
+    use uuid::Uuid;
+
+    fn assert_round_trip(value: CqlValue, typ: ColumnType, literal: &str) {
+        assert_eq!(value.to_string(), literal);
+        assert_eq!(CqlValue::from_cql_literal(literal, &typ), Ok(value));
+    }
+
+    #[test]
+    fn simple_literals() {
+        assert_round_trip(CqlValue::Int(-7), ColumnType::Int, "-7");
+        assert_round_trip(
+            CqlValue::BigInt(1 << 40),
+            ColumnType::BigInt,
+            "1099511627776",
+        );
+        assert_round_trip(CqlValue::SmallInt(3), ColumnType::SmallInt, "3");
+        assert_round_trip(CqlValue::TinyInt(-1), ColumnType::TinyInt, "-1");
+        assert_round_trip(CqlValue::Counter(Counter(5)), ColumnType::Counter, "5");
+        assert_round_trip(CqlValue::Boolean(true), ColumnType::Boolean, "true");
+        assert_round_trip(CqlValue::Double(1.0), ColumnType::Double, "1.0");
+        assert_round_trip(CqlValue::Float(0.1), ColumnType::Float, "0.1");
+        assert_round_trip(
+            CqlValue::Double(f64::NEG_INFINITY),
+            ColumnType::Double,
+            "-Infinity",
+        );
+        assert_eq!(CqlValue::Double(f64::NAN).to_string(), "NaN");
+        assert_round_trip(
+            CqlValue::Text("it's".to_string()),
+            ColumnType::Text,
+            "'it''s'",
+        );
+        assert_round_trip(CqlValue::Ascii(String::new()), ColumnType::Ascii, "''");
+        assert_round_trip(
+            CqlValue::Blob(vec![0x00, 0xab, 0x10]),
+            ColumnType::Blob,
+            "0x00ab10",
+        );
+        assert_round_trip(
+            CqlValue::Varint(CqlVarint::from_str("-123456789012345678901234567890").unwrap()),
+            ColumnType::Varint,
+            "-123456789012345678901234567890",
+        );
+        assert_round_trip(
+            CqlValue::Decimal(CqlDecimal::from_str("12.345").unwrap()),
+            ColumnType::Decimal,
+            "12.345",
+        );
+        let uuid = Uuid::parse_str("8e14e760-7fa8-11eb-bc66-000000000001").unwrap();
+        assert_round_trip(
+            CqlValue::Timeuuid(uuid.into()),
+            ColumnType::Timeuuid,
+            "8e14e760-7fa8-11eb-bc66-000000000001",
+        );
+        assert_round_trip(
+            CqlValue::Inet("::1".parse().unwrap()),
+            ColumnType::Inet,
+            "'::1'",
+        );
+        assert_eq!(
+            CqlValue::from_cql_literal("$$it's$$", &ColumnType::Text),
+            Ok(CqlValue::Text("it's".to_string()))
+        );
+    }
+
+    #[test]
+    fn date_and_time_literals() {
+        assert_round_trip(
+            CqlValue::Date((1 << 31) + 19000),
+            ColumnType::Date,
+            "'2022-01-08'",
+        );
+        assert_round_trip(CqlValue::Date(0), ColumnType::Date, "'0'");
+        assert_round_trip(
+            CqlValue::Time(chrono::Duration::nanoseconds(
+                (13 * 3600 + 5 * 60 + 7) * 1_000_000_000 + 42,
+            )),
+            ColumnType::Time,
+            "'13:05:07.000000042'",
+        );
+        assert_eq!(
+            CqlValue::from_cql_literal("'13:05:07'", &ColumnType::Time),
+            Ok(CqlValue::Time(chrono::Duration::seconds(
+                13 * 3600 + 5 * 60 + 7
+            )))
+        );
+
+        let timestamp = CqlValue::Timestamp(chrono::Duration::milliseconds(1_640_995_200_123));
+        assert_round_trip(
+            timestamp.clone(),
+            ColumnType::Timestamp,
+            "'2022-01-01 00:00:00.123+0000'",
+        );
+        for literal in [
+            "1640995200123",
+            "'2022-01-01T00:00:00.123Z'",
+            "'2022-01-01 01:00:00.123+0100'",
+            "'2022-01-01 00:00:00.123'",
+        ] {
+            assert_eq!(
+                CqlValue::from_cql_literal(literal, &ColumnType::Timestamp),
+                Ok(timestamp.clone()),
+                "{}",
+                literal
+            );
+        }
+        assert_eq!(
+            CqlValue::from_cql_literal("'2022-01-01'", &ColumnType::Timestamp),
+            Ok(CqlValue::Timestamp(chrono::Duration::milliseconds(
+                1_640_995_200_000
+            )))
+        );
+    }
+
+    #[test]
+    fn duration_literals() {
+        let duration = |months, days, nanoseconds| {
+            CqlValue::Duration(CqlDuration {
+                months,
+                days,
+                nanoseconds,
+            })
+        };
+        assert_round_trip(duration(0, 0, 0), ColumnType::Duration, "0s");
+        assert_round_trip(
+            duration(14, 3, 3_600_000_000_001),
+            ColumnType::Duration,
+            "1y2mo3d1h1ns",
+        );
+        assert_round_trip(duration(0, -1, -1_000), ColumnType::Duration, "-1d1us");
+        assert_eq!(
+            CqlValue::from_cql_literal("2w1d90m", &ColumnType::Duration),
+            Ok(duration(0, 15, 90 * 60 * 1_000_000_000))
+        );
+        assert!(CqlValue::from_cql_literal("1x", &ColumnType::Duration).is_err());
+    }
+
+    #[test]
+    fn collection_literals() {
+        assert_round_trip(
+            CqlValue::List(vec![CqlValue::Int(1), CqlValue::Int(2)]),
+            ColumnType::List(Box::new(ColumnType::Int)),
+            "[1, 2]",
+        );
+        assert_round_trip(
+            CqlValue::Set(vec![]),
+            ColumnType::Set(Box::new(ColumnType::Text)),
+            "{}",
+        );
+        assert_round_trip(
+            CqlValue::Map(vec![
+                (
+                    CqlValue::Text("a".to_string()),
+                    CqlValue::List(vec![CqlValue::BigInt(1)]),
+                ),
+                (CqlValue::Text("b".to_string()), CqlValue::List(vec![])),
+            ]),
+            ColumnType::Map(
+                Box::new(ColumnType::Text),
+                Box::new(ColumnType::List(Box::new(ColumnType::BigInt))),
+            ),
+            "{'a': [1], 'b': []}",
+        );
+        assert_round_trip(
+            CqlValue::Tuple(vec![Some(CqlValue::Int(1)), None]),
+            ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Text]),
+            "(1, null)",
+        );
+        assert_round_trip(
+            CqlValue::Vector(vec![CqlValue::Float(1.5), CqlValue::Float(-2.0)]),
+            ColumnType::Vector {
+                element: Box::new(ColumnType::Float),
+                dimensions: 2,
+            },
+            "[1.5, -2.0]",
+        );
+        assert!(CqlValue::from_cql_literal(
+            "[1.5]",
+            &ColumnType::Vector {
+                element: Box::new(ColumnType::Float),
+                dimensions: 2,
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn udt_keyword_fields() {
+        let typ = ColumnType::UserDefinedType {
+            type_name: "item".to_string(),
+            keyspace: "ks".to_string(),
+            field_types: vec![
+                ("order".to_string(), ColumnType::Int),
+                ("key".to_string(), ColumnType::Text),
+            ],
+        };
+        let value = CqlValue::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "item".to_string(),
+            fields: vec![
+                ("order".to_string(), Some(CqlValue::Int(1))),
+                ("key".to_string(), Some(CqlValue::Text("a".to_string()))),
+            ],
+        };
+        assert_round_trip(value, typ, "{\"order\": 1, \"key\": 'a'}");
+    }
+
+    #[test]
+    fn udt_literals() {
+        let typ = ColumnType::UserDefinedType {
+            type_name: "address".to_string(),
+            keyspace: "ks".to_string(),
+            field_types: vec![
+                ("street".to_string(), ColumnType::Text),
+                ("Number".to_string(), ColumnType::Int),
+                ("zip".to_string(), ColumnType::Text),
+            ],
+        };
+        let value = CqlValue::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "address".to_string(),
+            fields: vec![
+                (
+                    "street".to_string(),
+                    Some(CqlValue::Text("Main".to_string())),
+                ),
+                ("Number".to_string(), Some(CqlValue::Int(7))),
+                ("zip".to_string(), None),
+            ],
+        };
+        assert_round_trip(
+            value.clone(),
+            typ.clone(),
+            "{street: 'Main', \"Number\": 7, zip: null}",
+        );
+        // Fields may be given in any order and omitted
+        assert_eq!(
+            CqlValue::from_cql_literal("{ \"Number\" : 7, STREET: 'Main' }", &typ),
+            Ok(value)
+        );
+        let error = CqlValue::from_cql_literal("{street: 'Main', city: 'X'}", &typ).unwrap_err();
+        assert_eq!(error.position, 17);
+    }
+
+    #[test]
+    fn invalid_literals() {
+        for (literal, typ) in [
+            ("1 2", ColumnType::Int),
+            ("'a", ColumnType::Text),
+            ("300", ColumnType::TinyInt),
+            ("0x123", ColumnType::Blob),
+            ("[1, 2", ColumnType::List(Box::new(ColumnType::Int))),
+            ("null", ColumnType::Int),
+            ("'25:00:00'", ColumnType::Time),
+            ("'ż'", ColumnType::Ascii),
+            ("8e14e760-7fa8-41eb-bc66-000000000001", ColumnType::Timeuuid),
+        ] {
+            assert!(
+                CqlValue::from_cql_literal(literal, &typ).is_err(),
+                "{}",
+                literal
+            );
+        }
+    }
+}
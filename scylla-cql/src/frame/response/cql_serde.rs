@@ -0,0 +1,483 @@
+//! Integration with [serde](https://serde.rs), enabled by the `serde` feature.
+//!
+//! [`CqlValue`]s implement [`Serialize`], which allows converting them to e.g. JSON.
+//! A [`Row`] is serialized as a map from column names to values through [`Row::with_names`],
+//! as the names are known only from the column specifications of the result.
+//! [`RowDeserializer`] allows reading a row as any type implementing [`Deserialize`](serde::Deserialize).
+//!
+//! CQL values are represented as follows:
+//! * `ascii`, `text` - strings
+//! * `boolean`, `float`, `double`, `tinyint`, `smallint`, `int`, `bigint`, `counter` - numbers
+//! * `blob` - bytes
+//! * `date` - string like `2021-03-24`, or the raw `u32` if it's out of range of `chrono::NaiveDate`
+//! * `time` - number of nanoseconds since midnight
+//! * `timestamp` - number of milliseconds since unix epoch
+//! * `duration` - struct with fields `months`, `days` and `nanoseconds`
+//! * `decimal`, `varint`, `inet`, `uuid`, `timeuuid` - strings
+//! * `list`, `set`, `vector`, `tuple` - sequences
+//! * `map`, user defined types - maps
+//! * `null` and empty values - none / unit
+
+use super::result::{ColumnSpec, CqlValue, Row};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, IntoDeserializer, Visitor};
+use serde::ser::{SerializeMap, SerializeStruct};
+use serde::{Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+impl Serialize for CqlValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            CqlValue::Ascii(s) | CqlValue::Text(s) => serializer.serialize_str(s),
+            CqlValue::Boolean(b) => serializer.serialize_bool(*b),
+            CqlValue::Blob(b) => serializer.serialize_bytes(b),
+            CqlValue::Counter(c) => serializer.serialize_i64(c.0),
+            CqlValue::Decimal(d) => serializer.collect_str(d),
+            CqlValue::Date(d) => match self.as_date() {
+                Some(date) => serializer.collect_str(&date),
+                None => serializer.serialize_u32(*d),
+            },
+            CqlValue::Double(d) => serializer.serialize_f64(*d),
+            CqlValue::Duration(d) => {
+                let mut s = serializer.serialize_struct("CqlDuration", 3)?;
+                s.serialize_field("months", &d.months)?;
+                s.serialize_field("days", &d.days)?;
+                s.serialize_field("nanoseconds", &d.nanoseconds)?;
+                s.end()
+            }
+            CqlValue::Empty => serializer.serialize_unit(),
+            CqlValue::Float(f) => serializer.serialize_f32(*f),
+            CqlValue::Int(i) => serializer.serialize_i32(*i),
+            CqlValue::BigInt(i) => serializer.serialize_i64(*i),
+            CqlValue::Timestamp(t) => serializer.serialize_i64(t.num_milliseconds()),
+            CqlValue::Inet(i) => serializer.collect_str(i),
+            CqlValue::List(v) | CqlValue::Set(v) | CqlValue::Vector(v) => serializer.collect_seq(v),
+            CqlValue::Map(m) => serializer.collect_map(m.iter().map(|(k, v)| (k, v))),
+            CqlValue::UserDefinedType { fields, .. } => {
+                serializer.collect_map(fields.iter().map(|(k, v)| (k, v)))
+            }
+            CqlValue::SmallInt(i) => serializer.serialize_i16(*i),
+            CqlValue::TinyInt(i) => serializer.serialize_i8(*i),
+            CqlValue::Time(t) => match t.num_nanoseconds() {
+                Some(nanoseconds) => serializer.serialize_i64(nanoseconds),
+                None => Err(serde::ser::Error::custom("time value out of range")),
+            },
+            CqlValue::Timeuuid(u) => serializer.collect_str(u),
+            CqlValue::Uuid(u) => serializer.collect_str(u),
+            CqlValue::Tuple(t) => serializer.collect_seq(t),
+            CqlValue::Varint(v) => serializer.collect_str(v),
+        }
+    }
+}
+
+impl Row {
+    /// Returns a view of the row which is serialized as a map from column names to values.\
+    /// `col_specs` should be the column specifications of the result this row comes from.\
+    /// To serialize only the values, as a sequence, serialize [`Row::columns`](Row::columns) instead.
+    pub fn with_names<'a>(&'a self, col_specs: &'a [ColumnSpec]) -> NamedRow<'a> {
+        NamedRow {
+            row: self,
+            col_specs,
+        }
+    }
+}
+
+/// A row together with its column names, serialized as a map.\
+/// Created using [`Row::with_names`].
+#[derive(Debug, Clone, Copy)]
+pub struct NamedRow<'a> {
+    row: &'a Row,
+    col_specs: &'a [ColumnSpec],
+}
+
+impl Serialize for NamedRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.row.columns.len()))?;
+        for (spec, column) in self.col_specs.iter().zip(&self.row.columns) {
+            map.serialize_entry(&spec.name, column)?;
+        }
+        map.end()
+    }
+}
+
+/// Error returned when a row or a value can't be deserialized using serde
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{0}")]
+pub struct SerdeDeserializationError(String);
+
+impl serde::de::Error for SerdeDeserializationError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Deserializes a row as `T`, which can be any type implementing [`Deserialize`](serde::Deserialize).\
+/// Structs and maps are read using column names from `col_specs`, sequences and tuples
+/// are read in the order of columns.
+///
+/// # Example
+/// ```rust
+/// # use scylla_cql::frame::response::cql_serde::from_row;
+/// # use scylla_cql::frame::response::result::{ColumnSpec, Row};
+/// # fn check_only_compiles(row: Row, col_specs: &[ColumnSpec]) -> Result<(), Box<dyn std::error::Error>> {
+/// #[derive(serde::Deserialize)]
+/// struct MyRow {
+///     a: i32,
+///     b: Option<String>,
+/// }
+///
+/// let my_row: MyRow = from_row(row, col_specs)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_row<T: DeserializeOwned>(
+    row: Row,
+    col_specs: &[ColumnSpec],
+) -> Result<T, SerdeDeserializationError> {
+    T::deserialize(RowDeserializer::new(row, col_specs))
+}
+
+/// A serde [`Deserializer`] over a single row, see [`from_row`]
+pub struct RowDeserializer<'a> {
+    row: Row,
+    col_specs: &'a [ColumnSpec],
+}
+
+impl<'a> RowDeserializer<'a> {
+    pub fn new(row: Row, col_specs: &'a [ColumnSpec]) -> Self {
+        Self { row, col_specs }
+    }
+
+    fn visit_map<'de, V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, SerdeDeserializationError> {
+        let mut map = MapDeserializer::new(
+            self.col_specs
+                .iter()
+                .map(|spec| spec.name.as_str())
+                .zip(self.row.columns.into_iter().map(CqlValueDeserializer::new)),
+        );
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    fn visit_seq<'de, V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, SerdeDeserializationError> {
+        let mut seq =
+            SeqDeserializer::new(self.row.columns.into_iter().map(CqlValueDeserializer::new));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+}
+
+impl<'de> Deserializer<'de> for RowDeserializer<'_> {
+    type Error = SerdeDeserializationError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_seq(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.visit_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.visit_seq(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+/// A serde [`Deserializer`] over a single, possibly null, CQL value
+pub struct CqlValueDeserializer {
+    value: Option<CqlValue>,
+}
+
+impl CqlValueDeserializer {
+    pub fn new(value: Option<CqlValue>) -> Self {
+        Self { value }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, SerdeDeserializationError> for CqlValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+fn visit_seq_of<'de, V: Visitor<'de>>(
+    values: impl Iterator<Item = Option<CqlValue>>,
+    visitor: V,
+) -> Result<V::Value, SerdeDeserializationError> {
+    let mut seq = SeqDeserializer::new(values.map(CqlValueDeserializer::new));
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+fn visit_map_of<'de, K, V>(
+    entries: impl Iterator<Item = (K, Option<CqlValue>)>,
+    visitor: V,
+) -> Result<V::Value, SerdeDeserializationError>
+where
+    K: IntoDeserializer<'de, SerdeDeserializationError>,
+    V: Visitor<'de>,
+{
+    let mut map = MapDeserializer::new(entries.map(|(k, v)| (k, CqlValueDeserializer::new(v))));
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+impl<'de> Deserializer<'de> for CqlValueDeserializer {
+    type Error = SerdeDeserializationError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let value = match self.value {
+            Some(value) => value,
+            None => return visitor.visit_none(),
+        };
+
+        match value {
+            CqlValue::Ascii(s) | CqlValue::Text(s) => visitor.visit_string(s),
+            CqlValue::Boolean(b) => visitor.visit_bool(b),
+            CqlValue::Blob(b) => visitor.visit_byte_buf(b),
+            CqlValue::Counter(c) => visitor.visit_i64(c.0),
+            CqlValue::Decimal(d) => visitor.visit_string(d.to_string()),
+            CqlValue::Date(d) => match value.as_date() {
+                Some(date) => visitor.visit_string(date.to_string()),
+                None => visitor.visit_u32(d),
+            },
+            CqlValue::Double(d) => visitor.visit_f64(d),
+            CqlValue::Duration(d) => visit_map_of(
+                [
+                    ("months", CqlValue::Int(d.months)),
+                    ("days", CqlValue::Int(d.days)),
+                    ("nanoseconds", CqlValue::BigInt(d.nanoseconds)),
+                ]
+                .into_iter()
+                .map(|(k, v)| (k, Some(v))),
+                visitor,
+            ),
+            CqlValue::Empty => visitor.visit_unit(),
+            CqlValue::Float(f) => visitor.visit_f32(f),
+            CqlValue::Int(i) => visitor.visit_i32(i),
+            CqlValue::BigInt(i) => visitor.visit_i64(i),
+            CqlValue::Timestamp(t) => visitor.visit_i64(t.num_milliseconds()),
+            CqlValue::Inet(i) => visitor.visit_string(i.to_string()),
+            CqlValue::List(v) | CqlValue::Set(v) | CqlValue::Vector(v) => {
+                visit_seq_of(v.into_iter().map(Some), visitor)
+            }
+            CqlValue::Map(m) => {
+                let mut map = MapDeserializer::new(m.into_iter().map(|(k, v)| {
+                    (
+                        CqlValueDeserializer::new(Some(k)),
+                        CqlValueDeserializer::new(Some(v)),
+                    )
+                }));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            CqlValue::UserDefinedType { fields, .. } => visit_map_of(fields.into_iter(), visitor),
+            CqlValue::SmallInt(i) => visitor.visit_i16(i),
+            CqlValue::TinyInt(i) => visitor.visit_i8(i),
+            CqlValue::Time(t) => match t.num_nanoseconds() {
+                Some(nanoseconds) => visitor.visit_i64(nanoseconds),
+                None => Err(serde::de::Error::custom("time value out of range")),
+            },
+            CqlValue::Timeuuid(u) => visitor.visit_string(u.to_string()),
+            CqlValue::Uuid(u) => visitor.visit_string(u.to_string()),
+            CqlValue::Tuple(t) => visit_seq_of(t.into_iter(), visitor),
+            CqlValue::Varint(v) => visitor.visit_string(v.to_string()),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            None | Some(CqlValue::Empty) => visitor.visit_none(),
+            Some(_) => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Unit variants of enums can be read from text values
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Some(CqlValue::Ascii(s) | CqlValue::Text(s)) => {
+                visitor.visit_enum(s.into_deserializer())
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_row, SerdeDeserializationError};
+    use crate::frame::response::result::{ColumnSpec, ColumnType, CqlValue, Row, TableSpec};
+    use crate::frame::value::{Counter, CqlDuration};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    fn col_specs(names: &[&str]) -> Vec<ColumnSpec> {
+        names
+            .iter()
+            .map(|name| ColumnSpec {
+                table_spec: TableSpec {
+                    ks_name: "ks".to_string(),
+                    table_name: "tab".to_string(),
+                },
+                name: name.to_string(),
+                typ: ColumnType::Int,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cql_value_to_json() {
+        let value = CqlValue::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "udt".to_string(),
+            fields: vec![
+                ("a".to_string(), Some(CqlValue::Int(1))),
+                ("b".to_string(), None),
+                (
+                    "c".to_string(),
+                    Some(CqlValue::List(vec![
+                        CqlValue::Text("x".to_string()),
+                        CqlValue::Text("y".to_string()),
+                    ])),
+                ),
+                ("d".to_string(), Some(CqlValue::Date(1 << 31))),
+                (
+                    "e".to_string(),
+                    Some(CqlValue::Duration(CqlDuration {
+                        months: 1,
+                        days: 2,
+                        nanoseconds: 3,
+                    })),
+                ),
+                ("f".to_string(), Some(CqlValue::Counter(Counter(4)))),
+            ],
+        };
+
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"a":1,"b":null,"c":["x","y"],"d":"1970-01-01","e":{"months":1,"days":2,"nanoseconds":3},"f":4}"#
+        );
+    }
+
+    #[test]
+    fn row_to_json() {
+        let row = Row {
+            columns: vec![Some(CqlValue::Int(1)), None],
+        };
+        let specs = col_specs(&["a", "b"]);
+
+        assert_eq!(serde_json::to_string(&row.columns).unwrap(), "[1,null]");
+        assert_eq!(
+            serde_json::to_string(&row.with_names(&specs)).unwrap(),
+            r#"{"a":1,"b":null}"#
+        );
+    }
+
+    #[test]
+    fn row_from_serde() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum Kind {
+            Small,
+            Big,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct MyRow {
+            b: Option<String>,
+            a: i32,
+            c: BTreeMap<String, Vec<i64>>,
+            d: Kind,
+            #[serde(default)]
+            e: Option<i32>,
+        }
+
+        let specs = col_specs(&["a", "b", "c", "d"]);
+        let row = Row {
+            columns: vec![
+                Some(CqlValue::Int(1)),
+                None,
+                Some(CqlValue::Map(vec![(
+                    CqlValue::Text("x".to_string()),
+                    CqlValue::List(vec![CqlValue::BigInt(2), CqlValue::BigInt(3)]),
+                )])),
+                Some(CqlValue::Text("Big".to_string())),
+            ],
+        };
+
+        let my_row: MyRow = from_row(row, &specs).unwrap();
+        assert_eq!(
+            my_row,
+            MyRow {
+                b: None,
+                a: 1,
+                c: BTreeMap::from([("x".to_string(), vec![2, 3])]),
+                d: Kind::Big,
+                e: None,
+            }
+        );
+
+        // Tuples are read in the order of columns
+        let row = Row {
+            columns: vec![Some(CqlValue::Int(1)), Some(CqlValue::BigInt(2))],
+        };
+        let tuple: (i32, i64) = from_row(row, &specs).unwrap();
+        assert_eq!(tuple, (1, 2));
+
+        let row = Row {
+            columns: vec![Some(CqlValue::Text("a".to_string()))],
+        };
+        let err: SerdeDeserializationError = from_row::<(i32,)>(row, &specs).unwrap_err();
+        assert_eq!(err.to_string(), r#"invalid type: string "a", expected i32"#);
+    }
+}
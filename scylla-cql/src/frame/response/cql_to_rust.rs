@@ -1,8 +1,7 @@
-use super::result::{CqlValue, Row};
-use crate::frame::value::{Counter, CqlDuration};
-use bigdecimal::BigDecimal;
+use super::result::{ColumnSpec, ColumnType, CqlValue, Row};
+use crate::frame::type_markers::DeserializedCqlTypes;
+use crate::frame::value::{Counter, CqlDecimal, CqlDuration, CqlTimeuuid, CqlVarint};
 use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
-use num_bigint::BigInt;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::hash::Hash;
 use std::net::IpAddr;
@@ -18,6 +17,53 @@ pub enum FromRowError {
     BadCqlVal { err: FromCqlValError, column: usize },
     #[error("Wrong row size: expected {expected}, actual {actual}")]
     WrongRowSize { expected: usize, actual: usize },
+    #[error(transparent)]
+    TypeCheckError(#[from] RowTypeCheckError),
+}
+
+/// The Rust type of a row doesn't match the columns of a result
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RowTypeCheckError {
+    #[error("Wrong row size: expected {expected}, actual {actual}")]
+    WrongRowSize { expected: usize, actual: usize },
+    #[error("Columns don't match the Rust types: {}", display_mismatches(.0))]
+    ColumnTypeMismatch(Vec<ColumnTypeMismatch>),
+    #[error("Missing columns for fields of the Rust type: {}", .0.join(", "))]
+    MissingColumns(Vec<String>),
+    #[error("Columns not matching any field of the Rust type: {}", .0.join(", "))]
+    UnexpectedColumns(Vec<String>),
+}
+
+/// A column whose CQL type can't be converted to the Rust type of a row's field
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("column {column} has CQL type {cql_type:?}, which can't be read as {rust_type}")]
+pub struct ColumnTypeMismatch {
+    pub column: String,
+    pub cql_type: ColumnType,
+    pub rust_type: &'static str,
+}
+
+fn display_mismatches(mismatches: &[ColumnTypeMismatch]) -> String {
+    mismatches
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Checks whether a column can be converted to `T`, used by implementations of [`FromRow::type_check`]
+pub fn type_check_column<T: FromCqlVal<Option<CqlValue>>>(
+    spec: &ColumnSpec,
+) -> Option<ColumnTypeMismatch> {
+    // Custom types are opaque to the driver
+    if matches!(spec.typ, ColumnType::Custom(_)) || T::matches_type(&spec.typ) {
+        return None;
+    }
+    Some(ColumnTypeMismatch {
+        column: spec.name.clone(),
+        cql_type: spec.typ.clone(),
+        rust_type: std::any::type_name::<T>(),
+    })
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
@@ -31,6 +77,12 @@ pub enum CqlTypeError {
 // is forbidden since neither From nor String are defined in this crate
 pub trait FromCqlVal<T>: Sized {
     fn from_cql(cql_val: T) -> Result<Self, FromCqlValError>;
+
+    /// Checks whether values of the given CQL type can be converted to this type.\
+    /// Types which don't override it are assumed to match every CQL type.
+    fn matches_type(_typ: &ColumnType) -> bool {
+        true
+    }
 }
 
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
@@ -46,6 +98,168 @@ pub enum FromCqlValError {
 /// This trait defines a way to convert CQL Row into some rust type
 pub trait FromRow: Sized {
     fn from_row(row: Row) -> Result<Self, FromRowError>;
+
+    /// Checks whether rows with columns described by `specs` can be converted to this type.\
+    /// Done once per result or page, before converting its rows.
+    /// Types which don't override it accept all columns.
+    fn type_check(_specs: &[ColumnSpec]) -> Result<(), RowTypeCheckError> {
+        Ok(())
+    }
+}
+
+/// Converts rows of a result, knowing the specs of its columns.\
+/// Used wherever the specs are available, e.g. by `QueryResult::rows_typed`.
+/// Implemented for every [`FromRow`] type, which reads the columns by position,
+/// and by structs deriving `FromRow` with `#[scylla(match_by_name)]`,
+/// which can't be converted without the specs and so don't implement [`FromRow`].
+pub trait FromRowWithSpecs: Sized {
+    /// Which column is read by which part of the type, computed once per result or page
+    type ColumnMapping;
+
+    /// Checks whether rows with columns described by `specs` can be converted to this type,
+    /// and computes the mapping used to convert them
+    fn map_columns(specs: &[ColumnSpec]) -> Result<Self::ColumnMapping, RowTypeCheckError>;
+
+    /// Converts a row whose columns are described by the specs `mapping` was computed from
+    fn from_row_with_mapping(row: Row, mapping: &Self::ColumnMapping)
+        -> Result<Self, FromRowError>;
+}
+
+impl<T: FromRow> FromRowWithSpecs for T {
+    type ColumnMapping = ();
+
+    fn map_columns(specs: &[ColumnSpec]) -> Result<(), RowTypeCheckError> {
+        T::type_check(specs)
+    }
+
+    fn from_row_with_mapping(row: Row, _mapping: &()) -> Result<Self, FromRowError> {
+        T::from_row(row)
+    }
+}
+
+/// Reads a struct deriving `FromRow` as a part of another one, for `#[scylla(flatten)]` fields.
+/// Implemented by `#[derive(FromRow)]`.
+#[doc(hidden)]
+pub trait FromRowFields: Sized {
+    /// Number of columns read by the struct, including columns of flattened fields
+    const COLUMN_COUNT: usize;
+
+    /// Appends names of the columns read by the struct, in order,
+    /// together with whether the column may be missing
+    fn column_names(names: &mut Vec<(&'static str, bool)>);
+
+    /// Reads the struct from the values of its columns, in the order of `column_names`.
+    /// Each value comes with the index of its column, `None` for missing columns.
+    fn from_columns(
+        columns: &mut dyn Iterator<Item = (Option<usize>, Option<CqlValue>)>,
+    ) -> Result<Self, FromRowError>;
+
+    /// Checks the specs of the struct's columns, in the order of `column_names`.
+    /// Missing columns have no specs.
+    fn type_check_columns(
+        specs: &mut dyn Iterator<Item = Option<&ColumnSpec>>,
+        mismatches: &mut Vec<ColumnTypeMismatch>,
+    );
+}
+
+/// Index of the column read by each field of a struct deriving `FromRow`
+/// with `#[scylla(match_by_name)]`, its [`FromRowWithSpecs::ColumnMapping`].
+/// Names of the fields come from [`FromRowFields::column_names`].
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnsByName {
+    // Number of columns in the result, checked against each row
+    columns_count: usize,
+    // Missing columns have no index
+    indices: Vec<Option<usize>>,
+}
+
+impl ColumnsByName {
+    /// Finds the index of the column named like each of `names`
+    pub fn new(
+        names: &[(&str, bool)],
+        specs: &[ColumnSpec],
+        allow_extra_columns: bool,
+    ) -> Result<Self, RowTypeCheckError> {
+        if !allow_extra_columns {
+            let unexpected: Vec<String> = specs
+                .iter()
+                .filter(|spec| !names.iter().any(|(name, _)| *name == spec.name))
+                .map(|spec| spec.name.clone())
+                .collect();
+            if !unexpected.is_empty() {
+                return Err(RowTypeCheckError::UnexpectedColumns(unexpected));
+            }
+        }
+
+        let indices: Vec<Option<usize>> = names
+            .iter()
+            .map(|(name, _)| specs.iter().position(|spec| spec.name == *name))
+            .collect();
+        let missing: Vec<String> = names
+            .iter()
+            .zip(indices.iter())
+            .filter(|((_, may_be_missing), ix)| ix.is_none() && !may_be_missing)
+            .map(|((name, _), _)| name.to_string())
+            .collect();
+        if !missing.is_empty() {
+            return Err(RowTypeCheckError::MissingColumns(missing));
+        }
+        Ok(ColumnsByName {
+            columns_count: specs.len(),
+            indices,
+        })
+    }
+
+    /// Specs of the columns in the order of the names, for [`FromRowFields::type_check_columns`]
+    pub fn specs<'a>(
+        &'a self,
+        specs: &'a [ColumnSpec],
+    ) -> impl Iterator<Item = Option<&'a ColumnSpec>> + 'a {
+        self.indices.iter().map(move |ix| ix.map(|ix| &specs[ix]))
+    }
+
+    /// Values of the row's columns in the order of the names, for [`FromRowFields::from_columns`]
+    pub fn take_columns(
+        &self,
+        row: Row,
+    ) -> Result<impl Iterator<Item = (Option<usize>, Option<CqlValue>)> + '_, FromRowError> {
+        if row.columns.len() != self.columns_count {
+            return Err(FromRowError::WrongRowSize {
+                expected: self.columns_count,
+                actual: row.columns.len(),
+            });
+        }
+        let mut columns: Vec<Option<Option<CqlValue>>> =
+            row.columns.into_iter().map(Some).collect();
+        // A column matches a single name, so each one is taken at most once
+        Ok(self
+            .indices
+            .iter()
+            .map(move |&ix| (ix, ix.and_then(|ix| columns[ix].take().flatten()))))
+    }
+}
+
+/// Reads a struct deriving `FromUserType` as a part of another one, for `#[scylla(flatten)]` fields.
+/// Implemented by `#[derive(FromUserType)]`.
+#[doc(hidden)]
+pub trait FromUserTypeFields: Sized {
+    /// Reads the struct's fields, taking the values of the received UDT fields with their names.
+    /// Fields which weren't received are read from `None`.
+    fn from_fields(fields: &mut [(String, Option<CqlValue>)]) -> Result<Self, FromCqlValError>;
+
+    /// Checks the type of the UDT field called `name`, `None` if the struct doesn't read it
+    fn field_matches_type(name: &str, typ: &ColumnType) -> Option<bool>;
+}
+
+/// Takes the value of the received UDT field called `name`, `None` if it wasn't received.
+/// Used by `#[derive(FromUserType)]`.
+#[doc(hidden)]
+pub fn take_udt_field(fields: &mut [(String, Option<CqlValue>)], name: &str) -> Option<CqlValue> {
+    fields
+        .iter_mut()
+        .find(|(field_name, _)| field_name == name)
+        .and_then(|(_, value)| value.take())
 }
 
 // CqlValue can be converted to CqlValue
@@ -61,6 +275,10 @@ impl<T: FromCqlVal<CqlValue>> FromCqlVal<Option<CqlValue>> for T {
     fn from_cql(cql_val_opt: Option<CqlValue>) -> Result<Self, FromCqlValError> {
         T::from_cql(cql_val_opt.ok_or(FromCqlValError::ValIsNull)?)
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        <T as FromCqlVal<CqlValue>>::matches_type(typ)
+    }
 }
 
 // Implement from_cql<Option<CqlValue>> for Option<T> for every type that has from_cql<CqlValue>
@@ -73,6 +291,10 @@ impl<T: FromCqlVal<CqlValue>> FromCqlVal<Option<CqlValue>> for Option<T> {
             None => Ok(None),
         }
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        <T as FromCqlVal<CqlValue>>::matches_type(typ)
+    }
 }
 /// This macro implements FromCqlVal given a type and method of CqlValue that returns this type.
 ///
@@ -81,6 +303,10 @@ impl<T: FromCqlVal<CqlValue>> FromCqlVal<Option<CqlValue>> for Option<T> {
 /// The conversion method must return an `Option<T>`. `None` values will be
 /// converted to `CqlValue::BadCqlType`.
 ///
+/// Optionally, names of [`ColumnType`](crate::frame::response::result::ColumnType) variants
+/// which can be converted to the type can be listed after the method, separated by `|`.
+/// They are used to check the types of columns before any conversion is attempted.
+///
 /// # Example
 /// ```
 /// # use scylla_cql::frame::response::result::CqlValue;
@@ -97,7 +323,7 @@ impl<T: FromCqlVal<CqlValue>> FromCqlVal<Option<CqlValue>> for Option<T> {
 ///     }
 /// }
 ///
-/// impl_from_cql_value_from_method!(MyBytes, into_my_bytes);
+/// impl_from_cql_value_from_method!(MyBytes, into_my_bytes, Blob);
 /// ```
 #[macro_export]
 macro_rules! impl_from_cql_value_from_method {
@@ -117,25 +343,98 @@ macro_rules! impl_from_cql_value_from_method {
             }
         }
     };
+    ($T:ty, $convert_func:ident, $($cql_type:ident)|+) => {
+        impl
+            $crate::frame::response::cql_to_rust::FromCqlVal<
+                $crate::frame::response::result::CqlValue,
+            > for $T
+        {
+            fn from_cql(
+                cql_val: $crate::frame::response::result::CqlValue,
+            ) -> std::result::Result<$T, $crate::frame::response::cql_to_rust::FromCqlValError>
+            {
+                cql_val
+                    .$convert_func()
+                    .ok_or($crate::frame::response::cql_to_rust::FromCqlValError::BadCqlType)
+            }
+
+            fn matches_type(typ: &$crate::frame::response::result::ColumnType) -> bool {
+                matches!(
+                    typ,
+                    $($crate::frame::response::result::ColumnType::$cql_type)|+
+                )
+            }
+        }
+    };
 }
 
-impl_from_cql_value_from_method!(i32, as_int); // i32::from_cql<CqlValue>
-impl_from_cql_value_from_method!(i64, as_bigint); // i64::from_cql<CqlValue>
-impl_from_cql_value_from_method!(Counter, as_counter); // Counter::from_cql<CqlValue>
-impl_from_cql_value_from_method!(i16, as_smallint); // i16::from_cql<CqlValue>
-impl_from_cql_value_from_method!(BigInt, into_varint); // BigInt::from_cql<CqlValue>
-impl_from_cql_value_from_method!(i8, as_tinyint); // i8::from_cql<CqlValue>
-impl_from_cql_value_from_method!(NaiveDate, as_date); // NaiveDate::from_cql<CqlValue>
-impl_from_cql_value_from_method!(f32, as_float); // f32::from_cql<CqlValue>
-impl_from_cql_value_from_method!(f64, as_double); // f64::from_cql<CqlValue>
-impl_from_cql_value_from_method!(bool, as_boolean); // bool::from_cql<CqlValue>
-impl_from_cql_value_from_method!(String, into_string); // String::from_cql<CqlValue>
-impl_from_cql_value_from_method!(Vec<u8>, into_blob); // Vec<u8>::from_cql<CqlValue>
-impl_from_cql_value_from_method!(IpAddr, as_inet); // IpAddr::from_cql<CqlValue>
-impl_from_cql_value_from_method!(Uuid, as_uuid); // Uuid::from_cql<CqlValue>
-impl_from_cql_value_from_method!(BigDecimal, into_decimal); // BigDecimal::from_cql<CqlValue>
-impl_from_cql_value_from_method!(Duration, as_duration); // Duration::from_cql<CqlValue>
-impl_from_cql_value_from_method!(CqlDuration, as_cql_duration); // CqlDuration::from_cql<CqlValue>
+// Implements FromCqlVal for native types, whose CQL types come from the tables in `type_markers`
+macro_rules! impl_from_cql_value_native {
+    ($T:ty, $convert_func:ident) => {
+        impl FromCqlVal<CqlValue> for $T {
+            fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
+                cql_val.$convert_func().ok_or(FromCqlValError::BadCqlType)
+            }
+
+            fn matches_type(typ: &ColumnType) -> bool {
+                <$T as DeserializedCqlTypes>::matches(typ)
+            }
+        }
+    };
+}
+
+impl_from_cql_value_native!(i32, as_int); // i32::from_cql<CqlValue>
+impl_from_cql_value_native!(i64, as_bigint); // i64::from_cql<CqlValue>
+impl_from_cql_value_native!(Counter, as_counter); // Counter::from_cql<CqlValue>
+impl_from_cql_value_native!(i16, as_smallint); // i16::from_cql<CqlValue>
+impl_from_cql_value_native!(CqlVarint, into_cql_varint); // CqlVarint::from_cql<CqlValue>
+impl_from_cql_value_native!(i8, as_tinyint); // i8::from_cql<CqlValue>
+impl_from_cql_value_native!(NaiveDate, as_date); // NaiveDate::from_cql<CqlValue>
+impl_from_cql_value_native!(f32, as_float); // f32::from_cql<CqlValue>
+impl_from_cql_value_native!(f64, as_double); // f64::from_cql<CqlValue>
+impl_from_cql_value_native!(bool, as_boolean); // bool::from_cql<CqlValue>
+impl_from_cql_value_native!(String, into_string); // String::from_cql<CqlValue>
+impl_from_cql_value_native!(Vec<u8>, into_blob); // Vec<u8>::from_cql<CqlValue>
+impl_from_cql_value_native!(IpAddr, as_inet); // IpAddr::from_cql<CqlValue>
+impl_from_cql_value_native!(Uuid, as_uuid); // Uuid::from_cql<CqlValue>
+impl_from_cql_value_native!(CqlTimeuuid, as_timeuuid); // CqlTimeuuid::from_cql<CqlValue>
+impl_from_cql_value_native!(CqlDecimal, into_cql_decimal); // CqlDecimal::from_cql<CqlValue>
+impl_from_cql_value_native!(Duration, as_duration); // Duration::from_cql<CqlValue>
+impl_from_cql_value_native!(CqlDuration, as_cql_duration); // CqlDuration::from_cql<CqlValue>
+
+// Types from optional crates, converted from CqlVarint and CqlDecimal
+#[cfg(any(
+    feature = "num-bigint-03",
+    feature = "num-bigint-04",
+    feature = "bigdecimal-02",
+    feature = "bigdecimal-04",
+    feature = "rust_decimal"
+))]
+macro_rules! impl_from_cql_value_via {
+    ($T:ty, $convert_func:ident) => {
+        impl FromCqlVal<CqlValue> for $T {
+            fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
+                let value = cql_val.$convert_func().ok_or(FromCqlValError::BadCqlType)?;
+                <$T>::try_from(value).map_err(|_| FromCqlValError::BadVal)
+            }
+
+            fn matches_type(typ: &ColumnType) -> bool {
+                <$T as DeserializedCqlTypes>::matches(typ)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "num-bigint-03")]
+impl_from_cql_value_via!(num_bigint_03::BigInt, into_cql_varint);
+#[cfg(feature = "num-bigint-04")]
+impl_from_cql_value_via!(num_bigint_04::BigInt, into_cql_varint);
+#[cfg(feature = "bigdecimal-02")]
+impl_from_cql_value_via!(bigdecimal_02::BigDecimal, into_cql_decimal);
+#[cfg(feature = "bigdecimal-04")]
+impl_from_cql_value_via!(bigdecimal_04::BigDecimal, into_cql_decimal);
+#[cfg(feature = "rust_decimal")]
+impl_from_cql_value_via!(rust_decimal::Decimal, into_cql_decimal);
 
 impl FromCqlVal<CqlValue> for crate::frame::value::Date {
     fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
@@ -144,6 +443,10 @@ impl FromCqlVal<CqlValue> for crate::frame::value::Date {
             _ => Err(FromCqlValError::BadCqlType),
         }
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        <Self as DeserializedCqlTypes>::matches(typ)
+    }
 }
 
 impl FromCqlVal<CqlValue> for crate::frame::value::Time {
@@ -153,6 +456,10 @@ impl FromCqlVal<CqlValue> for crate::frame::value::Time {
             _ => Err(FromCqlValError::BadCqlType),
         }
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        <Self as DeserializedCqlTypes>::matches(typ)
+    }
 }
 
 impl FromCqlVal<CqlValue> for crate::frame::value::Timestamp {
@@ -162,6 +469,10 @@ impl FromCqlVal<CqlValue> for crate::frame::value::Timestamp {
             _ => Err(FromCqlValError::BadCqlType),
         }
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        <Self as DeserializedCqlTypes>::matches(typ)
+    }
 }
 
 impl FromCqlVal<CqlValue> for DateTime<Utc> {
@@ -172,6 +483,94 @@ impl FromCqlVal<CqlValue> for DateTime<Utc> {
             _ => Err(FromCqlValError::BadVal),
         }
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        <Self as DeserializedCqlTypes>::matches(typ)
+    }
+}
+
+#[cfg(feature = "time")]
+impl FromCqlVal<CqlValue> for time::Date {
+    fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
+        use crate::frame::value::UNIX_EPOCH_JULIAN_DAY;
+
+        match cql_val {
+            CqlValue::Date(days) => {
+                let julian_day = days as i64 - (1 << 31) + UNIX_EPOCH_JULIAN_DAY;
+                i32::try_from(julian_day)
+                    .ok()
+                    .and_then(|julian_day| time::Date::from_julian_day(julian_day).ok())
+                    .ok_or(FromCqlValError::BadVal)
+            }
+            _ => Err(FromCqlValError::BadCqlType),
+        }
+    }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        <Self as DeserializedCqlTypes>::matches(typ)
+    }
+}
+
+#[cfg(feature = "time")]
+impl FromCqlVal<CqlValue> for time::Time {
+    fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
+        match cql_val {
+            CqlValue::Time(d) => {
+                let nanoseconds = d.num_nanoseconds().ok_or(FromCqlValError::BadVal)?;
+                // Time is encoded as nanoseconds since midnight, must be in 0..86399999999999
+                if !(0..86_400_000_000_000).contains(&nanoseconds) {
+                    return Err(FromCqlValError::BadVal);
+                }
+
+                let seconds = nanoseconds / 1_000_000_000;
+                time::Time::from_hms_nano(
+                    (seconds / 3600) as u8,
+                    (seconds / 60 % 60) as u8,
+                    (seconds % 60) as u8,
+                    (nanoseconds % 1_000_000_000) as u32,
+                )
+                .map_err(|_| FromCqlValError::BadVal)
+            }
+            _ => Err(FromCqlValError::BadCqlType),
+        }
+    }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        <Self as DeserializedCqlTypes>::matches(typ)
+    }
+}
+
+#[cfg(feature = "time")]
+impl FromCqlVal<CqlValue> for time::OffsetDateTime {
+    fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
+        match cql_val {
+            CqlValue::Timestamp(d) => time::OffsetDateTime::from_unix_timestamp_nanos(
+                d.num_milliseconds() as i128 * 1_000_000,
+            )
+            .map_err(|_| FromCqlValError::BadVal),
+            _ => Err(FromCqlValError::BadCqlType),
+        }
+    }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        <Self as DeserializedCqlTypes>::matches(typ)
+    }
+}
+
+/// `PrimitiveDateTime` is returned in UTC
+#[cfg(feature = "time")]
+impl FromCqlVal<CqlValue> for time::PrimitiveDateTime {
+    fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
+        let datetime = time::OffsetDateTime::from_cql(cql_val)?;
+        Ok(time::PrimitiveDateTime::new(
+            datetime.date(),
+            datetime.time(),
+        ))
+    }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        <Self as DeserializedCqlTypes>::matches(typ)
+    }
 }
 
 #[cfg(feature = "secret")]
@@ -179,6 +578,10 @@ impl<V: FromCqlVal<CqlValue> + Zeroize> FromCqlVal<CqlValue> for Secret<V> {
     fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
         Ok(Secret::new(FromCqlVal::from_cql(cql_val)?))
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        V::matches_type(typ)
+    }
 }
 
 // Vec<T>::from_cql<CqlValue>
@@ -191,6 +594,35 @@ impl<T: FromCqlVal<CqlValue>> FromCqlVal<CqlValue> for Vec<T> {
             .map(T::from_cql)
             .collect::<Result<Vec<T>, FromCqlValError>>()
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::List(elem_type)
+            | ColumnType::Set(elem_type)
+            | ColumnType::Vector {
+                element: elem_type, ..
+            } => T::matches_type(elem_type),
+            _ => false,
+        }
+    }
+}
+
+// [T; N]::from_cql<CqlValue>, used for vectors
+impl<T: FromCqlVal<CqlValue>, const N: usize> FromCqlVal<CqlValue> for [T; N] {
+    fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
+        let vec = Vec::<T>::from_cql(cql_val)?;
+        <[T; N]>::try_from(vec).map_err(|_| FromCqlValError::BadCqlType)
+    }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::Vector {
+                element,
+                dimensions,
+            } => *dimensions as usize == N && T::matches_type(element),
+            _ => false,
+        }
+    }
 }
 
 impl<T1: FromCqlVal<CqlValue> + Eq + Hash, T2: FromCqlVal<CqlValue>> FromCqlVal<CqlValue>
@@ -204,6 +636,15 @@ impl<T1: FromCqlVal<CqlValue> + Eq + Hash, T2: FromCqlVal<CqlValue>> FromCqlVal<
         }
         Ok(res)
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::Map(key_type, value_type) => {
+                T1::matches_type(key_type) && T2::matches_type(value_type)
+            }
+            _ => false,
+        }
+    }
 }
 
 impl<T: FromCqlVal<CqlValue> + Eq + Hash> FromCqlVal<CqlValue> for HashSet<T> {
@@ -215,6 +656,13 @@ impl<T: FromCqlVal<CqlValue> + Eq + Hash> FromCqlVal<CqlValue> for HashSet<T> {
             .map(T::from_cql)
             .collect::<Result<HashSet<T>, FromCqlValError>>()
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::List(elem_type) | ColumnType::Set(elem_type) => T::matches_type(elem_type),
+            _ => false,
+        }
+    }
 }
 
 impl<T: FromCqlVal<CqlValue> + Ord> FromCqlVal<CqlValue> for BTreeSet<T> {
@@ -226,6 +674,13 @@ impl<T: FromCqlVal<CqlValue> + Ord> FromCqlVal<CqlValue> for BTreeSet<T> {
             .map(T::from_cql)
             .collect::<Result<BTreeSet<T>, FromCqlValError>>()
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::List(elem_type) | ColumnType::Set(elem_type) => T::matches_type(elem_type),
+            _ => false,
+        }
+    }
 }
 
 impl<K: FromCqlVal<CqlValue> + Ord, V: FromCqlVal<CqlValue>> FromCqlVal<CqlValue>
@@ -239,6 +694,15 @@ impl<K: FromCqlVal<CqlValue> + Ord, V: FromCqlVal<CqlValue>> FromCqlVal<CqlValue
         }
         Ok(res)
     }
+
+    fn matches_type(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::Map(key_type, value_type) => {
+                K::matches_type(key_type) && V::matches_type(value_type)
+            }
+            _ => false,
+        }
+    }
 }
 
 macro_rules! replace_expr {
@@ -286,6 +750,34 @@ macro_rules! impl_tuple_from_row {
                     ,)+
                 ))
             }
+
+            fn type_check(specs: &[ColumnSpec]) -> Result<(), RowTypeCheckError> {
+                let expected_len = <[()]>::len(&[$(replace_expr!(($Ti) ())),*]);
+
+                if expected_len != specs.len() {
+                    return Err(RowTypeCheckError::WrongRowSize {
+                        expected: expected_len,
+                        actual: specs.len(),
+                    });
+                }
+                let mut specs_iter = specs.iter();
+
+                let mismatches: Vec<ColumnTypeMismatch> = [
+                    $(
+                        // specs_iter size is checked above, so it is safe to unwrap
+                        type_check_column::<$Ti>(specs_iter.next().unwrap()),
+                    )+
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+
+                if mismatches.is_empty() {
+                    Ok(())
+                } else {
+                    Err(RowTypeCheckError::ColumnTypeMismatch(mismatches))
+                }
+            }
         }
     }
 }
@@ -328,6 +820,20 @@ macro_rules! impl_tuple_from_cql {
                     ,)+
                 ))
             }
+
+            fn matches_type(typ: &ColumnType) -> bool {
+                let mut types = match typ {
+                    ColumnType::Tuple(types) => types.iter(),
+                    _ => return false,
+                };
+                $(
+                    match types.next() {
+                        Some(typ) if <$Ti as FromCqlVal<Option<CqlValue>>>::matches_type(typ) => {}
+                        _ => return false,
+                    }
+                )+
+                types.next().is_none()
+            }
         }
     }
 }
@@ -351,13 +857,16 @@ impl_tuple_from_cql!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14
 
 #[cfg(test)]
 mod tests {
-    use super::{CqlValue, FromCqlVal, FromCqlValError, FromRow, FromRowError, Row};
+    use super::{
+        CqlValue, FromCqlVal, FromCqlValError, FromRow, FromRowError, FromRowWithSpecs, Row,
+        RowTypeCheckError,
+    };
     use crate as scylla;
-    use crate::frame::value::Counter;
-    use crate::macros::FromRow;
-    use bigdecimal::BigDecimal;
+    use crate::frame::response::result::{ColumnSpec, ColumnType, TableSpec};
+    use crate::frame::value::Value;
+    use crate::frame::value::{Counter, CqlDecimal, CqlTimeuuid, CqlVarint};
+    use crate::macros::{CqlEnum, FromRow, FromUserType};
     use chrono::{Duration, NaiveDate};
-    use num_bigint::{BigInt, ToBigInt};
     use std::collections::HashSet;
     use std::net::{IpAddr, Ipv4Addr};
     use std::str::FromStr;
@@ -417,19 +926,65 @@ mod tests {
 
     #[test]
     fn varint_from_cql() {
-        let big_int = 0.to_bigint().unwrap();
+        let varint = CqlVarint::from_signed_bytes_be(vec![0x00]);
         assert_eq!(
-            Ok(big_int),
-            BigInt::from_cql(CqlValue::Varint(0.to_bigint().unwrap()))
+            Ok(varint.clone()),
+            CqlVarint::from_cql(CqlValue::Varint(varint))
         );
     }
 
     #[test]
     fn decimal_from_cql() {
-        let decimal = BigDecimal::from_str("123.4").unwrap();
+        let decimal = CqlDecimal::from_str("123.4").unwrap();
         assert_eq!(
             Ok(decimal.clone()),
-            BigDecimal::from_cql(CqlValue::Decimal(decimal))
+            CqlDecimal::from_cql(CqlValue::Decimal(decimal))
+        );
+    }
+
+    #[cfg(feature = "num-bigint-04")]
+    #[test]
+    fn num_bigint_from_cql() {
+        use num_bigint_04::BigInt;
+
+        let varint = CqlVarint::from_str("-123456789012345678901234567890").unwrap();
+        assert_eq!(
+            BigInt::from_cql(CqlValue::Varint(varint)),
+            Ok(BigInt::from_str("-123456789012345678901234567890").unwrap())
+        );
+        assert_eq!(
+            BigInt::from_cql(CqlValue::Int(1)),
+            Err(FromCqlValError::BadCqlType)
+        );
+    }
+
+    #[cfg(feature = "bigdecimal-04")]
+    #[test]
+    fn bigdecimal_from_cql() {
+        use bigdecimal_04::BigDecimal;
+
+        let decimal = CqlDecimal::from_str("-1234.5678").unwrap();
+        assert_eq!(
+            BigDecimal::from_cql(CqlValue::Decimal(decimal)),
+            Ok(BigDecimal::from_str("-1234.5678").unwrap())
+        );
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn rust_decimal_from_cql() {
+        use rust_decimal::Decimal;
+
+        let decimal = CqlDecimal::from_str("-1234.5678").unwrap();
+        assert_eq!(
+            Decimal::from_cql(CqlValue::Decimal(decimal)),
+            Ok(Decimal::from_str("-1234.5678").unwrap())
+        );
+        // Doesn't fit in the 96-bit mantissa
+        let too_big = CqlDecimal::from_str("1e40").unwrap();
+        assert_eq!(
+            Decimal::from_cql(CqlValue::Decimal(too_big)),
+            Err(FromCqlValError::BadVal)
         );
     }
 
@@ -566,7 +1121,16 @@ mod tests {
 
         assert_eq!(
             test_uuid,
-            Uuid::from_cql(CqlValue::Timeuuid(test_uuid)).unwrap()
+            Uuid::from_cql(CqlValue::Timeuuid(test_uuid.into())).unwrap()
+        );
+
+        assert_eq!(
+            CqlTimeuuid::from(test_uuid),
+            CqlTimeuuid::from_cql(CqlValue::Timeuuid(test_uuid.into())).unwrap()
+        );
+        assert_eq!(
+            CqlTimeuuid::from_cql(CqlValue::Uuid(test_uuid)),
+            Err(FromCqlValError::BadCqlType)
         );
     }
 
@@ -757,4 +1321,573 @@ mod tests {
             })
         );
     }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time_crate_from_cql() {
+        use time::macros::{date, datetime, time};
+
+        assert_eq!(
+            time::Date::from_cql(CqlValue::Date(1 << 31)),
+            Ok(date!(1970 - 01 - 01))
+        );
+        assert_eq!(
+            time::Date::from_cql(CqlValue::Date((1 << 31) - 30)),
+            Ok(date!(1969 - 12 - 02))
+        );
+        // Out of range of time::Date
+        assert_eq!(
+            time::Date::from_cql(CqlValue::Date(0)),
+            Err(FromCqlValError::BadVal)
+        );
+
+        let nanos = (3600 + 2 * 60 + 3) * 1_000_000_000 + 4;
+        assert_eq!(
+            time::Time::from_cql(CqlValue::Time(Duration::nanoseconds(nanos))),
+            Ok(time!(01:02:03.000_000_004))
+        );
+        assert_eq!(
+            time::Time::from_cql(CqlValue::Time(Duration::hours(24))),
+            Err(FromCqlValError::BadVal)
+        );
+        assert_eq!(
+            time::Time::from_cql(CqlValue::Time(Duration::nanoseconds(-1))),
+            Err(FromCqlValError::BadVal)
+        );
+
+        let timestamp = CqlValue::Timestamp(Duration::milliseconds(-1));
+        assert_eq!(
+            time::OffsetDateTime::from_cql(timestamp.clone()),
+            Ok(datetime!(1969-12-31 23:59:59.999 UTC))
+        );
+        assert_eq!(
+            time::PrimitiveDateTime::from_cql(timestamp),
+            Ok(datetime!(1969-12-31 23:59:59.999))
+        );
+        assert_eq!(
+            time::OffsetDateTime::from_cql(CqlValue::Timestamp(Duration::milliseconds(i64::MAX))),
+            Err(FromCqlValError::BadVal)
+        );
+    }
+
+    #[test]
+    fn vector_from_cql() {
+        let vector = CqlValue::Vector(vec![CqlValue::Float(0.5), CqlValue::Float(2.0)]);
+        assert_eq!(<[f32; 2]>::from_cql(vector.clone()), Ok([0.5, 2.0]));
+        assert_eq!(Vec::<f32>::from_cql(vector.clone()), Ok(vec![0.5, 2.0]));
+        assert_eq!(
+            <[f32; 3]>::from_cql(vector),
+            Err(FromCqlValError::BadCqlType)
+        );
+
+        let vector_type = ColumnType::Vector {
+            element: Box::new(ColumnType::Float),
+            dimensions: 2,
+        };
+        assert!(<[f32; 2] as FromCqlVal<CqlValue>>::matches_type(
+            &vector_type
+        ));
+        assert!(!<[f32; 3] as FromCqlVal<CqlValue>>::matches_type(
+            &vector_type
+        ));
+        assert!(<Vec<f32> as FromCqlVal<CqlValue>>::matches_type(
+            &vector_type
+        ));
+        assert!(!<Vec<i32> as FromCqlVal<CqlValue>>::matches_type(
+            &vector_type
+        ));
+    }
+
+    fn col_spec(name: &str, typ: ColumnType) -> ColumnSpec {
+        ColumnSpec {
+            table_spec: TableSpec {
+                ks_name: "ks".to_string(),
+                table_name: "tab".to_string(),
+            },
+            name: name.to_string(),
+            typ,
+        }
+    }
+
+    #[test]
+    fn row_type_check() {
+        #[derive(FromRow)]
+        struct MyRow {
+            _a: i32,
+            _b: Option<String>,
+            _c: Option<Vec<i32>>,
+        }
+
+        let specs = vec![
+            col_spec("a", ColumnType::Int),
+            col_spec("b", ColumnType::Text),
+            col_spec("c", ColumnType::Set(Box::new(ColumnType::Int))),
+        ];
+        MyRow::type_check(&specs).unwrap();
+        <(i32, String, Vec<i32>)>::type_check(&specs).unwrap();
+        <(i32, CqlValue, Option<CqlValue>)>::type_check(&specs).unwrap();
+
+        let mismatches = match <(i64, String, Vec<String>)>::type_check(&specs) {
+            Err(RowTypeCheckError::ColumnTypeMismatch(mismatches)) => mismatches,
+            res => panic!("Unexpected result: {:?}", res),
+        };
+        let columns: Vec<&str> = mismatches.iter().map(|m| m.column.as_str()).collect();
+        assert_eq!(columns, vec!["a", "c"]);
+        assert_eq!(mismatches[0].cql_type, ColumnType::Int);
+        assert_eq!(mismatches[0].rust_type, "i64");
+
+        assert_eq!(
+            <(i32, String)>::type_check(&specs),
+            Err(RowTypeCheckError::WrongRowSize {
+                expected: 2,
+                actual: 3
+            })
+        );
+        assert!(MyRow::type_check(&specs[..2]).is_err());
+    }
+
+    #[test]
+    fn struct_from_row_by_name() {
+        #[derive(FromRow, PartialEq, Eq, Debug)]
+        #[scylla(match_by_name)]
+        struct MyRow {
+            a: i32,
+            #[scylla(rename = "bee")]
+            b: Option<String>,
+            c: Option<Vec<i32>>,
+        }
+
+        let specs = vec![
+            col_spec("c", ColumnType::Set(Box::new(ColumnType::Int))),
+            col_spec("a", ColumnType::Int),
+            col_spec("bee", ColumnType::Text),
+        ];
+        let row = || Row {
+            columns: vec![
+                Some(CqlValue::Set(vec![CqlValue::Int(1), CqlValue::Int(2)])),
+                Some(CqlValue::Int(16)),
+                None,
+            ],
+        };
+
+        assert_eq!(
+            row().into_typed_with_specs::<MyRow>(&specs),
+            Ok(MyRow {
+                a: 16,
+                b: None,
+                c: Some(vec![1, 2]),
+            })
+        );
+
+        // The mapping is computed once and used for all rows of a result
+        let mapping = MyRow::map_columns(&specs).unwrap();
+        for _ in 0..2 {
+            assert_eq!(
+                MyRow::from_row_with_mapping(row(), &mapping),
+                Ok(MyRow {
+                    a: 16,
+                    b: None,
+                    c: Some(vec![1, 2]),
+                })
+            );
+        }
+        assert_eq!(
+            MyRow::from_row_with_mapping(
+                Row {
+                    columns: vec![Some(CqlValue::Int(16))]
+                },
+                &mapping
+            ),
+            Err(FromRowError::WrongRowSize {
+                expected: 3,
+                actual: 1
+            })
+        );
+
+        let mismatched_specs = vec![
+            col_spec("c", ColumnType::Set(Box::new(ColumnType::Int))),
+            col_spec("a", ColumnType::Text),
+            col_spec("bee", ColumnType::Text),
+        ];
+        let mismatches = match MyRow::map_columns(&mismatched_specs) {
+            Err(RowTypeCheckError::ColumnTypeMismatch(mismatches)) => mismatches,
+            res => panic!("Unexpected result: {:?}", res),
+        };
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].column, "a");
+    }
+
+    #[test]
+    fn struct_from_row_by_name_missing_and_extra_columns() {
+        #[derive(FromRow, PartialEq, Eq, Debug)]
+        #[scylla(match_by_name)]
+        struct MyRow {
+            a: i32,
+            b: Option<String>,
+        }
+
+        #[derive(FromRow, PartialEq, Eq, Debug)]
+        #[scylla(match_by_name, allow_extra_columns)]
+        struct MyLenientRow {
+            b: Option<String>,
+            a: i32,
+        }
+
+        let missing_specs = vec![col_spec("a", ColumnType::Int)];
+        assert_eq!(
+            MyRow::map_columns(&missing_specs),
+            Err(RowTypeCheckError::MissingColumns(vec!["b".to_string()]))
+        );
+        assert_eq!(
+            MyLenientRow::map_columns(&missing_specs),
+            Err(RowTypeCheckError::MissingColumns(vec!["b".to_string()]))
+        );
+
+        let extra_specs = vec![
+            col_spec("a", ColumnType::Int),
+            col_spec("x", ColumnType::Int),
+            col_spec("b", ColumnType::Text),
+        ];
+        let row = || Row {
+            columns: vec![
+                Some(CqlValue::Int(16)),
+                Some(CqlValue::Int(32)),
+                Some(CqlValue::Text("abc".to_string())),
+            ],
+        };
+        assert_eq!(
+            MyRow::map_columns(&extra_specs),
+            Err(RowTypeCheckError::UnexpectedColumns(vec!["x".to_string()]))
+        );
+        assert_eq!(
+            row().into_typed_with_specs::<MyRow>(&extra_specs),
+            Err(FromRowError::TypeCheckError(
+                RowTypeCheckError::UnexpectedColumns(vec!["x".to_string()])
+            ))
+        );
+        MyLenientRow::map_columns(&extra_specs).unwrap();
+        assert_eq!(
+            row().into_typed_with_specs::<MyLenientRow>(&extra_specs),
+            Ok(MyLenientRow {
+                b: Some("abc".to_string()),
+                a: 16,
+            })
+        );
+    }
+
+    #[test]
+    fn struct_from_row_field_attributes() {
+        #[derive(FromRow, PartialEq, Eq, Debug)]
+        struct Inner<T> {
+            b: T,
+            #[scylla(default)]
+            c: i32,
+        }
+
+        #[derive(FromRow, PartialEq, Eq, Debug)]
+        struct MyRow<T>(
+            i32,
+            #[scylla(skip)] Vec<String>,
+            #[scylla(flatten)] Inner<T>,
+        );
+
+        let specs = vec![
+            col_spec("a", ColumnType::Int),
+            col_spec("b", ColumnType::Text),
+            col_spec("c", ColumnType::Int),
+        ];
+        MyRow::<String>::type_check(&specs).unwrap();
+        assert!(MyRow::<i64>::type_check(&specs).is_err());
+
+        let row = Row {
+            columns: vec![
+                Some(CqlValue::Int(16)),
+                Some(CqlValue::Text("abc".to_string())),
+                None,
+            ],
+        };
+        assert_eq!(
+            MyRow::from_row(row),
+            Ok(MyRow(
+                16,
+                Vec::new(),
+                Inner {
+                    b: "abc".to_string(),
+                    c: 0
+                }
+            ))
+        );
+
+        assert_eq!(
+            MyRow::<String>::from_row(Row {
+                columns: vec![Some(CqlValue::Int(16)), None],
+            }),
+            Err(FromRowError::WrongRowSize {
+                expected: 3,
+                actual: 2
+            })
+        );
+    }
+
+    #[test]
+    fn struct_from_row_by_name_field_attributes() {
+        #[derive(FromRow, PartialEq, Eq, Debug)]
+        #[scylla(match_by_name)]
+        struct Inner {
+            #[scylla(rename = "bee")]
+            b: String,
+            #[scylla(default)]
+            c: i32,
+        }
+
+        #[derive(FromRow, PartialEq, Eq, Debug)]
+        #[scylla(match_by_name)]
+        struct MyRow {
+            #[scylla(flatten)]
+            inner: Inner,
+            a: i32,
+            #[scylla(skip)]
+            d: Option<String>,
+        }
+
+        // "c" is missing, but it has a default
+        let specs = vec![
+            col_spec("bee", ColumnType::Text),
+            col_spec("a", ColumnType::Int),
+        ];
+        MyRow::map_columns(&specs).unwrap();
+        let row = Row {
+            columns: vec![
+                Some(CqlValue::Text("abc".to_string())),
+                Some(CqlValue::Int(16)),
+            ],
+        };
+        assert_eq!(
+            row.into_typed_with_specs::<MyRow>(&specs),
+            Ok(MyRow {
+                inner: Inner {
+                    b: "abc".to_string(),
+                    c: 0,
+                },
+                a: 16,
+                d: None,
+            })
+        );
+
+        assert_eq!(
+            MyRow::map_columns(&specs[1..]),
+            Err(RowTypeCheckError::MissingColumns(vec!["bee".to_string()]))
+        );
+    }
+
+    #[test]
+    fn udt_field_attributes() {
+        #[derive(FromUserType, PartialEq, Eq, Debug)]
+        struct Inner(
+            #[scylla(rename = "b")] String,
+            #[scylla(rename = "c", default)] i32,
+        );
+
+        #[derive(FromUserType, PartialEq, Eq, Debug)]
+        struct MyUdt<T> {
+            #[scylla(rename = "type")]
+            kind: T,
+            #[scylla(skip)]
+            cache: Option<String>,
+            #[scylla(flatten)]
+            inner: Inner,
+        }
+
+        let udt = |fields: Vec<(&str, Option<CqlValue>)>| CqlValue::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "my_udt".to_string(),
+            fields: fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        };
+
+        // Fields are matched by name, unknown ones are ignored
+        assert_eq!(
+            MyUdt::from_cql(udt(vec![
+                ("d", Some(CqlValue::Int(4))),
+                ("c", Some(CqlValue::Int(3))),
+                ("b", Some(CqlValue::Text("abc".to_string()))),
+                ("type", Some(CqlValue::Int(1))),
+            ])),
+            Ok(MyUdt {
+                kind: 1,
+                cache: None,
+                inner: Inner("abc".to_string(), 3),
+            })
+        );
+        // Null and missing fields are the same for a field with a default
+        assert_eq!(
+            MyUdt::from_cql(udt(vec![
+                ("type", Some(CqlValue::Int(1))),
+                ("b", Some(CqlValue::Text("abc".to_string()))),
+                ("c", None),
+            ])),
+            Ok(MyUdt {
+                kind: 1,
+                cache: None,
+                inner: Inner("abc".to_string(), 0),
+            })
+        );
+        assert_eq!(
+            MyUdt::from_cql(udt(vec![
+                ("type", Some(CqlValue::Int(1))),
+                ("b", Some(CqlValue::Text("abc".to_string()))),
+            ])),
+            Ok(MyUdt {
+                kind: 1,
+                cache: None,
+                inner: Inner("abc".to_string(), 0),
+            })
+        );
+
+        let udt_type = ColumnType::UserDefinedType {
+            type_name: "my_udt".to_string(),
+            keyspace: "ks".to_string(),
+            field_types: vec![
+                ("type".to_string(), ColumnType::Int),
+                ("b".to_string(), ColumnType::Text),
+            ],
+        };
+        assert!(<MyUdt<i32> as FromCqlVal<CqlValue>>::matches_type(
+            &udt_type
+        ));
+        assert!(!<MyUdt<String> as FromCqlVal<CqlValue>>::matches_type(
+            &udt_type
+        ));
+    }
+
+    #[test]
+    fn cql_enum_text() {
+        #[derive(CqlEnum, Debug, PartialEq, Eq)]
+        enum Status {
+            Active,
+            #[scylla(rename = "on_hold")]
+            OnHold,
+            #[scylla(other)]
+            Unknown(String),
+        }
+
+        let text = |s: &str| CqlValue::Text(s.to_string());
+        assert_eq!(Status::from_cql(text("Active")), Ok(Status::Active));
+        assert_eq!(Status::from_cql(text("on_hold")), Ok(Status::OnHold));
+        assert_eq!(
+            Status::from_cql(text("OnHold")),
+            Ok(Status::Unknown("OnHold".to_string()))
+        );
+        assert_eq!(
+            Status::from_cql(CqlValue::Int(1)),
+            Err(FromCqlValError::BadCqlType)
+        );
+
+        let mut buf = Vec::new();
+        Status::OnHold.serialize(&mut buf).unwrap();
+        Status::Unknown("x".to_string())
+            .serialize(&mut buf)
+            .unwrap();
+        let mut expected = Vec::new();
+        "on_hold".serialize(&mut expected).unwrap();
+        "x".serialize(&mut expected).unwrap();
+        assert_eq!(buf, expected);
+
+        assert!(<Status as FromCqlVal<CqlValue>>::matches_type(
+            &ColumnType::Ascii
+        ));
+        assert!(Status::Active.matches_type(&ColumnType::Text));
+        assert!(!Status::Active.matches_type(&ColumnType::Int));
+    }
+
+    #[test]
+    fn cql_enum_int() {
+        #[derive(CqlEnum, Debug, PartialEq, Eq, Clone, Copy)]
+        #[scylla(repr = "smallint")]
+        enum Priority {
+            Low = 1,
+            Medium,
+            #[scylla(rename = 10)]
+            High,
+            Urgent,
+        }
+
+        for (priority, value) in [
+            (Priority::Low, 1),
+            (Priority::Medium, 2),
+            (Priority::High, 10),
+            (Priority::Urgent, 11),
+        ] {
+            assert_eq!(Priority::from_cql(CqlValue::SmallInt(value)), Ok(priority));
+
+            let mut buf = Vec::new();
+            priority.serialize(&mut buf).unwrap();
+            let mut expected = Vec::new();
+            value.serialize(&mut expected).unwrap();
+            assert_eq!(buf, expected);
+        }
+
+        assert_eq!(
+            Priority::from_cql(CqlValue::SmallInt(3)),
+            Err(FromCqlValError::BadVal)
+        );
+        assert!(<Priority as FromCqlVal<CqlValue>>::matches_type(
+            &ColumnType::SmallInt
+        ));
+        assert!(!<Priority as FromCqlVal<CqlValue>>::matches_type(
+            &ColumnType::Int
+        ));
+
+        // Values given by constants are checked for duplicates when they are evaluated
+        const FIRST_LEVEL: i32 = 5;
+        #[derive(CqlEnum, Debug, PartialEq, Eq)]
+        #[scylla(repr = "int")]
+        #[repr(i32)]
+        enum Level {
+            First = FIRST_LEVEL,
+            Second,
+        }
+        assert_eq!(Level::from_cql(CqlValue::Int(6)), Ok(Level::Second));
+    }
+
+    #[test]
+    fn udt_matches_type() {
+        #[derive(FromUserType)]
+        struct MyUdt {
+            _a: i32,
+            _b: Option<String>,
+        }
+
+        let udt_type = |field_types: Vec<(&str, ColumnType)>| ColumnType::UserDefinedType {
+            type_name: "my_udt".to_string(),
+            keyspace: "ks".to_string(),
+            field_types: field_types
+                .into_iter()
+                .map(|(name, typ)| (name.to_string(), typ))
+                .collect(),
+        };
+
+        assert!(<MyUdt as FromCqlVal<CqlValue>>::matches_type(&udt_type(
+            vec![("_a", ColumnType::Int), ("_b", ColumnType::Text)]
+        )));
+        assert!(<MyUdt as FromCqlVal<CqlValue>>::matches_type(&udt_type(
+            vec![("_a", ColumnType::Int)]
+        )));
+        assert!(!<MyUdt as FromCqlVal<CqlValue>>::matches_type(&udt_type(
+            vec![("_a", ColumnType::Text)]
+        )));
+        // Fields added to the UDT later don't break reading it
+        assert!(<MyUdt as FromCqlVal<CqlValue>>::matches_type(&udt_type(
+            vec![
+                ("_c", ColumnType::Int),
+                ("_b", ColumnType::Text),
+                ("_a", ColumnType::Int)
+            ]
+        )));
+        assert!(!<MyUdt as FromCqlVal<CqlValue>>::matches_type(
+            &ColumnType::Int
+        ));
+    }
 }
@@ -0,0 +1,1086 @@
+//! Deserialization of rows straight from the bytes of a response.
+//!
+//! [`FromRow`](super::cql_to_rust::FromRow) and [`FromCqlVal`] work on rows which were
+//! already parsed into [`CqlValue`]s, so every cell is allocated twice before it reaches
+//! the user. The traits in this module read values directly from the serialized rows
+//! instead. Values can also borrow from the response, e.g. as `&'frame str`
+//! or `&'frame [u8]`, which avoids copying them at all.
+
+use super::cql_to_rust::{FromCqlVal, FromCqlValError};
+use super::result::{
+    deser_cql_value, vector_element_fixed_length, ColumnSpec, ColumnType, CqlValue, Row,
+};
+use crate::frame::frame_errors::ParseError;
+use crate::frame::type_markers::DeserializedCqlTypes;
+use crate::frame::types;
+use crate::frame::value::{Counter, CqlDecimal, CqlDuration, CqlTimeuuid, CqlVarint};
+use bytes::{Buf, Bytes, BytesMut};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::TryInto;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// A part of a response frame, along with the [`Bytes`] object holding the whole frame.
+///
+/// Allows to borrow from the frame, or to obtain an owned [`Bytes`] object
+/// pointing into the frame without copying anything.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameSlice<'frame> {
+    mem: &'frame [u8],
+    frame: &'frame Bytes,
+}
+
+impl<'frame> FrameSlice<'frame> {
+    /// Creates a slice covering the whole frame.
+    pub fn new(frame: &'frame Bytes) -> Self {
+        Self {
+            mem: frame.as_ref(),
+            frame,
+        }
+    }
+
+    /// Returns the bytes of this slice, borrowed from the frame.
+    pub fn as_slice(&self) -> &'frame [u8] {
+        self.mem
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mem.is_empty()
+    }
+
+    /// Returns an owned [`Bytes`] object with the contents of this slice.
+    /// The frame is shared, not copied.
+    pub fn to_bytes(&self) -> Bytes {
+        self.frame.slice_ref(self.mem)
+    }
+
+    // Reads a [bytes] value from the front of the slice
+    fn read_cql_bytes(&mut self) -> Result<Option<FrameSlice<'frame>>, ParseError> {
+        Ok(types::read_bytes_opt(&mut self.mem)?.map(|mem| FrameSlice {
+            mem,
+            frame: self.frame,
+        }))
+    }
+
+    // Reads a [bytes] value which can't be null
+    fn read_non_null_cql_bytes(&mut self) -> Result<FrameSlice<'frame>, ParseError> {
+        let mem = types::read_bytes(&mut self.mem)?;
+        Ok(FrameSlice {
+            mem,
+            frame: self.frame,
+        })
+    }
+
+    // Reads an element of a vector, which is prefixed with its length
+    // as unsigned vint unless all elements have the same `fixed_length`
+    fn read_vector_element(
+        &mut self,
+        fixed_length: Option<usize>,
+    ) -> Result<FrameSlice<'frame>, ParseError> {
+        let len = match fixed_length {
+            Some(len) => len,
+            None => types::unsigned_vint_decode(&mut self.mem)?.try_into()?,
+        };
+        if self.mem.len() < len {
+            return Err(ParseError::BadIncomingData(format!(
+                "Not enough bytes for a vector element: expected {}, got {}",
+                len,
+                self.mem.len()
+            )));
+        }
+        let (mem, rest) = self.mem.split_at(len);
+        self.mem = rest;
+        Ok(FrameSlice {
+            mem,
+            frame: self.frame,
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DeserializationError {
+    #[error("CQL type {cql_type:?} can't be deserialized as {rust_type}")]
+    TypeMismatch {
+        rust_type: &'static str,
+        cql_type: ColumnType,
+    },
+    #[error("Value is null")]
+    UnexpectedNull,
+    #[error("Wrong row size: expected {expected}, actual {actual}")]
+    WrongRowSize { expected: usize, actual: usize },
+    #[error("No column {column} in a row with {columns_count} columns")]
+    NoSuchColumn {
+        column: String,
+        columns_count: usize,
+    },
+    #[error("{err} in the column {name} with index {column}")]
+    InColumn {
+        err: Box<DeserializationError>,
+        column: usize,
+        name: String,
+    },
+    #[error("Bad value: {0}")]
+    BadValue(#[from] FromCqlValError),
+    #[error(transparent)]
+    ParseError(#[from] ParseError),
+}
+
+impl DeserializationError {
+    fn type_mismatch<T>(cql_type: &ColumnType) -> Self {
+        DeserializationError::TypeMismatch {
+            rust_type: std::any::type_name::<T>(),
+            cql_type: cql_type.clone(),
+        }
+    }
+}
+
+/// A type which can be deserialized from a single CQL value.
+///
+/// `v` is `None` if the value is null.
+pub trait DeserializeCql<'frame>: Sized {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError>;
+}
+
+/// A type which can be deserialized from a whole row.
+pub trait DeserializeRow<'frame>: Sized {
+    fn deserialize(row: ColumnIterator<'frame>) -> Result<Self, DeserializationError>;
+}
+
+fn non_null(v: Option<FrameSlice<'_>>) -> Result<FrameSlice<'_>, DeserializationError> {
+    v.ok_or(DeserializationError::UnexpectedNull)
+}
+
+fn fixed_size<'frame, const N: usize>(
+    v: Option<FrameSlice<'frame>>,
+) -> Result<&'frame [u8; N], DeserializationError> {
+    let v = non_null(v)?.as_slice();
+    v.try_into().map_err(|_| {
+        ParseError::BadIncomingData(format!("Buffer length should be {} not {}", N, v.len())).into()
+    })
+}
+
+// The accepted CQL types come from the same table as `FromCqlVal::matches_type`
+macro_rules! impl_deserialize_fixed_size {
+    ($T:ty, $convert:expr) => {
+        impl<'frame> DeserializeCql<'frame> for $T {
+            fn deserialize(
+                typ: &'frame ColumnType,
+                v: Option<FrameSlice<'frame>>,
+            ) -> Result<Self, DeserializationError> {
+                if !<$T as DeserializedCqlTypes>::matches(typ) {
+                    return Err(DeserializationError::type_mismatch::<Self>(typ));
+                }
+                Ok($convert(*fixed_size(v)?))
+            }
+        }
+    };
+}
+
+impl_deserialize_fixed_size!(bool, |b: [u8; 1]| b[0] != 0x00);
+impl_deserialize_fixed_size!(i8, i8::from_be_bytes);
+impl_deserialize_fixed_size!(i16, i16::from_be_bytes);
+impl_deserialize_fixed_size!(i32, i32::from_be_bytes);
+// Timestamps are milliseconds since the epoch, stored like a bigint
+impl_deserialize_fixed_size!(i64, i64::from_be_bytes);
+impl_deserialize_fixed_size!(f32, f32::from_be_bytes);
+impl_deserialize_fixed_size!(f64, f64::from_be_bytes);
+impl_deserialize_fixed_size!(Counter, |b| Counter(i64::from_be_bytes(b)));
+impl_deserialize_fixed_size!(Uuid, Uuid::from_bytes);
+impl_deserialize_fixed_size!(CqlTimeuuid, CqlTimeuuid::from_bytes);
+
+impl<'frame> DeserializeCql<'frame> for &'frame str {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        if !matches!(typ, ColumnType::Ascii | ColumnType::Text) {
+            return Err(DeserializationError::type_mismatch::<Self>(typ));
+        }
+        let v = non_null(v)?.as_slice();
+        if matches!(typ, ColumnType::Ascii) && !v.is_ascii() {
+            return Err(ParseError::BadIncomingData("String is not ascii!".to_string()).into());
+        }
+        Ok(std::str::from_utf8(v).map_err(ParseError::from)?)
+    }
+}
+
+impl<'frame> DeserializeCql<'frame> for String {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        if !matches!(typ, ColumnType::Ascii | ColumnType::Text) {
+            return Err(DeserializationError::type_mismatch::<Self>(typ));
+        }
+        <&str>::deserialize(typ, v).map(str::to_owned)
+    }
+}
+
+impl<'frame> DeserializeCql<'frame> for &'frame [u8] {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        match typ {
+            ColumnType::Blob => Ok(non_null(v)?.as_slice()),
+            _ => Err(DeserializationError::type_mismatch::<Self>(typ)),
+        }
+    }
+}
+
+impl<'frame> DeserializeCql<'frame> for Vec<u8> {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        match typ {
+            ColumnType::Blob => Ok(non_null(v)?.as_slice().to_vec()),
+            _ => Err(DeserializationError::type_mismatch::<Self>(typ)),
+        }
+    }
+}
+
+impl<'frame> DeserializeCql<'frame> for Bytes {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        match typ {
+            ColumnType::Blob => Ok(non_null(v)?.to_bytes()),
+            _ => Err(DeserializationError::type_mismatch::<Self>(typ)),
+        }
+    }
+}
+
+impl<'frame> DeserializeCql<'frame> for IpAddr {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        if !matches!(typ, ColumnType::Inet) {
+            return Err(DeserializationError::type_mismatch::<Self>(typ));
+        }
+        let v = non_null(v)?.as_slice();
+        if let Ok(v4) = <[u8; 4]>::try_from(v) {
+            Ok(IpAddr::from(v4))
+        } else if let Ok(v6) = <[u8; 16]>::try_from(v) {
+            Ok(IpAddr::from(v6))
+        } else {
+            Err(
+                ParseError::BadIncomingData(format!("Invalid inet bytes length: {}", v.len()))
+                    .into(),
+            )
+        }
+    }
+}
+
+impl<'frame> DeserializeCql<'frame> for CqlValue {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        let mut v = non_null(v)?.as_slice();
+        Ok(deser_cql_value(typ, &mut v)?)
+    }
+}
+
+// Types which need more than a few bytes of the value to be built are converted
+// from CqlValue, using the same rules as FromCqlVal. Matching the type first
+// makes sure that a mismatch is reported before any parsing happens.
+macro_rules! impl_deserialize_via_cql_value {
+    ($T:ty) => {
+        impl<'frame> DeserializeCql<'frame> for $T {
+            fn deserialize(
+                typ: &'frame ColumnType,
+                v: Option<FrameSlice<'frame>>,
+            ) -> Result<Self, DeserializationError> {
+                if !<$T as DeserializedCqlTypes>::matches(typ) {
+                    return Err(DeserializationError::type_mismatch::<Self>(typ));
+                }
+                let value = CqlValue::deserialize(typ, v)?;
+                Ok(<$T as FromCqlVal<CqlValue>>::from_cql(value)?)
+            }
+        }
+    };
+}
+
+impl_deserialize_via_cql_value!(CqlVarint);
+impl_deserialize_via_cql_value!(CqlDecimal);
+#[cfg(feature = "num-bigint-03")]
+impl_deserialize_via_cql_value!(num_bigint_03::BigInt);
+#[cfg(feature = "num-bigint-04")]
+impl_deserialize_via_cql_value!(num_bigint_04::BigInt);
+#[cfg(feature = "bigdecimal-02")]
+impl_deserialize_via_cql_value!(bigdecimal_02::BigDecimal);
+#[cfg(feature = "bigdecimal-04")]
+impl_deserialize_via_cql_value!(bigdecimal_04::BigDecimal);
+#[cfg(feature = "rust_decimal")]
+impl_deserialize_via_cql_value!(rust_decimal::Decimal);
+impl_deserialize_via_cql_value!(NaiveDate);
+impl_deserialize_via_cql_value!(CqlDuration);
+impl_deserialize_via_cql_value!(chrono::Duration);
+impl_deserialize_via_cql_value!(crate::frame::value::Date);
+impl_deserialize_via_cql_value!(crate::frame::value::Time);
+impl_deserialize_via_cql_value!(crate::frame::value::Timestamp);
+impl_deserialize_via_cql_value!(DateTime<Utc>);
+
+// Null and empty values are both deserialized as None,
+// same as in the FromCqlVal implementation for Option
+impl<'frame, T: DeserializeCql<'frame>> DeserializeCql<'frame> for Option<T> {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        match v {
+            None => Ok(None),
+            Some(v)
+                if v.is_empty()
+                    && !matches!(typ, ColumnType::Ascii | ColumnType::Blob | ColumnType::Text) =>
+            {
+                Ok(None)
+            }
+            Some(v) => Ok(Some(T::deserialize(typ, Some(v))?)),
+        }
+    }
+}
+
+// Calls `f` for each element of a serialized list or set
+fn for_each_element<'frame>(
+    v: Option<FrameSlice<'frame>>,
+    mut f: impl FnMut(FrameSlice<'frame>) -> Result<(), DeserializationError>,
+) -> Result<(), DeserializationError> {
+    let mut v = non_null(v)?;
+    let len = types::read_int_length(&mut v.mem)?;
+    for _ in 0..len {
+        f(v.read_non_null_cql_bytes()?)?;
+    }
+    Ok(())
+}
+
+macro_rules! impl_deserialize_collection {
+    ($C:ident, [$($bound:path),*], $add:ident) => {
+        impl<'frame, T> DeserializeCql<'frame> for $C<T>
+        where
+            T: DeserializeCql<'frame> $(+ $bound)*,
+        {
+            fn deserialize(
+                typ: &'frame ColumnType,
+                v: Option<FrameSlice<'frame>>,
+            ) -> Result<Self, DeserializationError> {
+                let elem_type = match typ {
+                    ColumnType::List(elem_type) | ColumnType::Set(elem_type) => elem_type,
+                    _ => return Err(DeserializationError::type_mismatch::<Self>(typ)),
+                };
+                let mut res = $C::new();
+                for_each_element(v, |elem| {
+                    res.$add(T::deserialize(elem_type, Some(elem))?);
+                    Ok(())
+                })?;
+                Ok(res)
+            }
+        }
+    };
+}
+
+// Calls `f` for each element of a serialized vector of `dimensions` elements of type `elem_type`
+fn for_each_vector_element<'frame>(
+    elem_type: &ColumnType,
+    dimensions: u16,
+    v: Option<FrameSlice<'frame>>,
+    mut f: impl FnMut(FrameSlice<'frame>) -> Result<(), DeserializationError>,
+) -> Result<(), DeserializationError> {
+    let mut v = non_null(v)?;
+    let fixed_length = vector_element_fixed_length(elem_type);
+    for _ in 0..dimensions {
+        f(v.read_vector_element(fixed_length)?)?;
+    }
+    Ok(())
+}
+
+// Vec<u8> is deserialized from a blob, as u8 itself doesn't implement DeserializeCql.
+// Vec is also deserialized from a vector, same as in the FromCqlVal implementation.
+impl<'frame, T: DeserializeCql<'frame>> DeserializeCql<'frame> for Vec<T> {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        let mut res = Vec::new();
+        match typ {
+            ColumnType::List(elem_type) | ColumnType::Set(elem_type) => {
+                for_each_element(v, |elem| {
+                    res.push(T::deserialize(elem_type, Some(elem))?);
+                    Ok(())
+                })?;
+            }
+            ColumnType::Vector {
+                element,
+                dimensions,
+            } => {
+                res.reserve(*dimensions as usize);
+                for_each_vector_element(element, *dimensions, v, |elem| {
+                    res.push(T::deserialize(element, Some(elem))?);
+                    Ok(())
+                })?;
+            }
+            _ => return Err(DeserializationError::type_mismatch::<Self>(typ)),
+        }
+        Ok(res)
+    }
+}
+
+// Arrays are deserialized from vectors of the same length, e.g. `[f32; 3]` from `vector<float, 3>`
+impl<'frame, T: DeserializeCql<'frame>, const N: usize> DeserializeCql<'frame> for [T; N] {
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        match typ {
+            ColumnType::Vector { dimensions, .. } if *dimensions as usize == N => {
+                let elements = Vec::<T>::deserialize(typ, v)?;
+                // The length was checked above
+                Ok(elements.try_into().ok().unwrap())
+            }
+            _ => Err(DeserializationError::type_mismatch::<Self>(typ)),
+        }
+    }
+}
+
+impl_deserialize_collection!(HashSet, [Eq, Hash], insert);
+impl_deserialize_collection!(BTreeSet, [Ord], insert);
+
+// Calls `f` for each key and value of a serialized map
+fn for_each_entry<'frame>(
+    v: Option<FrameSlice<'frame>>,
+    mut f: impl FnMut(FrameSlice<'frame>, FrameSlice<'frame>) -> Result<(), DeserializationError>,
+) -> Result<(), DeserializationError> {
+    let mut v = non_null(v)?;
+    let len = types::read_int_length(&mut v.mem)?;
+    for _ in 0..len {
+        let key = v.read_non_null_cql_bytes()?;
+        let value = v.read_non_null_cql_bytes()?;
+        f(key, value)?;
+    }
+    Ok(())
+}
+
+macro_rules! impl_deserialize_map {
+    ($M:ident, $($key_bound:path),+) => {
+        impl<'frame, K, V> DeserializeCql<'frame> for $M<K, V>
+        where
+            K: DeserializeCql<'frame> $(+ $key_bound)+,
+            V: DeserializeCql<'frame>,
+        {
+            fn deserialize(
+                typ: &'frame ColumnType,
+                v: Option<FrameSlice<'frame>>,
+            ) -> Result<Self, DeserializationError> {
+                let (key_type, value_type) = match typ {
+                    ColumnType::Map(key_type, value_type) => (key_type, value_type),
+                    _ => return Err(DeserializationError::type_mismatch::<Self>(typ)),
+                };
+                let mut res = $M::new();
+                for_each_entry(v, |key, value| {
+                    res.insert(
+                        K::deserialize(key_type, Some(key))?,
+                        V::deserialize(value_type, Some(value))?,
+                    );
+                    Ok(())
+                })?;
+                Ok(res)
+            }
+        }
+    };
+}
+
+impl_deserialize_map!(HashMap, Eq, Hash);
+impl_deserialize_map!(BTreeMap, Ord);
+
+/// A single serialized column of a row.
+#[derive(Clone, Copy, Debug)]
+pub struct RawColumn<'frame> {
+    pub index: usize,
+    pub spec: &'frame ColumnSpec,
+    /// The serialized value, `None` if it is null
+    pub slice: Option<FrameSlice<'frame>>,
+}
+
+/// Iterates over the serialized columns of a single row.
+#[derive(Clone, Debug)]
+pub struct ColumnIterator<'frame> {
+    specs: std::iter::Enumerate<std::slice::Iter<'frame, ColumnSpec>>,
+    slice: FrameSlice<'frame>,
+}
+
+impl<'frame> ColumnIterator<'frame> {
+    /// Creates an iterator over a row serialized in `slice`,
+    /// which has columns described by `specs`.
+    pub fn new(specs: &'frame [ColumnSpec], slice: FrameSlice<'frame>) -> Self {
+        Self {
+            specs: specs.iter().enumerate(),
+            slice,
+        }
+    }
+
+    /// Returns the number of columns which were not read yet.
+    pub fn columns_remaining(&self) -> usize {
+        self.specs.len()
+    }
+}
+
+impl<'frame> Iterator for ColumnIterator<'frame> {
+    type Item = Result<RawColumn<'frame>, DeserializationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, spec) = self.specs.next()?;
+        Some(
+            self.slice
+                .read_cql_bytes()
+                .map(|slice| RawColumn { index, spec, slice })
+                .map_err(Into::into),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.specs.size_hint()
+    }
+}
+
+impl<'frame> RawColumn<'frame> {
+    /// Deserializes the value of the column as the given type.
+    /// The column is mentioned in the returned error.
+    pub fn deserialize<T: DeserializeCql<'frame>>(self) -> Result<T, DeserializationError> {
+        T::deserialize(&self.spec.typ, self.slice).map_err(|err| DeserializationError::InColumn {
+            err: Box::new(err),
+            column: self.index,
+            name: self.spec.name.clone(),
+        })
+    }
+}
+
+// Unlike `Option<CqlValue>`, an empty value is kept as `CqlValue::Empty`, as in `Row`s parsed eagerly
+impl<'frame> DeserializeRow<'frame> for Row {
+    fn deserialize(row: ColumnIterator<'frame>) -> Result<Self, DeserializationError> {
+        let columns = row
+            .map(|column| {
+                let column = column?;
+                match column.slice {
+                    Some(_) => column.deserialize::<CqlValue>().map(Some),
+                    None => Ok(None),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Row { columns })
+    }
+}
+
+macro_rules! impl_tuple_deserialize_row {
+    ( $($Ti:ident),+ ) => {
+        impl<'frame, $($Ti),+> DeserializeRow<'frame> for ($($Ti,)+)
+        where
+            $($Ti: DeserializeCql<'frame>),+
+        {
+            fn deserialize(mut row: ColumnIterator<'frame>) -> Result<Self, DeserializationError> {
+                let expected = <[()]>::len(&[$(replace_expr!(($Ti) ())),*]);
+                if row.columns_remaining() != expected {
+                    return Err(DeserializationError::WrongRowSize {
+                        expected,
+                        actual: row.columns_remaining(),
+                    });
+                }
+
+                Ok((
+                    $(
+                        // The number of columns was checked above, so this can't panic
+                        row.next().unwrap()?.deserialize::<$Ti>()?,
+                    )+
+                ))
+            }
+        }
+    }
+}
+
+macro_rules! replace_expr {
+    ($_t:tt $sub:expr) => {
+        $sub
+    };
+}
+
+impl_tuple_deserialize_row!(T1);
+impl_tuple_deserialize_row!(T1, T2);
+impl_tuple_deserialize_row!(T1, T2, T3);
+impl_tuple_deserialize_row!(T1, T2, T3, T4);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6, T7);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6, T7, T8);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15);
+impl_tuple_deserialize_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16);
+
+/// A row whose columns are deserialized only when they are accessed.
+#[derive(Clone, Debug)]
+pub struct LazyRow<'frame> {
+    columns: ColumnIterator<'frame>,
+}
+
+impl<'frame> LazyRow<'frame> {
+    pub fn columns_count(&self) -> usize {
+        self.columns.columns_remaining()
+    }
+
+    /// Returns an iterator over the serialized columns of the row.
+    pub fn columns(&self) -> ColumnIterator<'frame> {
+        self.columns.clone()
+    }
+
+    /// Deserializes the column with the given index as `T`.
+    /// Other columns are skipped without being deserialized.
+    pub fn column<T: DeserializeCql<'frame>>(
+        &self,
+        index: usize,
+    ) -> Result<T, DeserializationError> {
+        match self.columns().nth(index) {
+            Some(column) => column?.deserialize(),
+            None => Err(DeserializationError::NoSuchColumn {
+                column: index.to_string(),
+                columns_count: self.columns_count(),
+            }),
+        }
+    }
+
+    /// Deserializes the column with the given name as `T`.
+    /// Other columns are skipped without being deserialized.
+    pub fn column_by_name<T: DeserializeCql<'frame>>(
+        &self,
+        name: &str,
+    ) -> Result<T, DeserializationError> {
+        for column in self.columns() {
+            let column = column?;
+            if column.spec.name == name {
+                return column.deserialize();
+            }
+        }
+        Err(DeserializationError::NoSuchColumn {
+            column: name.to_string(),
+            columns_count: self.columns_count(),
+        })
+    }
+
+    /// Deserializes the whole row as the given type.
+    pub fn deserialize<RowT: DeserializeRow<'frame>>(&self) -> Result<RowT, DeserializationError> {
+        RowT::deserialize(self.columns())
+    }
+
+    /// Deserializes all columns of the row into a [`Row`].
+    pub fn into_row(self) -> Result<Row, DeserializationError> {
+        <Row as DeserializeRow>::deserialize(self.columns)
+    }
+}
+
+impl<'frame> DeserializeRow<'frame> for LazyRow<'frame> {
+    fn deserialize(row: ColumnIterator<'frame>) -> Result<Self, DeserializationError> {
+        Ok(LazyRow { columns: row })
+    }
+}
+
+/// Rows of a result in their serialized form.
+#[derive(Clone, Debug, Default)]
+pub struct RawRows {
+    rows_count: usize,
+    raw: Bytes,
+}
+
+impl RawRows {
+    /// Wraps `rows_count` rows serialized one after another in `raw`.
+    pub fn new(rows_count: usize, raw: Bytes) -> Self {
+        Self { rows_count, raw }
+    }
+
+    pub fn rows_count(&self) -> usize {
+        self.rows_count
+    }
+
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.raw
+    }
+
+    /// Returns an iterator which deserializes the rows as the given type.
+    /// `specs` must describe the columns of the rows.
+    pub fn rows<'frame, RowT: DeserializeRow<'frame>>(
+        &'frame self,
+        specs: &'frame [ColumnSpec],
+    ) -> TypedRowsIterator<'frame, RowT> {
+        TypedRowsIterator::new(specs, self.rows_count, FrameSlice::new(&self.raw))
+    }
+
+    /// Concatenates rows of several pages with the same columns.
+    /// Each page is copied once, a single page is not copied at all.
+    pub fn concat(pages: &[RawRows]) -> RawRows {
+        if let [page] = pages {
+            return page.clone();
+        }
+
+        let mut raw = BytesMut::with_capacity(pages.iter().map(|page| page.raw.len()).sum());
+        for page in pages {
+            raw.extend_from_slice(&page.raw);
+        }
+        RawRows {
+            rows_count: pages.iter().map(|page| page.rows_count).sum(),
+            raw: raw.freeze(),
+        }
+    }
+}
+
+/// Iterates over serialized rows, deserializing each of them as `RowT`.
+#[derive(Debug)]
+pub struct TypedRowsIterator<'frame, RowT> {
+    specs: &'frame [ColumnSpec],
+    rows_remaining: usize,
+    slice: FrameSlice<'frame>,
+    phantom_data: PhantomData<RowT>,
+}
+
+impl<'frame, RowT: DeserializeRow<'frame>> TypedRowsIterator<'frame, RowT> {
+    /// Creates an iterator over `rows_count` rows serialized in `slice`.
+    pub fn new(specs: &'frame [ColumnSpec], rows_count: usize, slice: FrameSlice<'frame>) -> Self {
+        Self {
+            specs,
+            rows_remaining: rows_count,
+            slice,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Returns the part of the rows which was not read yet.
+    pub fn remaining_slice(&self) -> FrameSlice<'frame> {
+        self.slice
+    }
+
+    // Finds where the next row ends, so that the iterator can move past it
+    // regardless of how much of it was read by `RowT`
+    fn split_row(&mut self) -> Result<FrameSlice<'frame>, ParseError> {
+        let mut rest = self.slice.mem;
+        for _ in self.specs {
+            types::read_bytes_opt(&mut rest)?;
+        }
+        let row_len = self.slice.mem.len() - rest.len();
+        let row = FrameSlice {
+            mem: &self.slice.mem[..row_len],
+            frame: self.slice.frame,
+        };
+        self.slice.mem.advance(row_len);
+        Ok(row)
+    }
+}
+
+impl<'frame, RowT: DeserializeRow<'frame>> Iterator for TypedRowsIterator<'frame, RowT> {
+    type Item = Result<RowT, DeserializationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rows_remaining == 0 {
+            return None;
+        }
+        self.rows_remaining -= 1;
+
+        let row = match self.split_row() {
+            Ok(row) => row,
+            Err(err) => {
+                // The rest of the rows can't be located anymore
+                self.rows_remaining = 0;
+                return Some(Err(err.into()));
+            }
+        };
+        Some(RowT::deserialize(ColumnIterator::new(self.specs, row)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.rows_remaining, Some(self.rows_remaining))
+    }
+}
+
+impl<'frame, RowT: DeserializeRow<'frame>> ExactSizeIterator for TypedRowsIterator<'frame, RowT> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::response::result::TableSpec;
+    use crate::frame::value::Value;
+    use bytes::BufMut;
+
+    fn spec(name: &str, typ: ColumnType) -> ColumnSpec {
+        ColumnSpec {
+            table_spec: TableSpec {
+                ks_name: "ks".to_string(),
+                table_name: "tab".to_string(),
+            },
+            name: name.to_string(),
+            typ,
+        }
+    }
+
+    fn serialize_cells(cells: &[Option<&[u8]>]) -> Bytes {
+        let mut buf = Vec::new();
+        for cell in cells {
+            match cell {
+                Some(cell) => types::write_bytes(cell, &mut buf).unwrap(),
+                None => buf.put_i32(-1),
+            }
+        }
+        buf.into()
+    }
+
+    #[test]
+    fn test_deserialize_borrowed_row() {
+        let specs = vec![
+            spec("a", ColumnType::Int),
+            spec("b", ColumnType::Text),
+            spec("c", ColumnType::Blob),
+            spec("d", ColumnType::BigInt),
+        ];
+        let raw = RawRows::new(
+            2,
+            serialize_cells(&[
+                Some(&7_i32.to_be_bytes()),
+                Some(b"seven"),
+                Some(&[7, 7]),
+                None,
+                Some(&8_i32.to_be_bytes()),
+                Some(b"eight"),
+                Some(&[]),
+                Some(&8_i64.to_be_bytes()),
+            ]),
+        );
+
+        let rows = raw
+            .rows::<(i32, &str, &[u8], Option<i64>)>(&specs)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (7, "seven", &[7, 7][..], None),
+                (8, "eight", &[][..], Some(8))
+            ]
+        );
+
+        let rows = raw
+            .rows::<Row>(&specs)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rows[0].columns[0], Some(CqlValue::Int(7)));
+        assert_eq!(rows[1].columns[3], Some(CqlValue::BigInt(8)));
+    }
+
+    #[test]
+    fn test_deserialize_empty_cell() {
+        let specs = vec![spec("a", ColumnType::Int), spec("b", ColumnType::Int)];
+        let raw = RawRows::new(1, serialize_cells(&[Some(&[]), None]));
+
+        let rows = raw
+            .rows::<Row>(&specs)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rows[0].columns, vec![Some(CqlValue::Empty), None]);
+
+        // Typed values treat empty values like nulls
+        let rows = raw
+            .rows::<(Option<i32>, Option<i32>)>(&specs)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rows, vec![(None, None)]);
+    }
+
+    #[test]
+    fn test_deserialize_timestamp_as_i64() {
+        let specs = vec![spec("a", ColumnType::Timestamp)];
+        let raw = RawRows::new(1, serialize_cells(&[Some(&1_000_i64.to_be_bytes())]));
+        let rows = raw
+            .rows::<(i64,)>(&specs)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rows, vec![(1_000,)]);
+    }
+
+    // Serializes `value` and deserializes it as a single cell of type `typ`
+    fn round_trip<T>(value: impl Value, typ: ColumnType) -> T
+    where
+        T: for<'frame> DeserializeCql<'frame>,
+    {
+        let mut cell = Vec::new();
+        value.serialize(&mut cell).unwrap();
+        let specs = [spec("v", typ)];
+        let raw = RawRows::new(1, cell.into());
+        let mut rows = raw.rows::<(T,)>(&specs);
+        let (res,) = rows.next().unwrap().unwrap();
+        res
+    }
+
+    #[test]
+    fn test_vector_round_trip() {
+        let float_vector = |dimensions| ColumnType::Vector {
+            element: Box::new(ColumnType::Float),
+            dimensions,
+        };
+        let nested_vector = ColumnType::Vector {
+            element: Box::new(float_vector(2)),
+            dimensions: 3,
+        };
+        let text_vector = ColumnType::Vector {
+            element: Box::new(ColumnType::Text),
+            dimensions: 2,
+        };
+
+        let floats = [0.5_f32, 1.5, -2.0];
+        assert!(floats.matches_type(&float_vector(3)));
+        assert_eq!(round_trip::<[f32; 3]>(floats, float_vector(3)), floats);
+        assert_eq!(
+            round_trip::<Vec<f32>>(floats, float_vector(3)),
+            floats.to_vec()
+        );
+        let cql_floats = CqlValue::Vector(floats.iter().map(|f| CqlValue::Float(*f)).collect());
+        assert_eq!(round_trip::<CqlValue>(floats, float_vector(3)), cql_floats);
+        assert_eq!(
+            <[f32; 3]>::from_cql(round_trip::<CqlValue>(cql_floats, float_vector(3))),
+            Ok(floats)
+        );
+
+        // Elements of a nested vector have a fixed length, they aren't prefixed with it
+        let nested = [[1.0_f32, 2.0], [3.0, 4.0], [5.0, 6.0]];
+        let mut serialized = Vec::new();
+        nested.serialize(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), 4 + 6 * 4);
+        assert!(nested.matches_type(&nested_vector));
+        assert!(![[1.0_f32, 2.0]].matches_type(&nested_vector));
+        assert!(![[1.0_f32], [2.0], [3.0]].matches_type(&nested_vector));
+        assert_eq!(
+            round_trip::<[[f32; 2]; 3]>(nested, nested_vector.clone()),
+            nested
+        );
+        let cql_nested = round_trip::<CqlValue>(nested, nested_vector.clone());
+        assert!(cql_nested.matches_type(&nested_vector));
+        assert_eq!(
+            round_trip::<[[f32; 2]; 3]>(cql_nested, nested_vector),
+            nested
+        );
+
+        // Elements of variable length are prefixed with it
+        let texts = CqlValue::Vector(vec![
+            CqlValue::Text("a".to_string()),
+            CqlValue::Text("bc".to_string()),
+        ]);
+        assert_eq!(
+            round_trip::<[String; 2]>(texts.clone(), text_vector.clone()),
+            ["a".to_string(), "bc".to_string()]
+        );
+        assert_eq!(round_trip::<CqlValue>(texts.clone(), text_vector), texts);
+
+        let mut cell = Vec::new();
+        floats.serialize(&mut cell).unwrap();
+        let specs = [spec("v", float_vector(3))];
+        let raw = RawRows::new(1, cell.into());
+        assert!(matches!(
+            raw.rows::<([f32; 2],)>(&specs).next().unwrap(),
+            Err(DeserializationError::InColumn { .. })
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_collections() {
+        let mut list = Vec::new();
+        list.put_i32(2);
+        types::write_bytes(b"x", &mut list).unwrap();
+        types::write_bytes(b"y", &mut list).unwrap();
+
+        let mut map = Vec::new();
+        map.put_i32(1);
+        types::write_bytes(&1_i32.to_be_bytes(), &mut map).unwrap();
+        types::write_bytes(b"one", &mut map).unwrap();
+
+        let specs = vec![
+            spec("l", ColumnType::List(Box::new(ColumnType::Text))),
+            spec(
+                "m",
+                ColumnType::Map(Box::new(ColumnType::Int), Box::new(ColumnType::Text)),
+            ),
+        ];
+        let raw = RawRows::new(1, serialize_cells(&[Some(&list), Some(&map)]));
+
+        let (l, m) = raw
+            .rows::<(Vec<&str>, BTreeMap<i32, String>)>(&specs)
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(l, vec!["x", "y"]);
+        assert_eq!(m, BTreeMap::from([(1, "one".to_string())]));
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        let specs = vec![spec("a", ColumnType::Int), spec("b", ColumnType::Text)];
+        let raw = RawRows::new(
+            2,
+            serialize_cells(&[
+                Some(&1_i32.to_be_bytes()),
+                None,
+                Some(&2_i32.to_be_bytes()),
+                Some(b"two"),
+            ]),
+        );
+
+        let mut rows = raw.rows::<(i64, String)>(&specs);
+        match rows.next().unwrap() {
+            Err(DeserializationError::InColumn { err, column, name }) => {
+                assert_eq!((column, name.as_str()), (0, "a"));
+                assert!(matches!(*err, DeserializationError::TypeMismatch { .. }));
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        // Errors don't prevent reading the following rows
+        let mut rows = raw.rows::<(i32, String)>(&specs);
+        assert!(matches!(
+            rows.next().unwrap(),
+            Err(DeserializationError::InColumn { column: 1, .. })
+        ));
+        assert_eq!(rows.next().unwrap().unwrap(), (2, "two".to_string()));
+        assert!(rows.next().is_none());
+
+        assert!(matches!(
+            raw.rows::<(i32,)>(&specs).next().unwrap(),
+            Err(DeserializationError::WrongRowSize {
+                expected: 1,
+                actual: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_lazy_row() {
+        let specs = vec![spec("a", ColumnType::Int), spec("b", ColumnType::Text)];
+        // The first column can't be deserialized as an int,
+        // which must not matter unless it's accessed
+        let raw = RawRows::new(1, serialize_cells(&[Some(&[1, 2]), Some(b"two")]));
+
+        let row = raw.rows::<LazyRow>(&specs).next().unwrap().unwrap();
+        assert_eq!(row.columns_count(), 2);
+        assert_eq!(row.column::<&str>(1).unwrap(), "two");
+        assert_eq!(row.column_by_name::<String>("b").unwrap(), "two");
+        assert!(row.column::<i32>(0).is_err());
+        assert!(matches!(
+            row.column_by_name::<i32>("c"),
+            Err(DeserializationError::NoSuchColumn { .. })
+        ));
+        assert!(row.into_row().is_err());
+    }
+}
@@ -2,6 +2,7 @@ use crate::errors::{DbError, OperationType, QueryError, WriteType};
 use crate::frame::frame_errors::ParseError;
 use crate::frame::protocol_features::ProtocolFeatures;
 use crate::frame::types;
+use crate::frame::ProtocolVersion;
 use byteorder::ReadBytesExt;
 use bytes::Bytes;
 
@@ -12,7 +13,11 @@ pub struct Error {
 }
 
 impl Error {
-    pub fn deserialize(features: &ProtocolFeatures, buf: &mut &[u8]) -> Result<Self, ParseError> {
+    pub fn deserialize(
+        features: &ProtocolFeatures,
+        version: ProtocolVersion,
+        buf: &mut &[u8],
+    ) -> Result<Self, ParseError> {
         let code = types::read_int(buf)?;
         let reason = types::read_string(buf)?.to_owned();
 
@@ -44,7 +49,7 @@ impl Error {
                 consistency: types::read_consistency(buf)?,
                 received: types::read_int(buf)?,
                 required: types::read_int(buf)?,
-                numfailures: types::read_int(buf)?,
+                numfailures: read_numfailures(buf, version)?,
                 data_present: buf.read_u8()? != 0,
             },
             0x1400 => DbError::FunctionFailure {
@@ -56,7 +61,7 @@ impl Error {
                 consistency: types::read_consistency(buf)?,
                 received: types::read_int(buf)?,
                 required: types::read_int(buf)?,
-                numfailures: types::read_int(buf)?,
+                numfailures: read_numfailures(buf, version)?,
                 write_type: WriteType::from(types::read_string(buf)?),
             },
             0x2000 => DbError::SyntaxError,
@@ -81,6 +86,27 @@ impl Error {
     }
 }
 
+// Protocol v4 sends only the number of failed replicas. Since v5, a map
+// from replica address to failure code is sent instead; only its size is kept.
+fn read_numfailures(buf: &mut &[u8], version: ProtocolVersion) -> Result<i32, ParseError> {
+    let numfailures = types::read_int(buf)?;
+    if version >= ProtocolVersion::V5 {
+        for _ in 0..numfailures {
+            let addr_len = buf.read_u8()? as usize;
+            if buf.len() < addr_len {
+                return Err(ParseError::BadIncomingData(format!(
+                    "Not enough bytes for a failed replica address: expected {}, received {}",
+                    addr_len,
+                    buf.len()
+                )));
+            }
+            *buf = &buf[addr_len..];
+            types::read_short(buf)?; // failure code
+        }
+    }
+    Ok(numfailures)
+}
+
 impl From<Error> for QueryError {
     fn from(error: Error) -> QueryError {
         QueryError::DbError(error.error, error.reason)
@@ -93,6 +119,7 @@ mod tests {
     use crate::errors::{DbError, OperationType, WriteType};
     use crate::frame::protocol_features::ProtocolFeatures;
     use crate::frame::types::LegacyConsistency;
+    use crate::frame::ProtocolVersion;
     use crate::Consistency;
     use bytes::Bytes;
     use std::convert::TryInto;
@@ -131,7 +158,8 @@ mod tests {
 
         for (error_code, expected_error) in &simple_error_mappings {
             let bytes: Vec<u8> = make_error_request_bytes(*error_code, "simple message");
-            let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+            let error: Error =
+                Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
             assert_eq!(error.error, *expected_error);
             assert_eq!(error.reason, "simple message");
         }
@@ -146,7 +174,8 @@ mod tests {
         bytes.extend(2_i32.to_be_bytes());
         bytes.extend(3_i32.to_be_bytes());
 
-        let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+        let error: Error =
+            Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
 
         assert_eq!(
             error.error,
@@ -173,7 +202,8 @@ mod tests {
         bytes.extend(write_type_str_len.to_be_bytes());
         bytes.extend(write_type_str.as_bytes());
 
-        let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+        let error: Error =
+            Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
 
         assert_eq!(
             error.error,
@@ -197,7 +227,8 @@ mod tests {
         bytes.extend(32_i32.to_be_bytes());
         bytes.push(0_u8);
 
-        let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+        let error: Error =
+            Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
 
         assert_eq!(
             error.error,
@@ -222,7 +253,8 @@ mod tests {
         bytes.extend(6_i32.to_be_bytes());
         bytes.push(123_u8); // Any non-zero value means data_present is true
 
-        let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+        let error: Error =
+            Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
 
         assert_eq!(
             error.error,
@@ -237,6 +269,39 @@ mod tests {
         assert_eq!(error.reason, "message 2");
     }
 
+    #[test]
+    fn deserialize_read_failure_v5() {
+        let features = ProtocolFeatures::default();
+
+        let mut bytes = make_error_request_bytes(0x1300, "message 2");
+        bytes.extend(0x0003_i16.to_be_bytes());
+        bytes.extend(4_i32.to_be_bytes());
+        bytes.extend(5_i32.to_be_bytes());
+        // Reason map with two entries: an IPv4 and an IPv6 replica
+        bytes.extend(2_i32.to_be_bytes());
+        bytes.push(4);
+        bytes.extend([127, 0, 0, 1]);
+        bytes.extend(0x0000_i16.to_be_bytes());
+        bytes.push(16);
+        bytes.extend([0; 16]);
+        bytes.extend(0x0001_i16.to_be_bytes());
+        bytes.push(1);
+
+        let error: Error =
+            Error::deserialize(&features, ProtocolVersion::V5, &mut bytes.as_slice()).unwrap();
+
+        assert_eq!(
+            error.error,
+            DbError::ReadFailure {
+                consistency: LegacyConsistency::Regular(Consistency::Three),
+                received: 4,
+                required: 5,
+                numfailures: 2,
+                data_present: true,
+            }
+        );
+    }
+
     #[test]
     fn deserialize_function_failure() {
         let features = ProtocolFeatures::default();
@@ -265,7 +330,8 @@ mod tests {
         bytes.extend(type2_len.to_be_bytes());
         bytes.extend(type2.as_bytes());
 
-        let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+        let error: Error =
+            Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
 
         assert_eq!(
             error.error,
@@ -294,7 +360,8 @@ mod tests {
         bytes.extend(write_type_str_len.to_be_bytes());
         bytes.extend(write_type_str.as_bytes());
 
-        let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+        let error: Error =
+            Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
 
         assert_eq!(
             error.error,
@@ -326,7 +393,8 @@ mod tests {
         bytes.extend(table_name_len.to_be_bytes());
         bytes.extend(table_name.as_bytes());
 
-        let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+        let error: Error =
+            Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
 
         assert_eq!(
             error.error,
@@ -347,7 +415,8 @@ mod tests {
         bytes.extend((statement_id.len() as i16).to_be_bytes());
         bytes.extend(statement_id);
 
-        let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+        let error: Error =
+            Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
 
         assert_eq!(
             error.error,
@@ -367,7 +436,8 @@ mod tests {
         let mut bytes = make_error_request_bytes(0x4321, "message 1");
         bytes.extend([0u8]); // Read type
         bytes.extend([1u8]); // Rejected by coordinator
-        let error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+        let error =
+            Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
 
         assert_eq!(
             error.error,
@@ -385,7 +455,8 @@ mod tests {
         let mut bytes = make_error_request_bytes(0x8765, "message 2");
         bytes.extend([1u8]); // Write type
         bytes.extend([0u8]); // Not rejected by coordinator
-        let error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+        let error =
+            Error::deserialize(&features, ProtocolVersion::V4, &mut bytes.as_slice()).unwrap();
 
         assert_eq!(
             error.error,
@@ -1,14 +1,20 @@
 pub mod authenticate;
+pub mod cql_literal;
+#[cfg(feature = "serde")]
+pub mod cql_serde;
 pub mod cql_to_rust;
+pub mod deserialize;
 pub mod error;
 pub mod event;
 pub mod result;
 pub mod supported;
 
 use crate::{errors::QueryError, frame::frame_errors::ParseError};
+use bytes::Bytes;
 use num_enum::TryFromPrimitive;
 
 use crate::frame::protocol_features::ProtocolFeatures;
+use crate::frame::ProtocolVersion;
 pub use error::Error;
 pub use supported::Supported;
 
@@ -40,17 +46,19 @@ pub enum Response {
 impl Response {
     pub fn deserialize(
         features: &ProtocolFeatures,
+        version: ProtocolVersion,
         opcode: ResponseOpcode,
-        buf: &mut &[u8],
+        body: Bytes,
     ) -> Result<Response, ParseError> {
+        let buf = &mut &*body;
         let response = match opcode {
-            ResponseOpcode::Error => Response::Error(Error::deserialize(features, buf)?),
+            ResponseOpcode::Error => Response::Error(Error::deserialize(features, version, buf)?),
             ResponseOpcode::Ready => Response::Ready,
             ResponseOpcode::Authenticate => {
                 Response::Authenticate(authenticate::Authenticate::deserialize(buf)?)
             }
             ResponseOpcode::Supported => Response::Supported(Supported::deserialize(buf)?),
-            ResponseOpcode::Result => Response::Result(result::deserialize(buf)?),
+            ResponseOpcode::Result => Response::Result(result::deserialize(&body, version)?),
             ResponseOpcode::Event => Response::Event(event::Event::deserialize(buf)?),
             ResponseOpcode::AuthChallenge => {
                 Response::AuthChallenge(authenticate::AuthChallenge::deserialize(buf)?)
@@ -1,14 +1,15 @@
-use crate::cql_to_rust::{FromRow, FromRowError};
+use crate::cql_to_rust::{FromRow, FromRowError, FromRowWithSpecs};
+use crate::frame::response::deserialize::{
+    DeserializationError, LazyRow, RawRows, TypedRowsIterator,
+};
 use crate::frame::response::event::SchemaChangeEvent;
 use crate::frame::types::vint_decode;
-use crate::frame::value::{Counter, CqlDuration};
-use crate::frame::{frame_errors::ParseError, types};
-use bigdecimal::BigDecimal;
+use crate::frame::value::{Counter, CqlDecimal, CqlDuration, CqlTimeuuid, CqlVarint};
+use crate::frame::{frame_errors::ParseError, types, ProtocolVersion};
 use byteorder::{BigEndian, ReadBytesExt};
 use bytes::{Buf, Bytes};
 use chrono;
 use chrono::prelude::*;
-use num_bigint::BigInt;
 use std::{
     convert::{TryFrom, TryInto},
     net::IpAddr,
@@ -25,6 +26,9 @@ pub struct SetKeyspace {
 #[derive(Debug)]
 pub struct Prepared {
     pub id: Bytes,
+    /// Id of `result_metadata`, which has to be sent back with EXECUTE.
+    /// Present since protocol v5.
+    pub result_metadata_id: Option<Bytes>,
     pub prepared_metadata: PreparedMetadata,
     pub result_metadata: ResultMetadata,
 }
@@ -40,7 +44,7 @@ pub struct TableSpec {
     pub table_name: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ColumnType {
     Custom(String),
     Ascii,
@@ -72,6 +76,11 @@ pub enum ColumnType {
     Tuple(Vec<ColumnType>),
     Uuid,
     Varint,
+    /// Fixed-length vector of non-null elements, e.g. `vector<float, 3>`
+    Vector {
+        element: Box<ColumnType>,
+        dimensions: u16,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -80,7 +89,7 @@ pub enum CqlValue {
     Boolean(bool),
     Blob(Vec<u8>),
     Counter(Counter),
-    Decimal(BigDecimal),
+    Decimal(CqlDecimal),
     /// Days since -5877641-06-23 i.e. 2^31 days before unix epoch
     /// Can be converted to chrono::NaiveDate (-262145-1-1 to 262143-12-31) using as_date
     Date(u32),
@@ -109,10 +118,11 @@ pub enum CqlValue {
     TinyInt(i8),
     /// Nanoseconds since midnight
     Time(chrono::Duration),
-    Timeuuid(Uuid),
+    Timeuuid(CqlTimeuuid),
     Tuple(Vec<Option<CqlValue>>),
     Uuid(Uuid),
-    Varint(BigInt),
+    Varint(CqlVarint),
+    Vector(Vec<CqlValue>),
 }
 
 impl CqlValue {
@@ -179,7 +189,7 @@ impl CqlValue {
     pub fn as_uuid(&self) -> Option<Uuid> {
         match self {
             Self::Uuid(u) => Some(*u),
-            Self::Timeuuid(u) => Some(*u),
+            Self::Timeuuid(u) => Some(*u.as_uuid()),
             _ => None,
         }
     }
@@ -234,7 +244,7 @@ impl CqlValue {
         }
     }
 
-    pub fn as_timeuuid(&self) -> Option<Uuid> {
+    pub fn as_timeuuid(&self) -> Option<CqlTimeuuid> {
         match self {
             Self::Timeuuid(u) => Some(*u),
             _ => None,
@@ -291,10 +301,18 @@ impl CqlValue {
         }
     }
 
+    pub fn as_vector(&self) -> Option<&Vec<CqlValue>> {
+        match self {
+            Self::Vector(s) => Some(s),
+            _ => None,
+        }
+    }
+
     pub fn into_vec(self) -> Option<Vec<CqlValue>> {
         match self {
             Self::List(s) => Some(s),
             Self::Set(s) => Some(s),
+            Self::Vector(s) => Some(s),
             _ => None,
         }
     }
@@ -313,14 +331,14 @@ impl CqlValue {
         }
     }
 
-    pub fn into_varint(self) -> Option<BigInt> {
+    pub fn into_cql_varint(self) -> Option<CqlVarint> {
         match self {
             Self::Varint(i) => Some(i),
             _ => None,
         }
     }
 
-    pub fn into_decimal(self) -> Option<BigDecimal> {
+    pub fn into_cql_decimal(self) -> Option<CqlDecimal> {
         match self {
             Self::Decimal(i) => Some(i),
             _ => None,
@@ -340,6 +358,9 @@ pub struct ColumnSpec {
 pub struct ResultMetadata {
     col_count: usize,
     pub paging_state: Option<Bytes>,
+    /// Set if the server reports that the result metadata has changed
+    /// since the statement was prepared. Present only since protocol v5.
+    pub new_metadata_id: Option<Bytes>,
     pub col_specs: Vec<ColumnSpec>,
 }
 
@@ -371,13 +392,39 @@ impl Row {
     pub fn into_typed<RowT: FromRow>(self) -> StdResult<RowT, FromRowError> {
         RowT::from_row(self)
     }
+
+    /// Like [`into_typed`](Row::into_typed), but also checks the specs of the row's columns,
+    /// which are needed by types matching columns by name.\
+    /// To convert many rows, compute [`FromRowWithSpecs::map_columns`] once instead.
+    pub fn into_typed_with_specs<RowT: FromRowWithSpecs>(
+        self,
+        specs: &[ColumnSpec],
+    ) -> StdResult<RowT, FromRowError> {
+        let mapping = RowT::map_columns(specs)?;
+        RowT::from_row_with_mapping(self, &mapping)
+    }
 }
 
 #[derive(Debug)]
 pub struct Rows {
     pub metadata: ResultMetadata,
     pub rows_count: usize,
-    pub rows: Vec<Row>,
+    /// The rows in their serialized form. They are deserialized only when accessed,
+    /// see [`deserialize`](super::deserialize).
+    pub raw_rows: RawRows,
+}
+
+impl Rows {
+    /// Returns an iterator over the rows, which locates each row only when it is reached
+    /// and deserializes its columns only when they are accessed.
+    pub fn iter(&self) -> TypedRowsIterator<'_, LazyRow<'_>> {
+        self.raw_rows.rows(&self.metadata.col_specs)
+    }
+
+    /// Deserializes all rows into [`Row`]s.
+    pub fn rows(&self) -> StdResult<Vec<Row>, DeserializationError> {
+        self.raw_rows.rows(&self.metadata.col_specs).collect()
+    }
 }
 
 #[derive(Debug)]
@@ -406,7 +453,10 @@ fn deser_type(buf: &mut &[u8]) -> StdResult<ColumnType, ParseError> {
             let type_str: String = types::read_string(buf)?.to_string();
             match type_str.as_str() {
                 "org.apache.cassandra.db.marshal.DurationType" => Duration,
-                _ => Custom(type_str),
+                _ => match parse_custom_type(&type_str) {
+                    Some((typ @ Vector { .. }, "")) => typ,
+                    _ => Custom(type_str),
+                },
             }
         }
         0x0001 => Ascii,
@@ -467,6 +517,105 @@ fn deser_type(buf: &mut &[u8]) -> StdResult<ColumnType, ParseError> {
     })
 }
 
+const MARSHAL_PREFIX: &str = "org.apache.cassandra.db.marshal.";
+
+// Parses a type given by its Cassandra class name, e.g.
+// `org.apache.cassandra.db.marshal.VectorType(org.apache.cassandra.db.marshal.FloatType, 3)`.
+// Returns the parsed type and the unparsed rest of the input.
+fn parse_custom_type(s: &str) -> Option<(ColumnType, &str)> {
+    use ColumnType::*;
+
+    let s = s.trim_start();
+    let name_len = s
+        .find(|c: char| c == '(' || c == ',' || c == ')' || c.is_whitespace())
+        .unwrap_or(s.len());
+    let (name, mut rest) = s.split_at(name_len);
+    let name = name.strip_prefix(MARSHAL_PREFIX).unwrap_or(name);
+
+    let mut params = Vec::new();
+    let mut dimensions = None;
+    if let Some(r) = rest.strip_prefix('(') {
+        rest = r;
+        loop {
+            let trimmed = rest.trim_start();
+            let digits = trimmed
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(trimmed.len());
+            if digits > 0 {
+                dimensions = Some(trimmed[..digits].parse().ok()?);
+                rest = &trimmed[digits..];
+            } else {
+                let (param, r) = parse_custom_type(trimmed)?;
+                params.push(param);
+                rest = r;
+            }
+
+            let trimmed = rest.trim_start();
+            if let Some(r) = trimmed.strip_prefix(',') {
+                rest = r;
+            } else {
+                rest = trimmed.strip_prefix(')')?;
+                break;
+            }
+        }
+    }
+
+    let mut params = params.into_iter();
+    let typ = match (name, params.len(), dimensions) {
+        ("AsciiType", 0, None) => Ascii,
+        ("BooleanType", 0, None) => Boolean,
+        ("BytesType", 0, None) => Blob,
+        ("CounterColumnType", 0, None) => Counter,
+        ("SimpleDateType", 0, None) => Date,
+        ("DecimalType", 0, None) => Decimal,
+        ("DoubleType", 0, None) => Double,
+        ("DurationType", 0, None) => Duration,
+        ("FloatType", 0, None) => Float,
+        ("Int32Type", 0, None) => Int,
+        ("LongType", 0, None) => BigInt,
+        ("UTF8Type", 0, None) => Text,
+        ("TimestampType", 0, None) => Timestamp,
+        ("InetAddressType", 0, None) => Inet,
+        ("ShortType", 0, None) => SmallInt,
+        ("ByteType", 0, None) => TinyInt,
+        ("TimeType", 0, None) => Time,
+        ("TimeUUIDType", 0, None) => Timeuuid,
+        ("UUIDType", 0, None) => Uuid,
+        ("IntegerType", 0, None) => Varint,
+        ("FrozenType", 1, None) => params.next()?,
+        ("ListType", 1, None) => List(Box::new(params.next()?)),
+        ("SetType", 1, None) => Set(Box::new(params.next()?)),
+        ("MapType", 2, None) => Map(Box::new(params.next()?), Box::new(params.next()?)),
+        ("TupleType", _, None) => Tuple(params.collect()),
+        ("VectorType", 1, Some(dimensions)) => Vector {
+            element: Box::new(params.next()?),
+            dimensions,
+        },
+        _ => return None,
+    };
+
+    Some((typ, rest))
+}
+
+/// Returns the length of a serialized value of given type if it's the same for all values,
+/// which means that the value is not prefixed with its length inside a vector.
+pub(crate) fn vector_element_fixed_length(typ: &ColumnType) -> Option<usize> {
+    use ColumnType::*;
+
+    match typ {
+        Boolean => Some(1),
+        Date | Float | Int => Some(4),
+        BigInt | Double | Time | Timestamp => Some(8),
+        Timeuuid | Uuid => Some(16),
+        // A vector of fixed-length elements has a fixed length too
+        Vector {
+            element,
+            dimensions,
+        } => vector_element_fixed_length(element).map(|len| len * *dimensions as usize),
+        _ => None,
+    }
+}
+
 fn deser_col_specs(
     buf: &mut &[u8],
     global_table_spec: &Option<TableSpec>,
@@ -495,6 +644,7 @@ fn deser_result_metadata(buf: &mut &[u8]) -> StdResult<ResultMetadata, ParseErro
     let global_tables_spec = flags & 0x0001 != 0;
     let has_more_pages = flags & 0x0002 != 0;
     let no_metadata = flags & 0x0004 != 0;
+    let metadata_changed = flags & 0x0008 != 0;
 
     let col_count: usize = types::read_int(buf)?.try_into()?;
 
@@ -504,10 +654,17 @@ fn deser_result_metadata(buf: &mut &[u8]) -> StdResult<ResultMetadata, ParseErro
         None
     };
 
+    let new_metadata_id = if metadata_changed {
+        Some(types::read_short_bytes(buf)?.to_owned().into())
+    } else {
+        None
+    };
+
     if no_metadata {
         return Ok(ResultMetadata {
             col_count,
             paging_state,
+            new_metadata_id,
             col_specs: vec![],
         });
     }
@@ -523,6 +680,7 @@ fn deser_result_metadata(buf: &mut &[u8]) -> StdResult<ResultMetadata, ParseErro
     Ok(ResultMetadata {
         col_count,
         paging_state,
+        new_metadata_id,
         col_specs,
     })
 }
@@ -618,11 +776,10 @@ pub fn deser_cql_value(typ: &ColumnType, buf: &mut &[u8]) -> StdResult<CqlValue,
             CqlValue::Counter(crate::frame::value::Counter(buf.read_i64::<BigEndian>()?))
         }
         Decimal => {
-            let scale = types::read_int(buf)? as i64;
-            let int_value = num_bigint::BigInt::from_signed_bytes_be(buf);
-            let big_decimal: BigDecimal = BigDecimal::from((int_value, scale));
-
-            CqlValue::Decimal(big_decimal)
+            let scale = types::read_int(buf)?;
+            CqlValue::Decimal(CqlDecimal::from_signed_be_bytes_slice_and_exponent(
+                buf, scale,
+            ))
         }
         Double => {
             if buf.len() != 8 {
@@ -717,7 +874,7 @@ pub fn deser_cql_value(typ: &ColumnType, buf: &mut &[u8]) -> StdResult<CqlValue,
                 )));
             }
             let uuid = uuid::Uuid::from_slice(buf).expect("Deserializing Uuid failed.");
-            CqlValue::Timeuuid(uuid)
+            CqlValue::Timeuuid(uuid.into())
         }
         Duration => {
             let months = i32::try_from(vint_decode(buf)?)?;
@@ -758,7 +915,7 @@ pub fn deser_cql_value(typ: &ColumnType, buf: &mut &[u8]) -> StdResult<CqlValue,
             let uuid = uuid::Uuid::from_slice(buf).expect("Deserializing Uuid failed.");
             CqlValue::Uuid(uuid)
         }
-        Varint => CqlValue::Varint(num_bigint::BigInt::from_signed_bytes_be(buf)),
+        Varint => CqlValue::Varint(CqlVarint::from_signed_bytes_be_slice(buf)),
         List(type_name) => {
             let len: usize = types::read_int(buf)?.try_into()?;
             let mut res = Vec::with_capacity(len);
@@ -830,10 +987,35 @@ pub fn deser_cql_value(typ: &ColumnType, buf: &mut &[u8]) -> StdResult<CqlValue,
 
             CqlValue::Tuple(res)
         }
+        Vector {
+            element,
+            dimensions,
+        } => {
+            let fixed_length = vector_element_fixed_length(element);
+            let mut res = Vec::with_capacity(*dimensions as usize);
+            for _ in 0..*dimensions {
+                let len = match fixed_length {
+                    Some(len) => len,
+                    None => types::unsigned_vint_decode(buf)?.try_into()?,
+                };
+                if buf.len() < len {
+                    return Err(ParseError::BadIncomingData(format!(
+                        "Not enough bytes for a vector element: expected {}, got {}",
+                        len,
+                        buf.len()
+                    )));
+                }
+                let mut b = &buf[..len];
+                buf.advance(len);
+                res.push(deser_cql_value(element, &mut b)?);
+            }
+
+            CqlValue::Vector(res)
+        }
     })
 }
 
-fn deser_rows(buf: &mut &[u8]) -> StdResult<Rows, ParseError> {
+fn deser_rows(buf: &mut &[u8], frame: &Bytes) -> StdResult<Rows, ParseError> {
     let metadata = deser_result_metadata(buf)?;
 
     // TODO: the protocol allows an optimization (which must be explicitly requested on query by
@@ -843,24 +1025,13 @@ fn deser_rows(buf: &mut &[u8]) -> StdResult<Rows, ParseError> {
     assert!(metadata.col_count == metadata.col_specs.len());
 
     let rows_count: usize = types::read_int(buf)?.try_into()?;
+    // Rows are deserialized lazily, they take the rest of the frame
+    let serialized_rows = std::mem::take(buf);
 
-    let mut rows = Vec::with_capacity(rows_count);
-    for _ in 0..rows_count {
-        let mut columns = Vec::with_capacity(metadata.col_count);
-        for i in 0..metadata.col_count {
-            let v = if let Some(mut b) = types::read_bytes_opt(buf)? {
-                Some(deser_cql_value(&metadata.col_specs[i].typ, &mut b)?)
-            } else {
-                None
-            };
-            columns.push(v);
-        }
-        rows.push(Row { columns });
-    }
     Ok(Rows {
         metadata,
         rows_count,
-        rows,
+        raw_rows: RawRows::new(rows_count, frame.slice_ref(serialized_rows)),
     })
 }
 
@@ -870,14 +1041,20 @@ fn deser_set_keyspace(buf: &mut &[u8]) -> StdResult<SetKeyspace, ParseError> {
     Ok(SetKeyspace { keyspace_name })
 }
 
-fn deser_prepared(buf: &mut &[u8]) -> StdResult<Prepared, ParseError> {
+fn deser_prepared(buf: &mut &[u8], version: ProtocolVersion) -> StdResult<Prepared, ParseError> {
     let id_len = types::read_short(buf)? as usize;
     let id: Bytes = buf[0..id_len].to_owned().into();
     buf.advance(id_len);
+    let result_metadata_id = if version >= ProtocolVersion::V5 {
+        Some(types::read_short_bytes(buf)?.to_owned().into())
+    } else {
+        None
+    };
     let prepared_metadata = deser_prepared_metadata(buf)?;
     let result_metadata = deser_result_metadata(buf)?;
     Ok(Prepared {
         id,
+        result_metadata_id,
         prepared_metadata,
         result_metadata,
     })
@@ -890,13 +1067,16 @@ fn deser_schema_change(buf: &mut &[u8]) -> StdResult<SchemaChange, ParseError> {
     })
 }
 
-pub fn deserialize(buf: &mut &[u8]) -> StdResult<Result, ParseError> {
+/// Deserializes the body of a RESULT response.
+/// Rows keep references to `frame`, so that they can be deserialized later.
+pub fn deserialize(frame: &Bytes, version: ProtocolVersion) -> StdResult<Result, ParseError> {
     use self::Result::*;
+    let buf = &mut &**frame;
     Ok(match types::read_int(buf)? {
         0x0001 => Void,
-        0x0002 => Rows(deser_rows(buf)?),
+        0x0002 => Rows(deser_rows(buf, frame)?),
         0x0003 => SetKeyspace(deser_set_keyspace(buf)?),
-        0x0004 => Prepared(deser_prepared(buf)?),
+        0x0004 => Prepared(deser_prepared(buf, version)?),
         0x0005 => SchemaChange(deser_schema_change(buf)?),
         k => {
             return Err(ParseError::BadIncomingData(format!(
@@ -910,12 +1090,9 @@ pub fn deserialize(buf: &mut &[u8]) -> StdResult<Result, ParseError> {
 #[cfg(test)]
 mod tests {
     use crate as scylla;
-    use crate::frame::value::{Counter, CqlDuration};
-    use bigdecimal::BigDecimal;
+    use crate::frame::value::{Counter, CqlDecimal, CqlDuration, CqlVarint};
     use chrono::Duration;
     use chrono::NaiveDate;
-    use num_bigint::BigInt;
-    use num_bigint::ToBigInt;
     use scylla::frame::response::result::{ColumnType, CqlValue};
     use std::str::FromStr;
     use uuid::Uuid;
@@ -941,7 +1118,7 @@ mod tests {
 
         let time_uuid_serialize =
             super::deser_cql_value(&ColumnType::Timeuuid, uuid_slice).unwrap();
-        assert_eq!(time_uuid_serialize, CqlValue::Timeuuid(my_uuid));
+        assert_eq!(time_uuid_serialize, CqlValue::Timeuuid(my_uuid.into()));
 
         let my_ip = "::1".parse().unwrap();
         let ip_buf: Vec<u8> = vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
@@ -977,7 +1154,7 @@ mod tests {
     #[test]
     fn test_varint() {
         struct Test<'a> {
-            value: BigInt,
+            value: CqlVarint,
             encoding: &'a [u8],
         }
 
@@ -997,35 +1174,35 @@ mod tests {
         */
         let tests = [
             Test {
-                value: 0.to_bigint().unwrap(),
+                value: CqlVarint::from_str("0").unwrap(),
                 encoding: &[0x00],
             },
             Test {
-                value: 1.to_bigint().unwrap(),
+                value: CqlVarint::from_str("1").unwrap(),
                 encoding: &[0x01],
             },
             Test {
-                value: 127.to_bigint().unwrap(),
+                value: CqlVarint::from_str("127").unwrap(),
                 encoding: &[0x7F],
             },
             Test {
-                value: 128.to_bigint().unwrap(),
+                value: CqlVarint::from_str("128").unwrap(),
                 encoding: &[0x00, 0x80],
             },
             Test {
-                value: 129.to_bigint().unwrap(),
+                value: CqlVarint::from_str("129").unwrap(),
                 encoding: &[0x00, 0x81],
             },
             Test {
-                value: (-1).to_bigint().unwrap(),
+                value: CqlVarint::from_str("-1").unwrap(),
                 encoding: &[0xFF],
             },
             Test {
-                value: (-128).to_bigint().unwrap(),
+                value: CqlVarint::from_str("-128").unwrap(),
                 encoding: &[0x80],
             },
             Test {
-                value: (-129).to_bigint().unwrap(),
+                value: CqlVarint::from_str("-129").unwrap(),
                 encoding: &[0xFF, 0x7F],
             },
         ];
@@ -1039,25 +1216,25 @@ mod tests {
     #[test]
     fn test_decimal() {
         struct Test<'a> {
-            value: BigDecimal,
+            value: CqlDecimal,
             encoding: &'a [u8],
         }
 
         let tests = [
             Test {
-                value: BigDecimal::from_str("-1.28").unwrap(),
+                value: CqlDecimal::from_str("-1.28").unwrap(),
                 encoding: &[0x0, 0x0, 0x0, 0x2, 0x80],
             },
             Test {
-                value: BigDecimal::from_str("1.29").unwrap(),
+                value: CqlDecimal::from_str("1.29").unwrap(),
                 encoding: &[0x0, 0x0, 0x0, 0x2, 0x0, 0x81],
             },
             Test {
-                value: BigDecimal::from_str("0").unwrap(),
+                value: CqlDecimal::from_str("0").unwrap(),
                 encoding: &[0x0, 0x0, 0x0, 0x0, 0x0],
             },
             Test {
-                value: BigDecimal::from_str("123").unwrap(),
+                value: CqlDecimal::from_str("123").unwrap(),
                 encoding: &[0x0, 0x0, 0x0, 0x0, 0x7b],
             },
         ];
@@ -1432,10 +1609,75 @@ mod tests {
             match cql_val {
                 CqlValue::Timeuuid(uuid) => {
                     assert_eq!(uuid.as_bytes(), uuid_bytes);
-                    assert_eq!(Uuid::parse_str(uuid_str).unwrap(), uuid);
+                    assert_eq!(Uuid::parse_str(uuid_str).unwrap(), *uuid.as_uuid());
                 }
                 _ => panic!("Timeuuid parsed as wrong CqlValue"),
             }
         }
     }
+
+    #[test]
+    fn test_parse_vector_type() {
+        let type_str = "org.apache.cassandra.db.marshal.VectorType(org.apache.cassandra.db.marshal.FloatType, 3)";
+        let mut buf = Vec::new();
+        scylla::frame::types::write_short(0, &mut buf);
+        scylla::frame::types::write_string(type_str, &mut buf).unwrap();
+        assert_eq!(
+            super::deser_type(&mut &buf[..]).unwrap(),
+            ColumnType::Vector {
+                element: Box::new(ColumnType::Float),
+                dimensions: 3
+            }
+        );
+
+        let type_str = "org.apache.cassandra.db.marshal.VectorType(\
+            org.apache.cassandra.db.marshal.ListType(org.apache.cassandra.db.marshal.UTF8Type),2)";
+        assert_eq!(
+            super::parse_custom_type(type_str),
+            Some((
+                ColumnType::Vector {
+                    element: Box::new(ColumnType::List(Box::new(ColumnType::Text))),
+                    dimensions: 2
+                },
+                ""
+            ))
+        );
+
+        let type_str = "com.example.SomeType";
+        let mut buf = Vec::new();
+        scylla::frame::types::write_short(0, &mut buf);
+        scylla::frame::types::write_string(type_str, &mut buf).unwrap();
+        assert_eq!(
+            super::deser_type(&mut &buf[..]).unwrap(),
+            ColumnType::Custom(type_str.to_string())
+        );
+    }
+
+    #[test]
+    fn test_deserialize_vector() {
+        let float_vector = ColumnType::Vector {
+            element: Box::new(ColumnType::Float),
+            dimensions: 2,
+        };
+        let buf: Vec<u8> = vec![63, 0, 0, 0, 64, 0, 0, 0];
+        assert_eq!(
+            super::deser_cql_value(&float_vector, &mut &buf[..]).unwrap(),
+            CqlValue::Vector(vec![CqlValue::Float(0.5), CqlValue::Float(2.0)])
+        );
+        assert!(super::deser_cql_value(&float_vector, &mut &buf[..6]).is_err());
+
+        // Elements of variable length are prefixed with their length
+        let text_vector = ColumnType::Vector {
+            element: Box::new(ColumnType::Text),
+            dimensions: 2,
+        };
+        let buf: Vec<u8> = vec![1, b'a', 2, b'b', b'c'];
+        assert_eq!(
+            super::deser_cql_value(&text_vector, &mut &buf[..]).unwrap(),
+            CqlValue::Vector(vec![
+                CqlValue::Text("a".to_string()),
+                CqlValue::Text("bc".to_string())
+            ])
+        );
+    }
 }
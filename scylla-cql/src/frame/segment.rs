@@ -0,0 +1,438 @@
+//! Segment framing, used since protocol v5.
+//!
+//! Once a v5 connection is ready, frames are no longer written to the socket
+//! directly. Instead, they are packed into segments which carry a CRC24
+//! checksum of their header and a CRC32 checksum of their payload.
+//! A self-contained segment holds one or more complete frames; a frame which
+//! does not fit into a single segment is split across several segments which
+//! are not self-contained.
+//!
+//! Ref: <https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v5.spec>
+
+use crate::frame::frame_errors::FrameError;
+use crate::frame::response::ResponseOpcode;
+use crate::frame::{parse_response_header, FrameParams, HEADER_SIZE};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::convert::TryInto;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Maximum size of a segment payload (2^17 - 1 bytes).
+pub const MAX_PAYLOAD_SIZE: usize = (1 << 17) - 1;
+
+const UNCOMPRESSED_HEADER_SIZE: usize = 3;
+const COMPRESSED_HEADER_SIZE: usize = 5;
+const HEADER_CRC_SIZE: usize = 3;
+const PAYLOAD_CRC_SIZE: usize = 4;
+
+const CRC24_INIT: u32 = 0x875060;
+const CRC24_POLY: u32 = 0x1974F0B;
+
+// The payload checksum is seeded with these bytes, so that a payload made
+// only of zeroes does not have a zero checksum.
+const CRC32_INITIAL_BYTES: [u8; 4] = [0xFA, 0x2D, 0x55, 0xCA];
+
+fn crc24(bytes: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for &byte in bytes {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&CRC32_INITIAL_BYTES);
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// Header of a single segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentHeader {
+    /// Length of the payload as sent on the wire.
+    pub payload_length: usize,
+    /// Length of the payload after decompression. Zero if the payload
+    /// was sent uncompressed, which is always the case on connections
+    /// without compression.
+    pub uncompressed_length: usize,
+    /// Whether the payload consists of complete frames only.
+    pub self_contained: bool,
+}
+
+impl SegmentHeader {
+    /// Returns the size of the header together with its checksum.
+    pub fn encoded_size(compressed: bool) -> usize {
+        if compressed {
+            COMPRESSED_HEADER_SIZE + HEADER_CRC_SIZE
+        } else {
+            UNCOMPRESSED_HEADER_SIZE + HEADER_CRC_SIZE
+        }
+    }
+
+    fn serialize(&self, compressed: bool, buf: &mut impl BufMut) {
+        let (header, header_size) = if compressed {
+            let header = self.payload_length as u64
+                | (self.uncompressed_length as u64) << 17
+                | (self.self_contained as u64) << 34;
+            (header, COMPRESSED_HEADER_SIZE)
+        } else {
+            let header = self.payload_length as u64 | (self.self_contained as u64) << 17;
+            (header, UNCOMPRESSED_HEADER_SIZE)
+        };
+
+        let header_bytes = &header.to_le_bytes()[..header_size];
+        buf.put_slice(header_bytes);
+        buf.put_slice(&crc24(header_bytes).to_le_bytes()[..HEADER_CRC_SIZE]);
+    }
+
+    /// Parses a header from exactly `SegmentHeader::encoded_size(compressed)`
+    /// bytes, verifying its checksum.
+    pub fn deserialize(raw: &[u8], compressed: bool) -> Result<Self, FrameError> {
+        let header_size = Self::encoded_size(compressed) - HEADER_CRC_SIZE;
+        let (header_bytes, crc_bytes) = raw.split_at(header_size);
+
+        let mut received = [0u8; 4];
+        received[..HEADER_CRC_SIZE].copy_from_slice(crc_bytes);
+        let received = u32::from_le_bytes(received);
+        let computed = crc24(header_bytes);
+        if computed != received {
+            return Err(FrameError::SegmentHeaderChecksumMismatch { computed, received });
+        }
+
+        let mut header = [0u8; 8];
+        header[..header_size].copy_from_slice(header_bytes);
+        let header = u64::from_le_bytes(header);
+
+        let length_mask = (1 << 17) - 1;
+        Ok(if compressed {
+            SegmentHeader {
+                payload_length: (header & length_mask) as usize,
+                uncompressed_length: ((header >> 17) & length_mask) as usize,
+                self_contained: header & (1 << 34) != 0,
+            }
+        } else {
+            SegmentHeader {
+                payload_length: (header & length_mask) as usize,
+                uncompressed_length: 0,
+                self_contained: header & (1 << 17) != 0,
+            }
+        })
+    }
+}
+
+/// Writes a single segment with the given payload.
+///
+/// LZ4 is the only compression algorithm supported by segments. If `compressed`
+/// is set but compression would not make the payload any shorter, the payload
+/// is sent uncompressed, as the protocol allows.
+pub fn write_segment(
+    payload: &[u8],
+    self_contained: bool,
+    compressed: bool,
+    out: &mut Vec<u8>,
+) -> Result<(), FrameError> {
+    if payload.len() > MAX_PAYLOAD_SIZE {
+        return Err(FrameError::SegmentTooLarge(payload.len(), MAX_PAYLOAD_SIZE));
+    }
+
+    let compressed_payload = if compressed {
+        Some(lz4_flex::block::compress(payload)).filter(|c| c.len() < payload.len())
+    } else {
+        None
+    };
+
+    let (header, payload) = match &compressed_payload {
+        Some(compressed_payload) => (
+            SegmentHeader {
+                payload_length: compressed_payload.len(),
+                uncompressed_length: payload.len(),
+                self_contained,
+            },
+            &compressed_payload[..],
+        ),
+        None => (
+            SegmentHeader {
+                payload_length: payload.len(),
+                uncompressed_length: 0,
+                self_contained,
+            },
+            payload,
+        ),
+    };
+
+    out.reserve(SegmentHeader::encoded_size(compressed) + payload.len() + PAYLOAD_CRC_SIZE);
+    header.serialize(compressed, out);
+    out.extend_from_slice(payload);
+    out.put_u32_le(crc32(payload));
+    Ok(())
+}
+
+/// Verifies the checksum of a segment payload and decompresses it if needed.
+/// `raw` must consist of the payload followed by its checksum.
+pub fn decode_payload(
+    header: &SegmentHeader,
+    raw: &[u8],
+    compressed: bool,
+) -> Result<Bytes, FrameError> {
+    let (payload, crc_bytes) = raw.split_at(raw.len() - PAYLOAD_CRC_SIZE);
+
+    let received = u32::from_le_bytes(crc_bytes.try_into().unwrap());
+    let computed = crc32(payload);
+    if computed != received {
+        return Err(FrameError::SegmentPayloadChecksumMismatch { computed, received });
+    }
+
+    if compressed && header.uncompressed_length != 0 {
+        Ok(lz4_flex::block::decompress(payload, header.uncompressed_length)?.into())
+    } else {
+        Ok(Bytes::copy_from_slice(payload))
+    }
+}
+
+/// Reads a single segment and returns its decoded payload.
+pub async fn read_segment(
+    reader: &mut (impl AsyncRead + Unpin),
+    compressed: bool,
+) -> Result<Bytes, FrameError> {
+    let mut raw_header = [0u8; COMPRESSED_HEADER_SIZE + HEADER_CRC_SIZE];
+    let raw_header = &mut raw_header[..SegmentHeader::encoded_size(compressed)];
+    reader.read_exact(raw_header).await?;
+    let header = SegmentHeader::deserialize(raw_header, compressed)?;
+
+    let mut raw_payload = vec![0u8; header.payload_length + PAYLOAD_CRC_SIZE];
+    reader.read_exact(&mut raw_payload).await?;
+    decode_payload(&header, &raw_payload, compressed)
+}
+
+/// Packs outgoing frames into segments.
+///
+/// Frames are gathered into self-contained segments until the maximum
+/// payload size is reached. A frame which does not fit into a single segment
+/// is split across as many segments as needed.
+pub struct SegmentEncoder {
+    compressed: bool,
+    pending: Vec<u8>,
+}
+
+impl SegmentEncoder {
+    pub fn new(compressed: bool) -> Self {
+        Self {
+            compressed,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Adds a frame, writing out the segments which became full to `out`.
+    pub fn push_frame(&mut self, frame: &[u8], out: &mut Vec<u8>) -> Result<(), FrameError> {
+        if frame.len() > MAX_PAYLOAD_SIZE {
+            self.flush(out)?;
+            for part in frame.chunks(MAX_PAYLOAD_SIZE) {
+                write_segment(part, false, self.compressed, out)?;
+            }
+            return Ok(());
+        }
+
+        if self.pending.len() + frame.len() > MAX_PAYLOAD_SIZE {
+            self.flush(out)?;
+        }
+        self.pending.extend_from_slice(frame);
+        Ok(())
+    }
+
+    /// Writes out the frames gathered so far as a single segment.
+    pub fn flush(&mut self, out: &mut Vec<u8>) -> Result<(), FrameError> {
+        if !self.pending.is_empty() {
+            write_segment(&self.pending, true, self.compressed, out)?;
+            self.pending.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Extracts incoming response frames from segments.
+pub struct SegmentDecoder {
+    compressed: bool,
+    buffer: BytesMut,
+}
+
+impl SegmentDecoder {
+    pub fn new(compressed: bool) -> Self {
+        Self {
+            compressed,
+            buffer: BytesMut::new(),
+        }
+    }
+
+    /// Returns the next response frame, reading as many segments as needed.
+    pub async fn read_response_frame(
+        &mut self,
+        reader: &mut (impl AsyncRead + Unpin),
+    ) -> Result<(FrameParams, ResponseOpcode, Bytes), FrameError> {
+        loop {
+            if let Some(frame) = self.next_frame()? {
+                return Ok(frame);
+            }
+            let payload = read_segment(reader, self.compressed).await?;
+            self.push_payload(&payload);
+        }
+    }
+
+    /// Appends a decoded segment payload to the frames being reassembled.
+    pub fn push_payload(&mut self, payload: &[u8]) {
+        self.buffer.extend_from_slice(payload);
+    }
+
+    /// Returns the next frame if all of its bytes have already been received.
+    pub fn next_frame(
+        &mut self,
+    ) -> Result<Option<(FrameParams, ResponseOpcode, Bytes)>, FrameError> {
+        if self.buffer.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let raw_header: &[u8; HEADER_SIZE] = self.buffer[..HEADER_SIZE].try_into().unwrap();
+        let (params, opcode, length) = parse_response_header(raw_header)?;
+        if self.buffer.len() < HEADER_SIZE + length {
+            return Ok(None);
+        }
+
+        self.buffer.advance(HEADER_SIZE);
+        let body = self.buffer.split_to(length).freeze();
+        Ok(Some((params, opcode, body)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_response_frame(stream: i16, body: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x85, 0x00];
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.push(ResponseOpcode::Result as u8);
+        frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frame.extend_from_slice(body);
+        frame
+    }
+
+    // Splits encoded segments back into their decoded payloads
+    fn decode_segments(mut buf: &[u8], compressed: bool) -> Vec<(SegmentHeader, Bytes)> {
+        let mut segments = Vec::new();
+        while !buf.is_empty() {
+            let header_size = SegmentHeader::encoded_size(compressed);
+            let header = SegmentHeader::deserialize(&buf[..header_size], compressed).unwrap();
+            buf.advance(header_size);
+            let raw_len = header.payload_length + PAYLOAD_CRC_SIZE;
+            let payload = decode_payload(&header, &buf[..raw_len], compressed).unwrap();
+            buf.advance(raw_len);
+            segments.push((header, payload));
+        }
+        segments
+    }
+
+    #[test]
+    fn segment_header_roundtrip() {
+        for compressed in [false, true] {
+            for self_contained in [false, true] {
+                let header = SegmentHeader {
+                    payload_length: MAX_PAYLOAD_SIZE,
+                    uncompressed_length: if compressed { 1234 } else { 0 },
+                    self_contained,
+                };
+                let mut buf = Vec::new();
+                header.serialize(compressed, &mut buf);
+                assert_eq!(buf.len(), SegmentHeader::encoded_size(compressed));
+                assert_eq!(
+                    SegmentHeader::deserialize(&buf, compressed).unwrap(),
+                    header
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted_segment_is_rejected() {
+        let mut buf = Vec::new();
+        write_segment(b"some payload", true, false, &mut buf).unwrap();
+
+        let mut bad_header = buf.clone();
+        bad_header[0] ^= 0x01;
+        assert!(matches!(
+            SegmentHeader::deserialize(&bad_header[..6], false),
+            Err(FrameError::SegmentHeaderChecksumMismatch { .. })
+        ));
+
+        let mut bad_payload = buf;
+        bad_payload[8] ^= 0x01;
+        let header = SegmentHeader::deserialize(&bad_payload[..6], false).unwrap();
+        assert!(matches!(
+            decode_payload(&header, &bad_payload[6..], false),
+            Err(FrameError::SegmentPayloadChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn compressed_segment_roundtrip() {
+        let compressible = "Hello, World!".repeat(100);
+        let incompressible: Vec<u8> = (0..=255).collect();
+
+        for payload in [compressible.as_bytes(), &incompressible[..]] {
+            let mut buf = Vec::new();
+            write_segment(payload, true, true, &mut buf).unwrap();
+            let segments = decode_segments(&buf, true);
+            assert_eq!(segments.len(), 1);
+            assert_eq!(segments[0].1, payload);
+        }
+
+        let mut buf = Vec::new();
+        write_segment(compressible.as_bytes(), true, true, &mut buf).unwrap();
+        assert!(buf.len() < compressible.len());
+    }
+
+    #[test]
+    fn frames_are_packed_and_reassembled() {
+        let small_frames: Vec<Vec<u8>> = (0..3)
+            .map(|i| make_response_frame(i, &[i as u8; 100]))
+            .collect();
+        let big_frame = make_response_frame(3, &vec![0xAB; 2 * MAX_PAYLOAD_SIZE + 10]);
+
+        let mut encoder = SegmentEncoder::new(false);
+        let mut out = Vec::new();
+        for frame in &small_frames {
+            encoder.push_frame(frame, &mut out).unwrap();
+        }
+        encoder.push_frame(&big_frame, &mut out).unwrap();
+        encoder.flush(&mut out).unwrap();
+
+        let segments = decode_segments(&out, false);
+        // All small frames share one self-contained segment, the big one is split into three
+        let self_contained: Vec<bool> = segments.iter().map(|(h, _)| h.self_contained).collect();
+        assert_eq!(self_contained, vec![true, false, false, false]);
+
+        let mut decoder = SegmentDecoder::new(false);
+        let mut received = Vec::new();
+        for (_, payload) in segments {
+            decoder.push_payload(&payload);
+            while let Some((params, _, body)) = decoder.next_frame().unwrap() {
+                received.push((params.stream, body));
+            }
+        }
+
+        assert_eq!(received.len(), 4);
+        for (i, (stream, body)) in received.iter().enumerate() {
+            assert_eq!(*stream, i as i16);
+            let expected = if i < 3 {
+                &small_frames[i][HEADER_SIZE..]
+            } else {
+                &big_frame[HEADER_SIZE..]
+            };
+            assert_eq!(body, expected);
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Types standing for CQL types at compile time.
+//! The `query!` macro uses them to check that Rust values match the columns of a schema:
+//! a value bound to an `int` column must implement [`SerializeAs<Int>`](SerializeAs),
+//! a field read from a `list<text>` column must implement [`DeserializeAs<List<Text>>`](DeserializeAs).
+//!
+//! Rows are checked with [`DeserializeRowAs`], implemented by `#[derive(FromRow)]`
+//! for the lists of columns the struct can be read from.
+//!
+//! Native Rust types get their markers from the same tables as `Value::matches_type`
+//! and `FromCqlVal::matches_type`, so the compile-time and run-time checks agree.
+//! Markers are never constructed.
+
+use super::response::result::{ColumnType, CqlValue};
+use super::value::{CqlDecimal, CqlDuration, CqlTimeuuid, CqlVarint, MaybeUnset, Unset, Value};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::marker::PhantomData;
+use std::net::IpAddr;
+
+#[cfg(feature = "secret")]
+use secrecy::{Secret, Zeroize};
+
+/// The Rust type can be sent as a value of the CQL type `M`
+pub trait SerializeAs<M> {}
+
+/// The Rust type can be read from a value of the CQL type `M`
+pub trait DeserializeAs<M> {}
+
+macro_rules! native_markers {
+    ($($(#[$attr:meta])* $M:ident),* $(,)?) => {
+        $(
+            $(#[$attr])*
+            pub enum $M {}
+
+            impl SerializeAs<$M> for CqlValue {}
+            impl DeserializeAs<$M> for CqlValue {}
+        )*
+    };
+}
+
+native_markers!(
+    Ascii, Boolean, Blob, Counter, Date, Decimal, Double, Duration, Float, Int, BigInt, Text,
+    Timestamp, Inet, SmallInt, TinyInt, Time, Timeuuid, Uuid, Varint,
+    /// Any user defined type, fields of UDTs aren't checked
+    Udt,
+);
+
+/// `list<T>`
+pub struct List<T>(PhantomData<T>);
+/// `set<T>`
+pub struct Set<T>(PhantomData<T>);
+/// `map<K, V>`
+pub struct Map<K, V>(PhantomData<(K, V)>);
+/// `tuple<...>`, `T` is a Rust tuple of markers of the elements
+pub struct Tuple<T>(PhantomData<T>);
+/// `vector<T, N>`
+pub struct Vector<T, const N: usize>(PhantomData<T>);
+
+impl<T> SerializeAs<List<T>> for CqlValue {}
+impl<T> SerializeAs<Set<T>> for CqlValue {}
+impl<K, V> SerializeAs<Map<K, V>> for CqlValue {}
+impl<T> SerializeAs<Tuple<T>> for CqlValue {}
+impl<T, const N: usize> SerializeAs<Vector<T, N>> for CqlValue {}
+impl<T> DeserializeAs<List<T>> for CqlValue {}
+impl<T> DeserializeAs<Set<T>> for CqlValue {}
+impl<K, V> DeserializeAs<Map<K, V>> for CqlValue {}
+impl<T> DeserializeAs<Tuple<T>> for CqlValue {}
+impl<T, const N: usize> DeserializeAs<Vector<T, N>> for CqlValue {}
+
+/// CQL types which values of a native Rust type can be sent as, checked by `Value::matches_type`
+pub(crate) trait SerializedCqlTypes {
+    fn matches(typ: &ColumnType) -> bool;
+}
+
+/// CQL types which a native Rust type can be read from, checked by `FromCqlVal::matches_type`
+pub(crate) trait DeserializedCqlTypes {
+    fn matches(typ: &ColumnType) -> bool;
+}
+
+// Implements a marker trait together with the matching run-time check from a single table,
+// so the `query!` macro and the checks against prepared metadata always agree
+macro_rules! native_types {
+    ($marker_trait:ident, $types_trait:ident; $($T:ty => $($M:ident)|+;)*) => {
+        $(
+            $(impl $marker_trait<$M> for $T {})+
+
+            impl $types_trait for $T {
+                fn matches(typ: &ColumnType) -> bool {
+                    matches!(typ, $(ColumnType::$M)|+)
+                }
+            }
+        )*
+    };
+}
+
+native_types!(SerializeAs, SerializedCqlTypes;
+    bool => Boolean;
+    i8 => TinyInt;
+    i16 => SmallInt;
+    i32 => Int;
+    // Timestamps are milliseconds since the epoch, stored like a bigint
+    i64 => BigInt | Counter | Timestamp;
+    f32 => Float;
+    f64 => Double;
+    &str => Ascii | Text;
+    String => Ascii | Text;
+    Vec<u8> => Blob;
+    IpAddr => Inet;
+    uuid::Uuid => Uuid | Timeuuid;
+    CqlTimeuuid => Timeuuid;
+    CqlVarint => Varint;
+    CqlDecimal => Decimal;
+    CqlDuration => Duration;
+    super::value::Counter => Counter;
+    NaiveDate => Date;
+    super::value::Date => Date;
+    super::value::Time => Time;
+    super::value::Timestamp => Timestamp;
+    DateTime<Utc> => BigInt | Timestamp;
+);
+
+native_types!(DeserializeAs, DeserializedCqlTypes;
+    bool => Boolean;
+    i8 => TinyInt;
+    i16 => SmallInt;
+    i32 => Int;
+    i64 => BigInt | Timestamp;
+    f32 => Float;
+    f64 => Double;
+    String => Ascii | Text;
+    Vec<u8> => Blob;
+    IpAddr => Inet;
+    uuid::Uuid => Uuid | Timeuuid;
+    CqlTimeuuid => Timeuuid;
+    CqlVarint => Varint;
+    CqlDecimal => Decimal;
+    CqlDuration => Duration;
+    super::value::Counter => Counter;
+    NaiveDate => Date;
+    chrono::Duration => Timestamp | Time;
+    super::value::Date => Date;
+    super::value::Time => Time;
+    super::value::Timestamp => Timestamp;
+    DateTime<Utc> => BigInt | Timestamp;
+);
+
+#[cfg(feature = "time")]
+native_types!(SerializeAs, SerializedCqlTypes;
+    time::Date => Date;
+    time::Time => Time;
+    time::OffsetDateTime => Timestamp;
+    time::PrimitiveDateTime => Timestamp;
+);
+
+#[cfg(feature = "time")]
+native_types!(DeserializeAs, DeserializedCqlTypes;
+    time::Date => Date;
+    time::Time => Time;
+    time::OffsetDateTime => Timestamp;
+    time::PrimitiveDateTime => Timestamp;
+);
+
+#[cfg(feature = "num-bigint-03")]
+native_types!(SerializeAs, SerializedCqlTypes; num_bigint_03::BigInt => Varint;);
+#[cfg(feature = "num-bigint-03")]
+native_types!(DeserializeAs, DeserializedCqlTypes; num_bigint_03::BigInt => Varint;);
+#[cfg(feature = "num-bigint-04")]
+native_types!(SerializeAs, SerializedCqlTypes; num_bigint_04::BigInt => Varint;);
+#[cfg(feature = "num-bigint-04")]
+native_types!(DeserializeAs, DeserializedCqlTypes; num_bigint_04::BigInt => Varint;);
+#[cfg(feature = "bigdecimal-02")]
+native_types!(SerializeAs, SerializedCqlTypes; bigdecimal_02::BigDecimal => Decimal;);
+#[cfg(feature = "bigdecimal-02")]
+native_types!(DeserializeAs, DeserializedCqlTypes; bigdecimal_02::BigDecimal => Decimal;);
+#[cfg(feature = "bigdecimal-04")]
+native_types!(SerializeAs, SerializedCqlTypes; bigdecimal_04::BigDecimal => Decimal;);
+#[cfg(feature = "bigdecimal-04")]
+native_types!(DeserializeAs, DeserializedCqlTypes; bigdecimal_04::BigDecimal => Decimal;);
+#[cfg(feature = "rust_decimal")]
+native_types!(SerializeAs, SerializedCqlTypes; rust_decimal::Decimal => Decimal;);
+#[cfg(feature = "rust_decimal")]
+native_types!(DeserializeAs, DeserializedCqlTypes; rust_decimal::Decimal => Decimal;);
+
+#[cfg(feature = "secret")]
+impl<M, V: SerializeAs<M> + Zeroize> SerializeAs<M> for Secret<V> {}
+#[cfg(feature = "secret")]
+impl<M, V: DeserializeAs<M> + Zeroize> DeserializeAs<M> for Secret<V> {}
+
+// Wrappers of values
+impl<M, T: SerializeAs<M>> SerializeAs<M> for Option<T> {}
+impl<M, T: SerializeAs<M> + Value> SerializeAs<M> for MaybeUnset<T> {}
+impl<M, T: SerializeAs<M> + ?Sized> SerializeAs<M> for &T {}
+impl<M, T: SerializeAs<M> + ?Sized> SerializeAs<M> for Box<T> {}
+impl<M> SerializeAs<M> for Unset {}
+impl<M, T: DeserializeAs<M>> DeserializeAs<M> for Option<T> {}
+
+// Collections, lists and sets are interchangeable
+impl<M, T: SerializeAs<M>> SerializeAs<List<M>> for Vec<T> {}
+impl<M, T: SerializeAs<M>> SerializeAs<Set<M>> for Vec<T> {}
+impl<M, T: SerializeAs<M>> SerializeAs<List<M>> for &[T] {}
+impl<M, T: SerializeAs<M>> SerializeAs<Set<M>> for &[T] {}
+impl<M, T: SerializeAs<M>> SerializeAs<Set<M>> for HashSet<T> {}
+impl<M, T: SerializeAs<M>> SerializeAs<Set<M>> for BTreeSet<T> {}
+impl<MK, MV, K: SerializeAs<MK>, V: SerializeAs<MV>> SerializeAs<Map<MK, MV>> for HashMap<K, V> {}
+impl<MK, MV, K: SerializeAs<MK>, V: SerializeAs<MV>> SerializeAs<Map<MK, MV>> for BTreeMap<K, V> {}
+impl<M, T: SerializeAs<M>, const N: usize> SerializeAs<Vector<M, N>> for [T; N] {}
+
+impl<M, T: DeserializeAs<M>> DeserializeAs<List<M>> for Vec<T> {}
+impl<M, T: DeserializeAs<M>> DeserializeAs<Set<M>> for Vec<T> {}
+impl<M, T: DeserializeAs<M>, const N: usize> DeserializeAs<Vector<M, N>> for Vec<T> {}
+impl<M, T: DeserializeAs<M>> DeserializeAs<List<M>> for HashSet<T> {}
+impl<M, T: DeserializeAs<M>> DeserializeAs<Set<M>> for HashSet<T> {}
+impl<M, T: DeserializeAs<M>> DeserializeAs<List<M>> for BTreeSet<T> {}
+impl<M, T: DeserializeAs<M>> DeserializeAs<Set<M>> for BTreeSet<T> {}
+impl<MK, MV, K: DeserializeAs<MK>, V: DeserializeAs<MV>> DeserializeAs<Map<MK, MV>>
+    for HashMap<K, V>
+{
+}
+impl<MK, MV, K: DeserializeAs<MK>, V: DeserializeAs<MV>> DeserializeAs<Map<MK, MV>>
+    for BTreeMap<K, V>
+{
+}
+impl<M, T: DeserializeAs<M>, const N: usize> DeserializeAs<Vector<M, N>> for [T; N] {}
+
+/// A column returned by a statement, named `N` and of type `T`, [`Typed`] or [`Untyped`].\
+/// Names are lists of characters, e.g. `(Ch<'i'>, (Ch<'d'>, ()))` for `id`.
+/// Columns without a name, e.g. results of functions without `AS`, are named [`Unnamed`].
+pub struct Column<N, T>(PhantomData<(N, T)>);
+/// A character of a column name
+pub struct Ch<const C: char>;
+/// The name of a column which can't be matched by name
+pub enum Unnamed {}
+/// A column of the CQL type `M`
+pub struct Typed<M>(PhantomData<M>);
+/// A column whose CQL type isn't known, it's not checked
+pub enum Untyped {}
+
+/// A field of type `F` can be read from the column
+pub trait ReadsField<F> {}
+impl<M, F: DeserializeAs<M>> ReadsField<F> for Typed<M> {}
+impl<F> ReadsField<F> for Untyped {}
+
+/// The row type can be read from the columns `L`, listed as `(Column<..>, (Column<..>, ()))`.\
+/// `X` is inferred: it tells where in `L` the fields of structs matching columns by name are.
+/// Implemented by `#[derive(FromRow)]`, checked by the `query_as!` macro.
+pub trait DeserializeRowAs<L, X> {}
+
+/// Reads its fields from the columns of `L` with the same names, `X` is inferred like in [`DeserializeRowAs`].
+/// Implemented by `#[derive(FromRow)]`, structs flattened into a struct matching columns by name
+/// are read this way.
+pub trait DeserializeNamedAs<L, X> {}
+
+/// Reads the first columns of `L` by position, leaving the other ones as `Rest`.
+/// Implemented by `#[derive(FromRow)]`, structs flattened into a struct reading columns by position
+/// are read this way.
+pub trait DeserializeColumnsAs<L> {
+    type Rest;
+}
+
+/// A non-empty list of columns
+pub trait ColumnList {
+    type Type;
+    type Tail;
+}
+impl<N, T, Tail> ColumnList for (Column<N, T>, Tail) {
+    type Type = T;
+    type Tail = Tail;
+}
+
+/// The column named `N` is in the list, at the position `I` which is inferred
+pub trait FindColumn<N, I> {
+    type Type;
+}
+/// The column is the first one of the list
+pub enum Here {}
+/// The column is in the tail of the list, at the position `I`
+pub struct There<I>(PhantomData<I>);
+impl<N, T, Tail> FindColumn<N, Here> for (Column<N, T>, Tail) {
+    type Type = T;
+}
+impl<N, Head, Tail: FindColumn<N, I>, I> FindColumn<N, There<I>> for (Head, Tail) {
+    type Type = Tail::Type;
+}
+
+macro_rules! impl_markers_for_tuple {
+    ($($T:ident: $M:ident),+) => {
+        impl<$($M, $T: SerializeAs<$M>),+> SerializeAs<Tuple<($($M,)+)>> for ($($T,)+) {}
+        impl<$($M, $T: DeserializeAs<$M>),+> DeserializeAs<Tuple<($($M,)+)>> for ($($T,)+) {}
+    };
+}
+
+impl_markers_for_tuple!(T0: M0);
+impl_markers_for_tuple!(T0: M0, T1: M1);
+impl_markers_for_tuple!(T0: M0, T1: M1, T2: M2);
+impl_markers_for_tuple!(T0: M0, T1: M1, T2: M2, T3: M3);
+impl_markers_for_tuple!(T0: M0, T1: M1, T2: M2, T3: M3, T4: M4);
+impl_markers_for_tuple!(T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5);
+impl_markers_for_tuple!(T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5, T6: M6);
+impl_markers_for_tuple!(T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5, T6: M6, T7: M7);
+impl_markers_for_tuple!(
+    T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5, T6: M6, T7: M7, T8: M8
+);
+impl_markers_for_tuple!(
+    T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5, T6: M6, T7: M7, T8: M8, T9: M9
+);
+impl_markers_for_tuple!(
+    T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5, T6: M6, T7: M7, T8: M8, T9: M9, T10: M10
+);
+impl_markers_for_tuple!(
+    T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5, T6: M6, T7: M7, T8: M8, T9: M9, T10: M10,
+    T11: M11
+);
+impl_markers_for_tuple!(
+    T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5, T6: M6, T7: M7, T8: M8, T9: M9, T10: M10,
+    T11: M11, T12: M12
+);
+impl_markers_for_tuple!(
+    T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5, T6: M6, T7: M7, T8: M8, T9: M9, T10: M10,
+    T11: M11, T12: M12, T13: M13
+);
+impl_markers_for_tuple!(
+    T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5, T6: M6, T7: M7, T8: M8, T9: M9, T10: M10,
+    T11: M11, T12: M12, T13: M13, T14: M14
+);
+impl_markers_for_tuple!(
+    T0: M0, T1: M1, T2: M2, T3: M3, T4: M4, T5: M5, T6: M6, T7: M7, T8: M8, T9: M9, T10: M10,
+    T11: M11, T12: M12, T13: M13, T14: M14, T15: M15
+);
@@ -1,18 +1,20 @@
 use crate::frame::types;
-use bigdecimal::BigDecimal;
 use bytes::BufMut;
 use chrono::prelude::*;
 use chrono::Duration;
-use num_bigint::BigInt;
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::convert::TryInto;
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering as AtomicOrdering};
+use std::time::SystemTime;
 use thiserror::Error;
 use uuid::Uuid;
 
-use super::response::result::CqlValue;
+use super::response::result::{ColumnSpec, ColumnType, CqlValue};
 use super::types::vint_encode;
+use super::type_markers::SerializedCqlTypes;
 
 #[cfg(feature = "secret")]
 use secrecy::{ExposeSecret, Secret, Zeroize};
@@ -21,6 +23,12 @@ use secrecy::{ExposeSecret, Secret, Zeroize};
 /// serialize() should write the Value as [bytes] to the provided buffer
 pub trait Value {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig>;
+
+    /// Checks whether the value can be sent as a value of the given CQL type.\
+    /// Types which don't override it are assumed to match every CQL type.
+    fn matches_type(&self, _typ: &ColumnType) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Error, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -73,6 +81,638 @@ pub struct CqlDuration {
     pub nanoseconds: i64,
 }
 
+/// Represents a CQL Timeuuid value, i.e. a version 1 (time-based) UUID.
+///
+/// Unlike `Uuid`, timeuuids are ordered the way Scylla compares them: by their timestamp first,
+/// and then by the remaining bytes (clock sequence and node id) compared as signed bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct CqlTimeuuid(Uuid);
+
+/// Number of 100ns intervals between the start of the Gregorian calendar (1582-10-15),
+/// which is the epoch of version 1 UUIDs, and the unix epoch
+const GREGORIAN_EPOCH_OFFSET: i64 = 0x01B2_1DD2_1381_4000;
+const TICKS_PER_MILLISECOND: i64 = 10_000;
+/// Lowest and highest `lsb` (bytes 8..16), as compared by `CqlTimeuuid::lsb_signed`
+const MIN_CLOCK_SEQ_AND_NODE: u64 = 0x8080_8080_8080_8080;
+const MAX_CLOCK_SEQ_AND_NODE: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+
+impl CqlTimeuuid {
+    /// Generates a new timeuuid for the current time.
+    ///
+    /// Timeuuids generated by one process are unique and strictly increasing: when the clock
+    /// doesn't advance between calls, or goes backwards, the timestamp is bumped by 100ns instead.
+    /// The clock sequence and the node id are chosen randomly once per process,
+    /// with the multicast bit of the node id set, as RFC 4122 requires for ids that aren't MAC addresses.
+    pub fn now() -> Self {
+        static LAST_TICKS: AtomicI64 = AtomicI64::new(i64::MIN);
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since_epoch| {
+                (since_epoch.as_nanos() / 100) as i64 + GREGORIAN_EPOCH_OFFSET
+            });
+        let mut last = LAST_TICKS.load(AtomicOrdering::Relaxed);
+        let ticks = loop {
+            let next = now.max(last.saturating_add(1));
+            match LAST_TICKS.compare_exchange_weak(
+                last,
+                next,
+                AtomicOrdering::Relaxed,
+                AtomicOrdering::Relaxed,
+            ) {
+                Ok(_) => break next,
+                Err(current) => last = current,
+            }
+        };
+        Self::from_ticks(ticks, process_clock_seq_and_node())
+    }
+
+    /// Creates a timeuuid from a timestamp (time since the unix epoch, with 100ns precision),
+    /// a clock sequence (only the lowest 14 bits are used) and a node id
+    pub fn from_unix_timestamp(timestamp: Duration, clock_seq: u16, node_id: [u8; 6]) -> Self {
+        let mut lsb = [0u8; 8];
+        lsb[..2].copy_from_slice(&(clock_seq & 0x3fff | 0x8000).to_be_bytes());
+        lsb[2..].copy_from_slice(&node_id);
+        Self::from_ticks(Self::timestamp_to_ticks(timestamp), u64::from_be_bytes(lsb))
+    }
+
+    /// The smallest timeuuid with the given timestamp, equivalent to CQL `minTimeuuid`.
+    ///
+    /// Like in CQL, the timestamp is truncated to milliseconds, so that
+    /// `min_timeuuid(t)..=max_timeuuid(t)` contains all timeuuids generated during that millisecond.
+    pub fn min_timeuuid(timestamp: Duration) -> Self {
+        let ticks = timestamp.num_milliseconds() * TICKS_PER_MILLISECOND + GREGORIAN_EPOCH_OFFSET;
+        Self::from_ticks(ticks, MIN_CLOCK_SEQ_AND_NODE)
+    }
+
+    /// The greatest timeuuid with the given timestamp, equivalent to CQL `maxTimeuuid`
+    pub fn max_timeuuid(timestamp: Duration) -> Self {
+        let ticks =
+            (timestamp.num_milliseconds() + 1) * TICKS_PER_MILLISECOND + GREGORIAN_EPOCH_OFFSET - 1;
+        Self::from_ticks(ticks, MAX_CLOCK_SEQ_AND_NODE)
+    }
+
+    /// Time since the unix epoch encoded in the timeuuid, with 100ns precision
+    pub fn unix_timestamp(&self) -> Duration {
+        let ticks = self.ticks() - GREGORIAN_EPOCH_OFFSET;
+        Duration::milliseconds(ticks.div_euclid(TICKS_PER_MILLISECOND))
+            + Duration::nanoseconds(ticks.rem_euclid(TICKS_PER_MILLISECOND) * 100)
+    }
+
+    /// Time since the unix epoch encoded in the timeuuid, truncated to milliseconds
+    /// like the CQL `toUnixTimestamp` function does
+    pub fn unix_timestamp_millis(&self) -> i64 {
+        (self.ticks() - GREGORIAN_EPOCH_OFFSET).div_euclid(TICKS_PER_MILLISECOND)
+    }
+
+    /// The 14-bit clock sequence of the timeuuid
+    pub fn clock_seq(&self) -> u16 {
+        let bytes = self.0.as_bytes();
+        u16::from_be_bytes([bytes[8], bytes[9]]) & 0x3fff
+    }
+
+    /// The node id of the timeuuid
+    pub fn node_id(&self) -> [u8; 6] {
+        let mut node_id = [0u8; 6];
+        node_id.copy_from_slice(&self.0.as_bytes()[10..]);
+        node_id
+    }
+
+    pub fn as_uuid(&self) -> &Uuid {
+        &self.0
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        self.0.as_bytes()
+    }
+
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(Uuid::from_bytes(bytes))
+    }
+
+    fn timestamp_to_ticks(timestamp: Duration) -> i64 {
+        let millis = timestamp.num_milliseconds();
+        let sub_milli_nanos = (timestamp - Duration::milliseconds(millis))
+            .num_nanoseconds()
+            .unwrap_or(0);
+        millis * TICKS_PER_MILLISECOND + sub_milli_nanos / 100 + GREGORIAN_EPOCH_OFFSET
+    }
+
+    fn from_ticks(ticks: i64, clock_seq_and_node: u64) -> Self {
+        let ticks = ticks as u64;
+        let mut bytes = [0u8; 16];
+        bytes[..4].copy_from_slice(&(ticks as u32).to_be_bytes());
+        bytes[4..6].copy_from_slice(&((ticks >> 32) as u16).to_be_bytes());
+        bytes[6..8].copy_from_slice(&((ticks >> 48) as u16 & 0x0fff | 0x1000).to_be_bytes());
+        bytes[8..].copy_from_slice(&clock_seq_and_node.to_be_bytes());
+        Self(Uuid::from_bytes(bytes))
+    }
+
+    /// The 60-bit timestamp, reassembled from the time_hi, time_mid and time_low fields
+    fn ticks(&self) -> i64 {
+        let bytes = self.0.as_bytes();
+        let time_hi = u16::from_be_bytes([bytes[6] & 0x0f, bytes[7]]) as i64;
+        let time_mid = u16::from_be_bytes([bytes[4], bytes[5]]) as i64;
+        let time_low = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64;
+        time_hi << 48 | time_mid << 32 | time_low
+    }
+
+    /// Bytes 8..16 of the timeuuid, transformed so that comparing them as unsigned integers
+    /// gives the same result as comparing the bytes one by one as `i8`
+    fn lsb_signed(&self) -> u64 {
+        let mut lsb = [0u8; 8];
+        lsb.copy_from_slice(&self.0.as_bytes()[8..]);
+        u64::from_be_bytes(lsb) ^ 0x8080_8080_8080_8080
+    }
+}
+
+// Chosen once per process, see CqlTimeuuid::now
+fn process_clock_seq_and_node() -> u64 {
+    static CLOCK_SEQ_AND_NODE: AtomicU64 = AtomicU64::new(0);
+    let current = CLOCK_SEQ_AND_NODE.load(AtomicOrdering::Relaxed);
+    if current != 0 {
+        return current;
+    }
+    let random = Uuid::new_v4();
+    let mut lsb = [0u8; 8];
+    lsb.copy_from_slice(&random.as_bytes()[8..]);
+    // RFC 4122 variant in the clock sequence, multicast bit in the node id
+    lsb[0] = lsb[0] & 0x3f | 0x80;
+    lsb[2] |= 0x01;
+    let generated = u64::from_be_bytes(lsb);
+    // The variant bits make a generated value nonzero. If another thread was first, use its value.
+    match CLOCK_SEQ_AND_NODE.compare_exchange(
+        0,
+        generated,
+        AtomicOrdering::Relaxed,
+        AtomicOrdering::Relaxed,
+    ) {
+        Ok(_) => generated,
+        Err(existing) => existing,
+    }
+}
+
+impl PartialEq for CqlTimeuuid {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for CqlTimeuuid {}
+
+impl PartialOrd for CqlTimeuuid {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CqlTimeuuid {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ticks()
+            .cmp(&other.ticks())
+            .then_with(|| self.lsb_signed().cmp(&other.lsb_signed()))
+    }
+}
+
+impl std::hash::Hash for CqlTimeuuid {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // Consistent with Eq, which ignores the version bits
+        self.ticks().hash(state);
+        self.lsb_signed().hash(state);
+    }
+}
+
+impl From<Uuid> for CqlTimeuuid {
+    fn from(uuid: Uuid) -> Self {
+        Self(uuid)
+    }
+}
+
+impl From<CqlTimeuuid> for Uuid {
+    fn from(timeuuid: CqlTimeuuid) -> Self {
+        timeuuid.0
+    }
+}
+
+impl AsRef<Uuid> for CqlTimeuuid {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for CqlTimeuuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::str::FromStr for CqlTimeuuid {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::from_str(s).map(Self)
+    }
+}
+
+/// Represents a CQL Varint value: an arbitrary-precision integer, stored as big-endian
+/// two's complement bytes, the same way as in the protocol.
+///
+/// Conversions to and from `num_bigint::BigInt` are available with
+/// the `num-bigint-03` and `num-bigint-04` features.
+///
+/// Values are compared by the number they represent, so redundant leading sign bytes
+/// (e.g. `[0x00, 0x01]` and `[0x01]`) don't affect equality.
+#[derive(Clone, Debug)]
+pub struct CqlVarint(Vec<u8>);
+
+/// Represents a CQL Decimal value: an arbitrary-precision integer and a scale,
+/// the value being `unscaled * 10^-scale`.
+///
+/// Conversions to and from `bigdecimal::BigDecimal` are available with the `bigdecimal-02`
+/// and `bigdecimal-04` features, and to and from `rust_decimal::Decimal`
+/// with the `rust_decimal` feature.
+///
+/// Like in CQL, decimals with different scales are different values, e.g. `1.0 != 1`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CqlDecimal {
+    int_val: CqlVarint,
+    scale: i32,
+}
+
+/// A number couldn't be parsed from its decimal representation
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[error("Invalid number")]
+pub struct ParseCqlNumberError;
+
+/// A value doesn't fit in the range of the type it's converted to
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[error("Value out of range of the target type")]
+pub struct ValueOverflow;
+
+impl CqlVarint {
+    pub fn from_signed_bytes_be(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    pub fn from_signed_bytes_be_slice(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+
+    pub fn as_signed_bytes_be_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_signed_bytes_be(self) -> Vec<u8> {
+        self.0
+    }
+
+    fn is_negative(&self) -> bool {
+        matches!(self.0.first(), Some(byte) if byte & 0x80 != 0)
+    }
+
+    /// The bytes without redundant leading sign bytes. Zero is represented by an empty slice.
+    fn normalized(&self) -> &[u8] {
+        let bytes = strip_redundant_sign_bytes(&self.0);
+        if bytes == [0x00] {
+            &[]
+        } else {
+            bytes
+        }
+    }
+
+    fn from_normalized_vec(mut bytes: Vec<u8>) -> Self {
+        let redundant = bytes.len() - strip_redundant_sign_bytes(&bytes).len();
+        bytes.drain(..redundant);
+        Self(bytes)
+    }
+}
+
+fn strip_redundant_sign_bytes(mut bytes: &[u8]) -> &[u8] {
+    while let [first, second, ..] = bytes {
+        let redundant =
+            (*first == 0x00 && second & 0x80 == 0) || (*first == 0xff && second & 0x80 != 0);
+        if !redundant {
+            break;
+        }
+        bytes = &bytes[1..];
+    }
+    bytes
+}
+
+// Two's complement negation of a big-endian number
+fn negate_bytes(bytes: &mut [u8]) {
+    let mut carry = true;
+    for byte in bytes.iter_mut().rev() {
+        let (negated, overflow) = (!*byte).overflowing_add(carry as u8);
+        *byte = negated;
+        carry = overflow;
+    }
+}
+
+impl PartialEq for CqlVarint {
+    fn eq(&self, other: &Self) -> bool {
+        self.normalized() == other.normalized()
+    }
+}
+
+impl Eq for CqlVarint {}
+
+impl std::hash::Hash for CqlVarint {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.normalized().hash(state);
+    }
+}
+
+impl std::fmt::Display for CqlVarint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const CHUNK: u64 = 1_000_000_000;
+        let mut magnitude = self.0.clone();
+        if self.is_negative() {
+            negate_bytes(&mut magnitude);
+        }
+        // Base 10^9 digits, least significant first, computed by repeated long division
+        let mut chunks = Vec::new();
+        while !magnitude.is_empty() {
+            let mut remainder: u64 = 0;
+            for byte in magnitude.iter_mut() {
+                let current = remainder << 8 | *byte as u64;
+                *byte = (current / CHUNK) as u8;
+                remainder = current % CHUNK;
+            }
+            chunks.push(remainder);
+            let leading_zeros = magnitude.iter().take_while(|byte| **byte == 0).count();
+            magnitude.drain(..leading_zeros);
+        }
+        if self.is_negative() {
+            f.write_str("-")?;
+        }
+        match chunks.split_last() {
+            None => f.write_str("0"),
+            Some((most_significant, rest)) => {
+                write!(f, "{}", most_significant)?;
+                rest.iter()
+                    .rev()
+                    .try_for_each(|chunk| write!(f, "{:09}", chunk))
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for CqlVarint {
+    type Err = ParseCqlNumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(ParseCqlNumberError);
+        }
+        // Big-endian magnitude, multiplied by 10^k and increased by the next k digits at a time
+        let mut magnitude: Vec<u8> = Vec::new();
+        for chunk in digits.as_bytes().chunks(9) {
+            let multiplier = 10u64.pow(chunk.len() as u32);
+            let mut carry = chunk
+                .iter()
+                .fold(0u64, |acc, digit| acc * 10 + (digit - b'0') as u64);
+            for byte in magnitude.iter_mut().rev() {
+                let current = *byte as u64 * multiplier + carry;
+                *byte = current as u8;
+                carry = current >> 8;
+            }
+            while carry > 0 {
+                magnitude.insert(0, carry as u8);
+                carry >>= 8;
+            }
+        }
+        // Make room for the sign bit
+        magnitude.insert(0, 0);
+        if negative {
+            negate_bytes(&mut magnitude);
+        }
+        Ok(Self::from_normalized_vec(magnitude))
+    }
+}
+
+impl CqlDecimal {
+    pub fn from_signed_be_bytes_and_exponent(bytes: Vec<u8>, scale: i32) -> Self {
+        Self {
+            int_val: CqlVarint::from_signed_bytes_be(bytes),
+            scale,
+        }
+    }
+
+    pub fn from_signed_be_bytes_slice_and_exponent(bytes: &[u8], scale: i32) -> Self {
+        Self::from_signed_be_bytes_and_exponent(bytes.to_vec(), scale)
+    }
+
+    pub fn as_signed_be_bytes_slice_and_exponent(&self) -> (&[u8], i32) {
+        (self.int_val.as_signed_bytes_be_slice(), self.scale)
+    }
+
+    pub fn into_signed_be_bytes_and_exponent(self) -> (Vec<u8>, i32) {
+        (self.int_val.into_signed_bytes_be(), self.scale)
+    }
+}
+
+// Uses the same notation as Java's BigDecimal::toString, so that values look like in cqlsh
+impl std::fmt::Display for CqlDecimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unscaled = self.int_val.to_string();
+        let (sign, digits) = match unscaled.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", unscaled.as_str()),
+        };
+        let adjusted_exponent = digits.len() as i64 - 1 - self.scale as i64;
+        if self.scale == 0 {
+            write!(f, "{}{}", sign, digits)
+        } else if self.scale > 0 && adjusted_exponent >= -6 {
+            let scale = self.scale as usize;
+            if digits.len() > scale {
+                let (integer, fraction) = digits.split_at(digits.len() - scale);
+                write!(f, "{}{}.{}", sign, integer, fraction)
+            } else {
+                write!(f, "{}0.{:0>width$}", sign, digits, width = scale)
+            }
+        } else {
+            let (first, rest) = digits.split_at(1);
+            let point = if rest.is_empty() { "" } else { "." };
+            let exponent_sign = if adjusted_exponent >= 0 { "+" } else { "" };
+            write!(
+                f,
+                "{}{}{}{}E{}{}",
+                sign, first, point, rest, exponent_sign, adjusted_exponent
+            )
+        }
+    }
+}
+
+impl std::str::FromStr for CqlDecimal {
+    type Err = ParseCqlNumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(e) => (
+                &s[..e],
+                s[e + 1..].parse::<i64>().map_err(|_| ParseCqlNumberError)?,
+            ),
+            None => (s, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if !fraction.bytes().all(|c| c.is_ascii_digit())
+            || (fraction.is_empty() && !integer.ends_with(|c: char| c.is_ascii_digit()))
+        {
+            return Err(ParseCqlNumberError);
+        }
+        let int_val: CqlVarint = format!("{}{}", integer, fraction).parse()?;
+        let scale = (fraction.len() as i64)
+            .checked_sub(exponent)
+            .and_then(|scale| i32::try_from(scale).ok())
+            .ok_or(ParseCqlNumberError)?;
+        Ok(Self { int_val, scale })
+    }
+}
+
+#[cfg(any(feature = "num-bigint-03", feature = "num-bigint-04"))]
+macro_rules! impl_num_bigint_conversions {
+    ($num_bigint:ident) => {
+        impl From<$num_bigint::BigInt> for CqlVarint {
+            fn from(value: $num_bigint::BigInt) -> Self {
+                Self(value.to_signed_bytes_be())
+            }
+        }
+
+        impl From<CqlVarint> for $num_bigint::BigInt {
+            fn from(value: CqlVarint) -> Self {
+                Self::from_signed_bytes_be(&value.0)
+            }
+        }
+
+        impl Value for $num_bigint::BigInt {
+            fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+                let serialized = self.to_signed_bytes_be();
+                let serialized_len: i32 = serialized.len().try_into().map_err(|_| ValueTooBig)?;
+
+                buf.put_i32(serialized_len);
+                buf.extend_from_slice(&serialized);
+
+                Ok(())
+            }
+
+            fn matches_type(&self, typ: &ColumnType) -> bool {
+                <Self as SerializedCqlTypes>::matches(typ)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "num-bigint-03")]
+impl_num_bigint_conversions!(num_bigint_03);
+#[cfg(feature = "num-bigint-04")]
+impl_num_bigint_conversions!(num_bigint_04);
+
+#[cfg(any(feature = "bigdecimal-02", feature = "bigdecimal-04"))]
+macro_rules! impl_bigdecimal_conversions {
+    ($bigdecimal:ident, $num_bigint:ident) => {
+        impl TryFrom<$bigdecimal::BigDecimal> for CqlDecimal {
+            type Error = ValueOverflow;
+
+            fn try_from(value: $bigdecimal::BigDecimal) -> Result<Self, Self::Error> {
+                let (int_val, scale) = value.as_bigint_and_exponent();
+                Ok(Self {
+                    int_val: int_val.into(),
+                    scale: scale.try_into().map_err(|_| ValueOverflow)?,
+                })
+            }
+        }
+
+        impl From<CqlDecimal> for $bigdecimal::BigDecimal {
+            fn from(value: CqlDecimal) -> Self {
+                let int_val: $num_bigint::BigInt = value.int_val.into();
+                Self::from((int_val, value.scale as i64))
+            }
+        }
+
+        impl Value for $bigdecimal::BigDecimal {
+            fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+                let (value, scale) = self.as_bigint_and_exponent();
+
+                let serialized = value.to_signed_bytes_be();
+                let serialized_len: i32 = serialized.len().try_into().map_err(|_| ValueTooBig)?;
+
+                buf.put_i32(serialized_len + 4);
+                buf.put_i32(scale.try_into().map_err(|_| ValueTooBig)?);
+                buf.extend_from_slice(&serialized);
+
+                Ok(())
+            }
+
+            fn matches_type(&self, typ: &ColumnType) -> bool {
+                <Self as SerializedCqlTypes>::matches(typ)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "bigdecimal-02")]
+impl_bigdecimal_conversions!(bigdecimal_02, num_bigint_03);
+#[cfg(feature = "bigdecimal-04")]
+impl_bigdecimal_conversions!(bigdecimal_04, num_bigint_04);
+
+#[cfg(feature = "rust_decimal")]
+impl From<rust_decimal::Decimal> for CqlDecimal {
+    fn from(value: rust_decimal::Decimal) -> Self {
+        Self {
+            int_val: CqlVarint::from_normalized_vec(value.mantissa().to_be_bytes().to_vec()),
+            scale: value.scale() as i32,
+        }
+    }
+}
+
+/// Fails if the value doesn't fit in the 96-bit mantissa of `rust_decimal::Decimal`,
+/// or its scale is above 28
+#[cfg(feature = "rust_decimal")]
+impl TryFrom<CqlDecimal> for rust_decimal::Decimal {
+    type Error = ValueOverflow;
+
+    fn try_from(value: CqlDecimal) -> Result<Self, Self::Error> {
+        let bytes = value.int_val.normalized();
+        if bytes.len() > 16 {
+            return Err(ValueOverflow);
+        }
+        let sign_extension = if value.int_val.is_negative() {
+            0xff
+        } else {
+            0x00
+        };
+        let mut mantissa_bytes = [sign_extension; 16];
+        mantissa_bytes[16 - bytes.len()..].copy_from_slice(bytes);
+        let mut mantissa = i128::from_be_bytes(mantissa_bytes);
+        let mut scale = value.scale;
+        if scale < 0 {
+            mantissa = 10i128
+                .checked_pow(scale.unsigned_abs())
+                .and_then(|multiplier| mantissa.checked_mul(multiplier))
+                .ok_or(ValueOverflow)?;
+            scale = 0;
+        }
+        rust_decimal::Decimal::try_from_i128_with_scale(mantissa, scale as u32)
+            .map_err(|_| ValueOverflow)
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl Value for rust_decimal::Decimal {
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+        CqlDecimal::from(*self).serialize(buf)
+    }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
+}
+
 #[derive(Debug, Error, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SerializeValuesError {
     #[error("Too many values to add, max 32 767 values can be sent in a request")]
@@ -85,6 +725,23 @@ pub enum SerializeValuesError {
     ParseError,
 }
 
+/// Values passed to a statement don't match its bind markers
+#[derive(Debug, Error, Clone)]
+pub enum ValueTypeCheckError {
+    #[error(
+        "Wrong number of values: the statement has {expected} bind markers, got {actual} values"
+    )]
+    WrongValuesCount { expected: usize, actual: usize },
+    #[error("Value for column {column} has a wrong type: expected CQL type {expected:?}, got Rust type {actual}")]
+    TypeMismatch {
+        column: String,
+        expected: ColumnType,
+        actual: &'static str,
+    },
+    #[error("Value named {0} doesn't match any bind marker")]
+    NoSuchBindMarker(String),
+}
+
 pub type SerializedResult<'a> = Result<Cow<'a, SerializedValues>, SerializeValuesError>;
 
 /// Represents list of values to be sent in a query
@@ -94,6 +751,13 @@ pub trait ValueList {
     /// returns `Cow<SerializedValues>` to make impl ValueList for SerializedValues efficient
     fn serialized(&self) -> SerializedResult<'_>;
 
+    /// Checks that the values match the bind markers described by `specs`,
+    /// which usually come from [`PreparedMetadata`](super::response::result::PreparedMetadata).\
+    /// Lists which can't inspect their values, e.g. already serialized ones, accept any specs.
+    fn type_check(&self, _specs: &[ColumnSpec]) -> Result<(), ValueTypeCheckError> {
+        Ok(())
+    }
+
     fn write_to_request(&self, buf: &mut impl BufMut) -> Result<(), SerializeValuesError> {
         let serialized = self.serialized()?;
         SerializedValues::write_to_request(&serialized, buf);
@@ -102,6 +766,21 @@ pub trait ValueList {
     }
 }
 
+/// Writes a struct deriving `IntoUserType` as a part of another one, for `#[scylla(flatten)]` fields.
+/// Implemented by `#[derive(IntoUserType)]`.
+#[doc(hidden)]
+pub trait IntoUserTypeFields {
+    /// Serializes the struct's fields, without the size of the whole value
+    fn serialize_fields(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig>;
+}
+
+/// Adds a struct deriving `ValueList` to the values of another one, for `#[scylla(flatten)]` fields.
+/// Implemented by `#[derive(ValueList)]`.
+#[doc(hidden)]
+pub trait ValueListFields {
+    fn add_values(&self, values: &mut SerializedValues) -> Result<(), SerializeValuesError>;
+}
+
 impl SerializedValues {
     /// Creates empty value list
     pub const fn new() -> Self {
@@ -245,6 +924,14 @@ pub trait BatchValuesIterator<'a> {
         buf: &mut impl BufMut,
     ) -> Option<Result<(), SerializeValuesError>>;
     fn skip_next(&mut self) -> Option<()>;
+    /// Checks the next `ValueList` against `specs`, same as [`ValueList::type_check`].\
+    /// Iterators which can't inspect their values skip them without checking.
+    fn type_check_next(
+        &mut self,
+        _specs: &[ColumnSpec],
+    ) -> Option<Result<(), ValueTypeCheckError>> {
+        self.skip_next().map(|()| Ok(()))
+    }
 }
 
 /// Implements `BatchValuesIterator` from an `Iterator` over references to things that implement `ValueList`
@@ -272,6 +959,9 @@ where
     fn skip_next(&mut self) -> Option<()> {
         self.it.next().map(|_| ())
     }
+    fn type_check_next(&mut self, specs: &[ColumnSpec]) -> Option<Result<(), ValueTypeCheckError>> {
+        self.it.next().map(|vl| vl.type_check(specs))
+    }
 }
 
 impl<IT> From<IT> for BatchValuesIteratorFromIterator<IT>
@@ -295,6 +985,10 @@ impl Value for i8 {
         buf.put_i8(*self);
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for i16 {
@@ -303,6 +997,10 @@ impl Value for i16 {
         buf.put_i16(*self);
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for i32 {
@@ -311,6 +1009,10 @@ impl Value for i32 {
         buf.put_i32(*self);
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for i64 {
@@ -319,20 +1021,9 @@ impl Value for i64 {
         buf.put_i64(*self);
         Ok(())
     }
-}
-
-impl Value for BigDecimal {
-    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
-        let (value, scale) = self.as_bigint_and_exponent();
-
-        let serialized = value.to_signed_bytes_be();
-        let serialized_len: i32 = serialized.len().try_into().map_err(|_| ValueTooBig)?;
-
-        buf.put_i32(serialized_len + 4);
-        buf.put_i32(scale.try_into().map_err(|_| ValueTooBig)?);
-        buf.extend_from_slice(&serialized);
 
-        Ok(())
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
     }
 }
 
@@ -351,6 +1042,10 @@ impl Value for NaiveDate {
         buf.put_u32(days);
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for Date {
@@ -359,6 +1054,10 @@ impl Value for Date {
         buf.put_u32(self.0);
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for Timestamp {
@@ -367,6 +1066,10 @@ impl Value for Timestamp {
         buf.put_i64(self.0.num_milliseconds());
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for Time {
@@ -375,6 +1078,10 @@ impl Value for Time {
         buf.put_i64(self.0.num_nanoseconds().ok_or(ValueTooBig)?);
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for DateTime<Utc> {
@@ -383,6 +1090,78 @@ impl Value for DateTime<Utc> {
         buf.put_i64(self.timestamp_millis());
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
+}
+
+/// Julian day of 1970-01-01, used to convert dates of the `time` crate
+#[cfg(feature = "time")]
+pub(crate) const UNIX_EPOCH_JULIAN_DAY: i64 = 2_440_588;
+
+#[cfg(feature = "time")]
+impl Value for time::Date {
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+        buf.put_i32(4);
+
+        let days: u32 = (self.to_julian_day() as i64 - UNIX_EPOCH_JULIAN_DAY)
+            .checked_add(1 << 31)
+            .and_then(|days| days.try_into().ok()) // convert to u32
+            .ok_or(ValueTooBig)?;
+
+        buf.put_u32(days);
+        Ok(())
+    }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
+}
+
+#[cfg(feature = "time")]
+impl Value for time::Time {
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+        let (hours, minutes, seconds, nanoseconds) = self.as_hms_nano();
+        let seconds = hours as i64 * 3600 + minutes as i64 * 60 + seconds as i64;
+
+        buf.put_i32(8);
+        buf.put_i64(seconds * 1_000_000_000 + nanoseconds as i64);
+        Ok(())
+    }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
+}
+
+#[cfg(feature = "time")]
+impl Value for time::OffsetDateTime {
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+        let millis: i64 = (self.unix_timestamp_nanos().div_euclid(1_000_000))
+            .try_into()
+            .map_err(|_| ValueTooBig)?;
+
+        buf.put_i32(8);
+        buf.put_i64(millis);
+        Ok(())
+    }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
+}
+
+/// `PrimitiveDateTime` is assumed to be in UTC
+#[cfg(feature = "time")]
+impl Value for time::PrimitiveDateTime {
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+        self.assume_utc().serialize(buf)
+    }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 #[cfg(feature = "secret")]
@@ -390,6 +1169,10 @@ impl<V: Value + Zeroize> Value for Secret<V> {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         self.expose_secret().serialize(buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        self.expose_secret().matches_type(typ)
+    }
 }
 
 impl Value for bool {
@@ -405,6 +1188,10 @@ impl Value for bool {
 
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for f32 {
@@ -413,6 +1200,10 @@ impl Value for f32 {
         buf.put_f32(*self);
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for f64 {
@@ -421,6 +1212,10 @@ impl Value for f64 {
         buf.put_f64(*self);
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for Uuid {
@@ -429,18 +1224,52 @@ impl Value for Uuid {
         buf.extend_from_slice(self.as_bytes());
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
+}
+
+impl Value for CqlTimeuuid {
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+        self.0.serialize(buf)
+    }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
-impl Value for BigInt {
+impl Value for CqlVarint {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
-        let serialized = self.to_signed_bytes_be();
-        let serialized_len: i32 = serialized.len().try_into().map_err(|_| ValueTooBig)?;
+        let serialized_len: i32 = self.0.len().try_into().map_err(|_| ValueTooBig)?;
 
         buf.put_i32(serialized_len);
-        buf.extend_from_slice(&serialized);
+        buf.extend_from_slice(&self.0);
+
+        Ok(())
+    }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
+}
+
+impl Value for CqlDecimal {
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+        let bytes = self.int_val.as_signed_bytes_be_slice();
+        let serialized_len: i32 = bytes.len().try_into().map_err(|_| ValueTooBig)?;
+
+        buf.put_i32(serialized_len.checked_add(4).ok_or(ValueTooBig)?);
+        buf.put_i32(self.scale);
+        buf.extend_from_slice(bytes);
 
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for &str {
@@ -453,6 +1282,10 @@ impl Value for &str {
 
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for Vec<u8> {
@@ -464,6 +1297,10 @@ impl Value for Vec<u8> {
 
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for IpAddr {
@@ -481,12 +1318,20 @@ impl Value for IpAddr {
 
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for String {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         <&str as Value>::serialize(&self.as_str(), buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 /// Every `Option<T>` can be serialized as None -> NULL, Some(val) -> val.serialize()
@@ -500,6 +1345,13 @@ impl<T: Value> Value for Option<T> {
             }
         }
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        match self {
+            Some(val) => <T as Value>::matches_type(val, typ),
+            None => true,
+        }
+    }
 }
 
 impl Value for Unset {
@@ -514,6 +1366,10 @@ impl Value for Counter {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         self.0.serialize(buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl Value for CqlDuration {
@@ -531,6 +1387,10 @@ impl Value for CqlDuration {
 
         Ok(())
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <Self as SerializedCqlTypes>::matches(typ)
+    }
 }
 
 impl<V: Value> Value for MaybeUnset<V> {
@@ -540,6 +1400,13 @@ impl<V: Value> Value for MaybeUnset<V> {
             MaybeUnset::Unset => Unset.serialize(buf),
         }
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        match self {
+            MaybeUnset::Set(v) => v.matches_type(typ),
+            MaybeUnset::Unset => true,
+        }
+    }
 }
 
 // Every &impl Value and &dyn Value should also implement Value
@@ -547,6 +1414,10 @@ impl<T: Value + ?Sized> Value for &T {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         <T as Value>::serialize(*self, buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <T as Value>::matches_type(*self, typ)
+    }
 }
 
 // Every Boxed Value should also implement Value
@@ -554,6 +1425,10 @@ impl<T: Value + ?Sized> Value for Box<T> {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         <T as Value>::serialize(self.as_ref(), buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        <T as Value>::matches_type(self.as_ref(), typ)
+    }
 }
 
 fn serialize_map<K: Value, V: Value>(
@@ -601,36 +1476,92 @@ impl<V: Value> Value for HashSet<V> {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_list_or_set(self.iter(), self.len(), buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::Set(elem_type) => self.iter().all(|v| v.matches_type(elem_type)),
+            _ => false,
+        }
+    }
 }
 
 impl<K: Value, V: Value> Value for HashMap<K, V> {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_map(self.iter(), self.len(), buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        map_matches_type(self.iter(), typ)
+    }
 }
 
 impl<V: Value> Value for BTreeSet<V> {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_list_or_set(self.iter(), self.len(), buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::Set(elem_type) => self.iter().all(|v| v.matches_type(elem_type)),
+            _ => false,
+        }
+    }
 }
 
 impl<K: Value, V: Value> Value for BTreeMap<K, V> {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_map(self.iter(), self.len(), buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        map_matches_type(self.iter(), typ)
+    }
 }
 
 impl<T: Value> Value for Vec<T> {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_list_or_set(self.iter(), self.len(), buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        list_matches_type(self.iter(), typ)
+    }
 }
 
 impl<T: Value> Value for &[T] {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_list_or_set(self.iter(), self.len(), buf)
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        list_matches_type(self.iter(), typ)
+    }
+}
+
+// Lists and sets are serialized in the same way, so lists can be bound to both.
+// Vectors are serialized differently, only arrays and `CqlValue::Vector` can be bound to them.
+fn list_matches_type<'a, V: 'a + Value>(
+    mut elements_iter: impl Iterator<Item = &'a V>,
+    typ: &ColumnType,
+) -> bool {
+    match typ {
+        ColumnType::List(elem_type) | ColumnType::Set(elem_type) => {
+            elements_iter.all(|v| v.matches_type(elem_type))
+        }
+        _ => false,
+    }
+}
+
+fn map_matches_type<K: Value, V: Value>(
+    mut kv_iter: impl Iterator<Item = (K, V)>,
+    typ: &ColumnType,
+) -> bool {
+    match typ {
+        ColumnType::Map(key_type, value_type) => {
+            kv_iter.all(|(k, v)| k.matches_type(key_type) && v.matches_type(value_type))
+        }
+        _ => false,
+    }
 }
 
 fn serialize_tuple<V: Value>(
@@ -651,6 +1582,97 @@ fn serialize_tuple<V: Value>(
     Ok(())
 }
 
+// Vector elements are not prefixed with [int] length like other values. Elements of fixed-length
+// types are stored one after another, others are prefixed with their length as unsigned vint.
+fn serialize_vector<V: Value>(
+    elem_iter: impl Iterator<Item = V>,
+    has_fixed_length: impl Fn(&V) -> bool,
+    buf: &mut Vec<u8>,
+) -> Result<(), ValueTooBig> {
+    let bytes_num_pos: usize = buf.len();
+    buf.put_i32(0);
+
+    let mut elem_buf = Vec::new();
+    for elem in elem_iter {
+        elem_buf.clear();
+        elem.serialize(&mut elem_buf)?;
+        // Vector elements can't be null, the length is always present
+        let elem_bytes = &elem_buf[4..];
+        if !has_fixed_length(&elem) {
+            types::unsigned_vint_encode(elem_bytes.len() as u64, buf);
+        }
+        buf.extend_from_slice(elem_bytes);
+    }
+
+    let written_bytes: usize = buf.len() - bytes_num_pos - 4;
+    let written_bytes_i32: i32 = written_bytes.try_into().map_err(|_| ValueTooBig)?;
+    buf[bytes_num_pos..(bytes_num_pos + 4)].copy_from_slice(&written_bytes_i32.to_be_bytes());
+
+    Ok(())
+}
+
+fn vector_matches_type<'a, V: 'a + Value>(elements: &'a [V], typ: &ColumnType) -> bool {
+    match typ {
+        ColumnType::Vector {
+            element,
+            dimensions,
+        } => {
+            elements.len() == *dimensions as usize
+                && elements.iter().all(|v| v.matches_type(element))
+        }
+        _ => false,
+    }
+}
+
+macro_rules! impl_value_for_array {
+    ($($T:ty),*) => {
+        $(
+            /// Arrays are serialized as CQL vectors, e.g. `[f32; 3]` can be bound to `vector<float, 3>`
+            impl<const N: usize> Value for [$T; N] {
+                fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+                    serialize_vector(self.iter(), |_| true, buf)
+                }
+
+                fn matches_type(&self, typ: &ColumnType) -> bool {
+                    vector_matches_type(self, typ)
+                }
+            }
+
+            /// Nested arrays are serialized as vectors of vectors,
+            /// e.g. `[[f32; 2]; 3]` can be bound to `vector<vector<float, 2>, 3>`
+            impl<const N: usize, const M: usize> Value for [[$T; M]; N] {
+                fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+                    serialize_vector(self.iter(), |_| true, buf)
+                }
+
+                fn matches_type(&self, typ: &ColumnType) -> bool {
+                    vector_matches_type(self, typ)
+                }
+            }
+        )*
+    };
+}
+
+impl_value_for_array!(f32, f64, i32, i64);
+
+// Same as `vector_element_fixed_length` in `result.rs`, but based on a value instead of its type
+fn cql_value_has_fixed_length(value: &CqlValue) -> bool {
+    match value {
+        CqlValue::Boolean(_)
+        | CqlValue::Date(_)
+        | CqlValue::Float(_)
+        | CqlValue::Int(_)
+        | CqlValue::BigInt(_)
+        | CqlValue::Double(_)
+        | CqlValue::Time(_)
+        | CqlValue::Timestamp(_)
+        | CqlValue::Timeuuid(_)
+        | CqlValue::Uuid(_) => true,
+        CqlValue::Vector(elements) => elements.iter().all(cql_value_has_fixed_length),
+        _ => false,
+    }
+}
+
 fn serialize_empty(buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
     buf.put_i32(0);
     Ok(())
@@ -675,6 +1697,9 @@ impl Value for CqlValue {
 
             CqlValue::Ascii(s) | CqlValue::Text(s) => s.serialize(buf),
             CqlValue::List(v) | CqlValue::Set(v) => v.serialize(buf),
+            CqlValue::Vector(v) => {
+                serialize_vector(v.iter(), |elem| cql_value_has_fixed_length(elem), buf)
+            }
 
             CqlValue::Blob(b) => b.serialize(buf),
             CqlValue::Boolean(b) => b.serialize(buf),
@@ -694,6 +1719,63 @@ impl Value for CqlValue {
             CqlValue::Empty => serialize_empty(buf),
         }
     }
+
+    fn matches_type(&self, typ: &ColumnType) -> bool {
+        match (self, typ) {
+            (CqlValue::Map(m), _) => map_matches_type(m.iter().map(|(k, v)| (k, v)), typ),
+            (CqlValue::List(v) | CqlValue::Set(v), _) => list_matches_type(v.iter(), typ),
+            (CqlValue::Vector(v), _) => vector_matches_type(v, typ),
+            (CqlValue::Tuple(t), ColumnType::Tuple(types)) => {
+                t.len() == types.len() && t.iter().zip(types).all(|(v, typ)| v.matches_type(typ))
+            }
+            (
+                CqlValue::UserDefinedType {
+                    type_name, fields, ..
+                },
+                ColumnType::UserDefinedType {
+                    type_name: expected_name,
+                    field_types,
+                    ..
+                },
+            ) => {
+                type_name == expected_name
+                    && fields.len() <= field_types.len()
+                    && fields
+                        .iter()
+                        .zip(field_types)
+                        .all(|((_, v), (_, typ))| v.matches_type(typ))
+            }
+
+            (CqlValue::Ascii(_) | CqlValue::Text(_), ColumnType::Ascii | ColumnType::Text) => true,
+            (
+                CqlValue::Uuid(_) | CqlValue::Timeuuid(_),
+                ColumnType::Uuid | ColumnType::Timeuuid,
+            ) => true,
+
+            (CqlValue::Blob(_), ColumnType::Blob)
+            | (CqlValue::Boolean(_), ColumnType::Boolean)
+            | (CqlValue::Counter(_), ColumnType::Counter)
+            | (CqlValue::Decimal(_), ColumnType::Decimal)
+            | (CqlValue::Date(_), ColumnType::Date)
+            | (CqlValue::Double(_), ColumnType::Double)
+            | (CqlValue::Duration(_), ColumnType::Duration)
+            | (CqlValue::Float(_), ColumnType::Float)
+            | (CqlValue::Int(_), ColumnType::Int)
+            | (
+                CqlValue::BigInt(_),
+                ColumnType::BigInt | ColumnType::Counter | ColumnType::Timestamp,
+            )
+            | (CqlValue::Timestamp(_), ColumnType::Timestamp)
+            | (CqlValue::Inet(_), ColumnType::Inet)
+            | (CqlValue::SmallInt(_), ColumnType::SmallInt)
+            | (CqlValue::TinyInt(_), ColumnType::TinyInt)
+            | (CqlValue::Time(_), ColumnType::Time)
+            | (CqlValue::Varint(_), ColumnType::Varint)
+            | (CqlValue::Empty, _) => true,
+
+            _ => false,
+        }
+    }
 }
 
 macro_rules! impl_value_for_tuple {
@@ -715,6 +1797,20 @@ macro_rules! impl_value_for_tuple {
 
                 Ok(())
             }
+
+            fn matches_type(&self, typ: &ColumnType) -> bool {
+                let mut types = match typ {
+                    ColumnType::Tuple(types) => types.iter(),
+                    _ => return false,
+                };
+                $(
+                    match types.next() {
+                        Some(typ) if <$Ti as Value>::matches_type(&self.$FieldI, typ) => {}
+                        _ => return false,
+                    }
+                )*
+                types.next().is_none()
+            }
         }
     }
 }
@@ -747,11 +1843,43 @@ impl_value_for_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13
 //  ValueList impls
 //
 
+fn type_check_values_count(
+    specs: &[ColumnSpec],
+    values_count: usize,
+) -> Result<(), ValueTypeCheckError> {
+    if specs.len() != values_count {
+        return Err(ValueTypeCheckError::WrongValuesCount {
+            expected: specs.len(),
+            actual: values_count,
+        });
+    }
+    Ok(())
+}
+
+fn type_check_value<T: Value + ?Sized>(
+    value: &T,
+    spec: &ColumnSpec,
+) -> Result<(), ValueTypeCheckError> {
+    // Custom types are opaque to the driver
+    if matches!(spec.typ, ColumnType::Custom(_)) || value.matches_type(&spec.typ) {
+        return Ok(());
+    }
+    Err(ValueTypeCheckError::TypeMismatch {
+        column: spec.name.clone(),
+        expected: spec.typ.clone(),
+        actual: std::any::type_name::<T>(),
+    })
+}
+
 // Implement ValueList for the unit type
 impl ValueList for () {
     fn serialized(&self) -> SerializedResult<'_> {
         Ok(Cow::Owned(SerializedValues::new()))
     }
+
+    fn type_check(&self, specs: &[ColumnSpec]) -> Result<(), ValueTypeCheckError> {
+        type_check_values_count(specs, 0)
+    }
 }
 
 // Implement ValueList for &[] - u8 because otherwise rust can't infer type
@@ -759,6 +1887,10 @@ impl ValueList for [u8; 0] {
     fn serialized(&self) -> SerializedResult<'_> {
         Ok(Cow::Owned(SerializedValues::new()))
     }
+
+    fn type_check(&self, specs: &[ColumnSpec]) -> Result<(), ValueTypeCheckError> {
+        type_check_values_count(specs, 0)
+    }
 }
 
 // Implement ValueList for slices of Value types
@@ -771,6 +1903,14 @@ impl<T: Value> ValueList for &[T] {
 
         Ok(Cow::Owned(result))
     }
+
+    fn type_check(&self, specs: &[ColumnSpec]) -> Result<(), ValueTypeCheckError> {
+        type_check_values_count(specs, self.len())?;
+        for (val, spec) in self.iter().zip(specs) {
+            type_check_value(val, spec)?;
+        }
+        Ok(())
+    }
 }
 
 // Implement ValueList for Vec<Value>
@@ -783,6 +1923,14 @@ impl<T: Value> ValueList for Vec<T> {
 
         Ok(Cow::Owned(result))
     }
+
+    fn type_check(&self, specs: &[ColumnSpec]) -> Result<(), ValueTypeCheckError> {
+        type_check_values_count(specs, self.len())?;
+        for (val, spec) in self.iter().zip(specs) {
+            type_check_value(val, spec)?;
+        }
+        Ok(())
+    }
 }
 
 // Implement ValueList for maps, which serializes named values
@@ -797,6 +1945,17 @@ macro_rules! impl_value_list_for_map {
 
                 Ok(Cow::Owned(result))
             }
+
+            fn type_check(&self, specs: &[ColumnSpec]) -> Result<(), ValueTypeCheckError> {
+                type_check_values_count(specs, self.len())?;
+                for (key, val) in self {
+                    match specs.iter().find(|spec| spec.name == *key) {
+                        Some(spec) => type_check_value(val, spec)?,
+                        None => return Err(ValueTypeCheckError::NoSuchBindMarker(key.to_string())),
+                    }
+                }
+                Ok(())
+            }
         }
     };
 }
@@ -816,6 +1975,11 @@ impl<T0: Value> ValueList for (T0,) {
         result.add_value(&self.0)?;
         Ok(Cow::Owned(result))
     }
+
+    fn type_check(&self, specs: &[ColumnSpec]) -> Result<(), ValueTypeCheckError> {
+        type_check_values_count(specs, 1)?;
+        type_check_value(&self.0, &specs[0])
+    }
 }
 
 macro_rules! impl_value_list_for_tuple {
@@ -831,6 +1995,14 @@ macro_rules! impl_value_list_for_tuple {
                 )*
                 Ok(Cow::Owned(result))
             }
+
+            fn type_check(&self, specs: &[ColumnSpec]) -> Result<(), ValueTypeCheckError> {
+                type_check_values_count(specs, $size)?;
+                $(
+                    type_check_value(&self.$FieldI, &specs[$FieldI])?;
+                )*
+                Ok(())
+            }
         }
     }
 }
@@ -863,6 +2035,10 @@ impl<T: ValueList> ValueList for &T {
     fn serialized(&self) -> SerializedResult<'_> {
         <T as ValueList>::serialized(*self)
     }
+
+    fn type_check(&self, specs: &[ColumnSpec]) -> Result<(), ValueTypeCheckError> {
+        <T as ValueList>::type_check(*self, specs)
+    }
 }
 
 impl ValueList for SerializedValues {
@@ -1009,6 +2185,19 @@ macro_rules! impl_batch_values_for_tuple {
                     None
                 }
             }
+            fn type_check_next(
+                &mut self,
+                specs: &[ColumnSpec],
+            ) -> Option<Result<(), ValueTypeCheckError>> {
+                let ret = match self.idx {
+                    $(
+                        $FieldI => self.tuple.$FieldI.type_check(specs),
+                    )*
+                    _ => return None,
+                };
+                self.idx += 1;
+                Some(ret)
+            }
         }
     }
 }
@@ -1104,4 +2293,9 @@ impl<'a, 'f: 'a, IT: BatchValuesIterator<'a>> BatchValuesIterator<'a>
         self.rest.skip_next();
         self.first.take().map(|_| ())
     }
+    fn type_check_next(&mut self, specs: &[ColumnSpec]) -> Option<Result<(), ValueTypeCheckError>> {
+        // The first value is still kept by `rest`, only in its original form
+        self.first = None;
+        self.rest.type_check_next(specs)
+    }
 }
@@ -1,11 +1,13 @@
+use crate::frame::response::result::{ColumnSpec, ColumnType, CqlValue, TableSpec};
 use crate::frame::value::BatchValuesIterator;
 
 use super::value::{
-    BatchValues, Date, MaybeUnset, SerializeValuesError, SerializedValues, Time, Timestamp, Unset,
-    Value, ValueList, ValueTooBig,
+    BatchValues, CqlDecimal, CqlTimeuuid, CqlVarint, Date, MaybeUnset, SerializeValuesError,
+    SerializedValues, Time, Timestamp, Unset, Value, ValueList, ValueTooBig, ValueTypeCheckError,
 };
 use bytes::BufMut;
 use chrono::{Duration, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
 use std::{borrow::Cow, convert::TryInto};
 use uuid::Uuid;
 
@@ -59,6 +61,42 @@ fn date_serialization() {
     );
 }
 
+#[cfg(feature = "time")]
+#[test]
+fn time_crate_serialization() {
+    use time::macros::{date, datetime, time};
+
+    // Dates use the same encoding as NaiveDate
+    assert_eq!(
+        serialized(date!(1970 - 01 - 01)),
+        vec![0, 0, 0, 4, 128, 0, 0, 0]
+    );
+    assert_eq!(
+        serialized(date!(1969 - 12 - 02)),
+        vec![0, 0, 0, 4, 127, 255, 255, 226]
+    );
+
+    // Time is nanoseconds since midnight
+    let nanos: i64 = (3600 + 2 * 60 + 3) * 1_000_000_000 + 4;
+    let mut expected = vec![0, 0, 0, 8];
+    expected.extend_from_slice(&nanos.to_be_bytes());
+    assert_eq!(serialized(time!(01:02:03.000_000_004)), expected);
+
+    // Timestamps are milliseconds since unix epoch, rounded down
+    let millis: i64 = -1;
+    let mut expected = vec![0, 0, 0, 8];
+    expected.extend_from_slice(&millis.to_be_bytes());
+    assert_eq!(
+        serialized(datetime!(1969-12-31 23:59:59.999_9 UTC)),
+        expected
+    );
+    assert_eq!(serialized(datetime!(1969-12-31 23:59:59.999_9)), expected);
+    assert_eq!(
+        serialized(datetime!(1970-01-01 01:00:00.0 +01:00)),
+        vec![0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0]
+    );
+}
+
 #[test]
 fn time_serialization() {
     // Time is an i64 - nanoseconds since midnight
@@ -150,6 +188,291 @@ fn timeuuid_serialization() {
     }
 }
 
+#[test]
+fn cql_timeuuid_serialization() {
+    let timeuuid: CqlTimeuuid = "8e14e760-7fa8-11eb-bc66-000000000001".parse().unwrap();
+    let mut expected = vec![0, 0, 0, 16];
+    expected.extend_from_slice(timeuuid.as_bytes());
+    assert_eq!(serialized(timeuuid), expected);
+    assert!(timeuuid.matches_type(&ColumnType::Timeuuid));
+    assert!(!timeuuid.matches_type(&ColumnType::Uuid));
+}
+
+#[test]
+fn timeuuid_timestamp() {
+    let timeuuid: CqlTimeuuid = "8e14e760-7fa8-11eb-bc66-000000000001".parse().unwrap();
+    assert_eq!(timeuuid.unix_timestamp_millis(), 1615164747990);
+    assert_eq!(timeuuid.clock_seq(), 0x3c66);
+    assert_eq!(timeuuid.node_id(), [0, 0, 0, 0, 0, 0x01]);
+
+    let timestamp = Duration::milliseconds(1615164747990) + Duration::nanoseconds(12_300);
+    let node_id = [0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc];
+    let created = CqlTimeuuid::from_unix_timestamp(timestamp, 0x1234, node_id);
+    assert_eq!(created.as_uuid().get_version_num(), 1);
+    assert_eq!(created.as_uuid().get_variant(), uuid::Variant::RFC4122);
+    assert_eq!(created.unix_timestamp(), timestamp);
+    assert_eq!(created.clock_seq(), 0x1234);
+    assert_eq!(created.node_id(), node_id);
+
+    let min = CqlTimeuuid::min_timeuuid(Duration::milliseconds(1615164747990));
+    let max = CqlTimeuuid::max_timeuuid(Duration::milliseconds(1615164747990));
+    assert!(min < created && created < max);
+    assert!(min <= timeuuid && timeuuid <= max);
+    assert!(max < CqlTimeuuid::min_timeuuid(Duration::milliseconds(1615164747991)));
+    assert_eq!(max.unix_timestamp_millis(), 1615164747990);
+}
+
+#[test]
+fn timeuuid_ordering() {
+    let parse = |s: &str| s.parse::<CqlTimeuuid>().unwrap();
+    // The timestamp is compared before the clock sequence and node, time_hi and time_mid before time_low
+    let mut timeuuids = vec![
+        parse("00000000-0001-1000-8000-000000000000"),
+        parse("ffffffff-0000-1000-8000-000000000000"),
+        parse("00000000-0000-1001-0000-000000000000"),
+        parse("00000000-0000-1000-7f00-000000000000"),
+        parse("00000000-0000-1000-8000-000000000000"),
+    ];
+    timeuuids.sort();
+    assert_eq!(
+        timeuuids,
+        vec![
+            // Bytes after the timestamp are compared as signed
+            parse("00000000-0000-1000-8000-000000000000"),
+            parse("00000000-0000-1000-7f00-000000000000"),
+            parse("ffffffff-0000-1000-8000-000000000000"),
+            parse("00000000-0001-1000-8000-000000000000"),
+            parse("00000000-0000-1001-0000-000000000000"),
+        ]
+    );
+}
+
+#[test]
+fn timeuuid_now_is_monotonic() {
+    let mut previous = CqlTimeuuid::now();
+    for _ in 0..1000 {
+        let next = CqlTimeuuid::now();
+        assert!(previous < next);
+        assert_eq!(next.as_uuid().get_version_num(), 1);
+        assert_eq!(next.node_id(), previous.node_id());
+        // Multicast bit marks a node id which is not a MAC address
+        assert_eq!(next.node_id()[0] & 0x01, 0x01);
+        previous = next;
+    }
+}
+
+#[test]
+fn cql_varint_serialization() {
+    let varint: CqlVarint = "-129".parse().unwrap();
+    assert_eq!(varint.as_signed_bytes_be_slice(), &[0xff, 0x7f]);
+    assert_eq!(serialized(varint), vec![0, 0, 0, 2, 0xff, 0x7f]);
+
+    let decimal: CqlDecimal = "-1.28".parse().unwrap();
+    assert_eq!(
+        decimal.as_signed_be_bytes_slice_and_exponent(),
+        (&[0x80][..], 2)
+    );
+    assert_eq!(serialized(decimal), vec![0, 0, 0, 5, 0, 0, 0, 2, 0x80]);
+}
+
+#[test]
+fn cql_varint_text_conversions() {
+    let mut values: Vec<i128> = vec![0, i128::MIN, i128::MAX];
+    for i in 0..127 {
+        values.extend([(1 << i) - 1, 1 << i, (1 << i) + 1]);
+    }
+    for i in 0..38 {
+        values.extend([10i128.pow(i) - 1, 10i128.pow(i), 10i128.pow(i) + 1]);
+    }
+    for value in values.iter().flat_map(|v| [*v, v.wrapping_neg()]) {
+        let varint = CqlVarint::from_signed_bytes_be(value.to_be_bytes().to_vec());
+        assert_eq!(varint.to_string(), value.to_string());
+
+        let parsed: CqlVarint = value.to_string().parse().unwrap();
+        assert_eq!(parsed, varint);
+        // Parsing gives the shortest two's complement representation
+        let bytes = parsed.as_signed_bytes_be_slice();
+        assert!(
+            bytes.len() == 1
+                || (bytes[0] != 0x00 && bytes[0] != 0xff)
+                || (bytes[0] ^ bytes[1]) & 0x80 != 0
+        );
+    }
+
+    assert_eq!(
+        CqlVarint::from_signed_bytes_be(vec![0x00, 0x00, 0x01]),
+        CqlVarint::from_signed_bytes_be(vec![0x01])
+    );
+    assert_eq!(
+        CqlVarint::from_signed_bytes_be(vec![]),
+        CqlVarint::from_signed_bytes_be(vec![0x00])
+    );
+    assert_ne!(
+        CqlVarint::from_signed_bytes_be(vec![0x00, 0xff]),
+        CqlVarint::from_signed_bytes_be(vec![0xff])
+    );
+    for invalid in ["", "-", "1.5", "12a", " 1"] {
+        assert!(invalid.parse::<CqlVarint>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn cql_decimal_text_conversions() {
+    let decimal = |scale| CqlDecimal::from_signed_be_bytes_and_exponent(vec![0x30, 0x39], scale);
+    for (scale, text) in [
+        (0, "12345"),
+        (2, "123.45"),
+        (5, "0.12345"),
+        (10, "0.0000012345"),
+        (11, "1.2345E-7"),
+        (-2, "1.2345E+6"),
+    ] {
+        assert_eq!(decimal(scale).to_string(), text);
+        assert_eq!(text.parse::<CqlDecimal>(), Ok(decimal(scale)));
+    }
+    assert_eq!(
+        CqlDecimal::from_signed_be_bytes_and_exponent(vec![0x80], 2).to_string(),
+        "-1.28"
+    );
+    assert_eq!(
+        CqlDecimal::from_signed_be_bytes_and_exponent(vec![0x00], 2).to_string(),
+        "0.00"
+    );
+    assert_eq!("1e3".parse::<CqlDecimal>(), Ok("1E+3".parse().unwrap()));
+    assert_eq!("-.5".parse::<CqlDecimal>(), "-0.5".parse());
+    // Decimals with different scales are different values
+    assert_ne!("1.0".parse::<CqlDecimal>(), "1".parse());
+    for invalid in ["", ".", "1.2.3", "1e", "e5", "1e99999999999"] {
+        assert!(invalid.parse::<CqlDecimal>().is_err(), "{}", invalid);
+    }
+}
+
+#[cfg(all(feature = "num-bigint-03", feature = "bigdecimal-04"))]
+#[test]
+fn num_bigint_and_bigdecimal_conversions() {
+    use std::str::FromStr;
+    let text = "-123456789012345678901234567890";
+    let varint: CqlVarint = text.parse().unwrap();
+    let big_int = num_bigint_03::BigInt::from_str(text).unwrap();
+    assert_eq!(CqlVarint::from(big_int.clone()), varint);
+    assert_eq!(num_bigint_03::BigInt::from(varint.clone()), big_int);
+    assert_eq!(serialized(big_int), serialized(varint));
+
+    let text = "-1234567890.0987654321";
+    let decimal: CqlDecimal = text.parse().unwrap();
+    let big_decimal = bigdecimal_04::BigDecimal::from_str(text).unwrap();
+    assert_eq!(
+        CqlDecimal::try_from(big_decimal.clone()),
+        Ok(decimal.clone())
+    );
+    assert_eq!(
+        bigdecimal_04::BigDecimal::from(decimal.clone()),
+        big_decimal
+    );
+    assert_eq!(serialized(big_decimal), serialized(decimal));
+}
+
+// Same values as in the varint and decimal tests against the database
+#[cfg(any(feature = "num-bigint-03", feature = "num-bigint-04"))]
+const VARINT_TESTS: [&str; 10] = [
+    "0",
+    "1",
+    "127",
+    "128",
+    "129",
+    "-1",
+    "-128",
+    "-129",
+    "123456789012345678901234567890",
+    "-123456789012345678901234567890",
+];
+
+#[cfg(any(feature = "bigdecimal-02", feature = "bigdecimal-04"))]
+const DECIMAL_TESTS: [&str; 6] = [
+    "4.2",
+    "0",
+    "1.999999999999999999999999999999999999999",
+    "997",
+    "123456789012345678901234567890.1234567890",
+    "-123456789012345678901234567890.1234567890",
+];
+
+// Serializes each value parsed from `texts` and reads it back as a value of type `typ`
+#[cfg(any(
+    feature = "num-bigint-03",
+    feature = "num-bigint-04",
+    feature = "bigdecimal-02",
+    feature = "bigdecimal-04"
+))]
+fn check_round_trip<T>(texts: &[&str], typ: ColumnType)
+where
+    T: Value
+        + crate::frame::response::cql_to_rust::FromCqlVal<CqlValue>
+        + std::str::FromStr
+        + PartialEq
+        + std::fmt::Debug,
+    T::Err: std::fmt::Debug,
+{
+    use crate::frame::response::result::deser_cql_value;
+
+    for text in texts {
+        let value = T::from_str(text).unwrap();
+        let bytes = serialized(&value);
+        let cql_value = deser_cql_value(&typ, &mut &bytes[4..]).unwrap();
+        assert_eq!(T::from_cql(cql_value).unwrap(), value, "{}", text);
+    }
+}
+
+#[cfg(feature = "num-bigint-03")]
+#[test]
+fn num_bigint_03_round_trip() {
+    check_round_trip::<num_bigint_03::BigInt>(&VARINT_TESTS, ColumnType::Varint);
+}
+
+#[cfg(feature = "num-bigint-04")]
+#[test]
+fn num_bigint_04_round_trip() {
+    check_round_trip::<num_bigint_04::BigInt>(&VARINT_TESTS, ColumnType::Varint);
+}
+
+#[cfg(feature = "bigdecimal-02")]
+#[test]
+fn bigdecimal_02_round_trip() {
+    check_round_trip::<bigdecimal_02::BigDecimal>(&DECIMAL_TESTS, ColumnType::Decimal);
+}
+
+#[cfg(feature = "bigdecimal-04")]
+#[test]
+fn bigdecimal_04_round_trip() {
+    check_round_trip::<bigdecimal_04::BigDecimal>(&DECIMAL_TESTS, ColumnType::Decimal);
+}
+
+#[cfg(feature = "rust_decimal")]
+#[test]
+fn rust_decimal_conversions() {
+    use std::str::FromStr;
+    for text in [
+        "0",
+        "-1.28",
+        "79228162514264337593543950335",
+        "0.0000000000000000000000000001",
+    ] {
+        let decimal: CqlDecimal = text.parse().unwrap();
+        let rust_decimal = rust_decimal::Decimal::from_str(text).unwrap();
+        assert_eq!(CqlDecimal::from(rust_decimal), decimal);
+        assert_eq!(
+            rust_decimal::Decimal::try_from(decimal.clone()),
+            Ok(rust_decimal)
+        );
+        assert_eq!(serialized(rust_decimal), serialized(decimal));
+    }
+    assert_eq!(
+        rust_decimal::Decimal::try_from("12E+3".parse::<CqlDecimal>().unwrap()),
+        Ok(rust_decimal::Decimal::from(12000))
+    );
+    assert!(rust_decimal::Decimal::try_from("1.5E-30".parse::<CqlDecimal>().unwrap()).is_err());
+}
+
 #[test]
 fn option_value() {
     assert_eq!(serialized(Some(32_i32)), vec![0, 0, 0, 4, 0, 0, 0, 32]);
@@ -386,6 +709,62 @@ fn tuple_value_list() {
     );
 }
 
+#[test]
+fn derived_value_list_field_attributes() {
+    use crate as scylla;
+    use crate::macros::ValueList;
+
+    #[derive(ValueList)]
+    struct Inner {
+        b: i8,
+        c: i8,
+    }
+
+    #[derive(ValueList)]
+    struct Values<T>(T, #[scylla(skip)] String, #[scylla(flatten)] Inner);
+
+    let values = Values(1_i8, "skipped".to_string(), Inner { b: 2_i8, c: 3_i8 });
+    let serialized: SerializedValues = values.serialized().unwrap().into_owned();
+    let serialized_vals: Vec<u8> = serialized.iter().map(|o| o.unwrap()[0]).collect();
+    assert_eq!(serialized_vals, vec![1, 2, 3]);
+    assert_eq!(values.1, "skipped");
+}
+
+#[test]
+fn derived_user_type_field_attributes() {
+    use crate as scylla;
+    use crate::macros::IntoUserType;
+
+    #[derive(IntoUserType)]
+    struct Inner {
+        b: i32,
+    }
+
+    #[derive(IntoUserType)]
+    struct Udt<T> {
+        a: T,
+        #[scylla(skip)]
+        _cache: Option<String>,
+        #[scylla(flatten)]
+        inner: Inner,
+        d: Option<i32>,
+    }
+
+    let udt = Udt {
+        a: 1_i32,
+        _cache: Some("skipped".to_string()),
+        inner: Inner { b: 2 },
+        d: None,
+    };
+
+    let mut expected: Vec<u8> = Vec::new();
+    expected.put_i32(8 + 8 + 4);
+    expected.extend(serialized(1_i32));
+    expected.extend(serialized(2_i32));
+    expected.extend(serialized(None::<i32>));
+    assert_eq!(serialized(udt), expected);
+}
+
 #[test]
 fn ref_value_list() {
     let values: &[i32] = &[1, 2, 3];
@@ -718,3 +1097,147 @@ fn check_batch_values_iterator_is_not_lending() {
     }
     f(((10,), (11,)))
 }
+
+fn col_spec(name: &str, typ: ColumnType) -> ColumnSpec {
+    ColumnSpec {
+        table_spec: TableSpec {
+            ks_name: "ks".to_string(),
+            table_name: "tab".to_string(),
+        },
+        name: name.to_string(),
+        typ,
+    }
+}
+
+#[test]
+fn value_matches_type() {
+    assert!(1_i32.matches_type(&ColumnType::Int));
+    assert!(!1_i64.matches_type(&ColumnType::Int));
+    assert!("a".matches_type(&ColumnType::Text));
+    assert!(None::<i64>.matches_type(&ColumnType::Int));
+    assert!(MaybeUnset::<i64>::Unset.matches_type(&ColumnType::Int));
+
+    let list_of_ints = ColumnType::List(Box::new(ColumnType::Int));
+    assert!(vec![1_i32, 2].matches_type(&list_of_ints));
+    assert!(!vec![1_i64, 2].matches_type(&list_of_ints));
+    assert!(!1_i32.matches_type(&list_of_ints));
+
+    let map = ColumnType::Map(Box::new(ColumnType::Text), Box::new(ColumnType::Int));
+    assert!(BTreeMap::from([("a", 1_i32)]).matches_type(&map));
+    assert!(!BTreeMap::from([(1_i32, "a")]).matches_type(&map));
+
+    let tuple = ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Text]);
+    assert!((1_i32, "a").matches_type(&tuple));
+    assert!(!(1_i32,).matches_type(&tuple));
+    assert!(CqlValue::Tuple(vec![Some(CqlValue::Int(1)), None]).matches_type(&tuple));
+    assert!(!CqlValue::BigInt(1).matches_type(&ColumnType::Int));
+
+    // Sets are serialized like lists
+    let set = CqlValue::Set(vec![CqlValue::Int(1)]);
+    assert!(set.matches_type(&list_of_ints));
+    assert!(set.matches_type(&ColumnType::Set(Box::new(ColumnType::Int))));
+    assert!(!set.matches_type(&ColumnType::List(Box::new(ColumnType::Text))));
+
+    // Timestamps are milliseconds stored like a bigint
+    assert!(1_i64.matches_type(&ColumnType::Timestamp));
+    assert!(1_i64.matches_type(&ColumnType::Counter));
+    assert!(!1_i64.matches_type(&ColumnType::Time));
+    assert!(CqlValue::BigInt(1).matches_type(&ColumnType::Timestamp));
+    assert!(chrono::Utc::now().matches_type(&ColumnType::BigInt));
+    assert!(!1_i32.matches_type(&ColumnType::Date));
+}
+
+#[test]
+fn batch_values_type_check() {
+    let specs = vec![col_spec("a", ColumnType::Int)];
+
+    let values = ((1_i32,), (1_i64,));
+    let mut it = values.batch_values_iter();
+    it.type_check_next(&specs).unwrap().unwrap();
+    assert!(matches!(
+        it.type_check_next(&specs),
+        Some(Err(ValueTypeCheckError::TypeMismatch { .. }))
+    ));
+    assert!(it.type_check_next(&specs).is_none());
+
+    let values = vec![(1_i32,), (2_i32,)];
+    let mut it = values.batch_values_iter();
+    it.type_check_next(&specs).unwrap().unwrap();
+    it.type_check_next(&[]).unwrap().unwrap_err();
+    assert!(it.type_check_next(&specs).is_none());
+}
+
+#[test]
+fn vector_serialization() {
+    assert_eq!(
+        serialized([0.5_f32, 2.0]),
+        vec![0, 0, 0, 8, 63, 0, 0, 0, 64, 0, 0, 0]
+    );
+    assert_eq!(
+        serialized(CqlValue::Vector(vec![
+            CqlValue::Float(0.5),
+            CqlValue::Float(2.0)
+        ])),
+        serialized([0.5_f32, 2.0])
+    );
+    assert_eq!(
+        serialized(CqlValue::Vector(vec![
+            CqlValue::Text("a".to_string()),
+            CqlValue::Text("bc".to_string())
+        ])),
+        vec![0, 0, 0, 5, 1, b'a', 2, b'b', b'c']
+    );
+
+    let float_vector = ColumnType::Vector {
+        element: Box::new(ColumnType::Float),
+        dimensions: 2,
+    };
+    assert!([0.5_f32, 2.0].matches_type(&float_vector));
+    assert!(![0.5_f32].matches_type(&float_vector));
+    assert!(![1_i32, 2].matches_type(&float_vector));
+    assert!(!vec![0.5_f32, 2.0].matches_type(&float_vector));
+}
+
+#[test]
+fn value_list_type_check() {
+    let specs = vec![
+        col_spec("a", ColumnType::Int),
+        col_spec("b", ColumnType::Text),
+    ];
+
+    (1_i32, "b").type_check(&specs).unwrap();
+    vec![CqlValue::Int(1), CqlValue::Text("b".to_string())]
+        .type_check(&specs)
+        .unwrap();
+    HashMap::from([
+        ("a", CqlValue::Int(1)),
+        ("b", CqlValue::Ascii("b".to_string())),
+    ])
+    .type_check(&specs)
+    .unwrap();
+    // Serialized values can't be checked
+    SerializedValues::new().type_check(&specs).unwrap();
+
+    match (1_i64, "b").type_check(&specs) {
+        Err(ValueTypeCheckError::TypeMismatch {
+            column,
+            expected: ColumnType::Int,
+            actual,
+        }) => {
+            assert_eq!(column, "a");
+            assert_eq!(actual, "i64");
+        }
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    assert!(matches!(
+        (1_i32,).type_check(&specs),
+        Err(ValueTypeCheckError::WrongValuesCount {
+            expected: 2,
+            actual: 1
+        })
+    ));
+    assert!(matches!(
+        HashMap::from([("a", 1_i32), ("c", 2_i32)]).type_check(&specs),
+        Err(ValueTypeCheckError::NoSuchBindMarker(name)) if name == "c"
+    ));
+}
@@ -11,11 +11,15 @@ pub use crate::frame::types::Consistency;
 #[doc(hidden)]
 pub mod _macro_internal {
     pub use crate::frame::response::cql_to_rust::{
-        FromCqlVal, FromCqlValError, FromRow, FromRowError,
+        take_udt_field, type_check_column, ColumnTypeMismatch, ColumnsByName, FromCqlVal,
+        FromCqlValError, FromRow, FromRowError, FromRowFields, FromRowWithSpecs,
+        FromUserTypeFields, RowTypeCheckError,
     };
-    pub use crate::frame::response::result::{CqlValue, Row};
+    pub use crate::frame::response::result::{ColumnSpec, ColumnType, CqlValue, Row};
+    pub use crate::frame::type_markers::{self, DeserializeAs, SerializeAs};
     pub use crate::frame::value::{
-        SerializedResult, SerializedValues, Value, ValueList, ValueTooBig,
+        IntoUserTypeFields, SerializeValuesError, SerializedResult, SerializedValues, Value,
+        ValueList, ValueListFields, ValueTooBig,
     };
     pub use crate::macros::*;
 }
@@ -1,18 +1,37 @@
 /// #[derive(FromRow)] derives FromRow for struct
-/// Works only on simple structs without generics etc
+/// Works on structs with named fields, tuple structs and generic structs,
+/// fields can be customized with `#[scylla(...)]` attributes: `rename`, `skip`, `default`, `flatten`
 pub use scylla_macros::FromRow;
 
 /// #[derive(FromUserType)] allows to parse struct as a User Defined Type
-/// Works only on simple structs without generics etc
+/// Works on structs with named fields, tuple structs and generic structs,
+/// fields can be customized with `#[scylla(...)]` attributes: `rename`, `skip`, `default`, `flatten`
 pub use scylla_macros::FromUserType;
 
 /// #[derive(IntoUserType)] allows to pass struct a User Defined Type Value in queries
-/// Works only on simple structs without generics etc
+/// Works on structs with named fields, tuple structs and generic structs,
+/// fields can be customized with `#[scylla(...)]` attributes: `rename`, `skip`, `default`, `flatten`
 pub use scylla_macros::IntoUserType;
 
 /// #[derive(ValueList)] allows to pass struct as a list of values for a query
 pub use scylla_macros::ValueList;
 
+/// #[derive(CqlEnum)] allows to store an enum as a text or an integer column,
+/// it implements both `Value` and `FromCqlVal<CqlValue>`
+pub use scylla_macros::CqlEnum;
+
+/// #[derive(Table)] maps a struct to the rows of a table, generating statements
+/// which insert, select, update and delete a single row, see the `scylla_macros` documentation
+pub use scylla_macros::Table;
+
+/// query!("CQL", values...) checks a statement and the types of its values against a schema file
+/// at compile time, see the `scylla_macros` documentation for the details
+pub use scylla_macros::query;
+
+/// query_as!(RowType, "CQL", values...) works like `query!`,
+/// and checks that the returned columns can be read as `RowType`
+pub use scylla_macros::query_as;
+
 // Reexports for derive(IntoUserType)
 pub use bytes::{BufMut, Bytes, BytesMut};
 
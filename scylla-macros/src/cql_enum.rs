@@ -0,0 +1,296 @@
+use crate::parser::EnumRepr;
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{DeriveInput, Expr, ExprLit, ExprUnary, Ident, Lit, LitStr, UnOp};
+
+/// #[derive(CqlEnum)] allows to store an enum as a text or an integer column
+pub fn cql_enum_derive(tokens_input: TokenStream) -> TokenStream {
+    let item = syn::parse::<DeriveInput>(tokens_input).expect("No DeriveInput");
+    match derive(&item) {
+        Ok(generated) => TokenStream::from(generated),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn derive(item: &DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let path = crate::parser::get_path(item)?;
+    let parsed = crate::parser::parse_enum(item, "CqlEnum")?;
+    let repr_type = parsed.repr.rust_type();
+
+    // Like discriminants, integer values which aren't given continue from the previous variant
+    let mut next_value = quote!(0);
+    // The same, evaluated by the macro while the values are literals
+    let mut next_int = Some(0_i128);
+    let mut int_values: Vec<(Option<i128>, &Ident)> = Vec::new();
+    let mut text_values: Vec<(LitStr, &Ident)> = Vec::new();
+    let mut unit_variants = Vec::new();
+    let mut other_variant = None;
+    for parsed_variant in &parsed.variants {
+        let variant = parsed_variant.variant;
+        if parsed_variant.other {
+            other_variant = Some(&variant.ident);
+            continue;
+        }
+
+        let value = match (parsed.repr, &parsed_variant.rename, &variant.discriminant) {
+            (EnumRepr::Text, Some(rename), _) => quote!(#rename),
+            (EnumRepr::Text, None, _) => {
+                let ident = variant.ident.unraw();
+                let name = LitStr::new(&ident.to_string(), ident.span());
+                quote!(#name)
+            }
+            (_, Some(rename), _) => quote!(#rename),
+            (_, None, Some((_, discriminant))) => quote!((#discriminant)),
+            (_, None, None) => next_value.clone(),
+        };
+        if let EnumRepr::Text = parsed.repr {
+            let text_value: LitStr = syn::parse2(value.clone())?;
+            let same = text_values
+                .iter()
+                .find(|(prev, _)| prev.value() == text_value.value());
+            if let Some((_, prev_ident)) = same {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    format!(
+                        "`{}` has the same value {:?} as `{}`",
+                        variant.ident,
+                        text_value.value(),
+                        prev_ident
+                    ),
+                ));
+            }
+            text_values.push((text_value, &variant.ident));
+        } else {
+            let int_value = match (&parsed_variant.rename, &variant.discriminant) {
+                (Some(Lit::Int(rename)), _) => Some(rename.base10_parse::<i128>()?),
+                (Some(_), _) => None,
+                (None, Some((_, discriminant))) => int_literal_value(discriminant),
+                (None, None) => next_int,
+            };
+            if let Some(int_value) = int_value {
+                let same = int_values.iter().find(|(prev, _)| *prev == Some(int_value));
+                if let Some((_, prev_ident)) = same {
+                    return Err(syn::Error::new_spanned(
+                        variant,
+                        format!(
+                            "`{}` has the same value {} as `{}`",
+                            variant.ident, int_value, prev_ident
+                        ),
+                    ));
+                }
+            }
+            int_values.push((int_value, &variant.ident));
+            next_int = int_value.and_then(|int_value| int_value.checked_add(1));
+            next_value = quote!(#value + 1);
+        }
+        unit_variants.push((&variant.ident, value));
+    }
+
+    // Values given by other expressions, e.g. constants, are compared when they are evaluated
+    let const_check_code = if int_values.iter().any(|(int_value, _)| int_value.is_none()) {
+        let values = unit_variants.iter().map(|(_, value)| value);
+        quote! {
+            const _: () = {
+                let values: &[#repr_type] = &[#(#values),*];
+                let mut i = 0;
+                while i < values.len() {
+                    let mut j = i + 1;
+                    while j < values.len() {
+                        if values[i] == values[j] {
+                            ::std::panic!("derive(CqlEnum): two variants have the same value");
+                        }
+                        j += 1;
+                    }
+                    i += 1;
+                }
+            };
+        }
+    } else {
+        quote!()
+    };
+
+    // The same CQL types, as used by the `query!` macro
+    let type_markers = match parsed.repr {
+        EnumRepr::Text => vec![quote!(Ascii), quote!(Text)],
+        EnumRepr::TinyInt => vec![quote!(TinyInt)],
+        EnumRepr::SmallInt => vec![quote!(SmallInt)],
+        EnumRepr::Int => vec![quote!(Int)],
+        EnumRepr::BigInt => vec![quote!(BigInt)],
+    };
+    let matches_type_code = match parsed.repr {
+        EnumRepr::Text => quote!(ColumnType::Ascii | ColumnType::Text),
+        EnumRepr::TinyInt => quote!(ColumnType::TinyInt),
+        EnumRepr::SmallInt => quote!(ColumnType::SmallInt),
+        EnumRepr::Int => quote!(ColumnType::Int),
+        EnumRepr::BigInt => quote!(ColumnType::BigInt),
+    };
+    // Text values are serialized from `&str`, so no String has to be allocated
+    let value_type = match parsed.repr {
+        EnumRepr::Text => quote!(&str),
+        _ => repr_type.clone(),
+    };
+
+    let serialize_code = unit_variants.iter().map(|(ident, value)| {
+        quote! {
+            Self::#ident => {
+                let value: #value_type = #value;
+                Value::serialize(&value, buf)
+            }
+        }
+    });
+    let serialize_other_code = other_variant.map(|ident| {
+        quote! {
+            Self::#ident(value) => Value::serialize(value, buf),
+        }
+    });
+
+    let from_cql_code = unit_variants.iter().map(|(ident, value)| {
+        quote! {
+            if raw == #value {
+                return Ok(Self::#ident);
+            }
+        }
+    });
+    let unknown_value_code = match other_variant {
+        Some(ident) => quote!(Ok(Self::#ident(::std::convert::Into::into(raw)))),
+        None => quote!(Err(FromCqlValError::BadVal)),
+    };
+
+    let enum_name = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    let generated = quote! {
+        #const_check_code
+
+        impl #impl_generics #path::Value for #enum_name #ty_generics #where_clause {
+            fn serialize(&self, buf: &mut ::std::vec::Vec<::core::primitive::u8>)
+            -> ::std::result::Result<(), #path::ValueTooBig> {
+                use #path::Value;
+
+                match self {
+                    #(#serialize_code)*
+                    #serialize_other_code
+                }
+            }
+
+            fn matches_type(&self, typ: &#path::ColumnType) -> bool {
+                use #path::ColumnType;
+
+                ::std::matches!(typ, #matches_type_code)
+            }
+        }
+
+        impl #impl_generics #path::FromCqlVal<#path::CqlValue> for #enum_name #ty_generics #where_clause {
+            fn from_cql(cql_val: #path::CqlValue)
+            -> ::std::result::Result<Self, #path::FromCqlValError> {
+                use #path::{FromCqlVal, FromCqlValError, CqlValue};
+                use ::std::result::Result::{Ok, Err};
+
+                let raw = <#repr_type as FromCqlVal<CqlValue>>::from_cql(cql_val)?;
+                #(#from_cql_code)*
+                #unknown_value_code
+            }
+
+            fn matches_type(typ: &#path::ColumnType) -> bool {
+                use #path::ColumnType;
+
+                ::std::matches!(typ, #matches_type_code)
+            }
+        }
+
+        #(
+            impl #impl_generics #path::SerializeAs<#path::type_markers::#type_markers>
+            for #enum_name #ty_generics #where_clause {}
+
+            impl #impl_generics #path::DeserializeAs<#path::type_markers::#type_markers>
+            for #enum_name #ty_generics #where_clause {}
+        )*
+    };
+
+    Ok(generated)
+}
+
+/// Value of a discriminant which is an integer literal, possibly negated or in parentheses
+fn int_literal_value(expr: &Expr) -> Option<i128> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => lit.base10_parse().ok(),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => int_literal_value(expr).and_then(i128::checked_neg),
+        Expr::Paren(paren) => int_literal_value(&paren.expr),
+        Expr::Group(group) => int_literal_value(&group.expr),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn cql_enum_duplicate_values() {
+        let errors: [DeriveInput; 4] = [
+            parse_quote! {
+                enum Text {
+                    A,
+                    #[scylla(rename = "A")]
+                    B,
+                }
+            },
+            parse_quote! {
+                #[scylla(repr = "int")]
+                enum Explicit {
+                    A = 1,
+                    B = 1,
+                }
+            },
+            parse_quote! {
+                #[scylla(repr = "int")]
+                enum AutoIncremented {
+                    A = 1,
+                    B = 0,
+                    C,
+                }
+            },
+            parse_quote! {
+                #[scylla(repr = "smallint")]
+                enum Renamed {
+                    A,
+                    #[scylla(rename = -1)]
+                    B,
+                    C,
+                }
+            },
+        ];
+        for item in errors.iter() {
+            assert!(derive(item).is_err());
+        }
+
+        let ok: [DeriveInput; 2] = [
+            parse_quote! {
+                #[scylla(repr = "int")]
+                enum Negative {
+                    A = -1,
+                    B,
+                    C = (-3),
+                }
+            },
+            // Checked when the constant is evaluated
+            parse_quote! {
+                #[scylla(repr = "bigint")]
+                enum Constant {
+                    A = FIRST,
+                    B,
+                }
+            },
+        ];
+        for item in ok.iter() {
+            assert!(derive(item).is_ok());
+        }
+    }
+}
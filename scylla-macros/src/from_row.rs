@@ -1,60 +1,413 @@
+use crate::parser::{default_value, StructField};
 use proc_macro::TokenStream;
-use quote::{quote, quote_spanned};
-use syn::{spanned::Spanned, DeriveInput};
+use proc_macro2::Span;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{parse_quote, DeriveInput, GenericParam, Ident, LitStr, WherePredicate};
 
 /// #[derive(FromRow)] derives FromRow for struct
 pub fn from_row_derive(tokens_input: TokenStream) -> TokenStream {
     let item = syn::parse::<DeriveInput>(tokens_input).expect("No DeriveInput");
-    let path = crate::parser::get_path(&item).expect("No path");
-    let struct_fields = crate::parser::parse_named_fields(&item, "FromRow");
+    match derive(&item) {
+        Ok(generated) => TokenStream::from(generated),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn derive(item: &DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let path = crate::parser::get_path(item)?;
+    let parsed = crate::parser::parse_struct(item, "FromRow")?;
+    let match_by_name = parsed.attrs.match_by_name;
+    let allow_extra_columns = parsed.attrs.allow_extra_columns;
+    let fields = &parsed.fields;
+
+    if !match_by_name {
+        if let Some(rename) = fields.iter().find_map(|field| field.attrs.rename.as_ref()) {
+            return Err(syn::Error::new_spanned(
+                rename,
+                "`rename` requires `#[scylla(match_by_name)]`",
+            ));
+        }
+    }
+
+    // Names of the columns read by fields which aren't skipped nor flattened.
+    // Fields of tuple structs read by position are named by their index.
+    let column_names = fields
+        .iter()
+        .filter(|field| !field.attrs.skip && !field.attrs.flatten)
+        .map(|field| match (match_by_name, field.db_name()) {
+            (true, _) => field.required_db_name("FromRow"),
+            (false, Some(name)) => Ok(name),
+            (false, None) => Ok(LitStr::new(&field_index(&field.member), field.span())),
+        })
+        .collect::<Result<Vec<LitStr>, syn::Error>>()?;
+    if match_by_name {
+        crate::parser::check_duplicate_names(&column_names)?;
+    }
+    let mut column_names = column_names.into_iter();
 
+    let generics = crate::parser::add_field_bounds(
+        &item.generics,
+        fields.iter().map(|field| {
+            let bound = if field.attrs.skip {
+                quote!(::std::default::Default)
+            } else if field.attrs.flatten {
+                quote!(#path::FromRowFields)
+            } else if field.attrs.default {
+                quote!(#path::FromCqlVal<::std::option::Option<#path::CqlValue>> + ::std::default::Default)
+            } else {
+                quote!(#path::FromCqlVal<::std::option::Option<#path::CqlValue>>)
+            };
+            (field.ty(), bound)
+        }),
+    );
     let struct_name = &item.ident;
-    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
-
-    // Generates tokens for field_name: field_type::from_cql(vals_iter.next().ok_or(...)?), ...
-    let set_fields_code = struct_fields.named.iter().map(|field| {
-        let field_name = &field.ident;
-        let field_type = &field.ty;
-
-        quote_spanned! {field.span() =>
-            #field_name: {
-                let (col_ix, col_value) = vals_iter
-                    .next()
-                    .unwrap(); // vals_iter size is checked before this code is reached, so
-                               // it is safe to unwrap
-
-                <#field_type as FromCqlVal<::std::option::Option<CqlValue>>>::from_cql(col_value)
-                    .map_err(|e| FromRowError::BadCqlVal {
-                        err: e,
-                        column: col_ix,
-                    })?
-            },
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let mut column_count_code = Vec::new();
+    let mut column_names_code = Vec::new();
+    let mut set_fields_code = Vec::new();
+    let mut type_check_fields_code = Vec::new();
+    for field in fields {
+        let member = &field.member;
+        let field_type = field.ty();
+
+        if field.attrs.skip {
+            let default = default_value();
+            set_fields_code.push(quote_spanned! {field.span() => #member: #default, });
+            continue;
+        }
+
+        if field.attrs.flatten {
+            column_count_code.push(quote_spanned! {field.span() =>
+                <#field_type as #path::FromRowFields>::COLUMN_COUNT
+            });
+            column_names_code.push(quote_spanned! {field.span() =>
+                <#field_type as FromRowFields>::column_names(names);
+            });
+            set_fields_code.push(quote_spanned! {field.span() =>
+                #member: <#field_type as FromRowFields>::from_columns(columns)?,
+            });
+            type_check_fields_code.push(quote_spanned! {field.span() =>
+                <#field_type as FromRowFields>::type_check_columns(specs, mismatches);
+            });
+            continue;
         }
-    });
-
-    let fields_count = struct_fields.named.len();
-    let generated = quote! {
-        impl #impl_generics #path::FromRow for #struct_name #ty_generics #where_clause {
-            fn from_row(row: #path::Row)
-            -> ::std::result::Result<Self, #path::FromRowError> {
-                use #path::{CqlValue, FromCqlVal, FromRow, FromRowError};
-                use ::std::result::Result::{Ok, Err};
-                use ::std::iter::{Iterator, IntoIterator};
-
-                if #fields_count != row.columns.len() {
-                    return Err(FromRowError::WrongRowSize {
-                        expected: #fields_count,
-                        actual: row.columns.len(),
-                    });
+
+        let column_name = column_names.next().unwrap();
+        let may_be_missing = field.attrs.default;
+        column_count_code.push(quote!(1));
+        column_names_code.push(quote_spanned! {field.span() =>
+            names.push((#column_name, #may_be_missing));
+        });
+
+        let from_cql = quote_spanned! {field.span() =>
+            <#field_type as FromCqlVal<::std::option::Option<CqlValue>>>::from_cql(col_value)
+                .map_err(|e| FromRowError::BadCqlVal {
+                    err: e,
+                    column: col_ix.unwrap_or_default(),
+                })?
+        };
+        let value = if field.attrs.default {
+            let default = default_value();
+            quote! {
+                match col_value {
+                    ::std::option::Option::None => #default,
+                    col_value => #from_cql,
                 }
-                let mut vals_iter = row.columns.into_iter().enumerate();
+            }
+        } else {
+            from_cql
+        };
+        set_fields_code.push(quote_spanned! {field.span() =>
+            #member: {
+                // The number of columns is checked before this code is reached,
+                // so it is safe to unwrap
+                let (col_ix, col_value) = columns.next().unwrap();
+                #value
+            },
+        });
+
+        type_check_fields_code.push(quote_spanned! {field.span() =>
+            // Only columns which may be missing have no specs
+            if let ::std::option::Option::Some(spec) = specs.next().unwrap() {
+                mismatches.extend(type_check_column::<#field_type>(spec));
+            }
+        });
+    }
+
+    let from_row_fields_impl = quote! {
+        impl #impl_generics #path::FromRowFields for #struct_name #ty_generics #where_clause {
+            const COLUMN_COUNT: usize = 0 #(+ #column_count_code)*;
+
+            fn column_names(names: &mut ::std::vec::Vec<(&'static str, bool)>) {
+                use #path::FromRowFields;
 
-                Ok(#struct_name {
+                #(#column_names_code)*
+            }
+
+            fn from_columns(
+                columns: &mut dyn ::std::iter::Iterator<
+                    Item = (::std::option::Option<usize>, ::std::option::Option<#path::CqlValue>),
+                >,
+            ) -> ::std::result::Result<Self, #path::FromRowError> {
+                use #path::{CqlValue, FromCqlVal, FromRowError, FromRowFields};
+                use ::std::result::Result::Ok;
+                use ::std::iter::Iterator;
+
+                Ok(Self {
                     #(#set_fields_code)*
                 })
             }
+
+            fn type_check_columns(
+                specs: &mut dyn ::std::iter::Iterator<
+                    Item = ::std::option::Option<&#path::ColumnSpec>,
+                >,
+                mismatches: &mut ::std::vec::Vec<#path::ColumnTypeMismatch>,
+            ) {
+                use #path::{type_check_column, FromRowFields};
+                use ::std::iter::{Extend, Iterator};
+
+                #(#type_check_fields_code)*
+            }
         }
     };
 
-    TokenStream::from(generated)
+    let from_row_impl = if match_by_name {
+        // Such structs can't be converted without the specs of the columns,
+        // so they implement only FromRowWithSpecs
+        quote! {
+            impl #impl_generics #path::FromRowWithSpecs for #struct_name #ty_generics #where_clause {
+                type ColumnMapping = #path::ColumnsByName;
+
+                fn map_columns(specs: &[#path::ColumnSpec])
+                -> ::std::result::Result<#path::ColumnsByName, #path::RowTypeCheckError> {
+                    use #path::{ColumnsByName, FromRowFields, RowTypeCheckError};
+                    use ::std::result::Result::{Ok, Err};
+
+                    let mut names = ::std::vec::Vec::new();
+                    <Self as FromRowFields>::column_names(&mut names);
+                    let columns = ColumnsByName::new(&names, specs, #allow_extra_columns)?;
+
+                    let mut mismatches = ::std::vec::Vec::new();
+                    <Self as FromRowFields>::type_check_columns(
+                        &mut columns.specs(specs),
+                        &mut mismatches,
+                    );
+
+                    if mismatches.is_empty() {
+                        Ok(columns)
+                    } else {
+                        Err(RowTypeCheckError::ColumnTypeMismatch(mismatches))
+                    }
+                }
+
+                fn from_row_with_mapping(row: #path::Row, mapping: &#path::ColumnsByName)
+                -> ::std::result::Result<Self, #path::FromRowError> {
+                    use #path::FromRowFields;
+
+                    let mut columns = mapping.take_columns(row)?;
+                    <Self as FromRowFields>::from_columns(&mut columns)
+                }
+            }
+        }
+    } else {
+        quote! {
+            impl #impl_generics #path::FromRow for #struct_name #ty_generics #where_clause {
+                fn from_row(row: #path::Row)
+                -> ::std::result::Result<Self, #path::FromRowError> {
+                    use #path::{FromRowError, FromRowFields};
+                    use ::std::result::Result::Err;
+                    use ::std::iter::{Iterator, IntoIterator};
+
+                    let fields_count = <Self as FromRowFields>::COLUMN_COUNT;
+                    if fields_count != row.columns.len() {
+                        return Err(FromRowError::WrongRowSize {
+                            expected: fields_count,
+                            actual: row.columns.len(),
+                        });
+                    }
+                    let mut columns = row
+                        .columns
+                        .into_iter()
+                        .enumerate()
+                        .map(|(ix, value)| (::std::option::Option::Some(ix), value));
+                    <Self as FromRowFields>::from_columns(&mut columns)
+                }
+
+                fn type_check(specs: &[#path::ColumnSpec])
+                -> ::std::result::Result<(), #path::RowTypeCheckError> {
+                    use #path::{FromRowFields, RowTypeCheckError};
+                    use ::std::result::Result::{Ok, Err};
+                    use ::std::iter::{Iterator, IntoIterator};
+
+                    let fields_count = <Self as FromRowFields>::COLUMN_COUNT;
+                    if fields_count != specs.len() {
+                        return Err(RowTypeCheckError::WrongRowSize {
+                            expected: fields_count,
+                            actual: specs.len(),
+                        });
+                    }
+
+                    let mut specs_iter = specs.iter().map(::std::option::Option::Some);
+                    let mut mismatches = ::std::vec::Vec::new();
+                    <Self as FromRowFields>::type_check_columns(&mut specs_iter, &mut mismatches);
+
+                    if mismatches.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(RowTypeCheckError::ColumnTypeMismatch(mismatches))
+                    }
+                }
+            }
+        }
+    };
+
+    let check_impls = check_impls(item, &path, fields, match_by_name);
+
+    Ok(quote! {
+        #from_row_fields_impl
+        #from_row_impl
+        #check_impls
+    })
+}
+
+/// Implements the traits which the `query_as!` macro uses to check the struct
+/// against the columns of a statement, by position or by name like the struct reads them.
+/// Both ways are implemented because flattened structs are read the way of the outer struct.
+/// Fields with `#[scylla(default)]` matched by name aren't checked, their columns may be missing.
+fn check_impls(
+    item: &DeriveInput,
+    path: &proc_macro2::TokenStream,
+    fields: &[StructField],
+    match_by_name: bool,
+) -> proc_macro2::TokenStream {
+    let markers = quote!(#path::type_markers);
+    let struct_name = &item.ident;
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let fields: Vec<&StructField> = fields.iter().filter(|field| !field.attrs.skip).collect();
+
+    // By name: every field finds its column anywhere in the list
+    let named_impl = {
+        let list = Ident::new("__L", Span::call_site());
+        let mut generics = item.generics.clone();
+        let mut predicates: Vec<WherePredicate> = Vec::new();
+        let mut params: Vec<Ident> = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            let field_type = field.ty();
+            if field.attrs.flatten {
+                let position = format_ident!("__X{}", i);
+                predicates.push(parse_quote! {
+                    #field_type: #markers::DeserializeNamedAs<#list, #position>
+                });
+                params.push(position);
+            } else if !field.attrs.default {
+                // Only named fields match columns by name
+                let name = match field.db_name() {
+                    Some(name) => crate::parser::column_name_type(path, &name.value()),
+                    None => continue,
+                };
+                let position = format_ident!("__I{}", i);
+                predicates.push(parse_quote! {
+                    #list: #markers::FindColumn<#name, #position>
+                });
+                predicates.push(parse_quote! {
+                    <#list as #markers::FindColumn<#name, #position>>::Type:
+                        #markers::ReadsField<#field_type>
+                });
+                params.push(position);
+            }
+        }
+
+        generics.params.push(parse_quote!(#list));
+        generics.params.extend(
+            params
+                .iter()
+                .map(|param| -> GenericParam { parse_quote!(#param) }),
+        );
+        generics.make_where_clause().predicates.extend(predicates);
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            impl #impl_generics #markers::DeserializeNamedAs<#list, (#(#params,)*)>
+            for #struct_name #ty_generics #where_clause {}
+        }
+    };
+
+    // By position: each field reads the columns following the ones of the previous field
+    let columns_impl = {
+        let mut generics = item.generics.clone();
+        let mut predicates: Vec<WherePredicate> = Vec::new();
+        let mut list = Ident::new("__L0", Span::call_site());
+        let mut params = vec![list.clone()];
+        for (i, field) in fields.iter().enumerate() {
+            let field_type = field.ty();
+            let rest = format_ident!("__L{}", i + 1);
+            if field.attrs.flatten {
+                predicates.push(parse_quote! {
+                    #field_type: #markers::DeserializeColumnsAs<#list, Rest = #rest>
+                });
+            } else {
+                predicates.push(parse_quote! {
+                    #list: #markers::ColumnList<Tail = #rest>
+                });
+                predicates.push(parse_quote! {
+                    <#list as #markers::ColumnList>::Type: #markers::ReadsField<#field_type>
+                });
+            }
+            params.push(rest.clone());
+            list = rest;
+        }
+
+        generics.params.extend(
+            params
+                .iter()
+                .map(|param| -> GenericParam { parse_quote!(#param) }),
+        );
+        generics.make_where_clause().predicates.extend(predicates);
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let first = &params[0];
+        quote! {
+            impl #impl_generics #markers::DeserializeColumnsAs<#first>
+            for #struct_name #ty_generics #where_clause {
+                type Rest = #list;
+            }
+        }
+    };
+
+    let mut generics = item.generics.clone();
+    generics.params.push(parse_quote!(__L));
+    let row_impl = if match_by_name {
+        generics.params.push(parse_quote!(__X));
+        generics.make_where_clause().predicates.push(parse_quote! {
+            Self: #markers::DeserializeNamedAs<__L, __X>
+        });
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            impl #impl_generics #markers::DeserializeRowAs<__L, __X>
+            for #struct_name #ty_generics #where_clause {}
+        }
+    } else {
+        generics.make_where_clause().predicates.push(parse_quote! {
+            Self: #markers::DeserializeColumnsAs<__L, Rest = ()>
+        });
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            // All the columns have to be read
+            impl #impl_generics #markers::DeserializeRowAs<__L, ()>
+            for #struct_name #ty_generics #where_clause {}
+        }
+    };
+
+    quote! {
+        #named_impl
+        #columns_impl
+        #row_impl
+    }
+}
+
+fn field_index(member: &syn::Member) -> String {
+    match member {
+        syn::Member::Named(ident) => ident.to_string(),
+        syn::Member::Unnamed(index) => index.index.to_string(),
+    }
 }
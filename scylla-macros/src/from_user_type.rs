@@ -1,78 +1,166 @@
+use crate::parser::default_value;
 use proc_macro::TokenStream;
 use quote::{quote, quote_spanned};
-use syn::{spanned::Spanned, DeriveInput};
+use syn::DeriveInput;
 
 /// #[derive(FromUserType)] allows to parse a struct as User Defined Type
 pub fn from_user_type_derive(tokens_input: TokenStream) -> TokenStream {
     let item = syn::parse::<DeriveInput>(tokens_input).expect("No DeriveInput");
-    let path = crate::parser::get_path(&item).expect("Couldn't get path to the scylla crate");
-    let struct_fields = crate::parser::parse_named_fields(&item, "FromUserType");
+    match derive(&item) {
+        Ok(generated) => TokenStream::from(generated),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn derive(item: &DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let path = crate::parser::get_path(item)?;
+    let parsed = crate::parser::parse_struct(item, "FromUserType")?;
+    let fields = &parsed.fields;
 
+    let field_names = fields
+        .iter()
+        .filter(|field| !field.attrs.skip && !field.attrs.flatten)
+        .map(|field| field.required_db_name("FromUserType"))
+        .collect::<Result<Vec<_>, syn::Error>>()?;
+    crate::parser::check_duplicate_names(&field_names)?;
+    let mut field_names = field_names.into_iter();
+
+    let generics = crate::parser::add_field_bounds(
+        &item.generics,
+        fields.iter().map(|field| {
+            let bound = if field.attrs.skip {
+                quote!(::std::default::Default)
+            } else if field.attrs.flatten {
+                quote!(#path::FromUserTypeFields)
+            } else if field.attrs.default {
+                quote!(#path::FromCqlVal<::std::option::Option<#path::CqlValue>> + ::std::default::Default)
+            } else {
+                quote!(#path::FromCqlVal<::std::option::Option<#path::CqlValue>>)
+            };
+            (field.ty(), bound)
+        }),
+    );
     let struct_name = &item.ident;
-    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
-
-    // Generates tokens for field_name: field_type::from_cql(fields.remove(stringify!(#field_name)).unwrap_or(None)) ?, ...
-    let set_fields_code = struct_fields.named.iter().map(|field| {
-        let field_name = &field.ident;
-        let field_type = &field.ty;
-
-        quote_spanned! {field.span() =>
-            #field_name: <#field_type as FromCqlVal<::std::option::Option<CqlValue>>>::from_cql(
-                {
-                    let received_field_name: Option<&::std::string::String> = fields_iter
-                        .peek()
-                        .map(|(ref name, _)| name);
-
-                    // Order of received fields is the same as the order of processed struct's
-                    // fields. There cannot be an extra received field present, so it is safe to
-                    // assign subsequent received fields, to processed struct's fields (inserting
-                    // None if there is no received field corresponding to processed struct's
-                    // field)
-                    if let Some(received_field_name) = received_field_name {
-                        if received_field_name == stringify!(#field_name) {
-                            let (_, value) = fields_iter.next().unwrap();
-                            value
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                }
-            ) ?,
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // Generates tokens for field_name: field_type::from_cql(<received value or None>)?, ...
+    let mut set_fields_code = Vec::new();
+    // Generates tokens for "field_name" => field_type::matches_type(typ), ...
+    let mut match_field_types_code = Vec::new();
+    let mut flattened_types = Vec::new();
+    for field in fields {
+        let member = &field.member;
+        let field_type = field.ty();
+
+        if field.attrs.skip {
+            let default = default_value();
+            set_fields_code.push(quote_spanned! {field.span() => #member: #default, });
+            continue;
+        }
+
+        if field.attrs.flatten {
+            set_fields_code.push(quote_spanned! {field.span() =>
+                #member: <#field_type as FromUserTypeFields>::from_fields(fields)?,
+            });
+            flattened_types.push(field_type);
+            continue;
         }
-    });
+
+        let field_name = field_names.next().unwrap();
+        let from_cql = quote_spanned! {field.span() =>
+            <#field_type as FromCqlVal<::std::option::Option<CqlValue>>>::from_cql(value)?
+        };
+        let value = if field.attrs.default {
+            let default = default_value();
+            quote! {
+                match value {
+                    ::std::option::Option::None => #default,
+                    value => #from_cql,
+                }
+            }
+        } else {
+            from_cql
+        };
+        set_fields_code.push(quote_spanned! {field.span() =>
+            #member: {
+                // Fields are matched by name, so their order in the UDT doesn't matter
+                // and fields missing from it are read from None
+                let value = take_udt_field(fields, #field_name);
+                #value
+            },
+        });
+
+        match_field_types_code.push(quote_spanned! {field.span() =>
+            #field_name => Some(<#field_type as FromCqlVal<::std::option::Option<CqlValue>>>::matches_type(typ)),
+        });
+    }
 
     let generated = quote! {
+        impl #impl_generics #path::FromUserTypeFields for #struct_name #ty_generics #where_clause {
+            fn from_fields(
+                fields: &mut [(::std::string::String, ::std::option::Option<#path::CqlValue>)],
+            ) -> ::std::result::Result<Self, #path::FromCqlValError> {
+                use ::std::result::Result::Ok;
+                use #path::{take_udt_field, FromCqlVal, FromUserTypeFields, CqlValue};
+
+                Ok(Self {
+                    #(#set_fields_code)*
+                })
+            }
+
+            fn field_matches_type(name: &str, typ: &#path::ColumnType) -> ::std::option::Option<bool> {
+                use ::std::option::Option::{Some, None};
+                use #path::{FromCqlVal, FromUserTypeFields, CqlValue};
+
+                let matches = match name {
+                    #(#match_field_types_code)*
+                    _ => None,
+                };
+                #(
+                    let matches = matches.or_else(|| {
+                        <#flattened_types as FromUserTypeFields>::field_matches_type(name, typ)
+                    });
+                )*
+                matches
+            }
+        }
+
         impl #impl_generics #path::FromCqlVal<#path::CqlValue> for #struct_name #ty_generics #where_clause {
             fn from_cql(cql_val: #path::CqlValue)
             -> ::std::result::Result<Self, #path::FromCqlValError> {
-                use ::std::collections::BTreeMap;
-                use ::std::option::Option::{self, Some, None};
-                use ::std::result::Result::{Ok, Err};
-                use #path::{FromCqlVal, FromCqlValError, CqlValue};
-                use ::std::iter::{Iterator, IntoIterator};
+                use ::std::result::Result::Err;
+                use #path::{FromCqlValError, FromUserTypeFields, CqlValue};
 
                 // Interpret CqlValue as CQlValue::UserDefinedType
-                let mut fields_iter = match cql_val {
-                    CqlValue::UserDefinedType{fields, ..} => fields.into_iter().peekable(),
+                let mut fields = match cql_val {
+                    CqlValue::UserDefinedType{fields, ..} => fields,
                     _ => return Err(FromCqlValError::BadCqlType),
                 };
 
-                // Parse struct using values from fields
-                let result = #struct_name {
-                    #(#set_fields_code)*
-                };
+                // Received fields which the struct doesn't read, e.g. ones added
+                // to the UDT after the struct was written, are ignored
+                <Self as FromUserTypeFields>::from_fields(&mut fields)
+            }
 
-                // There should be no unused fields when reading user defined type
-                if fields_iter.next().is_some() {
-                    return Err(FromCqlValError::BadCqlType);
-                }
+            fn matches_type(typ: &#path::ColumnType) -> bool {
+                use #path::{ColumnType, FromUserTypeFields};
+                use ::std::iter::Iterator;
 
-                return Ok(result);
+                let field_types = match typ {
+                    ColumnType::UserDefinedType { field_types, .. } => field_types,
+                    _ => return false,
+                };
+
+                // Fields read by the struct must have matching types, other ones are ignored
+                field_types.iter().all(|(name, typ)| {
+                    <Self as FromUserTypeFields>::field_matches_type(name, typ) != ::std::option::Option::Some(false)
+                })
             }
         }
+
+        impl #impl_generics #path::DeserializeAs<#path::type_markers::Udt>
+        for #struct_name #ty_generics #where_clause {}
     };
 
-    TokenStream::from(generated)
+    Ok(generated)
 }
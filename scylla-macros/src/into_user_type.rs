@@ -1,28 +1,69 @@
 use proc_macro::TokenStream;
 use quote::{quote, quote_spanned};
-use syn::{spanned::Spanned, DeriveInput};
+use syn::DeriveInput;
 
 /// #[derive(IntoUserType)] allows to parse a struct as User Defined Type
 pub fn into_user_type_derive(tokens_input: TokenStream) -> TokenStream {
     let item = syn::parse::<DeriveInput>(tokens_input).expect("No DeriveInput");
-    let path = crate::parser::get_path(&item).expect("No path");
-    let struct_fields = crate::parser::parse_named_fields(&item, "IntoUserType");
+    match derive(&item) {
+        Ok(generated) => TokenStream::from(generated),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn derive(item: &DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let path = crate::parser::get_path(item)?;
+    let parsed = crate::parser::parse_struct(item, "IntoUserType")?;
+    crate::parser::reject_reading_attrs(item, &parsed, "IntoUserType")?;
+    // Fields are serialized in the order of the struct's fields, so their names don't matter
+    let fields: Vec<_> = parsed
+        .fields
+        .iter()
+        .filter(|field| !field.attrs.skip)
+        .collect();
 
+    let generics = crate::parser::add_field_bounds(
+        &item.generics,
+        fields.iter().map(|field| {
+            let bound = if field.attrs.flatten {
+                quote!(#path::IntoUserTypeFields)
+            } else {
+                quote!(#path::Value)
+            };
+            (field.ty(), bound)
+        }),
+    );
     let struct_name = &item.ident;
-    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let serialize_code = struct_fields.named.iter().map(|field| {
-        let field_name = &field.ident;
+    let serialize_code = fields.iter().map(|field| {
+        let member = &field.member;
 
-        quote_spanned! {field.span() =>
-            <_ as Value>::serialize(&self.#field_name, buf) ?;
+        if field.attrs.flatten {
+            quote_spanned! {field.span() =>
+                <_ as IntoUserTypeFields>::serialize_fields(&self.#member, buf) ?;
+            }
+        } else {
+            quote_spanned! {field.span() =>
+                <_ as Value>::serialize(&self.#member, buf) ?;
+            }
         }
     });
 
     let generated = quote! {
+        impl #impl_generics #path::IntoUserTypeFields for #struct_name #ty_generics #where_clause {
+            fn serialize_fields(&self, buf: &mut ::std::vec::Vec<::core::primitive::u8>) -> ::std::result::Result<(), #path::ValueTooBig> {
+                use #path::{IntoUserTypeFields, Value};
+
+                #(#serialize_code)*
+
+                ::std::result::Result::Ok(())
+            }
+        }
+
         impl #impl_generics #path::Value for #struct_name #ty_generics #where_clause {
             fn serialize(&self, buf: &mut ::std::vec::Vec<::core::primitive::u8>) -> ::std::result::Result<(), #path::ValueTooBig> {
-                use #path::{BufMut, ValueTooBig, Value};
+                use #path::{BufMut, IntoUserTypeFields, ValueTooBig};
                 use ::std::convert::TryInto;
                 use ::core::primitive::{usize, i32};
 
@@ -33,7 +74,7 @@ pub fn into_user_type_derive(tokens_input: TokenStream) -> TokenStream {
                 let len_before_serialize = buf.len();
 
                 // Serialize fields
-                #(#serialize_code)*
+                <Self as IntoUserTypeFields>::serialize_fields(self, buf) ?;
 
                 // Put serialized size in its place
                 let total_size : usize = buf.len() - len_before_serialize;
@@ -43,7 +84,37 @@ pub fn into_user_type_derive(tokens_input: TokenStream) -> TokenStream {
                 ::std::result::Result::Ok(())
             }
         }
+
+        impl #impl_generics #path::SerializeAs<#path::type_markers::Udt>
+        for #struct_name #ty_generics #where_clause {}
     };
 
-    TokenStream::from(generated)
+    Ok(generated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn into_user_type_derive_errors() {
+        let errors: [DeriveInput; 2] = [
+            parse_quote! {
+                struct Renamed {
+                    #[scylla(rename = "b")]
+                    a: i32,
+                }
+            },
+            parse_quote! {
+                struct Defaulted {
+                    #[scylla(default)]
+                    a: i32,
+                }
+            },
+        ];
+        for item in errors.iter() {
+            assert!(derive(item).is_err());
+        }
+    }
 }
@@ -1,35 +1,122 @@
 use proc_macro::TokenStream;
 
+mod cql_enum;
 mod from_row;
 mod from_user_type;
 mod into_user_type;
 mod parser;
+mod query;
+mod schema;
+mod table;
 mod value_list;
 
 /// #[derive(FromRow)] derives FromRow for struct
-/// Works only on simple structs without generics etc
-#[proc_macro_derive(FromRow, attributes(scylla_crate))]
+/// Works on structs with named fields, tuple structs and generic structs
+///
+/// By default fields are matched to columns by position.
+/// With `#[scylla(match_by_name)]` they are matched by the column names instead,
+/// `#[scylla(rename = "...")]` on a field changes the name of its column
+/// and `#[scylla(allow_extra_columns)]` ignores columns not matching any field.
+/// Such structs need the specs of the columns, so they implement only `FromRowWithSpecs`:
+/// they can be read from a `QueryResult` or a paged iterator, but not with `Row::into_typed`.
+///
+/// Fields with `#[scylla(skip)]` aren't read and are set to `Default::default()`,
+/// so are fields with `#[scylla(default)]` whose column is null or missing.
+/// Columns of a `#[scylla(flatten)]` field, whose struct derives `FromRow` too,
+/// are read as if they were columns of this struct.
+#[proc_macro_derive(FromRow, attributes(scylla_crate, scylla))]
 pub fn from_row_derive(tokens_input: TokenStream) -> TokenStream {
     from_row::from_row_derive(tokens_input)
 }
 
 /// #[derive(FromUserType)] allows to parse a struct as User Defined Type
-/// Works only on simple structs without generics etc
-#[proc_macro_derive(FromUserType, attributes(scylla_crate))]
+/// Works on structs with named fields, tuple structs and generic structs
+///
+/// Fields are matched to UDT fields by name, `#[scylla(rename = "...")]` changes the matched name
+/// and is required for fields of tuple structs.
+/// Fields with `#[scylla(skip)]` aren't read and are set to `Default::default()`,
+/// so are fields with `#[scylla(default)]` whose UDT field is null or missing.
+/// Fields of a `#[scylla(flatten)]` field, whose struct derives `FromUserType` too,
+/// are read as if they were fields of this struct.
+#[proc_macro_derive(FromUserType, attributes(scylla_crate, scylla))]
 pub fn from_user_type_derive(tokens_input: TokenStream) -> TokenStream {
     from_user_type::from_user_type_derive(tokens_input)
 }
 
 /// #[derive(IntoUserType)] allows to parse a struct as User Defined Type
-/// Works only on simple structs without generics etc
-#[proc_macro_derive(IntoUserType, attributes(scylla_crate))]
+/// Works on structs with named fields, tuple structs and generic structs
+///
+/// Fields are serialized in order, fields with `#[scylla(skip)]` are left out.
+/// Attributes which only matter when reading, like `rename` and `default`, are rejected.
+/// Fields of a `#[scylla(flatten)]` field, whose struct derives `IntoUserType` too,
+/// are serialized as if they were fields of this struct.
+#[proc_macro_derive(IntoUserType, attributes(scylla_crate, scylla))]
 pub fn into_user_type_derive(tokens_input: TokenStream) -> TokenStream {
     into_user_type::into_user_type_derive(tokens_input)
 }
 
 /// #[derive(ValueList)] derives ValueList for struct
-/// Works only on simple structs without generics etc
-#[proc_macro_derive(ValueList, attributes(scylla_crate))]
+/// Works on structs with named fields, tuple structs and generic structs
+///
+/// Fields are bound in order, fields with `#[scylla(skip)]` are left out.
+/// Attributes which only matter when reading, like `rename` and `default`, are rejected.
+/// Values of a `#[scylla(flatten)]` field, whose struct derives `ValueList` too,
+/// are bound as if they were fields of this struct.
+#[proc_macro_derive(ValueList, attributes(scylla_crate, scylla))]
 pub fn value_list_derive(tokens_input: TokenStream) -> TokenStream {
     value_list::value_list_derive(tokens_input)
 }
+
+/// #[derive(CqlEnum)] allows to store an enum with unit variants as a text or an integer column
+///
+/// `#[scylla(repr = "...")]` on the enum sets the CQL type of the column:
+/// `"text"` (the default), `"tinyint"`, `"smallint"`, `"int"` or `"bigint"`.
+/// Text values are the names of the variants and integer values are their discriminants,
+/// `#[scylla(rename = ...)]` on a variant sets its value, a string or an integer literal.
+/// Variants with the same value are rejected.
+/// A variant with `#[scylla(other)]`, holding a single field, e.g. `Other(String)`,
+/// keeps values not matching any other variant. Without it such values fail to be read.
+#[proc_macro_derive(CqlEnum, attributes(scylla_crate, scylla))]
+pub fn cql_enum_derive(tokens_input: TokenStream) -> TokenStream {
+    cql_enum::cql_enum_derive(tokens_input)
+}
+
+/// #[derive(Table)] maps a struct with named fields to the rows of a table,
+/// implementing `Table` with statements which insert, select, update and delete a single row.
+/// The struct has to derive `FromRow` as well.
+///
+/// `#[table = "..."]` on the struct sets the name of the table, possibly qualified with its keyspace.
+/// Fields with `#[partition_key]` and `#[clustering_key]` make up the primary key, in this order.
+/// A struct holding them, named after this one, e.g. `UserKey` for `User`, is generated as well.
+/// Columns are named after the fields, `#[scylla(rename = "...")]` changes the name of a column
+/// and fields with `#[scylla(skip)]` aren't columns.
+#[proc_macro_derive(
+    Table,
+    attributes(scylla_crate, scylla, table, partition_key, clustering_key)
+)]
+pub fn table_derive(tokens_input: TokenStream) -> TokenStream {
+    table::table_derive(tokens_input)
+}
+
+/// query!("CQL", values...) checks a statement against a schema file at compile time
+/// and creates a `CheckedQuery` which returns rows of type `Row`.
+///
+/// Every bind marker `?` needs a value whose type can be sent as the type of its column,
+/// and the number of values has to match the number of bind markers.
+///
+/// The schema is a file of CQL statements, e.g. the output of `DESCRIBE KEYSPACE`.
+/// Its path, relative to the crate's manifest, is given with `schema = "..."` before the statement,
+/// with the `SCYLLA_SCHEMA_FILE` environment variable or is `scylla_schema.cql` by default.
+#[proc_macro]
+pub fn query(tokens_input: TokenStream) -> TokenStream {
+    query::query_macro(tokens_input, false)
+}
+
+/// query_as!(RowType, "CQL", values...) works like `query!`, and checks as well that the columns
+/// returned by a SELECT statement can be read into `RowType`.
+/// Elements of tuples and fields of structs are checked by position, like `FromRow` reads them.
+/// Fields of structs with `#[scylla(match_by_name)]` are checked by the names of the columns.
+#[proc_macro]
+pub fn query_as(tokens_input: TokenStream) -> TokenStream {
+    query::query_macro(tokens_input, true)
+}
@@ -1,22 +1,154 @@
-use syn::{Data, DeriveInput, Fields, FieldsNamed};
-use syn::{Lit, Meta};
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput, Field, Fields, Generics, Ident, Member, Type, Variant};
+use syn::{Lit, LitStr, Meta, NestedMeta};
 
-/// Parses the tokens_input to a DeriveInput and returns the struct name from which it derives and
-/// the named fields
-pub(crate) fn parse_named_fields<'a>(
+/// Options set on a struct with `#[scylla(...)]` attributes
+#[derive(Default)]
+pub(crate) struct StructAttrs {
+    pub(crate) match_by_name: bool,
+    pub(crate) allow_extra_columns: bool,
+}
+
+/// Options set on a field with `#[scylla(...)]` attributes
+#[derive(Default)]
+pub(crate) struct FieldAttrs {
+    /// Name of the column or UDT field, used instead of the field's name
+    pub(crate) rename: Option<LitStr>,
+    /// The field isn't read nor written, it is set to `Default::default()` when reading
+    pub(crate) skip: bool,
+    /// The field is set to `Default::default()` when its value is null or missing
+    pub(crate) default: bool,
+    /// The fields of the field's struct are read and written as if they were fields of this one
+    pub(crate) flatten: bool,
+}
+
+/// A field of a struct, along with its attributes
+pub(crate) struct StructField<'a> {
+    /// Accesses the field, in `self.#member` and `Self { #member: ... }`.
+    /// Works for named fields and for fields of tuple structs.
+    pub(crate) member: Member,
+    pub(crate) field: &'a Field,
+    pub(crate) attrs: FieldAttrs,
+}
+
+impl StructField<'_> {
+    pub(crate) fn ty(&self) -> &Type {
+        &self.field.ty
+    }
+
+    pub(crate) fn span(&self) -> Span {
+        self.field.span()
+    }
+
+    /// Name of the column or UDT field matching this field.\
+    /// Fields of tuple structs have a name only if they are renamed.
+    pub(crate) fn db_name(&self) -> Option<LitStr> {
+        match (&self.attrs.rename, &self.member) {
+            (Some(rename), _) => Some(rename.clone()),
+            (None, Member::Named(ident)) => {
+                Some(LitStr::new(&ident.unraw().to_string(), ident.span()))
+            }
+            (None, Member::Unnamed(_)) => None,
+        }
+    }
+
+    /// Like `db_name`, but fails for unnamed fields
+    pub(crate) fn required_db_name(&self, current_derive: &str) -> Result<LitStr, syn::Error> {
+        self.db_name().ok_or_else(|| {
+            syn::Error::new_spanned(
+                self.field,
+                format!(
+                    "derive({}) needs a name for every field of a tuple struct, \
+                     set it with `#[scylla(rename = \"...\")]`",
+                    current_derive
+                ),
+            )
+        })
+    }
+}
+
+/// A struct with parsed attributes of itself and of its fields
+pub(crate) struct ParsedStruct<'a> {
+    pub(crate) attrs: StructAttrs,
+    pub(crate) fields: Vec<StructField<'a>>,
+}
+
+/// Parses a struct with named fields, a tuple struct or a unit struct,
+/// together with `#[scylla(...)]` attributes of the struct and of its fields
+pub(crate) fn parse_struct<'a>(
     input: &'a DeriveInput,
     current_derive: &str,
-) -> &'a FieldsNamed {
-    match &input.data {
-        Data::Struct(data) => match &data.fields {
-            Fields::Named(named_fields) => named_fields,
-            _ => panic!(
-                "derive({}) works only for structs with named fields. Tuples don't need derive.",
-                current_derive
-            ),
-        },
-        _ => panic!("derive({}) works only on structs!", current_derive),
+) -> Result<ParsedStruct<'a>, syn::Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                format!("derive({}) works only on structs!", current_derive),
+            ))
+        }
+    };
+    let fields = match fields {
+        Fields::Named(named) => named.named.iter().collect(),
+        Fields::Unnamed(unnamed) => unnamed.unnamed.iter().collect(),
+        Fields::Unit => Vec::new(),
+    };
+
+    let fields = fields
+        .into_iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(i.into()),
+            };
+            Ok(StructField {
+                member,
+                field,
+                attrs: parse_field_attrs(field)?,
+            })
+        })
+        .collect::<Result<Vec<_>, syn::Error>>()?;
+
+    Ok(ParsedStruct {
+        attrs: parse_struct_attrs(input)?,
+        fields,
+    })
+}
+
+/// Fails on attributes which only matter when reading, for derives which write values
+/// in the order of the struct's fields and would otherwise silently ignore them
+pub(crate) fn reject_reading_attrs(
+    input: &DeriveInput,
+    parsed: &ParsedStruct,
+    current_derive: &str,
+) -> Result<(), syn::Error> {
+    let ignored = |attr: &str| {
+        format!(
+            "derive({}) writes values in the order of the fields, so `{}` would be ignored",
+            current_derive, attr
+        )
+    };
+    if parsed.attrs.match_by_name || parsed.attrs.allow_extra_columns {
+        let attr = if parsed.attrs.match_by_name {
+            "match_by_name"
+        } else {
+            "allow_extra_columns"
+        };
+        return Err(syn::Error::new_spanned(&input.ident, ignored(attr)));
+    }
+    for field in parsed.fields.iter() {
+        if let Some(rename) = &field.attrs.rename {
+            return Err(syn::Error::new_spanned(rename, ignored("rename")));
+        }
+        if field.attrs.default {
+            return Err(syn::Error::new_spanned(field.field, ignored("default")));
+        }
     }
+    Ok(())
 }
 
 pub(crate) fn get_path(input: &DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
@@ -57,3 +189,314 @@ pub(crate) fn get_path(input: &DeriveInput) -> Result<proc_macro2::TokenStream,
     }
     Ok(this_path.unwrap_or_else(|| quote::quote!(scylla::_macro_internal)))
 }
+
+/// Adds a `where` predicate `field_type: bound` for every field whose type
+/// uses some type parameter of the struct. Other fields need no bounds.
+pub(crate) fn add_field_bounds<'a>(
+    generics: &Generics,
+    bounded_types: impl Iterator<Item = (&'a Type, TokenStream)>,
+) -> Generics {
+    let params: Vec<&Ident> = generics.type_params().map(|param| &param.ident).collect();
+    let mut generics = generics.clone();
+    if params.is_empty() {
+        return generics;
+    }
+
+    let where_clause = generics.make_where_clause();
+    for (ty, bound) in bounded_types {
+        if uses_idents(ty.to_token_stream(), &params) {
+            where_clause.predicates.push(syn::parse_quote!(#ty: #bound));
+        }
+    }
+    generics
+}
+
+fn uses_idents(tokens: TokenStream, idents: &[&Ident]) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => idents.iter().any(|i| **i == ident),
+        TokenTree::Group(group) => uses_idents(group.stream(), idents),
+        _ => false,
+    })
+}
+
+/// A column name as a type, e.g. `(Ch<'i'>, (Ch<'d'>, ()))` for `id`,
+/// used by the checks of the `query_as!` macro
+pub(crate) fn column_name_type(path: &TokenStream, name: &str) -> TokenStream {
+    name.chars().rev().fold(
+        quote!(()),
+        |rest, c| quote!((#path::type_markers::Ch<#c>, #rest)),
+    )
+}
+
+/// `Default::default()`, for skipped and defaulted fields
+pub(crate) fn default_value() -> TokenStream {
+    quote!(<_ as ::std::default::Default>::default())
+}
+
+/// Parses `#[scylla(match_by_name, allow_extra_columns)]` attributes of a struct
+fn parse_struct_attrs(input: &DeriveInput) -> Result<StructAttrs, syn::Error> {
+    let mut attrs = StructAttrs::default();
+    for nested in scylla_attr_items(&input.attrs)? {
+        match &nested {
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("match_by_name") => {
+                attrs.match_by_name = true;
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("allow_extra_columns") => {
+                attrs.allow_extra_columns = true;
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "unknown `scylla` attribute, expected `match_by_name` or `allow_extra_columns`",
+                ))
+            }
+        }
+    }
+    if attrs.allow_extra_columns && !attrs.match_by_name {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`allow_extra_columns` requires `match_by_name`",
+        ));
+    }
+    Ok(attrs)
+}
+
+/// Parses `#[scylla(rename = "...", skip, default, flatten)]` attributes of a field
+fn parse_field_attrs(field: &Field) -> Result<FieldAttrs, syn::Error> {
+    let mut attrs = FieldAttrs::default();
+    for nested in scylla_attr_items(&field.attrs)? {
+        match &nested {
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("rename") => {
+                let lit_str = match &name_value.lit {
+                    Lit::Str(lit_str) => lit_str,
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "the `rename` attribute should be a string literal",
+                        ))
+                    }
+                };
+                if attrs.rename.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        name_value,
+                        "the `rename` attribute was set multiple times",
+                    ));
+                }
+                attrs.rename = Some(lit_str.clone());
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => attrs.skip = true,
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => {
+                attrs.default = true
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => {
+                attrs.flatten = true
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "unknown `scylla` attribute, expected `rename = \"...\"`, `skip`, `default` or `flatten`",
+                ))
+            }
+        }
+    }
+
+    let others_set = attrs.rename.is_some() || attrs.default || attrs.flatten;
+    if attrs.skip && others_set {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`skip` can't be combined with other `scylla` attributes",
+        ));
+    }
+    if attrs.flatten && (attrs.rename.is_some() || attrs.default) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`flatten` can't be combined with `rename` nor `default`",
+        ));
+    }
+    Ok(attrs)
+}
+
+/// How an enum deriving `CqlEnum` is stored, set with `#[scylla(repr = "...")]`
+#[derive(Clone, Copy)]
+pub(crate) enum EnumRepr {
+    Text,
+    TinyInt,
+    SmallInt,
+    Int,
+    BigInt,
+}
+
+impl EnumRepr {
+    /// The Rust type of the stored values
+    pub(crate) fn rust_type(self) -> TokenStream {
+        match self {
+            EnumRepr::Text => quote!(::std::string::String),
+            EnumRepr::TinyInt => quote!(i8),
+            EnumRepr::SmallInt => quote!(i16),
+            EnumRepr::Int => quote!(i32),
+            EnumRepr::BigInt => quote!(i64),
+        }
+    }
+}
+
+/// A variant of an enum, along with its attributes
+pub(crate) struct EnumVariant<'a> {
+    pub(crate) variant: &'a Variant,
+    /// The stored value, a string or an integer literal depending on the enum's repr
+    pub(crate) rename: Option<Lit>,
+    /// The variant holds values not matching any other variant
+    pub(crate) other: bool,
+}
+
+/// An enum with parsed attributes of itself and of its variants
+pub(crate) struct ParsedEnum<'a> {
+    pub(crate) repr: EnumRepr,
+    pub(crate) variants: Vec<EnumVariant<'a>>,
+}
+
+/// Parses an enum together with `#[scylla(repr = "...")]` attribute of the enum
+/// and `#[scylla(rename = ..., other)]` attributes of its variants
+pub(crate) fn parse_enum<'a>(
+    input: &'a DeriveInput,
+    current_derive: &str,
+) -> Result<ParsedEnum<'a>, syn::Error> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                format!("derive({}) works only on enums!", current_derive),
+            ))
+        }
+    };
+
+    let mut repr = EnumRepr::Text;
+    for nested in scylla_attr_items(&input.attrs)? {
+        match &nested {
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("repr") => {
+                repr = match &name_value.lit {
+                    Lit::Str(lit_str) => match lit_str.value().as_str() {
+                        "text" => EnumRepr::Text,
+                        "tinyint" => EnumRepr::TinyInt,
+                        "smallint" => EnumRepr::SmallInt,
+                        "int" => EnumRepr::Int,
+                        "bigint" => EnumRepr::BigInt,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                lit_str,
+                                "expected one of: \"text\", \"tinyint\", \"smallint\", \"int\", \"bigint\"",
+                            ))
+                        }
+                    },
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "the `repr` attribute should be a string literal",
+                        ))
+                    }
+                };
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "unknown `scylla` attribute, expected `repr = \"...\"`",
+                ))
+            }
+        }
+    }
+
+    let mut variants = Vec::new();
+    for variant in data.variants.iter() {
+        let mut parsed = EnumVariant {
+            variant,
+            rename: None,
+            other: false,
+        };
+        for nested in scylla_attr_items(&variant.attrs)? {
+            match &nested {
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("rename") =>
+                {
+                    let expected_lit = match (repr, &name_value.lit) {
+                        (EnumRepr::Text, Lit::Str(_)) => true,
+                        (EnumRepr::Text, _) => false,
+                        (_, lit) => matches!(lit, Lit::Int(_)),
+                    };
+                    if !expected_lit {
+                        return Err(syn::Error::new_spanned(
+                            &name_value.lit,
+                            "`rename` should be a string literal for `repr = \"text\"` \
+                             and an integer literal otherwise",
+                        ));
+                    }
+                    parsed.rename = Some(name_value.lit.clone());
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("other") => parsed.other = true,
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "unknown `scylla` attribute, expected `rename = ...` or `other`",
+                    ))
+                }
+            }
+        }
+
+        if parsed.other {
+            let holds_single_field =
+                matches!(&variant.fields, Fields::Unnamed(fields) if fields.unnamed.len() == 1);
+            if !holds_single_field || parsed.rename.is_some() {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "the `other` variant should hold the unknown value \
+                     in a single unnamed field, e.g. `Other(String)`",
+                ));
+            }
+            if variants.iter().any(|v: &EnumVariant| v.other) {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "only one variant can be marked with `other`",
+                ));
+            }
+        } else if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "derive({}) supports only unit variants, apart from the `other` one",
+                    current_derive
+                ),
+            ));
+        }
+        variants.push(parsed);
+    }
+
+    Ok(ParsedEnum { repr, variants })
+}
+
+fn scylla_attr_items(attrs: &[Attribute]) -> Result<Vec<NestedMeta>, syn::Error> {
+    let mut items = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("scylla")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => items.extend(list.nested),
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "the `scylla` attribute should be a list, e.g. `#[scylla(skip)]`",
+                ))
+            }
+        }
+    }
+    Ok(items)
+}
+
+/// Fails if two fields match the same column or UDT field
+pub(crate) fn check_duplicate_names(names: &[LitStr]) -> Result<(), syn::Error> {
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].iter().any(|prev| prev.value() == name.value()) {
+            return Err(syn::Error::new_spanned(
+                name,
+                format!("`{}` is matched by multiple fields", name.value()),
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,1028 @@
+use crate::schema::{tokenize, CqlType, Parser, Schema, Table, Token};
+use proc_macro::TokenStream;
+use quote::{quote, quote_spanned};
+use std::path::{Path, PathBuf};
+use syn::parse::ParseStream;
+use syn::spanned::Spanned;
+use syn::{Expr, Ident, LitStr, Type};
+
+const DEFAULT_SCHEMA_FILE: &str = "scylla_schema.cql";
+const SCHEMA_FILE_VAR: &str = "SCYLLA_SCHEMA_FILE";
+
+/// query!("...", values...) and query_as!(RowType, "...", values...)
+/// check a statement against a schema file at compile time
+pub fn query_macro(tokens_input: TokenStream, with_row_type: bool) -> TokenStream {
+    let parser = |input: ParseStream| QueryInput::parse(input, with_row_type);
+    match syn::parse::Parser::parse(parser, tokens_input).and_then(|input| expand(&input)) {
+        Ok(generated) => TokenStream::from(generated),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+struct QueryInput {
+    schema: Option<LitStr>,
+    scylla_crate: Option<LitStr>,
+    row_type: Option<Type>,
+    text: LitStr,
+    values: Vec<Expr>,
+}
+
+impl QueryInput {
+    fn parse(input: ParseStream, with_row_type: bool) -> syn::Result<Self> {
+        let mut schema = None;
+        let mut scylla_crate = None;
+        let row_type = if with_row_type {
+            // Options may come before or after the row type
+            Self::parse_options(input, &mut schema, &mut scylla_crate)?;
+            let row_type = input.parse()?;
+            input.parse::<syn::Token![,]>()?;
+            Some(row_type)
+        } else {
+            None
+        };
+        Self::parse_options(input, &mut schema, &mut scylla_crate)?;
+        let text = input.parse()?;
+        let mut values = Vec::new();
+        while !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            values.push(input.parse()?);
+        }
+
+        Ok(QueryInput {
+            schema,
+            scylla_crate,
+            row_type,
+            text,
+            values,
+        })
+    }
+    /// Options given as `name = "value",`
+    fn parse_options(
+        input: ParseStream,
+        schema: &mut Option<LitStr>,
+        scylla_crate: &mut Option<LitStr>,
+    ) -> syn::Result<()> {
+        while input.peek(Ident) && input.peek2(syn::Token![=]) {
+            let name: Ident = input.parse()?;
+            input.parse::<syn::Token![=]>()?;
+            let value: LitStr = input.parse()?;
+            input.parse::<syn::Token![,]>()?;
+            let option = match name.to_string().as_str() {
+                "schema" => &mut *schema,
+                "scylla_crate" => &mut *scylla_crate,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        name,
+                        "unknown option, expected `schema` or `scylla_crate`",
+                    ))
+                }
+            };
+            if option.is_some() {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "the option was set multiple times",
+                ));
+            }
+            *option = Some(value);
+        }
+        Ok(())
+    }
+}
+
+fn expand(input: &QueryInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let path = match &input.scylla_crate {
+        Some(scylla_crate) => {
+            let crate_path = scylla_crate
+                .value()
+                .parse::<proc_macro2::TokenStream>()
+                .map_err(|_| syn::Error::new_spanned(scylla_crate, "invalid path"))?;
+            quote!(#crate_path::_macro_internal)
+        }
+        None => quote!(scylla::_macro_internal),
+    };
+
+    let schema_span = input.schema.as_ref().unwrap_or(&input.text).span();
+    let schema_path = schema_path(input.schema.as_ref());
+    let schema_text = std::fs::read_to_string(&schema_path).map_err(|err| {
+        syn::Error::new(
+            schema_span,
+            format!(
+                "can't read the schema file {}: {}",
+                schema_path.display(),
+                err
+            ),
+        )
+    })?;
+    let schema = Schema::parse(&schema_text).map_err(|err| {
+        syn::Error::new(
+            schema_span,
+            format!("invalid schema file {}: {}", schema_path.display(), err),
+        )
+    })?;
+    // Rebuilds the code whenever the schema changes
+    let schema_path = schema_path
+        .to_str()
+        .ok_or_else(|| syn::Error::new(schema_span, "the schema path isn't valid UTF-8"))?;
+
+    let text = &input.text;
+    let statement =
+        analyze(&text.value(), &schema).map_err(|err| syn::Error::new(text.span(), err))?;
+    if statement.binds.len() != input.values.len() {
+        return Err(syn::Error::new(
+            text.span(),
+            format!(
+                "the statement has {} bind markers, but {} values were given",
+                statement.binds.len(),
+                input.values.len()
+            ),
+        ));
+    }
+
+    let values = &input.values;
+    let value_checks: Vec<_> = statement
+        .binds
+        .iter()
+        .zip(values)
+        .enumerate()
+        .filter_map(|(i, (typ, value))| {
+            let marker = typ.as_ref()?.marker(&path)?;
+            let index = syn::Index::from(i);
+            Some(quote_spanned! {value.span() =>
+                __check_value::<#marker, _>(&__values.#index);
+            })
+        })
+        .collect();
+    let check_value_fn = if value_checks.is_empty() {
+        None
+    } else {
+        Some(quote! {
+            fn __check_value<M, T: ?::core::marker::Sized + #path::SerializeAs<M>>(_: &T) {}
+        })
+    };
+
+    let row_check = match (&input.row_type, &statement.columns) {
+        (Some(row_type), Some(columns)) => Some(row_check(row_type, columns, &path)?),
+        _ => None,
+    };
+    let row_type = match &input.row_type {
+        Some(row_type) => quote!(#row_type),
+        None => quote!(#path::Row),
+    };
+
+    Ok(quote! {{
+        const _: &[::core::primitive::u8] = ::core::include_bytes!(#schema_path);
+        #check_value_fn
+        let __values = (#(#values,)*);
+        #(#value_checks)*
+        #row_check
+        #path::CheckedQuery::<_, #row_type>::new(#text, __values)
+    }})
+}
+
+/// The schema file given to the macro, set by `SCYLLA_SCHEMA_FILE` or the default one,
+/// relative to the directory of the crate's manifest
+fn schema_path(schema: Option<&LitStr>) -> PathBuf {
+    let file = match schema {
+        Some(schema) => schema.value(),
+        None => std::env::var(SCHEMA_FILE_VAR).unwrap_or_else(|_| DEFAULT_SCHEMA_FILE.to_string()),
+    };
+    match std::env::var("CARGO_MANIFEST_DIR") {
+        Ok(manifest_dir) => Path::new(&manifest_dir).join(file),
+        Err(_) => PathBuf::from(file),
+    }
+}
+
+/// Checks that the row type can be read from the returned columns.
+/// Elements of tuples are checked by position. Structs deriving `FromRow` are checked
+/// the way they read rows, by position or by the names of the columns.
+fn row_check(
+    row_type: &Type,
+    columns: &[ResultColumn],
+    path: &proc_macro2::TokenStream,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let span = row_type.span();
+    let tuple = match row_type {
+        Type::Tuple(tuple) => tuple,
+        _ => {
+            let markers = quote!(#path::type_markers);
+            let columns_list = columns.iter().rev().fold(quote!(()), |rest, column| {
+                let name = match &column.name {
+                    Some(name) => crate::parser::column_name_type(path, name),
+                    None => quote!(#markers::Unnamed),
+                };
+                let typ = match column.typ.as_ref().and_then(|typ| typ.marker(path)) {
+                    Some(marker) => quote!(#markers::Typed<#marker>),
+                    None => quote!(#markers::Untyped),
+                };
+                quote!((#markers::Column<#name, #typ>, #rest))
+            });
+            return Ok(quote_spanned! {span =>
+                {
+                    fn __check_row<L, X, R: #markers::DeserializeRowAs<L, X>>() {}
+                    __check_row::<#columns_list, _, #row_type>();
+                }
+            });
+        }
+    };
+
+    if tuple.elems.len() != columns.len() {
+        return Err(syn::Error::new_spanned(
+            row_type,
+            format!(
+                "the statement returns {} columns, but the tuple has {} elements",
+                columns.len(),
+                tuple.elems.len()
+            ),
+        ));
+    }
+    let checks: Vec<_> = columns
+        .iter()
+        .enumerate()
+        .filter_map(|(i, column)| {
+            let marker = column.typ.as_ref()?.marker(path)?;
+            let index = syn::Index::from(i);
+            Some(quote_spanned! {span => __check_column::<#marker, _>(&__row.#index);})
+        })
+        .collect();
+
+    Ok(quote! {
+        #[allow(unused_variables)]
+        let _ = |__row: #row_type| {
+            fn __check_column<M, T: #path::DeserializeAs<M>>(_: &T) {}
+            #(#checks)*
+        };
+    })
+}
+
+/// What the macro has to know about a statement
+#[derive(Debug)]
+struct Statement {
+    /// Types of the bind markers in order, None for markers which aren't checked
+    binds: Vec<Option<CqlType>>,
+    /// Columns of the result, None for statements which don't return rows
+    columns: Option<Vec<ResultColumn>>,
+}
+
+#[derive(Debug)]
+struct ResultColumn {
+    name: Option<String>,
+    /// None for results of functions which aren't known
+    typ: Option<CqlType>,
+}
+
+fn analyze(text: &str, schema: &Schema) -> Result<Statement, String> {
+    let tokens = tokenize(text)?;
+    let mut parser = StatementParser {
+        p: Parser::new(&tokens),
+        schema,
+        binds: Vec::new(),
+    };
+    let columns = parser.statement()?;
+    parser.p.eat_punct(";");
+    if !parser.p.at_end() {
+        return Err(parser.p.unexpected("the end of the statement"));
+    }
+    Ok(Statement {
+        binds: parser.binds,
+        columns,
+    })
+}
+
+/// A selector of a SELECT statement, resolved once the table is known
+enum Selector {
+    Column(String),
+    CountAll,
+    Cast(Box<Selector>, CqlType),
+    Function { name: String, args: Vec<Selector> },
+    Other,
+}
+
+fn column_type(table: &Table, name: &str) -> Result<CqlType, String> {
+    table
+        .column(name)
+        .map(|column| column.typ.clone())
+        .ok_or_else(|| format!("column `{}` isn't in the table", name))
+}
+
+fn resolve_selector(table: &Table, selector: Selector) -> Result<ResultColumn, String> {
+    let column = match selector {
+        Selector::Column(name) => ResultColumn {
+            typ: Some(column_type(table, &name)?),
+            name: Some(name),
+        },
+        Selector::CountAll => ResultColumn {
+            name: Some("count".to_string()),
+            typ: Some(CqlType::Native("BigInt")),
+        },
+        Selector::Cast(arg, typ) => {
+            resolve_selector(table, *arg)?;
+            ResultColumn {
+                name: None,
+                typ: Some(typ),
+            }
+        }
+        Selector::Function { name, args } => {
+            let args = args
+                .into_iter()
+                .map(|arg| resolve_selector(table, arg))
+                .collect::<Result<Vec<_>, String>>()?;
+            let typ = match (name.as_str(), args.as_slice()) {
+                ("count" | "writetime" | "token", _) => Some(CqlType::Native("BigInt")),
+                ("ttl", _) => Some(CqlType::Native("Int")),
+                ("min" | "max" | "sum" | "avg", [arg]) => arg.typ.clone(),
+                _ => None,
+            };
+            ResultColumn { name: None, typ }
+        }
+        Selector::Other => ResultColumn {
+            name: None,
+            typ: None,
+        },
+    };
+    Ok(column)
+}
+
+struct StatementParser<'a> {
+    p: Parser<'a>,
+    schema: &'a Schema,
+    binds: Vec<Option<CqlType>>,
+}
+
+impl<'a> StatementParser<'a> {
+    fn statement(&mut self) -> Result<Option<Vec<ResultColumn>>, String> {
+        if self.p.eat_keyword("select") {
+            self.select().map(Some)
+        } else if self.p.eat_keyword("insert") {
+            self.insert().map(|_| None)
+        } else if self.p.eat_keyword("update") {
+            self.update().map(|_| None)
+        } else if self.p.eat_keyword("delete") {
+            self.delete().map(|_| None)
+        } else if self.p.is_keyword("begin") {
+            Err("batches aren't supported, put the statements in a `Batch` instead".to_string())
+        } else {
+            Err(self.p.unexpected("SELECT, INSERT, UPDATE or DELETE"))
+        }
+    }
+
+    fn table(&mut self) -> Result<&'a Table, String> {
+        let (keyspace, name) = self.p.qualified_name()?;
+        self.schema.table(keyspace.as_deref(), &name)
+    }
+
+    fn select(&mut self) -> Result<Vec<ResultColumn>, String> {
+        let json = self.p.eat_keyword("json");
+        self.p.eat_keyword("distinct");
+        let selectors = if self.p.eat_punct("*") {
+            None
+        } else {
+            let mut selectors = Vec::new();
+            loop {
+                let selector = self.selector()?;
+                let alias = if self.p.eat_keyword("as") {
+                    Some(self.p.name()?)
+                } else {
+                    None
+                };
+                selectors.push((selector, alias));
+                if !self.p.eat_punct(",") {
+                    break;
+                }
+            }
+            Some(selectors)
+        };
+
+        self.p.expect_keyword("from")?;
+        let table = self.table()?;
+        let columns = match selectors {
+            None => table
+                .columns
+                .iter()
+                .map(|column| ResultColumn {
+                    name: Some(column.name.clone()),
+                    typ: Some(column.typ.clone()),
+                })
+                .collect(),
+            Some(selectors) => selectors
+                .into_iter()
+                .map(|(selector, alias)| {
+                    let mut column = resolve_selector(table, selector)?;
+                    if alias.is_some() {
+                        column.name = alias;
+                    }
+                    Ok(column)
+                })
+                .collect::<Result<_, String>>()?,
+        };
+
+        if self.p.eat_keyword("where") {
+            self.relations(table)?;
+        }
+        if self.p.eat_keyword("group") {
+            self.p.expect_keyword("by")?;
+            loop {
+                column_type(table, &self.p.name()?)?;
+                if !self.p.eat_punct(",") {
+                    break;
+                }
+            }
+        }
+        if self.p.eat_keyword("order") {
+            self.p.expect_keyword("by")?;
+            loop {
+                let typ = column_type(table, &self.p.name()?)?;
+                if self.p.eat_keyword("ann") {
+                    self.p.expect_keyword("of")?;
+                    self.term(Some(typ))?;
+                } else if !self.p.eat_keyword("asc") {
+                    self.p.eat_keyword("desc");
+                }
+                if !self.p.eat_punct(",") {
+                    break;
+                }
+            }
+        }
+        if self.p.eat_keyword("per") {
+            self.p.expect_keyword("partition")?;
+            self.p.expect_keyword("limit")?;
+            self.term(Some(CqlType::Native("Int")))?;
+        }
+        if self.p.eat_keyword("limit") {
+            self.term(Some(CqlType::Native("Int")))?;
+        }
+        if self.p.eat_keyword("allow") {
+            self.p.expect_keyword("filtering")?;
+        }
+        if self.p.eat_keyword("bypass") {
+            self.p.expect_keyword("cache")?;
+        }
+        self.using()?;
+
+        if json {
+            return Ok(vec![ResultColumn {
+                name: Some("[json]".to_string()),
+                typ: Some(CqlType::Native("Text")),
+            }]);
+        }
+        Ok(columns)
+    }
+
+    fn selector(&mut self) -> Result<Selector, String> {
+        let is_literal = ["true", "false", "null"]
+            .iter()
+            .any(|literal| self.p.is_keyword(literal));
+        if !matches!(self.p.peek(), Some(Token::Ident { .. })) || is_literal {
+            self.skip_term()?;
+            return Ok(Selector::Other);
+        }
+
+        // `function(...)` or `keyspace.function(...)`
+        let is_call = match self.p.peek_nth(1) {
+            Some(Token::Punct("(")) => true,
+            Some(Token::Punct(".")) => self.p.peek_nth(3) == Some(&Token::Punct("(")),
+            _ => false,
+        };
+        if !is_call {
+            let name = self.p.name()?;
+            if self.p.eat_punct("[") {
+                self.term(None)?;
+                self.p.expect_punct("]")?;
+                return Ok(Selector::Other);
+            }
+            if self.p.eat_punct(".") {
+                self.p.name()?;
+                return Ok(Selector::Other);
+            }
+            return Ok(Selector::Column(name));
+        }
+
+        let (_, name) = self.p.qualified_name()?;
+        self.p.expect_punct("(")?;
+        if name == "count" && self.p.eat_punct("*") {
+            self.p.expect_punct(")")?;
+            return Ok(Selector::CountAll);
+        }
+        if name == "cast" {
+            let arg = self.selector()?;
+            self.p.expect_keyword("as")?;
+            let typ = CqlType::parse(&mut self.p)?;
+            self.p.expect_punct(")")?;
+            return Ok(Selector::Cast(Box::new(arg), typ));
+        }
+        let mut args = Vec::new();
+        if !self.p.eat_punct(")") {
+            loop {
+                args.push(self.selector()?);
+                if !self.p.eat_punct(",") {
+                    break;
+                }
+            }
+            self.p.expect_punct(")")?;
+        }
+        Ok(Selector::Function { name, args })
+    }
+
+    fn insert(&mut self) -> Result<(), String> {
+        self.p.expect_keyword("into")?;
+        let table = self.table()?;
+        if self.p.eat_keyword("json") {
+            self.term(Some(CqlType::Native("Text")))?;
+            if self.p.eat_keyword("default") && !self.p.eat_keyword("unset") {
+                self.p.expect_keyword("null")?;
+            }
+        } else {
+            let types = self
+                .p
+                .names_in_parens()?
+                .iter()
+                .map(|name| column_type(table, name).map(Some))
+                .collect::<Result<Vec<_>, String>>()?;
+            self.p.expect_keyword("values")?;
+            self.terms_in_parens(types)?;
+        }
+        if self.p.eat_keyword("if") {
+            self.p.expect_keyword("not")?;
+            self.p.expect_keyword("exists")?;
+        }
+        self.using()
+    }
+
+    fn update(&mut self) -> Result<(), String> {
+        let table = self.table()?;
+        self.using()?;
+        self.p.expect_keyword("set")?;
+        loop {
+            self.assignment(table)?;
+            if !self.p.eat_punct(",") {
+                break;
+            }
+        }
+        self.p.expect_keyword("where")?;
+        self.relations(table)?;
+        self.conditions(table)
+    }
+
+    fn assignment(&mut self, table: &Table) -> Result<(), String> {
+        let name = self.p.name()?;
+        let typ = column_type(table, &name)?;
+        if self.p.eat_punct("[") {
+            self.term(typ.subscript())?;
+            self.p.expect_punct("]")?;
+            self.p.expect_punct("=")?;
+            return self.term(typ.element());
+        }
+        if self.p.eat_punct(".") {
+            self.p.name()?;
+            self.p.expect_punct("=")?;
+            return self.term(None);
+        }
+        self.p.expect_punct("=")?;
+
+        // `column = column + ?` and `column = column - ?`, for counters and collections
+        let is_same_column = matches!(self.p.peek(), Some(Token::Ident { name: other, .. }) if *other == name)
+            && matches!(self.p.peek_nth(1), Some(Token::Punct("+" | "-")));
+        if is_same_column {
+            self.p.next();
+            let removes = self.p.next() == Some(&Token::Punct("-"));
+            let operand = match typ {
+                // Keys are removed from maps by a set of them
+                CqlType::Map(key, _) if removes => CqlType::Set(key),
+                typ => typ,
+            };
+            return self.term(Some(operand));
+        }
+        self.term(Some(typ))?;
+        // Prepending to a list, `column = ? + column`
+        if self.p.eat_punct("+") {
+            self.p.name()?;
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self) -> Result<(), String> {
+        // Deleted elements come before the table, so bind markers of their indices get types later
+        let mut deleted = Vec::new();
+        if !self.p.is_keyword("from") {
+            loop {
+                let name = self.p.name()?;
+                let mut bind = None;
+                if self.p.eat_punct("[") {
+                    if self.p.is_punct("?") {
+                        bind = Some(self.binds.len());
+                    }
+                    self.term(None)?;
+                    self.p.expect_punct("]")?;
+                } else if self.p.eat_punct(".") {
+                    self.p.name()?;
+                }
+                deleted.push((name, bind));
+                if !self.p.eat_punct(",") {
+                    break;
+                }
+            }
+        }
+        self.p.expect_keyword("from")?;
+        let table = self.table()?;
+        for (name, bind) in deleted {
+            let typ = column_type(table, &name)?;
+            if let Some(bind) = bind {
+                self.binds[bind] = typ.subscript();
+            }
+        }
+        self.using()?;
+        self.p.expect_keyword("where")?;
+        self.relations(table)?;
+        self.conditions(table)
+    }
+
+    /// `USING TTL ? AND TIMESTAMP ? AND TIMEOUT ?`
+    fn using(&mut self) -> Result<(), String> {
+        if !self.p.eat_keyword("using") {
+            return Ok(());
+        }
+        loop {
+            let typ = if self.p.eat_keyword("ttl") {
+                "Int"
+            } else if self.p.eat_keyword("timestamp") {
+                "BigInt"
+            } else if self.p.eat_keyword("timeout") {
+                "Duration"
+            } else {
+                return Err(self.p.unexpected("TTL, TIMESTAMP or TIMEOUT"));
+            };
+            self.term(Some(CqlType::Native(typ)))?;
+            if !self.p.eat_keyword("and") {
+                return Ok(());
+            }
+        }
+    }
+
+    /// `IF EXISTS` or `IF condition AND ...`
+    fn conditions(&mut self, table: &Table) -> Result<(), String> {
+        if self.p.eat_keyword("if") && !self.p.eat_keyword("exists") {
+            self.relations(table)?;
+        }
+        Ok(())
+    }
+
+    fn relations(&mut self, table: &Table) -> Result<(), String> {
+        loop {
+            self.relation(table)?;
+            if !self.p.eat_keyword("and") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn relation(&mut self, table: &Table) -> Result<(), String> {
+        if self.is_token_call() {
+            self.p.next();
+            let types = self
+                .p
+                .names_in_parens()?
+                .iter()
+                .map(|name| column_type(table, name).map(Some))
+                .collect::<Result<Vec<_>, String>>()?;
+            self.operator()?;
+            if self.is_token_call() {
+                self.p.next();
+                return self.terms_in_parens(types);
+            }
+            return self.term(Some(CqlType::Native("BigInt")));
+        }
+
+        if self.p.is_punct("(") {
+            let types = self
+                .p
+                .names_in_parens()?
+                .iter()
+                .map(|name| column_type(table, name).map(Some))
+                .collect::<Result<Vec<_>, String>>()?;
+            let tuple = types
+                .iter()
+                .cloned()
+                .collect::<Option<Vec<_>>>()
+                .map(CqlType::Tuple);
+            if !self.p.eat_keyword("in") {
+                self.operator()?;
+                return self.tuple_term(&types, &tuple);
+            }
+            if self.p.eat_punct("?") {
+                self.binds
+                    .push(tuple.map(|tuple| CqlType::List(Box::new(tuple))));
+                return Ok(());
+            }
+            self.p.expect_punct("(")?;
+            if !self.p.eat_punct(")") {
+                loop {
+                    self.tuple_term(&types, &tuple)?;
+                    if !self.p.eat_punct(",") {
+                        break;
+                    }
+                }
+                self.p.expect_punct(")")?;
+            }
+            return Ok(());
+        }
+
+        let name = self.p.name()?;
+        let mut typ = Some(column_type(table, &name)?);
+        if self.p.eat_punct("[") {
+            self.term(typ.as_ref().and_then(CqlType::subscript))?;
+            self.p.expect_punct("]")?;
+            typ = typ.and_then(|typ| typ.element());
+        } else if self.p.eat_punct(".") {
+            // Fields of user defined types aren't checked
+            self.p.name()?;
+            typ = None;
+        }
+
+        if self.p.eat_keyword("is") {
+            self.p.expect_keyword("not")?;
+            self.p.expect_keyword("null")
+        } else if self.p.eat_keyword("in") {
+            if self.p.eat_punct("?") {
+                self.binds.push(typ.map(|typ| CqlType::List(Box::new(typ))));
+                return Ok(());
+            }
+            self.p.expect_punct("(")?;
+            if !self.p.eat_punct(")") {
+                loop {
+                    self.term(typ.clone())?;
+                    if !self.p.eat_punct(",") {
+                        break;
+                    }
+                }
+                self.p.expect_punct(")")?;
+            }
+            Ok(())
+        } else if self.p.eat_keyword("contains") {
+            if self.p.eat_keyword("key") {
+                self.term(typ.and_then(|typ| typ.map_key()))
+            } else {
+                self.term(typ.and_then(|typ| typ.element()))
+            }
+        } else {
+            if !self.p.eat_keyword("like") {
+                self.operator()?;
+            }
+            self.term(typ)
+        }
+    }
+
+    fn is_token_call(&self) -> bool {
+        self.p.is_keyword("token") && self.p.peek_nth(1) == Some(&Token::Punct("("))
+    }
+
+    fn operator(&mut self) -> Result<(), String> {
+        match self.p.peek() {
+            Some(Token::Punct("=" | "<" | ">" | "<=" | ">=" | "!=")) => {
+                self.p.next();
+                Ok(())
+            }
+            _ => Err(self.p.unexpected("an operator")),
+        }
+    }
+
+    /// A tuple of values compared with a tuple of columns, as a single value or element by element
+    fn tuple_term(
+        &mut self,
+        types: &[Option<CqlType>],
+        tuple: &Option<CqlType>,
+    ) -> Result<(), String> {
+        if self.p.eat_punct("?") {
+            self.binds.push(tuple.clone());
+            Ok(())
+        } else if self.p.is_punct("(") {
+            self.terms_in_parens(types.to_vec())
+        } else {
+            self.skip_term()
+        }
+    }
+
+    /// `(value, ...)` with the given types of the values
+    fn terms_in_parens(&mut self, types: Vec<Option<CqlType>>) -> Result<(), String> {
+        self.p.expect_punct("(")?;
+        for (i, typ) in types.into_iter().enumerate() {
+            if i > 0 {
+                self.p.expect_punct(",")?;
+            }
+            self.term(typ)?;
+        }
+        self.p.expect_punct(")")
+    }
+
+    /// A value of the given type, a bind marker or a literal
+    fn term(&mut self, typ: Option<CqlType>) -> Result<(), String> {
+        if self.p.eat_punct("?") {
+            self.binds.push(typ);
+            Ok(())
+        } else {
+            self.skip_term()
+        }
+    }
+
+    /// Skips a literal, a function call or a collection, bind markers in it aren't checked
+    fn skip_term(&mut self) -> Result<(), String> {
+        if self.p.is_punct(":") {
+            return Err("named bind markers aren't supported, use `?`".to_string());
+        }
+        match self.p.peek() {
+            Some(Token::Punct("?")) => self.binds.push(None),
+            Some(Token::Punct("-")) => {
+                self.p.next();
+                return self.skip_term();
+            }
+            Some(Token::Punct("(" | "[" | "{")) => {
+                self.p.next();
+                return self.skip_group();
+            }
+            Some(Token::Ident { .. }) => {
+                self.p.next();
+                if self.p.eat_punct("(") {
+                    return self.skip_group();
+                }
+                return Ok(());
+            }
+            Some(Token::Str | Token::Number(_)) => {}
+            _ => return Err(self.p.unexpected("a value")),
+        }
+        self.p.next();
+        Ok(())
+    }
+
+    /// Skips tokens up to the closing bracket of an already opened one
+    fn skip_group(&mut self) -> Result<(), String> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.p.next() {
+                Some(Token::Punct("(" | "[" | "{")) => depth += 1,
+                Some(Token::Punct(")" | "]" | "}")) => depth -= 1,
+                Some(Token::Punct("?")) => self.binds.push(None),
+                Some(_) => {}
+                None => return Err(self.p.unexpected("a closing bracket")),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::parse(
+            "CREATE TABLE ks.t (
+                pk int, ck text, v bigint, l list<int>, m map<text, boolean>, s set<uuid>,
+                PRIMARY KEY (pk, ck)
+            );
+            CREATE TABLE ks.counts (k text PRIMARY KEY, hits counter);",
+        )
+        .unwrap()
+    }
+
+    fn native(name: &'static str) -> Option<CqlType> {
+        Some(CqlType::Native(name))
+    }
+
+    fn list(typ: CqlType) -> Option<CqlType> {
+        Some(CqlType::List(Box::new(typ)))
+    }
+
+    #[test]
+    fn select_star() {
+        let statement = analyze(
+            "SELECT * FROM t WHERE pk = ? AND ck IN ? LIMIT ?",
+            &schema(),
+        )
+        .unwrap();
+        assert_eq!(
+            statement.binds,
+            [native("Int"), list(CqlType::Native("Text")), native("Int")]
+        );
+        let columns = statement.columns.unwrap();
+        let names: Vec<_> = columns.iter().map(|c| c.name.as_deref().unwrap()).collect();
+        assert_eq!(names, ["pk", "ck", "l", "m", "s", "v"]);
+    }
+
+    #[test]
+    fn select_selectors() {
+        let statement = analyze(
+            "SELECT v AS value, count(*), ttl(v), writetime(v), max(v), cast(pk AS text), now(), \
+             m['key'] FROM ks.t WHERE token(pk) > ? AND (ck) < (?) AND m CONTAINS KEY ? \
+             AND l CONTAINS ? AND m[?] = ? PER PARTITION LIMIT 1 ALLOW FILTERING",
+            &schema(),
+        )
+        .unwrap();
+        assert_eq!(
+            statement.binds,
+            [
+                native("BigInt"),
+                native("Text"),
+                native("Text"),
+                native("Int"),
+                native("Text"),
+                native("Boolean")
+            ]
+        );
+        let columns: Vec<_> = statement
+            .columns
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.name, c.typ))
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                (Some("value".to_string()), native("BigInt")),
+                (Some("count".to_string()), native("BigInt")),
+                (None, native("Int")),
+                (None, native("BigInt")),
+                (None, native("BigInt")),
+                (None, native("Text")),
+                (None, None),
+                (None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn modifications() {
+        let schema = schema();
+        let insert = analyze(
+            "INSERT INTO t (pk, ck, s) VALUES (?, 'a', ?) IF NOT EXISTS USING TTL ? AND TIMESTAMP ?",
+            &schema,
+        )
+        .unwrap();
+        assert_eq!(
+            insert.binds,
+            [
+                native("Int"),
+                Some(CqlType::Set(Box::new(CqlType::Native("Uuid")))),
+                native("Int"),
+                native("BigInt")
+            ]
+        );
+        assert!(insert.columns.is_none());
+
+        let update = analyze(
+            "UPDATE t USING TIMEOUT ? SET l = l + ?, l[?] = ?, m = m - ?, v = ? \
+             WHERE pk = ? AND ck = ? IF v = ?",
+            &schema,
+        )
+        .unwrap();
+        assert_eq!(
+            update.binds,
+            [
+                native("Duration"),
+                list(CqlType::Native("Int")),
+                native("Int"),
+                native("Int"),
+                Some(CqlType::Set(Box::new(CqlType::Native("Text")))),
+                native("BigInt"),
+                native("Int"),
+                native("Text"),
+                native("BigInt")
+            ]
+        );
+
+        let counter = analyze("UPDATE counts SET hits = hits + ? WHERE k = ?", &schema).unwrap();
+        assert_eq!(counter.binds, [native("Counter"), native("Text")]);
+
+        let delete = analyze(
+            "DELETE m[?], v FROM t WHERE pk = ? AND (ck) IN ((?), (?)) IF EXISTS",
+            &schema,
+        )
+        .unwrap();
+        assert_eq!(
+            delete.binds,
+            [
+                native("Text"),
+                native("Int"),
+                native("Text"),
+                native("Text")
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let schema = schema();
+        for text in [
+            "SELECT missing FROM t",
+            "SELECT * FROM missing",
+            "SELECT * FROM t WHERE pk = :pk",
+            "SELECT * FROM t WHERE",
+            "SELECT * FROM t LIMIT 1 garbage",
+            "INSERT INTO t (pk, ck) VALUES (?)",
+            "BEGIN BATCH INSERT INTO t (pk, ck) VALUES (?, ?) APPLY BATCH",
+            "TRUNCATE t",
+        ] {
+            assert!(analyze(text, &schema).is_err(), "{}", text);
+        }
+    }
+}
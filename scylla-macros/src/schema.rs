@@ -0,0 +1,815 @@
+//! The schema which `query!` checks statements against.
+//! It's read from a file of CQL statements, e.g. the output of `DESCRIBE KEYSPACE`.
+//! `CREATE TABLE`, `CREATE MATERIALIZED VIEW` and `USE` are taken into account,
+//! other statements are ignored.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Token {
+    /// Unquoted identifiers and keywords are lowercased, quoted identifiers keep their case
+    Ident {
+        name: String,
+        quoted: bool,
+    },
+    Str,
+    Number(String),
+    Punct(&'static str),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident {
+                name,
+                quoted: false,
+            } => format!("`{}`", name),
+            Token::Ident { name, quoted: true } => format!("`\"{}\"`", name),
+            Token::Str => "a string".to_string(),
+            Token::Number(number) => format!("`{}`", number),
+            Token::Punct(punct) => format!("`{}`", punct),
+        }
+    }
+}
+
+const PUNCTS: &[&str] = &[
+    "<=", ">=", "!=", "(", ")", ",", ";", "<", ">", "=", ".", "*", ":", "?", "[", "]", "{", "}",
+    "+", "-", "/", "%",
+];
+
+/// Splits CQL text into tokens, skipping comments
+pub(crate) fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if rest.starts_with("--") || rest.starts_with("//") {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let end = comment.find("*/").ok_or("unterminated comment")?;
+            rest = &comment[end + 2..];
+        } else if let Some(body) = rest.strip_prefix("$$") {
+            let end = body.find("$$").ok_or("unterminated string")?;
+            tokens.push(Token::Str);
+            rest = &body[end + 2..];
+        } else if c == '\'' || c == '"' {
+            let (content, after) = quoted(rest, c)?;
+            tokens.push(if c == '"' {
+                Token::Ident {
+                    name: content,
+                    quoted: true,
+                }
+            } else {
+                Token::Str
+            });
+            rest = after;
+        } else if let Some(len) = uuid_len(rest) {
+            tokens.push(Token::Number(rest[..len].to_string()));
+            rest = &rest[len..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident {
+                name: rest[..len].to_ascii_lowercase(),
+                quoted: false,
+            });
+            rest = &rest[len..];
+        } else if c.is_ascii_digit() {
+            // Covers floats, exponents, hex blobs and durations like `1h30m`
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Number(rest[..len].to_string()));
+            rest = &rest[len..];
+        } else if let Some(punct) = PUNCTS.iter().find(|punct| rest.starts_with(**punct)) {
+            tokens.push(Token::Punct(punct));
+            rest = &rest[punct.len()..];
+        } else {
+            return Err(format!("unexpected character `{}`", c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Reads a string or an identifier quoted with `quote`, in which the quote is escaped by doubling it
+fn quoted(text: &str, quote: char) -> Result<(String, &str), String> {
+    let mut content = String::new();
+    let mut rest = &text[1..];
+    loop {
+        let end = rest.find(quote).ok_or_else(|| {
+            format!(
+                "unterminated {}",
+                if quote == '"' { "identifier" } else { "string" }
+            )
+        })?;
+        content.push_str(&rest[..end]);
+        rest = &rest[end + 1..];
+        if rest.starts_with(quote) {
+            content.push(quote);
+            rest = &rest[1..];
+        } else {
+            return Ok((content, rest));
+        }
+    }
+}
+
+/// Length of the UUID literal at the beginning of the text, if there is one
+fn uuid_len(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let groups = [8, 4, 4, 4, 12];
+    let mut pos = 0;
+    for (i, group) in groups.iter().enumerate() {
+        if i > 0 {
+            if bytes.get(pos) != Some(&b'-') {
+                return None;
+            }
+            pos += 1;
+        }
+        let digits = bytes.get(pos..pos + group)?;
+        if !digits.iter().all(u8::is_ascii_hexdigit) {
+            return None;
+        }
+        pos += group;
+    }
+    match bytes.get(pos) {
+        Some(c) if c.is_ascii_alphanumeric() || *c == b'_' => None,
+        _ => Some(pos),
+    }
+}
+
+/// Walks over the tokens of a single statement
+pub(crate) struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(tokens: &'a [Token]) -> Self {
+        Parser { tokens, pos: 0 }
+    }
+
+    pub(crate) fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    pub(crate) fn peek_nth(&self, n: usize) -> Option<&'a Token> {
+        self.tokens.get(self.pos + n)
+    }
+
+    pub(crate) fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    pub(crate) fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    pub(crate) fn is_keyword(&self, keyword: &str) -> bool {
+        is_keyword(self.peek(), keyword)
+    }
+
+    pub(crate) fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let is_keyword = self.is_keyword(keyword);
+        if is_keyword {
+            self.pos += 1;
+        }
+        is_keyword
+    }
+
+    pub(crate) fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", keyword.to_ascii_uppercase())))
+        }
+    }
+
+    pub(crate) fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    pub(crate) fn eat_punct(&mut self, punct: &str) -> bool {
+        let is_punct = self.is_punct(punct);
+        if is_punct {
+            self.pos += 1;
+        }
+        is_punct
+    }
+
+    pub(crate) fn expect_punct(&mut self, punct: &str) -> Result<(), String> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", punct)))
+        }
+    }
+
+    /// Reads an identifier, quoted or not
+    pub(crate) fn name(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Ident { name, .. }) => {
+                self.pos += 1;
+                Ok(name.clone())
+            }
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    /// Reads `name` or `keyspace.name`
+    pub(crate) fn qualified_name(&mut self) -> Result<(Option<String>, String), String> {
+        let name = self.name()?;
+        if self.eat_punct(".") {
+            Ok((Some(name), self.name()?))
+        } else {
+            Ok((None, name))
+        }
+    }
+
+    /// Reads a comma separated list of names in parentheses
+    pub(crate) fn names_in_parens(&mut self) -> Result<Vec<String>, String> {
+        self.expect_punct("(")?;
+        let mut names = vec![self.name()?];
+        while self.eat_punct(",") {
+            names.push(self.name()?);
+        }
+        self.expect_punct(")")?;
+        Ok(names)
+    }
+
+    pub(crate) fn unexpected(&self, expected: &str) -> String {
+        match self.peek() {
+            Some(token) => format!("expected {}, found {}", expected, token.describe()),
+            None => format!("expected {}, found the end of the statement", expected),
+        }
+    }
+}
+
+pub(crate) fn is_keyword(token: Option<&Token>, keyword: &str) -> bool {
+    matches!(token, Some(Token::Ident { name, quoted: false }) if name == keyword)
+}
+
+/// Splits tokens into statements separated by semicolons
+fn statements(tokens: &[Token]) -> impl Iterator<Item = &[Token]> {
+    tokens
+        .split(|token| *token == Token::Punct(";"))
+        .filter(|statement| !statement.is_empty())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum CqlType {
+    /// Name of the marker type, e.g. `BigInt`
+    Native(&'static str),
+    List(Box<CqlType>),
+    Set(Box<CqlType>),
+    Map(Box<CqlType>, Box<CqlType>),
+    Tuple(Vec<CqlType>),
+    Vector(Box<CqlType>, usize),
+    Udt,
+    /// Custom types aren't checked
+    Custom,
+}
+
+const NATIVE_TYPES: &[(&str, &str)] = &[
+    ("ascii", "Ascii"),
+    ("bigint", "BigInt"),
+    ("blob", "Blob"),
+    ("boolean", "Boolean"),
+    ("counter", "Counter"),
+    ("date", "Date"),
+    ("decimal", "Decimal"),
+    ("double", "Double"),
+    ("duration", "Duration"),
+    ("float", "Float"),
+    ("inet", "Inet"),
+    ("int", "Int"),
+    ("smallint", "SmallInt"),
+    ("text", "Text"),
+    ("time", "Time"),
+    ("timestamp", "Timestamp"),
+    ("timeuuid", "Timeuuid"),
+    ("tinyint", "TinyInt"),
+    ("uuid", "Uuid"),
+    ("varchar", "Text"),
+    ("varint", "Varint"),
+];
+
+impl CqlType {
+    pub(crate) fn parse(p: &mut Parser) -> Result<CqlType, String> {
+        let name = match p.peek() {
+            Some(Token::Str) => {
+                p.next();
+                return Ok(CqlType::Custom);
+            }
+            Some(Token::Ident {
+                name,
+                quoted: false,
+            }) => {
+                p.next();
+                name.as_str()
+            }
+            Some(Token::Ident { quoted: true, .. }) => {
+                // A user defined type, possibly qualified with a keyspace
+                p.qualified_name()?;
+                return Ok(CqlType::Udt);
+            }
+            _ => return Err(p.unexpected("a type")),
+        };
+
+        let typ = match name {
+            "frozen" => {
+                p.expect_punct("<")?;
+                let typ = CqlType::parse(p)?;
+                p.expect_punct(">")?;
+                typ
+            }
+            "list" | "set" => {
+                p.expect_punct("<")?;
+                let element = Box::new(CqlType::parse(p)?);
+                p.expect_punct(">")?;
+                if name == "list" {
+                    CqlType::List(element)
+                } else {
+                    CqlType::Set(element)
+                }
+            }
+            "map" => {
+                p.expect_punct("<")?;
+                let key = Box::new(CqlType::parse(p)?);
+                p.expect_punct(",")?;
+                let value = Box::new(CqlType::parse(p)?);
+                p.expect_punct(">")?;
+                CqlType::Map(key, value)
+            }
+            "tuple" => {
+                p.expect_punct("<")?;
+                let mut elements = vec![CqlType::parse(p)?];
+                while p.eat_punct(",") {
+                    elements.push(CqlType::parse(p)?);
+                }
+                p.expect_punct(">")?;
+                CqlType::Tuple(elements)
+            }
+            "vector" => {
+                p.expect_punct("<")?;
+                let element = Box::new(CqlType::parse(p)?);
+                p.expect_punct(",")?;
+                let dimensions = match p.peek() {
+                    Some(Token::Number(number)) => number.parse::<usize>().ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| p.unexpected("the number of dimensions"))?;
+                p.next();
+                p.expect_punct(">")?;
+                CqlType::Vector(element, dimensions)
+            }
+            _ => match NATIVE_TYPES.iter().find(|(cql_name, _)| *cql_name == name) {
+                Some((_, marker)) => CqlType::Native(marker),
+                None => {
+                    if p.eat_punct(".") {
+                        p.name()?;
+                    }
+                    CqlType::Udt
+                }
+            },
+        };
+        Ok(typ)
+    }
+
+    /// The type of the elements of lists, sets and vectors, or the values of maps
+    pub(crate) fn element(&self) -> Option<CqlType> {
+        match self {
+            CqlType::List(element) | CqlType::Set(element) | CqlType::Vector(element, _) => {
+                Some((**element).clone())
+            }
+            CqlType::Map(_, value) => Some((**value).clone()),
+            _ => None,
+        }
+    }
+
+    /// The type of the index in `column[?]`
+    pub(crate) fn subscript(&self) -> Option<CqlType> {
+        match self {
+            CqlType::List(_) => Some(CqlType::Native("Int")),
+            CqlType::Map(key, _) => Some((**key).clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn map_key(&self) -> Option<CqlType> {
+        match self {
+            CqlType::Map(key, _) => Some((**key).clone()),
+            _ => None,
+        }
+    }
+
+    /// The marker type from `type_markers`, None if the type isn't checked
+    pub(crate) fn marker(&self, path: &TokenStream) -> Option<TokenStream> {
+        let markers = quote!(#path::type_markers);
+        let marker = match self {
+            CqlType::Native(name) => {
+                let name = syn::Ident::new(name, proc_macro2::Span::call_site());
+                quote!(#markers::#name)
+            }
+            CqlType::List(element) => {
+                let element = element.marker(path)?;
+                quote!(#markers::List<#element>)
+            }
+            CqlType::Set(element) => {
+                let element = element.marker(path)?;
+                quote!(#markers::Set<#element>)
+            }
+            CqlType::Map(key, value) => {
+                let key = key.marker(path)?;
+                let value = value.marker(path)?;
+                quote!(#markers::Map<#key, #value>)
+            }
+            CqlType::Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| element.marker(path))
+                    .collect::<Option<Vec<_>>>()?;
+                quote!(#markers::Tuple<(#(#elements,)*)>)
+            }
+            CqlType::Vector(element, dimensions) => {
+                let element = element.marker(path)?;
+                quote!(#markers::Vector<#element, #dimensions>)
+            }
+            CqlType::Udt => quote!(#markers::Udt),
+            CqlType::Custom => return None,
+        };
+        Some(marker)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ColumnKind {
+    PartitionKey,
+    Clustering,
+    Static,
+    Regular,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Column {
+    pub(crate) name: String,
+    pub(crate) typ: CqlType,
+    pub(crate) kind: ColumnKind,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Table {
+    /// Columns in the order of `SELECT *`: partition key, clustering key,
+    /// then static and regular columns sorted by name
+    pub(crate) columns: Vec<Column>,
+}
+
+impl Table {
+    pub(crate) fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    fn new(mut columns: Vec<Column>, partition_key: &[String], clustering_key: &[String]) -> Self {
+        let key_position = |column: &Column| {
+            partition_key
+                .iter()
+                .position(|name| *name == column.name)
+                .map(|pos| (ColumnKind::PartitionKey, pos))
+                .or_else(|| {
+                    clustering_key
+                        .iter()
+                        .position(|name| *name == column.name)
+                        .map(|pos| (ColumnKind::Clustering, pos))
+                })
+        };
+        for column in &mut columns {
+            if let Some((kind, _)) = key_position(column) {
+                column.kind = kind;
+            }
+        }
+        // Key columns are ordered by their position in the key, other ones by name
+        columns.sort_by(|a, b| {
+            (a.kind, key_position(a), &a.name).cmp(&(b.kind, key_position(b), &b.name))
+        });
+        Table { columns }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Schema {
+    /// Tables and materialized views by keyspace and name
+    tables: BTreeMap<(Option<String>, String), Table>,
+}
+
+impl Schema {
+    pub(crate) fn parse(text: &str) -> Result<Schema, String> {
+        let tokens = tokenize(text)?;
+        let mut schema = Schema::default();
+        let mut keyspace = None;
+        for statement in statements(&tokens) {
+            let mut p = Parser::new(statement);
+            if p.eat_keyword("use") {
+                keyspace = Some(p.name()?);
+            } else if p.eat_keyword("create") {
+                if p.eat_keyword("table") || p.eat_keyword("columnfamily") {
+                    let (name, table) = parse_create_table(&mut p)?;
+                    schema.insert(name, &keyspace, table);
+                } else if p.eat_keyword("materialized") {
+                    p.expect_keyword("view")?;
+                    let (name, view) = schema.parse_create_view(&mut p, &keyspace)?;
+                    schema.insert(name, &keyspace, view);
+                }
+            }
+        }
+        Ok(schema)
+    }
+
+    fn insert(
+        &mut self,
+        (keyspace, name): (Option<String>, String),
+        current_keyspace: &Option<String>,
+        table: Table,
+    ) {
+        let keyspace = keyspace.or_else(|| current_keyspace.clone());
+        self.tables.insert((keyspace, name), table);
+    }
+
+    /// Finds a table, a table name without a keyspace has to be unique in the schema
+    pub(crate) fn table(&self, keyspace: Option<&str>, name: &str) -> Result<&Table, String> {
+        let mut tables = self
+            .tables
+            .iter()
+            .filter(|((table_keyspace, table_name), _)| {
+                table_name == name && (keyspace.is_none() || table_keyspace.as_deref() == keyspace)
+            });
+        match (tables.next(), tables.next()) {
+            (Some((_, table)), None) => Ok(table),
+            (None, _) => Err(match keyspace {
+                Some(keyspace) => format!("table `{}.{}` isn't in the schema", keyspace, name),
+                None => format!("table `{}` isn't in the schema", name),
+            }),
+            (Some(_), Some(_)) => Err(format!(
+                "table `{}` is in many keyspaces, qualify it with the keyspace's name",
+                name
+            )),
+        }
+    }
+
+    fn parse_create_view(
+        &self,
+        p: &mut Parser,
+        current_keyspace: &Option<String>,
+    ) -> Result<((Option<String>, String), Table), String> {
+        if_not_exists(p)?;
+        let (keyspace, name) = p.qualified_name()?;
+        p.expect_keyword("as")?;
+        p.expect_keyword("select")?;
+        let selected = if p.eat_punct("*") {
+            None
+        } else {
+            let mut selected = vec![p.name()?];
+            while p.eat_punct(",") {
+                selected.push(p.name()?);
+            }
+            Some(selected)
+        };
+        p.expect_keyword("from")?;
+        let (base_keyspace, base_name) = p.qualified_name()?;
+        let base_keyspace = base_keyspace
+            .or_else(|| keyspace.clone())
+            .or_else(|| current_keyspace.clone());
+        let base = self.table(base_keyspace.as_deref(), &base_name)?;
+
+        // Skips the WHERE clause, up to the primary key
+        while !(p.is_keyword("primary") && is_keyword(p.peek_nth(1), "key")) {
+            if p.next().is_none() {
+                return Err(p.unexpected("`PRIMARY KEY`"));
+            }
+        }
+        p.expect_keyword("primary")?;
+        p.expect_keyword("key")?;
+        let (partition_key, clustering_key) = parse_primary_key(p)?;
+
+        let columns = base
+            .columns
+            .iter()
+            .filter(|column| match &selected {
+                Some(selected) => {
+                    selected.contains(&column.name)
+                        || partition_key.contains(&column.name)
+                        || clustering_key.contains(&column.name)
+                }
+                None => true,
+            })
+            .map(|column| Column {
+                kind: ColumnKind::Regular,
+                ..column.clone()
+            })
+            .collect();
+        Ok((
+            (keyspace, name),
+            Table::new(columns, &partition_key, &clustering_key),
+        ))
+    }
+}
+
+fn if_not_exists(p: &mut Parser) -> Result<(), String> {
+    if p.eat_keyword("if") {
+        p.expect_keyword("not")?;
+        p.expect_keyword("exists")?;
+    }
+    Ok(())
+}
+
+fn parse_create_table(p: &mut Parser) -> Result<((Option<String>, String), Table), String> {
+    if_not_exists(p)?;
+    let name = p.qualified_name()?;
+    p.expect_punct("(")?;
+
+    let mut columns = Vec::new();
+    let mut partition_key = Vec::new();
+    let mut clustering_key = Vec::new();
+    loop {
+        if p.eat_keyword("primary") {
+            p.expect_keyword("key")?;
+            (partition_key, clustering_key) = parse_primary_key(p)?;
+        } else {
+            let column_name = p.name()?;
+            let typ = CqlType::parse(p)?;
+            let kind = if p.eat_keyword("static") {
+                ColumnKind::Static
+            } else {
+                ColumnKind::Regular
+            };
+            if p.eat_keyword("primary") {
+                p.expect_keyword("key")?;
+                partition_key = vec![column_name.clone()];
+            }
+            columns.push(Column {
+                name: column_name,
+                typ,
+                kind,
+            });
+        }
+        if !p.eat_punct(",") {
+            break;
+        }
+    }
+    p.expect_punct(")")?;
+
+    if partition_key.is_empty() {
+        return Err(format!("table `{}` has no primary key", name.1));
+    }
+    for key_column in partition_key.iter().chain(&clustering_key) {
+        if !columns.iter().any(|column| column.name == *key_column) {
+            return Err(format!(
+                "primary key column `{}` isn't a column of table `{}`",
+                key_column, name.1
+            ));
+        }
+    }
+    Ok((name, Table::new(columns, &partition_key, &clustering_key)))
+}
+
+/// Parses `(pk, ck, ...)` or `((pk, ...), ck, ...)`
+fn parse_primary_key(p: &mut Parser) -> Result<(Vec<String>, Vec<String>), String> {
+    p.expect_punct("(")?;
+    let partition_key = if p.is_punct("(") {
+        p.names_in_parens()?
+    } else {
+        vec![p.name()?]
+    };
+    let mut clustering_key = Vec::new();
+    while p.eat_punct(",") {
+        clustering_key.push(p.name()?);
+    }
+    p.expect_punct(")")?;
+    Ok((partition_key, clustering_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        CREATE KEYSPACE ks WITH replication = {'class': 'SimpleStrategy', 'replication_factor': '1'};
+        USE ks;
+
+        -- Comments are skipped
+        CREATE TABLE ks.users (
+            id uuid,
+            bucket int,
+            "Name" text,
+            emails frozen<set<text>>,
+            scores map<text, frozen<list<int>>>,
+            embedding vector<float, 3>,
+            owner "Owner",
+            config 'org.apache.cassandra.db.marshal.BytesType',
+            created timestamp,
+            region text static,
+            PRIMARY KEY ((id, bucket), created)
+        ) WITH comment = 'users; with a semicolon';
+
+        /* Tables without a keyspace belong to the used one */
+        CREATE TABLE IF NOT EXISTS counters (key text PRIMARY KEY, hits counter);
+
+        CREATE MATERIALIZED VIEW ks.users_by_name AS
+            SELECT id, bucket, created FROM users
+            WHERE "Name" IS NOT NULL AND id IS NOT NULL AND bucket IS NOT NULL
+            PRIMARY KEY ("Name", id, bucket, created);
+
+        CREATE FUNCTION ks.f(a int) RETURNS NULL ON NULL INPUT RETURNS int
+            LANGUAGE lua AS $$ return a; $$;
+    "#;
+
+    #[test]
+    fn parse_tables() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+
+        let users = schema.table(Some("ks"), "users").unwrap();
+        let names: Vec<&str> = users.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "id",
+                "bucket",
+                "created",
+                "region",
+                "Name",
+                "config",
+                "emails",
+                "embedding",
+                "owner",
+                "scores"
+            ]
+        );
+        assert_eq!(users.column("region").unwrap().kind, ColumnKind::Static);
+        assert_eq!(
+            users.column("emails").unwrap().typ,
+            CqlType::Set(Box::new(CqlType::Native("Text")))
+        );
+        assert_eq!(
+            users.column("scores").unwrap().typ,
+            CqlType::Map(
+                Box::new(CqlType::Native("Text")),
+                Box::new(CqlType::List(Box::new(CqlType::Native("Int"))))
+            )
+        );
+        assert_eq!(users.column("owner").unwrap().typ, CqlType::Udt);
+        assert_eq!(users.column("config").unwrap().typ, CqlType::Custom);
+
+        let counters = schema.table(None, "counters").unwrap();
+        assert_eq!(
+            counters.column("key").unwrap().kind,
+            ColumnKind::PartitionKey
+        );
+        assert_eq!(
+            counters.column("hits").unwrap().typ,
+            CqlType::Native("Counter")
+        );
+        assert!(schema.table(Some("ks"), "counters").is_ok());
+
+        let view = schema.table(None, "users_by_name").unwrap();
+        let names: Vec<&str> = view.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Name", "id", "bucket", "created"]);
+
+        assert!(schema.table(None, "missing").is_err());
+        assert!(schema.table(Some("other"), "users").is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Schema::parse("CREATE TABLE t (a int, b text)").is_err());
+        assert!(Schema::parse("CREATE TABLE t (a int PRIMARY KEY, b list<int)").is_err());
+        assert!(Schema::parse("CREATE TABLE t (a int, PRIMARY KEY (b))").is_err());
+        assert!(Schema::parse("CREATE TABLE t (a int PRIMARY KEY, b 'unterminated)").is_err());
+    }
+
+    #[test]
+    fn ambiguous_tables() {
+        let schema = Schema::parse(
+            "CREATE TABLE a.t (x int PRIMARY KEY); CREATE TABLE b.t (y int PRIMARY KEY);",
+        )
+        .unwrap();
+        assert!(schema.table(None, "t").is_err());
+        assert!(schema.table(Some("b"), "t").unwrap().column("y").is_some());
+    }
+
+    #[test]
+    fn tokenize_literals() {
+        let tokens = tokenize("x = 123e4567-e89b-12d3-a456-426655440000 AND y = 'it''s'").unwrap();
+        assert_eq!(tokens.len(), 7);
+        assert_eq!(
+            tokens[2],
+            Token::Number("123e4567-e89b-12d3-a456-426655440000".to_string())
+        );
+        assert_eq!(tokens[6], Token::Str);
+    }
+}
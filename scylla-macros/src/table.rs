@@ -0,0 +1,488 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{Data, DeriveInput, Fields, Lit, LitStr, Meta};
+
+use crate::parser::StructField;
+
+/// #[derive(Table)] maps a struct to the rows of a table,
+/// generating statements which read and write a single row
+pub fn table_derive(tokens_input: TokenStream) -> TokenStream {
+    let item = syn::parse::<DeriveInput>(tokens_input).expect("No DeriveInput");
+    match derive(&item) {
+        Ok(generated) => TokenStream::from(generated),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum KeyKind {
+    Regular,
+    Partition,
+    Clustering,
+}
+
+/// A column of the table, read and written by a field of the struct
+struct Column<'a> {
+    field: &'a StructField<'a>,
+    name: String,
+    kind: KeyKind,
+}
+
+fn derive(item: &DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let path = crate::parser::get_path(item)?;
+    let parsed = crate::parser::parse_struct(item, "Table")?;
+    if !matches!(&item.data, Data::Struct(data) if matches!(data.fields, Fields::Named(_))) {
+        return Err(syn::Error::new_spanned(
+            &item.ident,
+            "derive(Table) works only on structs with named fields",
+        ));
+    }
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "derive(Table) doesn't support generic structs",
+        ));
+    }
+    let table_name = table_name(item)?;
+
+    let columns = table_columns(&parsed.fields)?;
+    let key_columns = key_columns(&columns);
+    if key_columns.first().map(|column| column.kind) != Some(KeyKind::Partition) {
+        return Err(syn::Error::new_spanned(
+            &item.ident,
+            "derive(Table) needs at least one field with `#[partition_key]`",
+        ));
+    }
+    let regular_columns = regular_columns(&columns);
+    let Statements {
+        insert,
+        select,
+        update,
+        delete,
+    } = Statements::new(&table_name.value(), &columns);
+
+    let struct_name = &item.ident;
+    let vis = &item.vis;
+    let key_name = format_ident!("{}Key", struct_name);
+    let key_doc = format!("Primary key of a row of [`{}`]", struct_name);
+    let key_members: Vec<_> = key_columns
+        .iter()
+        .map(|column| &column.field.member)
+        .collect();
+    let key_types: Vec<_> = key_columns.iter().map(|column| column.field.ty()).collect();
+    let key_len = key_columns.len();
+
+    let add_values = |columns: &[&Column]| {
+        let len = columns.len();
+        let add_value_code = columns.iter().map(|column| {
+            let member = &column.field.member;
+            quote_spanned! {column.field.span() =>
+                values.add_value(&self.#member)?;
+            }
+        });
+        quote! {
+            let mut values = #path::SerializedValues::with_capacity(#len);
+            #(#add_value_code)*
+            ::std::result::Result::Ok(::std::borrow::Cow::Owned(values))
+        }
+    };
+    let insert_values_code = add_values(&columns.iter().collect::<Vec<_>>());
+    let update_values_code = if regular_columns.is_empty() {
+        insert_values_code.clone()
+    } else {
+        add_values(
+            &regular_columns
+                .iter()
+                .chain(key_columns.iter())
+                .copied()
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    let generated = quote! {
+        #[doc = #key_doc]
+        #[derive(::core::fmt::Debug, ::core::clone::Clone, ::core::cmp::PartialEq)]
+        #vis struct #key_name {
+            #(#vis #key_members: #key_types,)*
+        }
+
+        impl #path::ValueList for #key_name {
+            fn serialized(&self) -> #path::SerializedResult {
+                let mut values = #path::SerializedValues::with_capacity(#key_len);
+                #(values.add_value(&self.#key_members)?;)*
+                ::std::result::Result::Ok(::std::borrow::Cow::Owned(values))
+            }
+        }
+
+        impl #path::Table for #struct_name {
+            type Key = #key_name;
+
+            const NAME: &'static str = #table_name;
+            const INSERT: &'static str = #insert;
+            const SELECT: &'static str = #select;
+            const UPDATE: &'static str = #update;
+            const DELETE: &'static str = #delete;
+
+            fn key(&self) -> #key_name {
+                #key_name {
+                    #(#key_members: ::std::clone::Clone::clone(&self.#key_members),)*
+                }
+            }
+
+            fn insert_values(&self) -> #path::SerializedResult {
+                #insert_values_code
+            }
+
+            fn update_values(&self) -> #path::SerializedResult {
+                #update_values_code
+            }
+        }
+    };
+
+    Ok(generated)
+}
+
+/// Columns of the non-skipped fields, in the order of the fields
+fn table_columns<'a>(fields: &'a [StructField<'a>]) -> Result<Vec<Column<'a>>, syn::Error> {
+    let mut columns = Vec::new();
+    for field in fields {
+        let kind = key_kind(field)?;
+        if field.attrs.skip {
+            if kind != KeyKind::Regular {
+                return Err(syn::Error::new_spanned(
+                    field.field,
+                    "a column of the primary key can't be skipped",
+                ));
+            }
+            continue;
+        }
+        if field.attrs.flatten {
+            return Err(syn::Error::new_spanned(
+                field.field,
+                "derive(Table) doesn't support `flatten`",
+            ));
+        }
+        columns.push(Column {
+            field,
+            name: field.required_db_name("Table")?.value(),
+            kind,
+        });
+    }
+    Ok(columns)
+}
+
+/// Columns of the primary key, the partition key ones come first and then the clustering ones
+fn key_columns<'a, 'b>(columns: &'b [Column<'a>]) -> Vec<&'b Column<'a>> {
+    let of_kind = |kind| columns.iter().filter(move |column| column.kind == kind);
+    of_kind(KeyKind::Partition)
+        .chain(of_kind(KeyKind::Clustering))
+        .collect()
+}
+
+/// Columns not in the primary key
+fn regular_columns<'a, 'b>(columns: &'b [Column<'a>]) -> Vec<&'b Column<'a>> {
+    columns
+        .iter()
+        .filter(|column| column.kind == KeyKind::Regular)
+        .collect()
+}
+
+/// Parses the `#[table = "..."]` attribute of the struct
+fn table_name(item: &DeriveInput) -> Result<LitStr, syn::Error> {
+    let mut table_name = None;
+    for attr in item.attrs.iter().filter(|attr| attr.path.is_ident("table")) {
+        let lit_str = match attr.parse_meta()? {
+            Meta::NameValue(name_value) => match name_value.lit {
+                Lit::Str(lit_str) => lit_str,
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "the `table` attribute should be a string literal",
+                    ))
+                }
+            },
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected `#[table = \"...\"]`",
+                ))
+            }
+        };
+        if table_name.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "the `table` attribute was set multiple times",
+            ));
+        }
+        table_name = Some(lit_str);
+    }
+    table_name.ok_or_else(|| {
+        syn::Error::new(
+            Span::call_site(),
+            "derive(Table) needs the name of the table, set it with `#[table = \"...\"]`",
+        )
+    })
+}
+
+/// Parses `#[partition_key]` and `#[clustering_key]` attributes of a field
+fn key_kind(field: &StructField) -> Result<KeyKind, syn::Error> {
+    let mut kind = KeyKind::Regular;
+    for attr in field.field.attrs.iter() {
+        let attr_kind = if attr.path.is_ident("partition_key") {
+            KeyKind::Partition
+        } else if attr.path.is_ident("clustering_key") {
+            KeyKind::Clustering
+        } else {
+            continue;
+        };
+        if !attr.tokens.is_empty() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "`partition_key` and `clustering_key` attributes take no arguments",
+            ));
+        }
+        if kind != KeyKind::Regular {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "a field can have only one `partition_key` or `clustering_key` attribute",
+            ));
+        }
+        kind = attr_kind;
+    }
+    Ok(kind)
+}
+
+/// Texts of the statements reading and writing a single row
+struct Statements {
+    insert: String,
+    select: String,
+    update: String,
+    delete: String,
+}
+
+impl Statements {
+    fn new(table: &str, columns: &[Column]) -> Statements {
+        let all_names = join_names(columns.iter(), ", ", "");
+        let markers = vec!["?"; columns.len()].join(", ");
+        let where_clause = join_names(key_columns(columns).into_iter(), " AND ", " = ?");
+        let regular_columns = regular_columns(columns);
+
+        let insert = format!("INSERT INTO {} ({}) VALUES ({})", table, all_names, markers);
+        let update = if regular_columns.is_empty() {
+            insert.clone()
+        } else {
+            let set_clause = join_names(regular_columns.into_iter(), ", ", " = ?");
+            format!("UPDATE {} SET {} WHERE {}", table, set_clause, where_clause)
+        };
+        Statements {
+            select: format!("SELECT {} FROM {} WHERE {}", all_names, table, where_clause),
+            delete: format!("DELETE FROM {} WHERE {}", table, where_clause),
+            insert,
+            update,
+        }
+    }
+}
+
+/// Joins the names of the columns, each followed by `suffix`
+fn join_names<'a: 'b, 'b>(
+    columns: impl Iterator<Item = &'b Column<'a>>,
+    separator: &str,
+    suffix: &str,
+) -> String {
+    columns
+        .map(|column| format!("{}{}", quote_identifier(&column.name), suffix))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+// Same as in `scylla_cql::frame::response::cql_literal`, which this crate can't depend on,
+// the tests of `derive(Table)` in the `scylla` crate check that both agree
+const RESERVED_KEYWORDS: &[&str] = &[
+    "add",
+    "allow",
+    "alter",
+    "and",
+    "apply",
+    "asc",
+    "authorize",
+    "batch",
+    "begin",
+    "by",
+    "columnfamily",
+    "create",
+    "default",
+    "delete",
+    "desc",
+    "describe",
+    "drop",
+    "entries",
+    "execute",
+    "from",
+    "full",
+    "grant",
+    "if",
+    "in",
+    "index",
+    "infinity",
+    "insert",
+    "into",
+    "is",
+    "key",
+    "keyspace",
+    "limit",
+    "materialized",
+    "mbean",
+    "mbeans",
+    "modify",
+    "nan",
+    "norecursive",
+    "not",
+    "null",
+    "of",
+    "on",
+    "or",
+    "order",
+    "primary",
+    "rename",
+    "replace",
+    "revoke",
+    "schema",
+    "select",
+    "set",
+    "table",
+    "to",
+    "token",
+    "truncate",
+    "unlogged",
+    "unset",
+    "update",
+    "use",
+    "using",
+    "view",
+    "where",
+    "with",
+];
+
+/// Quotes the name of a column unless it is a lowercase identifier and not a reserved keyword,
+/// like `scylla_cql::frame::response::cql_literal::quote_identifier`
+fn quote_identifier(name: &str) -> String {
+    let mut chars = name.chars();
+    let is_plain = matches!(chars.next(), Some('a'..='z'))
+        && chars.all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_'))
+        && !RESERVED_KEYWORDS.contains(&name);
+    if is_plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn statements(item: DeriveInput) -> Statements {
+        let parsed = crate::parser::parse_struct(&item, "Table").unwrap();
+        let columns = table_columns(&parsed.fields).unwrap();
+        Statements::new("ks.t", &columns)
+    }
+
+    #[test]
+    fn table_statements() {
+        let item: DeriveInput = parse_quote! {
+            struct Event {
+                #[clustering_key]
+                at: i64,
+                #[partition_key]
+                user_id: i32,
+                #[partition_key]
+                #[scylla(rename = "Day")]
+                day: i32,
+                payload: String,
+            }
+        };
+        let statements = statements(item);
+        assert_eq!(
+            statements.insert,
+            "INSERT INTO ks.t (at, user_id, \"Day\", payload) VALUES (?, ?, ?, ?)"
+        );
+        assert_eq!(
+            statements.select,
+            "SELECT at, user_id, \"Day\", payload FROM ks.t WHERE user_id = ? AND \"Day\" = ? AND at = ?"
+        );
+        assert_eq!(
+            statements.update,
+            "UPDATE ks.t SET payload = ? WHERE user_id = ? AND \"Day\" = ? AND at = ?"
+        );
+        assert_eq!(
+            statements.delete,
+            "DELETE FROM ks.t WHERE user_id = ? AND \"Day\" = ? AND at = ?"
+        );
+    }
+
+    #[test]
+    fn table_keyword_columns() {
+        let item: DeriveInput = parse_quote! {
+            struct Item {
+                #[partition_key]
+                key: i32,
+                #[clustering_key]
+                order: i32,
+                from: String,
+            }
+        };
+        let statements = statements(item);
+        assert_eq!(
+            statements.update,
+            "UPDATE ks.t SET \"from\" = ? WHERE \"key\" = ? AND \"order\" = ?"
+        );
+    }
+
+    #[test]
+    fn table_only_key_columns() {
+        let item: DeriveInput = parse_quote! {
+            struct Tag {
+                #[partition_key]
+                name: String,
+            }
+        };
+        let statements = statements(item);
+        assert_eq!(statements.update, statements.insert);
+    }
+
+    #[test]
+    fn table_derive_errors() {
+        let errors = [
+            parse_quote! {
+                struct NoTable {
+                    #[partition_key]
+                    a: i32,
+                }
+            },
+            parse_quote! {
+                #[table = "t"]
+                struct NoPartitionKey {
+                    #[clustering_key]
+                    a: i32,
+                }
+            },
+            parse_quote! {
+                #[table = "t"]
+                struct TwoKinds {
+                    #[partition_key]
+                    #[clustering_key]
+                    a: i32,
+                }
+            },
+            parse_quote! {
+                #[table = "t"]
+                struct Tuple(#[partition_key] i32);
+            },
+        ];
+        for item in errors.iter() {
+            assert!(derive(item).is_err());
+        }
+    }
+}
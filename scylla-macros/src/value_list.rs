@@ -1,31 +1,118 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
 use syn::DeriveInput;
 
 /// #[derive(ValueList)] allows to parse a struct as a list of values,
 /// which can be fed to the query directly.
 pub fn value_list_derive(tokens_input: TokenStream) -> TokenStream {
     let item = syn::parse::<DeriveInput>(tokens_input).expect("No DeriveInput");
-    let path = crate::parser::get_path(&item).expect("No path");
-    let struct_fields = crate::parser::parse_named_fields(&item, "ValueList");
+    match derive(&item) {
+        Ok(generated) => TokenStream::from(generated),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn derive(item: &DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let path = crate::parser::get_path(item)?;
+    let parsed = crate::parser::parse_struct(item, "ValueList")?;
+    crate::parser::reject_reading_attrs(item, &parsed, "ValueList")?;
+    // Values are bound by position, so names of the fields don't matter
+    let fields: Vec<_> = parsed
+        .fields
+        .iter()
+        .filter(|field| !field.attrs.skip)
+        .collect();
 
+    let generics = crate::parser::add_field_bounds(
+        &item.generics,
+        fields.iter().map(|field| {
+            let bound = if field.attrs.flatten {
+                quote!(#path::ValueListFields)
+            } else {
+                quote!(#path::Value)
+            };
+            (field.ty(), bound)
+        }),
+    );
     let struct_name = &item.ident;
-    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let values_len = fields.iter().filter(|field| !field.attrs.flatten).count();
+    let add_values_code = fields.iter().map(|field| {
+        let member = &field.member;
+
+        if field.attrs.flatten {
+            quote_spanned! {field.span() =>
+                #path::ValueListFields::add_values(&self.#member, values)?;
+            }
+        } else {
+            quote_spanned! {field.span() =>
+                values.add_value(&self.#member)?;
+            }
+        }
+    });
 
-    let values_len = struct_fields.named.len();
-    let field_name = struct_fields.named.iter().map(|field| &field.ident);
     let generated = quote! {
+        impl #impl_generics #path::ValueListFields for #struct_name #ty_generics #where_clause {
+            fn add_values(&self, values: &mut #path::SerializedValues)
+            -> ::std::result::Result<(), #path::SerializeValuesError> {
+                #(#add_values_code)*
+
+                ::std::result::Result::Ok(())
+            }
+        }
+
         impl #impl_generics #path::ValueList for #struct_name #ty_generics #where_clause {
             fn serialized(&self) -> #path::SerializedResult {
                 let mut result = #path::SerializedValues::with_capacity(#values_len);
-                #(
-                    result.add_value(&self.#field_name)?;
-                )*
+                #path::ValueListFields::add_values(self, &mut result)?;
 
                 ::std::result::Result::Ok(::std::borrow::Cow::Owned(result))
             }
         }
     };
 
-    TokenStream::from(generated)
+    Ok(generated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn value_list_derive_errors() {
+        let errors: [DeriveInput; 3] = [
+            parse_quote! {
+                struct Renamed {
+                    #[scylla(rename = "b")]
+                    a: i32,
+                }
+            },
+            parse_quote! {
+                struct Defaulted {
+                    #[scylla(default)]
+                    a: i32,
+                }
+            },
+            parse_quote! {
+                #[scylla(match_by_name)]
+                struct ByName {
+                    a: i32,
+                }
+            },
+        ];
+        for item in errors.iter() {
+            assert!(derive(item).is_err());
+        }
+
+        let skipped: DeriveInput = parse_quote! {
+            struct Skipped {
+                a: i32,
+                #[scylla(skip)]
+                b: i32,
+            }
+        };
+        assert!(derive(&skipped).is_ok());
+    }
 }
@@ -95,6 +95,8 @@
 
 #[doc(hidden)]
 pub mod _macro_internal {
+    pub use crate::statement::checked::CheckedQuery;
+    pub use crate::statement::table::Table;
     pub use scylla_cql::_macro_internal::*;
 }
 
@@ -118,10 +120,13 @@ pub use utils::test_utils;
 pub use statement::batch;
 pub use statement::prepared_statement;
 pub use statement::query;
+pub use statement::table::{Table, TableMapper};
 
 pub use frame::response::cql_to_rust;
-pub use frame::response::cql_to_rust::FromRow;
+pub use frame::response::cql_to_rust::{FromRow, FromRowWithSpecs};
+pub use frame::response::deserialize::{DeserializeCql, DeserializeRow};
 
+pub use transport::bulk_writer::{self, BulkWriter};
 pub use transport::caching_session::CachingSession;
 pub use transport::execution_profile::ExecutionProfile;
 pub use transport::query_result::QueryResult;
@@ -17,6 +17,108 @@ pub struct Token {
     pub value: i64,
 }
 
+/// Tokens greater than `start` and less than or equal to `end`,
+/// like in a `token(pk) > start AND token(pk) <= end` restriction.
+///
+/// If `start` is not less than `end`, the range wraps around the end of the ring:
+/// it contains the tokens greater than `start` and the tokens less than or equal to `end`.
+/// A range with `start` equal to `end` contains the whole ring.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub struct TokenRange {
+    pub start: Token,
+    pub end: Token,
+}
+
+impl TokenRange {
+    pub fn new(start: Token, end: Token) -> Self {
+        TokenRange { start, end }
+    }
+
+    /// Checks if the range wraps around the end of the ring
+    pub fn is_wrapping(&self) -> bool {
+        self.start >= self.end
+    }
+
+    /// Checks if the range contains the whole ring
+    pub fn is_full_ring(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Number of tokens in the range, 2^64 for the whole ring
+    pub fn token_count(&self) -> u128 {
+        let count = self.end.value.wrapping_sub(self.start.value) as u64;
+        if count == 0 {
+            1 << 64
+        } else {
+            count as u128
+        }
+    }
+
+    /// Checks if the range contains the token
+    pub fn contains(&self, token: Token) -> bool {
+        if self.is_wrapping() {
+            token > self.start || token <= self.end
+        } else {
+            token > self.start && token <= self.end
+        }
+    }
+
+    /// Checks if all the tokens of `other` are in this range
+    pub fn contains_range(&self, other: &TokenRange) -> bool {
+        if self.is_full_ring() {
+            return true;
+        }
+        let parts = self.unwrap();
+        other.unwrap().iter().all(|other_part| {
+            parts
+                .iter()
+                .any(|part| part.start <= other_part.start && other_part.end <= part.end)
+        })
+    }
+
+    /// Splits a range wrapping around the end of the ring into at most two ranges which don't,
+    /// ending at `i64::MAX` and starting at `i64::MIN`. Other ranges are returned unchanged.
+    ///
+    /// Ranges which don't wrap around can't contain `i64::MIN`, so it's the only token
+    /// left out of the returned ranges.
+    pub fn unwrap(&self) -> Vec<TokenRange> {
+        if !self.is_wrapping() {
+            return vec![*self];
+        }
+        let min = Token { value: i64::MIN };
+        let max = Token { value: i64::MAX };
+        [
+            TokenRange::new(self.start, max),
+            TokenRange::new(min, self.end),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_wrapping())
+        .collect()
+    }
+
+    /// Splits the range into `n` consecutive ranges of (almost) equal sizes.
+    /// If the range has less than `n` tokens, it's split into ranges of a single token.
+    pub fn split(&self, n: usize) -> Vec<TokenRange> {
+        assert!(n > 0, "number of ranges must be larger than 0");
+        let count = self.token_count();
+        let n = count.min(n as u128);
+        let boundary = |i: u128| Token {
+            value: self.start.value.wrapping_add((count * i / n) as u64 as i64),
+        };
+
+        (0..n)
+            .map(|i| {
+                let end = if i + 1 == n {
+                    self.end
+                } else {
+                    boundary(i + 1)
+                };
+                TokenRange::new(boundary(i), end)
+            })
+            .collect()
+    }
+}
+
 pub type Shard = u32;
 pub type ShardCount = NonZeroU16;
 
@@ -68,6 +170,36 @@ impl Sharder {
         (((biased_token as u128) * (self.nr_shards.get() as u128)) >> 64) as Shard
     }
 
+    /// Returns the first token after `token` which belongs to a different shard,
+    /// or `None` if all the following tokens belong to the same shard.
+    pub fn next_shard_boundary(&self, token: Token) -> Option<Token> {
+        let nr_shards = self.nr_shards.get() as u128;
+        if nr_shards == 1 {
+            return None;
+        }
+        // Tokens are split into cycles of 2^(64 - msb_ignore) tokens,
+        // each cycle is split evenly between all the shards
+        let biased_token = (token.value as u64).wrapping_add(1u64 << 63) as u128;
+        let cycle_len: u128 = 1 << (64 - self.msb_ignore as u32);
+        let cycle_start = biased_token - biased_token % cycle_len;
+        let next_shard = self.shard_of(token) as u128 + 1;
+        let boundary = if next_shard < nr_shards {
+            // The first position in the cycle for which shard_of returns next_shard,
+            // i.e. next_shard * cycle_len / nr_shards rounded up
+            let shard_start = next_shard * cycle_len;
+            let offset = shard_start / nr_shards;
+            cycle_start + offset + u128::from(offset * nr_shards < shard_start)
+        } else {
+            cycle_start + cycle_len
+        };
+        if boundary > u64::MAX as u128 {
+            return None;
+        }
+        Some(Token {
+            value: (boundary as u64).wrapping_sub(1u64 << 63) as i64,
+        })
+    }
+
     /// If we connect to Scylla using Scylla's shard aware port, then Scylla assigns a shard to the
     /// connection based on the source port. This calculates the assigned shard.
     pub fn shard_of_source_port(&self, source_port: u16) -> Shard {
@@ -143,8 +275,8 @@ impl<'a> TryFrom<&'a HashMap<String, Vec<String>>> for ShardInfo {
 
 #[cfg(test)]
 mod tests {
-    use super::Token;
     use super::{ShardCount, Sharder};
+    use super::{Token, TokenRange};
     use std::collections::HashSet;
 
     #[test]
@@ -165,6 +297,122 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_next_shard_boundary() {
+        let sharder = Sharder::new(ShardCount::new(3).unwrap(), 12);
+        let tokens = [i64::MIN, -9219783007514621794, -1, 0, 1, i64::MAX / 2];
+        for value in tokens {
+            let token = Token { value };
+            let boundary = sharder.next_shard_boundary(token).unwrap();
+            assert!(boundary > token);
+            let before_boundary = Token {
+                value: boundary.value - 1,
+            };
+            assert_eq!(sharder.shard_of(before_boundary), sharder.shard_of(token));
+            assert_eq!(
+                sharder.shard_of(boundary),
+                (sharder.shard_of(token) + 1) % 3
+            );
+        }
+
+        let last_token = Token { value: i64::MAX };
+        assert_eq!(sharder.next_shard_boundary(last_token), None);
+
+        let single_shard = Sharder::new(ShardCount::new(1).unwrap(), 12);
+        assert_eq!(single_shard.next_shard_boundary(Token { value: 0 }), None);
+    }
+
+    fn range(start: i64, end: i64) -> TokenRange {
+        TokenRange::new(Token { value: start }, Token { value: end })
+    }
+
+    #[test]
+    fn test_token_range_contains() {
+        let contains = |range: TokenRange, value| range.contains(Token { value });
+
+        let range = range(-10, 10);
+        assert!(!range.is_wrapping());
+        assert!(!contains(range, -10));
+        assert!(contains(range, -9));
+        assert!(contains(range, 10));
+        assert!(!contains(range, 11));
+
+        let wrapping = TokenRange::new(range.end, range.start);
+        assert!(wrapping.is_wrapping());
+        assert!(!contains(wrapping, 0));
+        assert!(!contains(wrapping, 10));
+        assert!(contains(wrapping, 11));
+        assert!(contains(wrapping, i64::MAX));
+        assert!(contains(wrapping, i64::MIN));
+        assert!(contains(wrapping, -10));
+
+        let full_ring = TokenRange::new(range.end, range.end);
+        assert!(full_ring.is_full_ring());
+        assert!(contains(full_ring, 10));
+        assert!(contains(full_ring, i64::MIN));
+
+        assert!(range.contains_range(&range));
+        assert!(range.contains_range(&TokenRange::new(range.start, Token { value: 0 })));
+        assert!(!range.contains_range(&wrapping));
+        assert!(!range.contains_range(&full_ring));
+        assert!(
+            wrapping.contains_range(&TokenRange::new(Token { value: 20 }, Token { value: -20 }))
+        );
+        assert!(
+            !wrapping.contains_range(&TokenRange::new(Token { value: 5 }, Token { value: -20 }))
+        );
+        assert!(full_ring.contains_range(&wrapping));
+    }
+
+    #[test]
+    fn test_token_range_unwrap() {
+        assert_eq!(range(-10, 10).unwrap(), vec![range(-10, 10)]);
+        assert_eq!(
+            range(10, -10).unwrap(),
+            vec![range(10, i64::MAX), range(i64::MIN, -10)]
+        );
+        assert_eq!(range(i64::MAX, 0).unwrap(), vec![range(i64::MIN, 0)]);
+        assert_eq!(range(0, i64::MIN).unwrap(), vec![range(0, i64::MAX)]);
+        assert_eq!(
+            range(i64::MIN, i64::MIN).unwrap(),
+            vec![range(i64::MIN, i64::MAX)]
+        );
+    }
+
+    #[test]
+    fn test_token_range_split() {
+        assert_eq!(range(0, 10).token_count(), 10);
+        assert_eq!(range(0, 10).split(1), vec![range(0, 10)]);
+        assert_eq!(
+            range(0, 10).split(3),
+            vec![range(0, 3), range(3, 6), range(6, 10)]
+        );
+        // Ranges of a single token, there are only 2 of them
+        assert_eq!(range(0, 2).split(5), vec![range(0, 1), range(1, 2)]);
+        assert_eq!(
+            range(i64::MAX - 1, i64::MIN + 2).split(2),
+            vec![range(i64::MAX - 1, i64::MIN), range(i64::MIN, i64::MIN + 2)]
+        );
+
+        let full_ring = range(0, 0);
+        assert_eq!(full_ring.token_count(), 1 << 64);
+        let parts = full_ring.split(4);
+        assert_eq!(
+            parts,
+            vec![
+                range(0, 1 << 62),
+                range(1 << 62, i64::MIN),
+                range(i64::MIN, -(1 << 62)),
+                range(-(1 << 62), 0),
+            ]
+        );
+        assert!(parts.iter().all(|part| full_ring.contains_range(part)));
+        assert_eq!(
+            parts.iter().map(TokenRange::token_count).sum::<u128>(),
+            full_ring.token_count()
+        );
+    }
+
     #[test]
     fn test_iter_source_ports_for_shard() {
         let nr_shards = 4;
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::history::HistoryListener;
@@ -17,6 +18,7 @@ pub struct Batch {
 
     pub statements: Vec<BatchStatement>,
     batch_type: BatchType,
+    keyspace: Option<String>,
 }
 
 impl Batch {
@@ -47,6 +49,18 @@ impl Batch {
         self.batch_type
     }
 
+    /// Sets the keyspace in which this batch is executed,
+    /// regardless of the keyspace used by the session.
+    /// Requires protocol v5; with older versions the batch will fail.
+    pub fn set_keyspace(&mut self, keyspace: Option<String>) {
+        self.keyspace = keyspace;
+    }
+
+    /// Gets the keyspace in which this batch is executed, if overridden.
+    pub fn get_keyspace(&self) -> Option<&str> {
+        self.keyspace.as_deref()
+    }
+
     /// Sets the consistency to be used when executing this batch.
     pub fn set_consistency(&mut self, c: Consistency) {
         self.config.consistency = Some(c);
@@ -108,6 +122,30 @@ impl Batch {
         self.config.timestamp
     }
 
+    /// Sets the time, in seconds since the Unix epoch, which the server should
+    /// consider as "now" when evaluating TTLs of this batch.
+    /// Useful for testing expiration; requires protocol v5.
+    pub fn set_now_in_seconds(&mut self, now_in_seconds: Option<i32>) {
+        self.config.now_in_seconds = now_in_seconds
+    }
+
+    /// Gets the "now" time, in seconds, overridden for this batch.
+    pub fn get_now_in_seconds(&self) -> Option<i32> {
+        self.config.now_in_seconds
+    }
+
+    /// Sets the custom payload sent along with this statement.
+    /// The payload is a map of opaque values which are passed to server-side
+    /// extensions, such as custom query handlers or audit plugins.
+    pub fn set_custom_payload(&mut self, custom_payload: Option<HashMap<String, Vec<u8>>>) {
+        self.config.custom_payload = custom_payload
+    }
+
+    /// Gets the custom payload sent along with this statement.
+    pub fn get_custom_payload(&self) -> Option<&HashMap<String, Vec<u8>>> {
+        self.config.custom_payload.as_ref()
+    }
+
     /// Sets the listener capable of listening what happens during query execution.
     pub fn set_history_listener(&mut self, history_listener: Arc<dyn HistoryListener>) {
         self.config.history_listener = Some(history_listener);
@@ -135,6 +173,7 @@ impl Default for Batch {
         Self {
             statements: Vec::new(),
             batch_type: BatchType::Logged,
+            keyspace: None,
             config: Default::default(),
         }
     }
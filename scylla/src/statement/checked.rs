@@ -0,0 +1,76 @@
+//! Statements checked at compile time by the [`query!`](crate::query) and
+//! [`query_as!`](crate::query_as) macros
+
+use super::query::Query;
+use crate::frame::response::cql_to_rust::FromRowWithSpecs;
+use crate::frame::response::result::Row;
+use crate::frame::value::ValueList;
+use crate::transport::errors::QueryError;
+use crate::transport::query_result::RowsTypedError;
+use crate::transport::session::TypedRowIter;
+use crate::{QueryResult, Session};
+use std::marker::PhantomData;
+use thiserror::Error;
+
+/// A statement along with its values, checked against a schema by the `query!` macros.\
+/// `R` is the type of the returned rows, given to `query_as!`.
+pub struct CheckedQuery<V, R = Row> {
+    query: Query,
+    values: V,
+    row_type: PhantomData<fn() -> R>,
+}
+
+/// An error of [`CheckedQuery::fetch`]
+#[derive(Error, Debug)]
+pub enum CheckedQueryError {
+    #[error(transparent)]
+    Query(#[from] QueryError),
+
+    /// The statement returned no rows or the rows couldn't be read as the row type.
+    /// The schema used by the macro may differ from the one in the database.
+    #[error(transparent)]
+    Rows(#[from] RowsTypedError),
+}
+
+impl<V: ValueList, R> CheckedQuery<V, R> {
+    #[doc(hidden)]
+    pub fn new(query_text: &str, values: V) -> Self {
+        CheckedQuery {
+            query: Query::new(query_text),
+            values,
+            row_type: PhantomData,
+        }
+    }
+
+    /// The statement, its text is the one given to the macro
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    /// Allows to configure the statement, e.g. its consistency or page size
+    pub fn query_mut(&mut self) -> &mut Query {
+        &mut self.query
+    }
+
+    pub fn values(&self) -> &V {
+        &self.values
+    }
+
+    /// Splits into the statement and its values, e.g. to prepare the statement
+    /// or to use [`Session::query_iter`]
+    pub fn into_parts(self) -> (Query, V) {
+        (self.query, self.values)
+    }
+
+    /// Executes the statement with [`Session::query`]
+    pub async fn execute(&self, session: &Session) -> Result<QueryResult, QueryError> {
+        session.query(self.query.clone(), &self.values).await
+    }
+}
+
+impl<V: ValueList, R: FromRowWithSpecs> CheckedQuery<V, R> {
+    /// Executes the statement and reads the returned rows as `R`
+    pub async fn fetch(&self, session: &Session) -> Result<TypedRowIter<R>, CheckedQueryError> {
+        Ok(self.execute(session).await?.rows_typed::<R>()?)
+    }
+}
@@ -1,11 +1,13 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use crate::history::HistoryListener;
 use crate::transport::execution_profile::ExecutionProfileHandle;
 
 pub mod batch;
+pub mod checked;
 pub mod prepared_statement;
 pub mod query;
+pub mod table;
 
 pub use crate::frame::types::{Consistency, SerialConsistency};
 
@@ -17,7 +19,10 @@ pub struct StatementConfig {
     pub is_idempotent: bool,
 
     pub tracing: bool,
+    pub lazy_deserialization: bool,
     pub timestamp: Option<i64>,
+    pub now_in_seconds: Option<i32>,
+    pub custom_payload: Option<HashMap<String, Vec<u8>>>,
     pub request_timeout: Option<Duration>,
 
     pub history_listener: Option<Arc<dyn HistoryListener>>,
@@ -33,7 +38,10 @@ impl Default for StatementConfig {
             serial_consistency: None,
             is_idempotent: false,
             tracing: false,
+            lazy_deserialization: false,
             timestamp: None,
+            now_in_seconds: None,
+            custom_payload: None,
             request_timeout: None,
             history_listener: None,
             execution_profile_handle: None,
@@ -44,6 +52,7 @@ impl Default for StatementConfig {
 impl Clone for StatementConfig {
     fn clone(&self) -> Self {
         Self {
+            custom_payload: self.custom_payload.clone(),
             history_listener: self.history_listener.clone(),
             execution_profile_handle: self.execution_profile_handle.clone(),
             ..*self
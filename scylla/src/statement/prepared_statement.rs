@@ -1,5 +1,8 @@
+use arc_swap::ArcSwapOption;
 use bytes::{BufMut, Bytes, BytesMut};
 use smallvec::{smallvec, SmallVec};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::sync::Arc;
 use std::time::Duration;
@@ -9,8 +12,9 @@ use uuid::Uuid;
 use super::StatementConfig;
 use crate::frame::response::result::PreparedMetadata;
 use crate::frame::types::{Consistency, SerialConsistency};
-use crate::frame::value::SerializedValues;
+use crate::frame::value::{SerializedValues, ValueList};
 use crate::history::HistoryListener;
+use crate::transport::errors::BadQuery;
 use crate::transport::execution_profile::ExecutionProfileHandle;
 use crate::transport::partitioner::PartitionerName;
 
@@ -21,6 +25,7 @@ pub struct PreparedStatement {
     pub prepare_tracing_ids: Vec<Uuid>,
 
     id: Bytes,
+    result_metadata_id: ResultMetadataId,
     metadata: PreparedMetadata,
     statement: String,
     page_size: Option<i32>,
@@ -34,6 +39,7 @@ impl Clone for PreparedStatement {
             config: self.config.clone(),
             prepare_tracing_ids: Vec::new(),
             id: self.id.clone(),
+            result_metadata_id: self.result_metadata_id.clone(),
             metadata: self.metadata.clone(),
             statement: self.statement.clone(),
             page_size: self.page_size,
@@ -43,9 +49,29 @@ impl Clone for PreparedStatement {
     }
 }
 
+/// Id of the result metadata of a prepared statement, shared by its clones,
+/// so that an id updated after a schema change is used by all of them
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ResultMetadataId(Arc<ArcSwapOption<Bytes>>);
+
+impl ResultMetadataId {
+    pub(crate) fn new(id: Option<Bytes>) -> Self {
+        ResultMetadataId(Arc::new(ArcSwapOption::from(id.map(Arc::new))))
+    }
+
+    pub(crate) fn get(&self) -> Option<Bytes> {
+        self.0.load().as_deref().cloned()
+    }
+
+    pub(crate) fn set(&self, id: Bytes) {
+        self.0.store(Some(Arc::new(id)));
+    }
+}
+
 impl PreparedStatement {
     pub(crate) fn new(
         id: Bytes,
+        result_metadata_id: ResultMetadataId,
         is_lwt: bool,
         metadata: PreparedMetadata,
         statement: String,
@@ -54,6 +80,7 @@ impl PreparedStatement {
     ) -> Self {
         Self {
             id,
+            result_metadata_id,
             metadata,
             statement,
             prepare_tracing_ids: Vec::new(),
@@ -68,6 +95,22 @@ impl PreparedStatement {
         &self.id
     }
 
+    /// Gets the id of the result metadata, returned by the server
+    /// since protocol v5. It's updated when the server reports that
+    /// the result metadata has changed, e.g. after a column was added.
+    pub fn get_result_metadata_id(&self) -> Option<Bytes> {
+        self.result_metadata_id.get()
+    }
+
+    pub(crate) fn get_shared_result_metadata_id(&self) -> &ResultMetadataId {
+        &self.result_metadata_id
+    }
+
+    /// Stores the id of the new result metadata, sent by the server in a response
+    pub(crate) fn update_result_metadata_id(&self, id: Bytes) {
+        self.result_metadata_id.set(id);
+    }
+
     pub fn get_statement(&self) -> &str {
         &self.statement
     }
@@ -111,6 +154,18 @@ impl PreparedStatement {
         self.is_confirmed_lwt
     }
 
+    /// Serializes values bound to this statement, after checking that their number
+    /// and types match the bind markers described by its prepared metadata.\
+    /// A mismatch is reported as [`BadQuery::ValueTypeCheckError`] naming the column,
+    /// instead of an error returned by the database after the request is sent.
+    pub fn serialize_values<'v>(
+        &self,
+        values: &'v impl ValueList,
+    ) -> Result<Cow<'v, SerializedValues>, BadQuery> {
+        values.type_check(&self.metadata.col_specs)?;
+        Ok(values.serialized()?)
+    }
+
     /// Computes the partition key of the target table from given values —
     /// it assumes that all partition key columns are passed in values.
     /// Partition keys have a specific serialization rules.
@@ -241,6 +296,19 @@ impl PreparedStatement {
         self.config.tracing
     }
 
+    /// Enables or disables lazy deserialization of the rows returned for this statement.\
+    /// If enabled, `QueryResult::rows` is left as `None` and the received rows are kept
+    /// in their serialized form. They can be accessed with `QueryResult::lazy_rows`,
+    /// which deserializes only the rows and columns which are actually read.
+    pub fn set_lazy_deserialization(&mut self, lazy: bool) {
+        self.config.lazy_deserialization = lazy;
+    }
+
+    /// Gets whether lazy deserialization of rows is enabled for this statement
+    pub fn get_lazy_deserialization(&self) -> bool {
+        self.config.lazy_deserialization
+    }
+
     /// Sets the default timestamp for this statement in microseconds.
     /// If not None, it will replace the server side assigned timestamp as default timestamp
     /// If a statement contains a `USING TIMESTAMP` clause, calling this method won't change
@@ -254,6 +322,30 @@ impl PreparedStatement {
         self.config.timestamp
     }
 
+    /// Sets the time, in seconds since the Unix epoch, which the server should
+    /// consider as "now" when evaluating TTLs of this statement.
+    /// Useful for testing expiration; requires protocol v5.
+    pub fn set_now_in_seconds(&mut self, now_in_seconds: Option<i32>) {
+        self.config.now_in_seconds = now_in_seconds
+    }
+
+    /// Gets the "now" time, in seconds, overridden for this statement.
+    pub fn get_now_in_seconds(&self) -> Option<i32> {
+        self.config.now_in_seconds
+    }
+
+    /// Sets the custom payload sent along with this statement.
+    /// The payload is a map of opaque values which are passed to server-side
+    /// extensions, such as custom query handlers or audit plugins.
+    pub fn set_custom_payload(&mut self, custom_payload: Option<HashMap<String, Vec<u8>>>) {
+        self.config.custom_payload = custom_payload
+    }
+
+    /// Gets the custom payload sent along with this statement.
+    pub fn get_custom_payload(&self) -> Option<&HashMap<String, Vec<u8>>> {
+        self.config.custom_payload.as_ref()
+    }
+
     /// Sets the client-side timeout for this statement.
     /// If not None, the driver will stop waiting for the request
     /// to finish after `timeout` passed.
@@ -2,6 +2,7 @@ use super::StatementConfig;
 use crate::frame::types::{Consistency, SerialConsistency};
 use crate::history::HistoryListener;
 use crate::transport::execution_profile::ExecutionProfileHandle;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -14,6 +15,7 @@ pub struct Query {
 
     pub contents: String,
     page_size: Option<i32>,
+    keyspace: Option<String>,
 }
 
 impl Query {
@@ -22,6 +24,7 @@ impl Query {
         Self {
             contents: query_text.into(),
             page_size: None,
+            keyspace: None,
             config: Default::default(),
         }
     }
@@ -48,6 +51,18 @@ impl Query {
         self.page_size
     }
 
+    /// Sets the keyspace in which this query is executed or prepared,
+    /// regardless of the keyspace used by the session.
+    /// Requires protocol v5; with older versions the query will fail.
+    pub fn set_keyspace(&mut self, keyspace: Option<String>) {
+        self.keyspace = keyspace;
+    }
+
+    /// Gets the keyspace in which this query is executed, if overridden.
+    pub fn get_keyspace(&self) -> Option<&str> {
+        self.keyspace.as_deref()
+    }
+
     /// Sets the consistency to be used when executing this statement.
     pub fn set_consistency(&mut self, c: Consistency) {
         self.config.consistency = Some(c);
@@ -97,6 +112,19 @@ impl Query {
         self.config.tracing
     }
 
+    /// Enables or disables lazy deserialization of the rows returned for this statement.\
+    /// If enabled, `QueryResult::rows` is left as `None` and the received rows are kept
+    /// in their serialized form. They can be accessed with `QueryResult::lazy_rows`,
+    /// which deserializes only the rows and columns which are actually read.
+    pub fn set_lazy_deserialization(&mut self, lazy: bool) {
+        self.config.lazy_deserialization = lazy;
+    }
+
+    /// Gets whether lazy deserialization of rows is enabled for this statement
+    pub fn get_lazy_deserialization(&self) -> bool {
+        self.config.lazy_deserialization
+    }
+
     /// Sets the default timestamp for this statement in microseconds.
     /// If not None, it will replace the server side assigned timestamp as default timestamp
     /// If a statement contains a `USING TIMESTAMP` clause, calling this method won't change
@@ -110,6 +138,30 @@ impl Query {
         self.config.timestamp
     }
 
+    /// Sets the time, in seconds since the Unix epoch, which the server should
+    /// consider as "now" when evaluating TTLs of this statement.
+    /// Useful for testing expiration; requires protocol v5.
+    pub fn set_now_in_seconds(&mut self, now_in_seconds: Option<i32>) {
+        self.config.now_in_seconds = now_in_seconds
+    }
+
+    /// Gets the "now" time, in seconds, overridden for this statement.
+    pub fn get_now_in_seconds(&self) -> Option<i32> {
+        self.config.now_in_seconds
+    }
+
+    /// Sets the custom payload sent along with this statement.
+    /// The payload is a map of opaque values which are passed to server-side
+    /// extensions, such as custom query handlers or audit plugins.
+    pub fn set_custom_payload(&mut self, custom_payload: Option<HashMap<String, Vec<u8>>>) {
+        self.config.custom_payload = custom_payload
+    }
+
+    /// Gets the custom payload sent along with this statement.
+    pub fn get_custom_payload(&self) -> Option<&HashMap<String, Vec<u8>>> {
+        self.config.custom_payload.as_ref()
+    }
+
     /// Sets the client-side timeout for this statement.
     /// If not None, the driver will stop waiting for the request
     /// to finish after `timeout` passed.
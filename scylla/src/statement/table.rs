@@ -0,0 +1,131 @@
+//! Mapping of structs to rows of a table, derived with [`derive(Table)`](crate::macros::Table)
+
+use super::prepared_statement::PreparedStatement;
+use crate::frame::response::cql_to_rust::FromRowWithSpecs;
+use crate::frame::value::{SerializedResult, ValueList};
+use crate::transport::errors::QueryError;
+use crate::transport::query_result::MaybeFirstRowTypedError;
+use crate::{QueryResult, Session};
+use std::marker::PhantomData;
+use thiserror::Error;
+
+/// A struct whose fields are the columns of a table, implemented by `#[derive(Table)]`.\
+/// The derive generates the statements reading and writing single rows,
+/// they are prepared and executed by [`TableMapper`].
+pub trait Table: FromRowWithSpecs {
+    /// Values of the primary key columns, identifying a row.
+    /// The derive generates a struct named after this one, e.g. `UserKey` for `User`.
+    type Key: ValueList;
+
+    /// Name of the table, possibly qualified with its keyspace
+    const NAME: &'static str;
+
+    /// `INSERT INTO table (columns...) VALUES (?...)`, bound with [`Table::insert_values`]
+    const INSERT: &'static str;
+
+    /// `SELECT columns... FROM table WHERE key = ?...`, bound with [`Table::Key`]
+    const SELECT: &'static str;
+
+    /// `UPDATE table SET column = ?... WHERE key = ?...`, bound with [`Table::update_values`].\
+    /// Tables having only primary key columns are updated with the `INSERT` statement.
+    const UPDATE: &'static str;
+
+    /// `DELETE FROM table WHERE key = ?...`, bound with [`Table::Key`]
+    const DELETE: &'static str;
+
+    /// Copies the values of the primary key columns
+    fn key(&self) -> Self::Key;
+
+    /// Values of all the columns, in the order of the struct's fields
+    fn insert_values(&self) -> SerializedResult<'_>;
+
+    /// Values of the columns not in the primary key followed by the values of the primary key
+    fn update_values(&self) -> SerializedResult<'_>;
+}
+
+/// An error of [`TableMapper::get`]
+#[derive(Error, Debug)]
+pub enum TableMapperError {
+    #[error(transparent)]
+    Query(#[from] QueryError),
+
+    /// The returned row couldn't be read as the struct
+    #[error(transparent)]
+    Row(#[from] MaybeFirstRowTypedError),
+}
+
+/// Statements of a [`Table`], prepared once and executed for any row.\
+/// Values of the partition key are bound to prepared statements,
+/// so the statements are routed to the replicas owning the row.
+pub struct TableMapper<T> {
+    insert: PreparedStatement,
+    select: PreparedStatement,
+    update: PreparedStatement,
+    delete: PreparedStatement,
+    table: PhantomData<fn() -> T>,
+}
+
+impl<T: Table> TableMapper<T> {
+    /// Prepares the statements of the table
+    pub async fn prepare(session: &Session) -> Result<Self, QueryError> {
+        Ok(TableMapper {
+            insert: session.prepare(T::INSERT).await?,
+            select: session.prepare(T::SELECT).await?,
+            update: session.prepare(T::UPDATE).await?,
+            delete: session.prepare(T::DELETE).await?,
+            table: PhantomData,
+        })
+    }
+
+    /// The prepared statements, e.g. to set their consistency
+    pub fn statements_mut(&mut self) -> impl Iterator<Item = &mut PreparedStatement> {
+        [
+            &mut self.insert,
+            &mut self.select,
+            &mut self.update,
+            &mut self.delete,
+        ]
+        .into_iter()
+    }
+
+    /// Inserts the row, overwriting all its columns
+    pub async fn insert(&self, session: &Session, row: &T) -> Result<QueryResult, QueryError> {
+        session.execute(&self.insert, InsertValues(row)).await
+    }
+
+    /// Reads the row with the given key, `None` if there's no such row
+    pub async fn get(
+        &self,
+        session: &Session,
+        key: &T::Key,
+    ) -> Result<Option<T>, TableMapperError> {
+        let result = session.execute(&self.select, key).await?;
+        Ok(result.maybe_first_row_typed::<T>()?)
+    }
+
+    /// Updates the columns of the row which aren't in its primary key
+    pub async fn update(&self, session: &Session, row: &T) -> Result<QueryResult, QueryError> {
+        session.execute(&self.update, UpdateValues(row)).await
+    }
+
+    /// Deletes the row with the given key
+    pub async fn delete(&self, session: &Session, key: &T::Key) -> Result<QueryResult, QueryError> {
+        session.execute(&self.delete, key).await
+    }
+}
+
+struct InsertValues<'a, T>(&'a T);
+
+impl<T: Table> ValueList for InsertValues<'_, T> {
+    fn serialized(&self) -> SerializedResult<'_> {
+        self.0.insert_values()
+    }
+}
+
+struct UpdateValues<'a, T>(&'a T);
+
+impl<T: Table> ValueList for UpdateValues<'_, T> {
+    fn serialized(&self) -> SerializedResult<'_> {
+        self.0.update_values()
+    }
+}
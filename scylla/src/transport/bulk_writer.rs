@@ -0,0 +1,464 @@
+//! Writing many rows with a single prepared statement, see [`BulkWriter`]
+
+use crate::batch::{Batch, BatchStatement, BatchType};
+use crate::frame::value::{SerializedValues, ValueList};
+use crate::prepared_statement::PreparedStatement;
+use crate::transport::errors::QueryError;
+use crate::transport::session::{calculate_partition_key, Session};
+use crate::transport::ClusterData;
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Writes a stream of rows with a prepared statement, e.g. an `INSERT`,
+/// keeping a bounded number of requests in flight.
+///
+/// The rows are read in chunks. Rows of a chunk are grouped by their partition,
+/// and the requests are spread evenly over the replicas owning the partitions.
+/// Rows of the same partition can be packed into unlogged batches,
+/// see [`BulkWriter::set_max_batch_size`].\
+/// A row which fails to be written doesn't stop the others,
+/// all failures are listed in the returned [`BulkWriteReport`].
+///
+/// # Example
+/// ```rust
+/// # use scylla::Session;
+/// # async fn example(session: &Session) -> Result<(), Box<dyn std::error::Error>> {
+/// use scylla::bulk_writer::BulkWriter;
+///
+/// let prepared = session
+///     .prepare("INSERT INTO ks.tab (a, b) VALUES (?, ?)")
+///     .await?;
+///
+/// let mut writer = BulkWriter::new(prepared);
+/// writer.set_concurrency(512);
+/// writer.set_max_batch_size(16);
+///
+/// let rows = futures::stream::iter((0..1_000_000).map(|i: i32| (i % 1000, i)));
+/// let report = writer.write(session, rows).await;
+/// println!(
+///     "Written {} rows in {:?} ({:.0} rows/s), {} failed",
+///     report.rows_written,
+///     report.elapsed,
+///     report.rows_per_second(),
+///     report.failures.len()
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct BulkWriter {
+    statement: PreparedStatement,
+    concurrency: usize,
+    chunk_size: usize,
+    max_batch_size: usize,
+}
+
+/// Summary of [`BulkWriter::write`]
+#[derive(Debug, Default)]
+pub struct BulkWriteReport {
+    /// Rows written successfully
+    pub rows_written: usize,
+    /// Rows which couldn't be written, ordered by the time of the failure
+    pub failures: Vec<RowFailure>,
+    /// Requests sent to the database, each one a single row or a batch
+    pub requests: usize,
+    /// Requests which were batches of multiple rows
+    pub batches: usize,
+    /// Time it took to write all the rows
+    pub elapsed: Duration,
+}
+
+impl BulkWriteReport {
+    /// Whether all the rows were written
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Rows written successfully per second
+    pub fn rows_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.rows_written as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    fn record(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Failed(failure) => self.failures.push(failure),
+            Outcome::Sent { rows, result } => {
+                self.requests += 1;
+                if rows.len() > 1 {
+                    self.batches += 1;
+                }
+                match result {
+                    Ok(()) => self.rows_written += rows.len(),
+                    Err(error) => self.failures.extend(rows.into_iter().map(|row| RowFailure {
+                        row,
+                        error: error.clone(),
+                    })),
+                }
+            }
+        }
+    }
+}
+
+/// A row which couldn't be written
+#[derive(Debug, Clone)]
+pub struct RowFailure {
+    /// Position of the row in the written stream, counted from 0
+    pub row: usize,
+    /// Why the row wasn't written. All rows of a failed batch have the batch's error.
+    pub error: QueryError,
+}
+
+/// Rows sent in a single request, or a row which can't be sent
+enum Write {
+    Request {
+        rows: Vec<usize>,
+        values: Vec<SerializedValues>,
+    },
+    Failed(RowFailure),
+}
+
+enum Outcome {
+    Sent {
+        rows: Vec<usize>,
+        result: Result<(), QueryError>,
+    },
+    Failed(RowFailure),
+}
+
+/// Rows of a single partition
+struct Partition {
+    replica: Option<Uuid>,
+    rows: Vec<(usize, SerializedValues)>,
+}
+
+impl BulkWriter {
+    /// Creates a writer executing `statement` once for each row.\
+    /// By default 256 requests are in flight at the same time and rows aren't batched.
+    pub fn new(statement: PreparedStatement) -> Self {
+        Self {
+            statement,
+            concurrency: 256,
+            chunk_size: 1024,
+            max_batch_size: 1,
+        }
+    }
+
+    /// Sets the maximum number of requests in flight
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        assert!(concurrency > 0, "concurrency must be larger than 0");
+        self.concurrency = concurrency;
+    }
+
+    /// Gets the maximum number of requests in flight
+    pub fn get_concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Sets the number of rows read from the stream and grouped by partition at once.
+    /// Larger chunks make fuller batches, at the cost of buffering more rows.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        assert!(chunk_size > 0, "chunk size must be larger than 0");
+        self.chunk_size = chunk_size;
+    }
+
+    /// Gets the number of rows grouped by partition at once
+    pub fn get_chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Sets the maximum number of rows of the same partition packed into one unlogged batch.
+    /// 1, the default, sends every row in a separate request.\
+    /// Rows can be batched only if the statement's partition key is known,
+    /// i.e. [`PreparedStatement::is_token_aware`] is true.
+    pub fn set_max_batch_size(&mut self, max_batch_size: usize) {
+        assert!(max_batch_size > 0, "batch size must be larger than 0");
+        self.max_batch_size = max_batch_size;
+    }
+
+    /// Gets the maximum number of rows packed into one batch
+    pub fn get_max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+
+    /// Gets the statement executed for each row
+    pub fn get_statement(&self) -> &PreparedStatement {
+        &self.statement
+    }
+
+    /// Writes all the rows of the stream and waits until they are written
+    pub async fn write<V: ValueList>(
+        &self,
+        session: &Session,
+        rows: impl Stream<Item = V>,
+    ) -> BulkWriteReport {
+        let start = Instant::now();
+        let mut report = rows
+            .enumerate()
+            .chunks(self.chunk_size)
+            .flat_map(|chunk| stream::iter(self.plan_chunk(&session.get_cluster_data(), chunk)))
+            .map(|write| self.run(session, write))
+            .buffer_unordered(self.concurrency)
+            .fold(
+                BulkWriteReport::default(),
+                |mut report, outcome| async move {
+                    report.record(outcome);
+                    report
+                },
+            )
+            .await;
+        report.elapsed = start.elapsed();
+        report
+    }
+
+    /// Groups the rows of a chunk into requests, ordered so that consecutive requests
+    /// go to different replicas
+    fn plan_chunk<V: ValueList>(
+        &self,
+        cluster_data: &ClusterData,
+        chunk: Vec<(usize, V)>,
+    ) -> Vec<Write> {
+        let keyspace = self.statement.get_keyspace_name();
+
+        let mut writes = Vec::new();
+        // In the order of the first row of each partition
+        let mut partitions: Vec<Partition> = Vec::new();
+        let mut partition_indexes: HashMap<Bytes, usize> = HashMap::new();
+        for (row, values) in chunk {
+            let values = match self.statement.serialize_values(&values) {
+                Ok(values) => values.into_owned(),
+                Err(err) => {
+                    writes.push(Write::Failed(RowFailure {
+                        row,
+                        error: err.into(),
+                    }));
+                    continue;
+                }
+            };
+            if !self.statement.is_token_aware() {
+                partitions.push(Partition {
+                    replica: None,
+                    rows: vec![(row, values)],
+                });
+                continue;
+            }
+            let partition_key = match calculate_partition_key(&self.statement, &values) {
+                Ok(partition_key) => partition_key,
+                Err(error) => {
+                    writes.push(Write::Failed(RowFailure { row, error }));
+                    continue;
+                }
+            };
+            match partition_indexes.entry(partition_key) {
+                Entry::Occupied(entry) => partitions[*entry.get()].rows.push((row, values)),
+                Entry::Vacant(entry) => {
+                    let token = self
+                        .statement
+                        .get_partitioner_name()
+                        .hash(entry.key().clone());
+                    let replica = keyspace.and_then(|keyspace| {
+                        let replicas = cluster_data.get_token_endpoints(keyspace, token);
+                        replicas.first().map(|node| node.host_id)
+                    });
+                    entry.insert(partitions.len());
+                    partitions.push(Partition {
+                        replica,
+                        rows: vec![(row, values)],
+                    });
+                }
+            }
+        }
+
+        let mut replica_writes: Vec<Vec<Write>> = Vec::new();
+        let mut replica_indexes: HashMap<Option<Uuid>, usize> = HashMap::new();
+        for partition in partitions {
+            let index = *replica_indexes.entry(partition.replica).or_insert_with(|| {
+                replica_writes.push(Vec::new());
+                replica_writes.len() - 1
+            });
+            let mut rows = partition.rows;
+            while !rows.is_empty() {
+                let rest = rows.split_off(rows.len().min(self.max_batch_size));
+                let (rows_in_request, values) = rows.into_iter().unzip();
+                replica_writes[index].push(Write::Request {
+                    rows: rows_in_request,
+                    values,
+                });
+                rows = rest;
+            }
+        }
+        writes.extend(interleave(replica_writes));
+        writes
+    }
+
+    async fn run(&self, session: &Session, write: Write) -> Outcome {
+        let (rows, values) = match write {
+            Write::Request { rows, values } => (rows, values),
+            Write::Failed(failure) => return Outcome::Failed(failure),
+        };
+        let result = if let [values] = values.as_slice() {
+            session.execute(&self.statement, values).await
+        } else {
+            session.batch(&self.batch(values.len()), &values).await
+        };
+        Outcome::Sent {
+            rows,
+            result: result.map(|_| ()),
+        }
+    }
+
+    /// An unlogged batch executing the statement `size` times, configured like the statement
+    fn batch(&self, size: usize) -> Batch {
+        let statement = BatchStatement::PreparedStatement(self.statement.clone());
+        let mut batch = Batch::new_with_statements(BatchType::Unlogged, vec![statement; size]);
+        batch.config = self.statement.config.clone();
+        batch
+    }
+}
+
+/// Takes the first items of all vectors, then the second ones and so on
+fn interleave<T>(vectors: Vec<Vec<T>>) -> Vec<T> {
+    let mut iters: Vec<_> = vectors.into_iter().map(Vec::into_iter).collect();
+    let mut result = Vec::new();
+    loop {
+        let len = result.len();
+        result.extend(iters.iter_mut().filter_map(Iterator::next));
+        if result.len() == len {
+            return result;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interleave, BulkWriteReport, BulkWriter, Outcome, Write};
+    use crate::frame::response::result::{
+        ColumnSpec, ColumnType, PartitionKeyIndex, PreparedMetadata, TableSpec,
+    };
+    use crate::prepared_statement::{PreparedStatement, ResultMetadataId};
+    use crate::transport::errors::QueryError;
+    use crate::transport::load_balancing::tests::mock_cluster_data_for_token_aware_tests;
+    use bytes::Bytes;
+
+    // Like `INSERT INTO t (pk, v) VALUES (?, ?)`, with `pk` being the partition key
+    fn make_token_aware_statement() -> PreparedStatement {
+        let table_spec = TableSpec {
+            ks_name: "keyspace_with_simple_strategy_replication_factor_2".to_string(),
+            table_name: "t".to_string(),
+        };
+        let col_specs = ["pk", "v"]
+            .iter()
+            .map(|name| ColumnSpec {
+                table_spec: table_spec.clone(),
+                name: name.to_string(),
+                typ: ColumnType::Int,
+            })
+            .collect();
+        let metadata = PreparedMetadata {
+            flags: 0,
+            col_count: 2,
+            pk_indexes: vec![PartitionKeyIndex {
+                index: 0,
+                sequence: 0,
+            }],
+            col_specs,
+        };
+        PreparedStatement::new(
+            Bytes::from_static(b"id"),
+            ResultMetadataId::default(),
+            false,
+            metadata,
+            "INSERT INTO t (pk, v) VALUES (?, ?)".to_string(),
+            None,
+            Default::default(),
+        )
+    }
+
+    // Rows of the requests, with each request's rows in the order they were written
+    fn planned_requests(writes: Vec<Write>) -> Vec<Vec<usize>> {
+        let mut requests: Vec<Vec<usize>> = writes
+            .into_iter()
+            .map(|write| match write {
+                Write::Request { rows, values } => {
+                    assert_eq!(rows.len(), values.len());
+                    rows
+                }
+                Write::Failed(failure) => panic!("Unexpected failure: {:?}", failure),
+            })
+            .collect();
+        requests.sort();
+        requests
+    }
+
+    #[tokio::test]
+    async fn plan_chunk_groups_rows_by_partition() {
+        let cluster_data = mock_cluster_data_for_token_aware_tests();
+        let chunk: Vec<(usize, (i32, i32))> = vec![
+            (0, (1, 10)),
+            (1, (2, 20)),
+            (2, (1, 11)),
+            (3, (1, 12)),
+            (4, (2, 21)),
+        ];
+
+        let mut writer = BulkWriter::new(make_token_aware_statement());
+        // Every row in its own request by default
+        assert_eq!(
+            planned_requests(writer.plan_chunk(&cluster_data, chunk.clone())),
+            vec![vec![0], vec![1], vec![2], vec![3], vec![4]]
+        );
+
+        // Rows of a partition are split into batches of at most `max_batch_size` rows
+        writer.set_max_batch_size(2);
+        assert_eq!(
+            planned_requests(writer.plan_chunk(&cluster_data, chunk.clone())),
+            vec![vec![0, 2], vec![1, 4], vec![3]]
+        );
+
+        writer.set_max_batch_size(16);
+        assert_eq!(
+            planned_requests(writer.plan_chunk(&cluster_data, chunk)),
+            vec![vec![0, 2, 3], vec![1, 4]]
+        );
+    }
+
+    #[test]
+    fn failed_batch_fails_all_rows() {
+        let mut report = BulkWriteReport::default();
+        report.record(Outcome::Sent {
+            rows: vec![0, 1],
+            result: Ok(()),
+        });
+        report.record(Outcome::Sent {
+            rows: vec![2, 4, 5],
+            result: Err(QueryError::TimeoutError),
+        });
+
+        assert_eq!(report.requests, 2);
+        assert_eq!(report.batches, 2);
+        assert_eq!(report.rows_written, 2);
+        assert!(!report.is_success());
+        let failed_rows: Vec<usize> = report.failures.iter().map(|failure| failure.row).collect();
+        assert_eq!(failed_rows, vec![2, 4, 5]);
+        assert!(report
+            .failures
+            .iter()
+            .all(|failure| matches!(failure.error, QueryError::TimeoutError)));
+    }
+
+    #[test]
+    fn interleave_vectors() {
+        assert_eq!(
+            interleave(vec![vec![1, 4, 6], vec![], vec![2, 5], vec![3]]),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+        assert!(interleave::<i32>(vec![]).is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 use crate::batch::{Batch, BatchStatement};
 use crate::frame::value::{BatchValues, ValueList};
-use crate::prepared_statement::PreparedStatement;
+use crate::prepared_statement::{PreparedStatement, ResultMetadataId};
 use crate::query::Query;
 use crate::transport::errors::QueryError;
 use crate::transport::iterator::RowIterator;
@@ -20,6 +20,7 @@ use std::hash::BuildHasher;
 #[derive(Debug)]
 struct RawPreparedStatementData {
     pub id: Bytes,
+    pub result_metadata_id: ResultMetadataId,
     pub is_confirmed_lwt: bool,
     pub metadata: PreparedMetadata,
     pub partitioner_name: PartitionerName,
@@ -74,7 +75,7 @@ where
     ) -> Result<QueryResult, QueryError> {
         let query = query.into();
         let prepared = self.add_prepared_statement_owned(query).await?;
-        let values = values.serialized()?;
+        let values = prepared.serialize_values(&values)?;
         self.session.execute(&prepared, values.clone()).await
     }
 
@@ -86,7 +87,7 @@ where
     ) -> Result<RowIterator, QueryError> {
         let query = query.into();
         let prepared = self.add_prepared_statement_owned(query).await?;
-        let values = values.serialized()?;
+        let values = prepared.serialize_values(&values)?;
         self.session.execute_iter(prepared, values.clone()).await
     }
 
@@ -99,7 +100,7 @@ where
     ) -> Result<QueryResult, QueryError> {
         let query = query.into();
         let prepared = self.add_prepared_statement_owned(query).await?;
-        let values = values.serialized()?;
+        let values = prepared.serialize_values(&values)?;
         self.session
             .execute_paged(&prepared, values.clone(), paging_state.clone())
             .await
@@ -168,6 +169,7 @@ where
             let page_size = query.get_page_size();
             let mut stmt = PreparedStatement::new(
                 raw.id.clone(),
+                raw.result_metadata_id.clone(),
                 raw.is_confirmed_lwt,
                 raw.metadata.clone(),
                 query.contents,
@@ -195,6 +197,7 @@ where
 
             let raw = RawPreparedStatementData {
                 id: prepared.get_id().clone(),
+                result_metadata_id: prepared.get_shared_result_metadata_id().clone(),
                 is_confirmed_lwt: prepared.is_confirmed_lwt(),
                 metadata: prepared.get_prepared_metadata().clone(),
                 partitioner_name: prepared.get_partitioner_name().clone(),
@@ -582,6 +585,7 @@ mod tests {
             .await
             .unwrap()
             .rows_typed_or_empty::<(i32, i64)>()
+            .unwrap()
             .collect::<Result<Vec<_>, _>>()
             .unwrap();
 
@@ -2,7 +2,7 @@
 use crate::frame::response::event::{Event, StatusChangeEvent};
 use crate::frame::value::ValueList;
 use crate::load_balancing::TokenAwarePolicy;
-use crate::routing::Token;
+use crate::routing::{Token, TokenRange};
 use crate::transport::host_filter::HostFilter;
 use crate::transport::{
     connection::{Connection, VerifiedKeyspaceName},
@@ -35,6 +35,7 @@ pub struct Cluster {
 
     refresh_channel: tokio::sync::mpsc::Sender<RefreshRequest>,
     use_keyspace_channel: tokio::sync::mpsc::Sender<UseKeyspaceRequest>,
+    shutdown_channel: tokio::sync::mpsc::Sender<ShutdownRequest>,
 
     _worker_handle: RemoteHandle<()>,
 }
@@ -109,6 +110,9 @@ struct ClusterWorker {
     // Channel used to receive server events
     server_events_channel: tokio::sync::mpsc::Receiver<Event>,
 
+    // Channel used to receive the shutdown request, the worker stops after handling it
+    shutdown_channel: tokio::sync::mpsc::Receiver<ShutdownRequest>,
+
     // Keyspace send in "USE <keyspace name>" when opening each connection
     used_keyspace: Option<VerifiedKeyspaceName>,
 
@@ -128,6 +132,12 @@ struct UseKeyspaceRequest {
     response_chan: tokio::sync::oneshot::Sender<Result<(), QueryError>>,
 }
 
+#[derive(Debug)]
+struct ShutdownRequest {
+    // Receives the number of closed connections
+    response_chan: tokio::sync::oneshot::Sender<usize>,
+}
+
 impl Cluster {
     pub async fn new(
         initial_peers: &[SocketAddr],
@@ -140,6 +150,7 @@ impl Cluster {
         let (refresh_sender, refresh_receiver) = tokio::sync::mpsc::channel(32);
         let (use_keyspace_sender, use_keyspace_receiver) = tokio::sync::mpsc::channel(32);
         let (server_events_sender, server_events_receiver) = tokio::sync::mpsc::channel(32);
+        let (shutdown_sender, shutdown_receiver) = tokio::sync::mpsc::channel(1);
 
         let mut metadata_reader = MetadataReader::new(
             initial_peers,
@@ -172,6 +183,7 @@ impl Cluster {
 
             refresh_channel: refresh_receiver,
             server_events_channel: server_events_receiver,
+            shutdown_channel: shutdown_receiver,
 
             use_keyspace_channel: use_keyspace_receiver,
             used_keyspace: None,
@@ -186,6 +198,7 @@ impl Cluster {
             data: cluster_data,
             refresh_channel: refresh_sender,
             use_keyspace_channel: use_keyspace_sender,
+            shutdown_channel: shutdown_sender,
             _worker_handle: worker_handle,
         };
 
@@ -204,13 +217,13 @@ impl Cluster {
                 response_chan: response_sender,
             })
             .await
-            .expect("Bug in Cluster::refresh_metadata sending");
-        // Other end of this channel is in ClusterWorker, can't be dropped while we have &self to Cluster with _worker_handle
+            .map_err(|_| QueryError::SessionShutDown)?;
+        // Other end of this channel is in ClusterWorker, it's dropped only after a shutdown
 
         response_receiver
             .await
-            .expect("Bug in Cluster::refresh_metadata receiving")
-        // ClusterWorker always responds
+            .map_err(|_| QueryError::SessionShutDown)?
+        // ClusterWorker always responds, unless it's shut down
     }
 
     pub async fn use_keyspace(
@@ -225,10 +238,32 @@ impl Cluster {
                 response_chan: response_sender,
             })
             .await
-            .expect("Bug in Cluster::use_keyspace sending");
-        // Other end of this channel is in ClusterWorker, can't be dropped while we have &self to Cluster with _worker_handle
+            .map_err(|_| QueryError::SessionShutDown)?;
+        // Other end of this channel is in ClusterWorker, it's dropped only after a shutdown
+
+        response_receiver
+            .await
+            .map_err(|_| QueryError::SessionShutDown)? // ClusterWorker always responds, unless it's shut down
+    }
+
+    /// Closes the connection pools of all nodes and stops the worker keeping the cluster updated.\
+    /// Returns the number of closed connections, 0 if the cluster was already shut down.
+    pub async fn shutdown(&self) -> usize {
+        let (response_sender, response_receiver) = tokio::sync::oneshot::channel();
+
+        if self
+            .shutdown_channel
+            .send(ShutdownRequest {
+                response_chan: response_sender,
+            })
+            .await
+            .is_err()
+        {
+            // The worker has already stopped after an earlier shutdown
+            return 0;
+        }
 
-        response_receiver.await.unwrap() // ClusterWorker always responds
+        response_receiver.await.unwrap_or(0)
     }
 
     /// Returns nonempty list of working connections to all shards
@@ -376,6 +411,29 @@ impl ClusterData {
         &self.ring
     }
 
+    /// Iterates over the token ranges of the ring, each one with the node owning it.\
+    /// Every range ends at a token of the ring and starts at the previous one.
+    /// The first range wraps around the end of the ring, the others are ordered by their tokens.
+    pub fn get_token_ranges(&self) -> impl Iterator<Item = (TokenRange, &Arc<Node>)> + '_ {
+        let last_token = self.ring.keys().next_back().copied();
+        last_token
+            .into_iter()
+            .chain(self.ring.keys().copied())
+            .zip(self.ring.iter())
+            .map(|(start, (end, node))| (TokenRange::new(start, *end), node))
+    }
+
+    /// Iterates over the token ranges of the ring, like [`ClusterData::get_token_ranges`],
+    /// each one with the replicas owning it in the keyspace.
+    /// The primary replica comes first.
+    pub fn get_token_range_replicas<'a>(
+        &'a self,
+        keyspace: &'a str,
+    ) -> impl Iterator<Item = (TokenRange, Vec<Arc<Node>>)> + 'a {
+        self.get_token_ranges()
+            .map(move |(range, _)| (range, self.get_token_endpoints(keyspace, range.end)))
+    }
+
     /// Access details about nodes known to the driver
     pub fn get_nodes_info(&self) -> &Vec<Arc<Node>> {
         &self.all_nodes
@@ -504,6 +562,16 @@ impl ClusterWorker {
 
                     continue; // Don't go to refreshing, wait for the next event
                 }
+                recv_res = self.shutdown_channel.recv() => {
+                    if let Some(request) = recv_res {
+                        let closed_connections = self.close_pools().await;
+                        // We can ignore sending error - if no one waits for the response we can drop it
+                        let _ = request.response_chan.send(closed_connections);
+                    }
+                    // The cluster was either shut down or dropped, we can stop working.
+                    // The control connection is closed when the metadata reader is dropped.
+                    return;
+                }
             }
 
             // Perform the refresh
@@ -519,6 +587,20 @@ impl ClusterWorker {
         }
     }
 
+    async fn close_pools(&self) -> usize {
+        let cluster_data = self.cluster_data.load_full();
+        debug!(
+            "Closing connection pools of {} nodes",
+            cluster_data.known_peers.len()
+        );
+
+        let close_futures = cluster_data
+            .known_peers
+            .values()
+            .map(|node| node.close_pool());
+        join_all(close_futures).await.into_iter().sum()
+    }
+
     fn change_node_down_marker(&mut self, addr: SocketAddr, is_down: bool) {
         let cluster_data = self.cluster_data.load_full();
 
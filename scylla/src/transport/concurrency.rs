@@ -0,0 +1,284 @@
+//! Client-side limits of the number of requests in flight
+
+use crate::transport::errors::QueryError;
+use crate::transport::metrics::Metrics;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Limits of the number of requests which are sent to the database at the same time.\
+/// A request over any of the limits waits in a queue until the requests in flight finish.
+/// If the queue is full or the request waits for longer than `queue_timeout`, it fails with
+/// [`QueryError::RequestQueueFull`] or [`QueryError::RequestQueueTimeout`].
+///
+/// By default there are no limits.
+///
+/// # Example
+/// ```
+/// # use scylla::transport::session::ConcurrencyLimits;
+/// # use std::time::Duration;
+/// let limits = ConcurrencyLimits {
+///     max_requests_per_connection: Some(1024),
+///     max_requests_per_node: Some(4096),
+///     queue_timeout: Duration::from_millis(500),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcurrencyLimits {
+    /// Maximum number of requests in flight on a single connection
+    pub max_requests_per_connection: Option<usize>,
+
+    /// Maximum number of requests in flight to a single node, on all of its connections
+    pub max_requests_per_node: Option<usize>,
+
+    /// Maximum number of requests in flight in the whole session
+    pub max_requests_per_session: Option<usize>,
+
+    /// Maximum number of requests waiting for the limits in the whole session
+    pub max_queued_requests: usize,
+
+    /// How long a request may wait in the queue
+    pub queue_timeout: Duration,
+}
+
+impl Default for ConcurrencyLimits {
+    fn default() -> Self {
+        Self {
+            max_requests_per_connection: None,
+            max_requests_per_node: None,
+            max_requests_per_session: None,
+            max_queued_requests: 4096,
+            queue_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ConcurrencyLimits {
+    /// Whether any of the limits is set
+    pub fn is_limited(&self) -> bool {
+        self.max_requests_per_connection.is_some()
+            || self.max_requests_per_node.is_some()
+            || self.max_requests_per_session.is_some()
+    }
+}
+
+/// Enforces [`ConcurrencyLimits`] in a session, shared by all of its connection pools.
+/// Keeps the queue of requests waiting for the limits.
+pub(crate) struct RequestLimiter {
+    limits: ConcurrencyLimits,
+    session: Option<Arc<Semaphore>>,
+    queued_requests: AtomicUsize,
+    metrics: Arc<Metrics>,
+}
+
+impl RequestLimiter {
+    pub(crate) fn new(limits: ConcurrencyLimits, metrics: Arc<Metrics>) -> Self {
+        Self {
+            session: limits
+                .max_requests_per_session
+                .map(|limit| Arc::new(Semaphore::new(limit))),
+            limits,
+            queued_requests: AtomicUsize::new(0),
+            metrics,
+        }
+    }
+
+    /// Makes a limiter for the connections of a single node
+    pub(crate) fn node_limiter(self: &Arc<Self>) -> NodeRequestLimiter {
+        NodeRequestLimiter {
+            node: self
+                .limits
+                .max_requests_per_node
+                .map(|limit| Arc::new(Semaphore::new(limit))),
+            session: self.clone(),
+        }
+    }
+}
+
+/// Limits of the requests sent to a single node, shared by the connections in its pool
+#[derive(Clone)]
+pub(crate) struct NodeRequestLimiter {
+    session: Arc<RequestLimiter>,
+    node: Option<Arc<Semaphore>>,
+}
+
+impl NodeRequestLimiter {
+    /// Makes a limiter for a new connection to the node
+    pub(crate) fn connection_limiter(&self) -> ConnectionRequestLimiter {
+        let limits = &self.session.limits;
+        // The most specific limit comes first, so that a request waiting for its connection
+        // doesn't hold the node's and session's permits needed by requests to other connections
+        let semaphores = limits
+            .max_requests_per_connection
+            .map(|limit| Arc::new(Semaphore::new(limit)))
+            .into_iter()
+            .chain(self.node.clone())
+            .chain(self.session.session.clone())
+            .collect();
+
+        ConnectionRequestLimiter {
+            session: self.session.clone(),
+            semaphores,
+        }
+    }
+}
+
+/// Limits of the requests sent on a single connection
+pub(crate) struct ConnectionRequestLimiter {
+    session: Arc<RequestLimiter>,
+    semaphores: Vec<Arc<Semaphore>>,
+}
+
+impl ConnectionRequestLimiter {
+    /// Waits until the request fits in the limits of the connection, its node and the session
+    pub(crate) async fn acquire(&self) -> Result<RequestPermit, QueryError> {
+        if let Some(permit) = self.try_acquire() {
+            return Ok(permit);
+        }
+
+        let session = &self.session;
+        let queued = session.queued_requests.fetch_add(1, Ordering::SeqCst);
+        if queued >= session.limits.max_queued_requests {
+            session.queued_requests.fetch_sub(1, Ordering::SeqCst);
+            session.metrics.inc_rejected_requests();
+            return Err(QueryError::RequestQueueFull);
+        }
+        session.metrics.inc_queued_requests();
+
+        let result = tokio::time::timeout(session.limits.queue_timeout, self.acquire_all()).await;
+
+        session.queued_requests.fetch_sub(1, Ordering::SeqCst);
+        session.metrics.dec_queued_requests();
+        result.map_err(|_| {
+            session.metrics.inc_queue_timeouts();
+            QueryError::RequestQueueTimeout
+        })
+    }
+
+    fn try_acquire(&self) -> Option<RequestPermit> {
+        let permits = self
+            .semaphores
+            .iter()
+            .map(|semaphore| semaphore.clone().try_acquire_owned().ok())
+            .collect::<Option<Vec<_>>>()?;
+        Some(RequestPermit { _permits: permits })
+    }
+
+    async fn acquire_all(&self) -> RequestPermit {
+        let mut permits = Vec::with_capacity(self.semaphores.len());
+        for semaphore in &self.semaphores {
+            // The semaphores are never closed
+            permits.push(semaphore.clone().acquire_owned().await.unwrap());
+        }
+        RequestPermit { _permits: permits }
+    }
+}
+
+/// Keeps a request counted as in flight until it's dropped
+pub(crate) struct RequestPermit {
+    _permits: Vec<OwnedSemaphorePermit>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConcurrencyLimits, RequestLimiter};
+    use crate::transport::errors::QueryError;
+    use crate::transport::metrics::Metrics;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn limiter(limits: ConcurrencyLimits) -> (Arc<RequestLimiter>, Arc<Metrics>) {
+        let metrics = Arc::new(Metrics::new());
+        let limiter = Arc::new(RequestLimiter::new(limits, metrics.clone()));
+        (limiter, metrics)
+    }
+
+    #[tokio::test]
+    async fn queued_request_waits_for_permit() {
+        let (limiter, metrics) = limiter(ConcurrencyLimits {
+            max_requests_per_connection: Some(1),
+            queue_timeout: Duration::from_secs(10),
+            ..Default::default()
+        });
+        let connection = Arc::new(limiter.node_limiter().connection_limiter());
+
+        let permit = connection.acquire().await.unwrap();
+        let queued = tokio::spawn({
+            let connection = connection.clone();
+            async move { connection.acquire().await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(metrics.get_queued_requests(), 1);
+
+        drop(permit);
+        queued.await.unwrap().unwrap();
+        assert_eq!(metrics.get_queued_requests(), 0);
+
+        // Other connections aren't affected by the limit
+        let _permit = connection.acquire().await.unwrap();
+        limiter
+            .node_limiter()
+            .connection_limiter()
+            .acquire()
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn node_and_session_limits() {
+        let (limiter, _) = limiter(ConcurrencyLimits {
+            max_requests_per_node: Some(2),
+            max_requests_per_session: Some(3),
+            max_queued_requests: 0,
+            ..Default::default()
+        });
+        let node = limiter.node_limiter();
+        let _permits = [
+            node.connection_limiter().acquire().await.unwrap(),
+            node.connection_limiter().acquire().await.unwrap(),
+        ];
+        assert!(matches!(
+            node.connection_limiter().acquire().await,
+            Err(QueryError::RequestQueueFull)
+        ));
+
+        let other_node = limiter.node_limiter();
+        let _permit = other_node.connection_limiter().acquire().await.unwrap();
+        assert!(matches!(
+            other_node.connection_limiter().acquire().await,
+            Err(QueryError::RequestQueueFull)
+        ));
+    }
+
+    #[tokio::test]
+    async fn queue_limits() {
+        let (limiter, metrics) = limiter(ConcurrencyLimits {
+            max_requests_per_session: Some(1),
+            max_queued_requests: 1,
+            queue_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+        let connection = Arc::new(limiter.node_limiter().connection_limiter());
+        let _permit = connection.acquire().await.unwrap();
+
+        let queued = tokio::spawn({
+            let connection = connection.clone();
+            async move { connection.acquire().await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(matches!(
+            connection.acquire().await,
+            Err(QueryError::RequestQueueFull)
+        ));
+        assert!(matches!(
+            queued.await.unwrap(),
+            Err(QueryError::RequestQueueTimeout)
+        ));
+
+        assert_eq!(metrics.get_rejected_requests_num(), 1);
+        assert_eq!(metrics.get_queue_timeouts_num(), 1);
+        assert_eq!(metrics.get_queued_requests(), 0);
+    }
+}
@@ -22,6 +22,7 @@ use std::collections::{BTreeSet, HashMap};
 use std::convert::TryFrom;
 use std::io::ErrorKind;
 use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::sync::Mutex as StdMutex;
 use std::{
@@ -29,6 +30,7 @@ use std::{
     net::{Ipv4Addr, Ipv6Addr},
 };
 
+use super::concurrency::{ConnectionRequestLimiter, NodeRequestLimiter, RequestPermit};
 use super::errors::{BadKeyspaceName, BadQuery, DbError, QueryError};
 
 use crate::batch::{Batch, BatchStatement};
@@ -37,13 +39,14 @@ use crate::frame::{
     self,
     request::{self, batch, execute, query, register, Request},
     response::{event::Event, result, NonErrorResponse, Response, ResponseOpcode},
+    segment::{SegmentDecoder, SegmentEncoder},
     server_event_type::EventType,
     value::{BatchValues, ValueList},
-    FrameParams, SerializedRequest,
+    FrameParams, ProtocolVersion, SerializedRequest,
 };
 use crate::query::Query;
 use crate::routing::ShardInfo;
-use crate::statement::prepared_statement::PreparedStatement;
+use crate::statement::prepared_statement::{PreparedStatement, ResultMetadataId};
 use crate::statement::Consistency;
 use crate::transport::session::IntoTypedRows;
 use crate::transport::Compression;
@@ -71,6 +74,7 @@ pub struct Connection {
     connect_address: SocketAddr,
     config: ConnectionConfig,
     features: ConnectionFeatures,
+    segment_framing: Arc<SegmentFraming>,
 
     // Each request send by `Connection::send_request` needs a unique request id.
     // This field is a monotonic generator of such ids.
@@ -81,6 +85,7 @@ pub struct Connection {
     // pushing values in a synchronous way (without an `.await`), which is
     // needed for pushing values in `Drop` implementations.
     orphan_notification_sender: mpsc::UnboundedSender<RequestId>,
+    request_limiter: Option<ConnectionRequestLimiter>,
 }
 
 #[derive(Default)]
@@ -92,6 +97,50 @@ pub(crate) struct ConnectionFeatures {
 
 type RequestId = u64;
 
+// Since protocol v5, frames are wrapped in segments once the server has
+// answered STARTUP. Both sides switch at that point, so the reader flips
+// `active` as soon as it receives the answer, before it reads any further
+// bytes. The writer checks the flag before each batch of requests.
+#[derive(Default)]
+struct SegmentFraming {
+    // Set just before STARTUP is sent on a connection which uses segments
+    awaiting_startup_response: AtomicBool,
+    active: AtomicBool,
+    // Segments are compressed with LZ4 if it was requested in STARTUP
+    compressed: AtomicBool,
+}
+
+impl SegmentFraming {
+    fn is_active(&self) -> bool {
+        self.active.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    fn is_compressed(&self) -> bool {
+        self.compressed.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    fn expect_startup_response(&self, compressed: bool) {
+        self.compressed
+            .store(compressed, std::sync::atomic::Ordering::Release);
+        self.awaiting_startup_response
+            .store(true, std::sync::atomic::Ordering::Release);
+    }
+
+    // Called by the reader for each frame received with the legacy framing
+    fn on_legacy_frame(&self, opcode: ResponseOpcode) {
+        let startup_answered =
+            matches!(opcode, ResponseOpcode::Ready | ResponseOpcode::Authenticate);
+        if startup_answered
+            && self
+                .awaiting_startup_response
+                .swap(false, std::sync::atomic::Ordering::AcqRel)
+        {
+            self.active
+                .store(true, std::sync::atomic::Ordering::Release);
+        }
+    }
+}
+
 struct ResponseHandler {
     response_sender: oneshot::Sender<Result<TaskResponse, QueryError>>,
     request_id: RequestId,
@@ -145,6 +194,7 @@ pub struct QueryResponse {
     pub response: Response,
     pub tracing_id: Option<Uuid>,
     pub warnings: Vec<String>,
+    pub custom_payload: Option<HashMap<String, Vec<u8>>>,
 }
 
 // A QueryResponse in which response can not be Response::Error
@@ -152,6 +202,7 @@ pub struct NonErrorQueryResponse {
     pub response: NonErrorResponse,
     pub tracing_id: Option<Uuid>,
     pub warnings: Vec<String>,
+    pub custom_payload: Option<HashMap<String, Vec<u8>>>,
 }
 
 impl QueryResponse {
@@ -160,6 +211,7 @@ impl QueryResponse {
             response: self.response.into_non_error_response()?,
             tracing_id: self.tracing_id,
             warnings: self.warnings,
+            custom_payload: self.custom_payload,
         })
     }
 
@@ -184,13 +236,30 @@ impl NonErrorQueryResponse {
     }
 
     pub fn into_query_result(self) -> Result<QueryResult, QueryError> {
-        let (rows, paging_state, col_specs) = match self.response {
-            NonErrorResponse::Result(result::Result::Rows(rs)) => (
-                Some(rs.rows),
-                rs.metadata.paging_state,
-                rs.metadata.col_specs,
-            ),
-            NonErrorResponse::Result(_) => (None, None, vec![]),
+        self.into_query_result_with_lazy_rows(false)
+    }
+
+    // With `lazy_rows`, the rows are only kept serialized in `QueryResult::raw_rows`,
+    // otherwise they are deserialized into `QueryResult::rows` and the bytes are dropped
+    pub(crate) fn into_query_result_with_lazy_rows(
+        self,
+        lazy_rows: bool,
+    ) -> Result<QueryResult, QueryError> {
+        let (rows, raw_rows, paging_state, col_specs) = match self.response {
+            NonErrorResponse::Result(result::Result::Rows(rs)) => {
+                let (rows, raw_rows) = if lazy_rows {
+                    (None, Some(rs.raw_rows))
+                } else {
+                    (Some(rs.rows()?), None)
+                };
+                (
+                    rows,
+                    raw_rows,
+                    rs.metadata.paging_state,
+                    rs.metadata.col_specs,
+                )
+            }
+            NonErrorResponse::Result(_) => (None, None, None, vec![]),
             _ => {
                 return Err(QueryError::ProtocolError(
                     "Unexpected server response, expected Result or Error",
@@ -200,8 +269,10 @@ impl NonErrorQueryResponse {
 
         Ok(QueryResult {
             rows,
+            raw_rows,
             warnings: self.warnings,
             tracing_id: self.tracing_id,
+            custom_payload: self.custom_payload,
             paging_state,
             col_specs,
         })
@@ -211,6 +282,7 @@ impl NonErrorQueryResponse {
 #[derive(Clone)]
 pub struct ConnectionConfig {
     pub compression: Option<Compression>,
+    pub protocol_version: ProtocolVersion,
     pub tcp_nodelay: bool,
     #[cfg(feature = "ssl")]
     pub ssl_context: Option<SslContext>,
@@ -219,12 +291,17 @@ pub struct ConnectionConfig {
     pub event_sender: Option<mpsc::Sender<Event>>,
     pub default_consistency: Consistency,
     pub authenticator: Option<Arc<dyn AuthenticatorProvider>>,
+    // Set by the connection pool when the session has concurrency limits
+    pub(crate) request_limiter: Option<NodeRequestLimiter>,
+    // Shared by all connections of a session
+    pub(crate) negotiated_protocol_version: NegotiatedProtocolVersion,
 }
 
 impl Default for ConnectionConfig {
     fn default() -> Self {
         Self {
             compression: None,
+            protocol_version: ProtocolVersion::default(),
             tcp_nodelay: true,
             event_sender: None,
             #[cfg(feature = "ssl")]
@@ -232,10 +309,39 @@ impl Default for ConnectionConfig {
             connect_timeout: std::time::Duration::from_secs(5),
             default_consistency: Default::default(),
             authenticator: None,
+            request_limiter: None,
+            negotiated_protocol_version: Default::default(),
         }
     }
 }
 
+/// The protocol version negotiated by the connections of a session.
+/// New connections start with it instead of the configured version,
+/// so that a version rejected by the nodes isn't tried on every connection.
+#[derive(Clone, Default)]
+pub(crate) struct NegotiatedProtocolVersion(Arc<std::sync::Mutex<Option<ProtocolVersion>>>);
+
+impl NegotiatedProtocolVersion {
+    /// The version which a new connection should try first
+    pub(crate) fn first_to_try(&self, configured: ProtocolVersion) -> ProtocolVersion {
+        match *self.0.lock().unwrap() {
+            Some(negotiated) => negotiated.min(configured),
+            None => configured,
+        }
+    }
+
+    /// Records a version which a node accepted or had to fall back to.
+    /// The lowest one is kept, so that all connections settle on a version
+    /// supported by every node.
+    fn settle(&self, version: ProtocolVersion) {
+        let mut negotiated = self.0.lock().unwrap();
+        *negotiated = Some(match *negotiated {
+            Some(negotiated) => negotiated.min(version),
+            None => version,
+        });
+    }
+}
+
 impl ConnectionConfig {
     #[cfg(feature = "ssl")]
     pub fn is_ssl(&self) -> bool {
@@ -278,6 +384,7 @@ impl Connection {
         let (error_sender, error_receiver) = tokio::sync::oneshot::channel();
         // Unbounded because it allows for synchronous pushes
         let (orphan_notification_sender, orphan_notification_receiver) = mpsc::unbounded_channel();
+        let segment_framing = Arc::new(SegmentFraming::default());
 
         let _worker_handle = Self::run_router(
             config.clone(),
@@ -285,44 +392,64 @@ impl Connection {
             receiver,
             error_sender,
             orphan_notification_receiver,
+            segment_framing.clone(),
         )
         .await?;
 
+        let request_limiter = config
+            .request_limiter
+            .as_ref()
+            .map(NodeRequestLimiter::connection_limiter);
+
         let connection = Connection {
             submit_channel: sender,
             _worker_handle,
             config,
             features: Default::default(),
+            segment_framing,
             connect_address: addr,
             request_id_generator: AtomicU64::new(0),
             orphan_notification_sender,
+            request_limiter,
         };
 
         Ok((connection, error_receiver))
     }
 
     pub async fn startup(&self, options: HashMap<String, String>) -> Result<Response, QueryError> {
+        if self.config.protocol_version.uses_segments() {
+            let compressed = self.config.compression == Some(Compression::Lz4);
+            self.segment_framing.expect_startup_response(compressed);
+        }
         Ok(self
-            .send_request(&request::Startup { options }, false, false)
+            .send_request(&request::Startup { options }, false, false, None)
             .await?
             .response)
     }
 
     pub async fn get_options(&self) -> Result<Response, QueryError> {
         Ok(self
-            .send_request(&request::Options {}, false, false)
+            .send_request(&request::Options {}, false, false, None)
             .await?
             .response)
     }
 
     pub async fn prepare(&self, query: &Query) -> Result<PreparedStatement, QueryError> {
+        let _permit = self.acquire_request_permit().await?;
+        self.send_prepare(query).await
+    }
+
+    /// Prepares the statement under the request permit of the caller
+    async fn send_prepare(&self, query: &Query) -> Result<PreparedStatement, QueryError> {
         let query_response = self
             .send_request(
                 &request::Prepare {
                     query: &query.contents,
+                    keyspace: query.get_keyspace(),
                 },
                 true,
                 query.config.tracing,
+                query.config.custom_payload.as_ref(),
             )
             .await?;
 
@@ -330,6 +457,7 @@ impl Connection {
             Response::Error(err) => return Err(err.into()),
             Response::Result(result::Result::Prepared(p)) => PreparedStatement::new(
                 p.id,
+                ResultMetadataId::new(p.result_metadata_id),
                 self.features
                     .protocol_features
                     .prepared_flags_contain_lwt_mark(p.prepared_metadata.flags as u32),
@@ -351,13 +479,15 @@ impl Connection {
         Ok(prepared_statement)
     }
 
+    /// Prepares the statement again while executing it, so it doesn't take another request permit:
+    /// with a limit of one request it would wait for the permit held by the execution forever
     async fn reprepare(
         &self,
         query: impl Into<Query>,
         previous_prepared: &PreparedStatement,
     ) -> Result<(), QueryError> {
         let reprepare_query: Query = query.into();
-        let reprepared = self.prepare(&reprepare_query).await?;
+        let reprepared = self.send_prepare(&reprepare_query).await?;
         // Reprepared statement should keep its id - it's the md5 sum
         // of statement contents
         if reprepared.get_id() != previous_prepared.get_id() {
@@ -365,6 +495,9 @@ impl Connection {
                 "Prepared statement Id changed, md5 sum should stay the same",
             ))
         } else {
+            if let Some(result_metadata_id) = reprepared.get_result_metadata_id() {
+                previous_prepared.update_result_metadata_id(result_metadata_id);
+            }
             Ok(())
         }
     }
@@ -373,7 +506,7 @@ impl Connection {
         &self,
         response: Option<Vec<u8>>,
     ) -> Result<QueryResponse, QueryError> {
-        self.send_request(&request::AuthResponse { response }, false, false)
+        self.send_request(&request::AuthResponse { response }, false, false, None)
             .await
     }
 
@@ -439,6 +572,7 @@ impl Connection {
         serial_consistency: Option<SerialConsistency>,
         paging_state: Option<Bytes>,
     ) -> Result<QueryResponse, QueryError> {
+        let _permit = self.acquire_request_permit().await?;
         let serialized_values = values.serialized()?;
 
         let query_frame = query::Query {
@@ -450,11 +584,18 @@ impl Connection {
                 page_size: query.get_page_size(),
                 paging_state,
                 timestamp: query.get_timestamp(),
+                keyspace: query.get_keyspace(),
+                now_in_seconds: query.get_now_in_seconds(),
             },
         };
 
-        self.send_request(&query_frame, true, query.config.tracing)
-            .await
+        self.send_request(
+            &query_frame,
+            true,
+            query.config.tracing,
+            query.config.custom_payload.as_ref(),
+        )
+        .await
     }
 
     /// Performs query_single_page multiple times to query all available pages
@@ -489,7 +630,7 @@ impl Connection {
             )));
         }
 
-        let mut final_result = QueryResult::default();
+        let mut pages: Vec<QueryResult> = Vec::new();
 
         let serialized_values = values.serialized()?;
         let mut paging_state: Option<Bytes> = None;
@@ -511,11 +652,11 @@ impl Connection {
             paging_state = cur_result.paging_state.take();
 
             // Add current query results to the final_result
-            final_result.merge_with_next_page_res(cur_result);
+            pages.push(cur_result);
 
             if paging_state.is_none() {
                 // No more pages to query, we can return the final result
-                return Ok(final_result);
+                return Ok(QueryResult::merge_pages(pages));
             }
         }
     }
@@ -557,25 +698,44 @@ impl Connection {
         serial_consistency: Option<SerialConsistency>,
         paging_state: Option<Bytes>,
     ) -> Result<QueryResponse, QueryError> {
+        let _permit = self.acquire_request_permit().await?;
+
+        // A statement prepared on a connection using an older protocol version
+        // has no result metadata id, which EXECUTE requires since protocol v5
+        if self.config.protocol_version.uses_segments()
+            && prepared_statement.get_result_metadata_id().is_none()
+        {
+            self.reprepare(prepared_statement.get_statement(), prepared_statement)
+                .await?;
+        }
+
         let serialized_values = values.serialized()?;
 
-        let execute_frame = execute::Execute {
+        let execute_frame = || execute::Execute {
             id: prepared_statement.get_id().to_owned(),
+            result_metadata_id: prepared_statement.get_result_metadata_id(),
             parameters: query::QueryParameters {
                 consistency,
                 serial_consistency,
                 values: &serialized_values,
                 page_size: prepared_statement.get_page_size(),
                 timestamp: prepared_statement.get_timestamp(),
-                paging_state,
+                paging_state: paging_state.clone(),
+                keyspace: None,
+                now_in_seconds: prepared_statement.get_now_in_seconds(),
             },
         };
 
         let query_response = self
-            .send_request(&execute_frame, true, prepared_statement.config.tracing)
+            .send_request(
+                &execute_frame(),
+                true,
+                prepared_statement.config.tracing,
+                prepared_statement.config.custom_payload.as_ref(),
+            )
             .await?;
 
-        match &query_response.response {
+        let query_response = match &query_response.response {
             Response::Error(frame::response::Error {
                 error: DbError::Unprepared { statement_id },
                 ..
@@ -584,11 +744,25 @@ impl Connection {
                 // Repreparation of a statement is needed
                 self.reprepare(prepared_statement.get_statement(), prepared_statement)
                     .await?;
-                self.send_request(&execute_frame, true, prepared_statement.config.tracing)
-                    .await
+                self.send_request(
+                    &execute_frame(),
+                    true,
+                    prepared_statement.config.tracing,
+                    prepared_statement.config.custom_payload.as_ref(),
+                )
+                .await?
+            }
+            _ => query_response,
+        };
+
+        // Since protocol v5 the server sends the id of the new result metadata
+        // once it changes, it has to be sent in the following requests
+        if let Response::Result(result::Result::Rows(rows)) = &query_response.response {
+            if let Some(new_metadata_id) = &rows.metadata.new_metadata_id {
+                prepared_statement.update_result_metadata_id(new_metadata_id.clone());
             }
-            _ => Ok(query_response),
         }
+        Ok(query_response)
     }
 
     /// Performs execute_single_page multiple times to fetch all available pages
@@ -604,7 +778,7 @@ impl Connection {
             )));
         }
 
-        let mut final_result = QueryResult::default();
+        let mut pages: Vec<QueryResult> = Vec::new();
 
         let serialized_values = values.serialized()?;
         let mut paging_state: Option<Bytes> = None;
@@ -619,11 +793,11 @@ impl Connection {
             paging_state = cur_result.paging_state.take();
 
             // Add current query results to the final_result
-            final_result.merge_with_next_page_res(cur_result);
+            pages.push(cur_result);
 
             if paging_state.is_none() {
                 // No more pages to query, we can return the final result
-                return Ok(final_result);
+                return Ok(QueryResult::merge_pages(pages));
             }
         }
     }
@@ -652,6 +826,7 @@ impl Connection {
         consistency: Consistency,
         serial_consistency: Option<SerialConsistency>,
     ) -> Result<QueryResult, QueryError> {
+        let _permit = self.acquire_request_permit().await?;
         let statements_iter = batch.statements.iter().map(|s| match s {
             BatchStatement::Query(q) => batch::BatchStatement::Query { text: &q.contents },
             BatchStatement::PreparedStatement(s) => {
@@ -667,11 +842,18 @@ impl Connection {
             consistency,
             serial_consistency,
             timestamp: batch.get_timestamp(),
+            keyspace: batch.get_keyspace(),
+            now_in_seconds: batch.get_now_in_seconds(),
         };
 
         loop {
             let query_response = self
-                .send_request(&batch_frame, true, batch.config.tracing)
+                .send_request(
+                    &batch_frame,
+                    true,
+                    batch.config.tracing,
+                    batch.config.custom_payload.as_ref(),
+                )
                 .await?;
 
             return match query_response.response {
@@ -744,7 +926,7 @@ impl Connection {
         };
 
         match self
-            .send_request(&register_frame, true, false)
+            .send_request(&register_frame, true, false, None)
             .await?
             .response
         {
@@ -774,18 +956,30 @@ impl Connection {
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
     }
 
+    /// Waits until the request fits in the concurrency limits, if the session has any
+    async fn acquire_request_permit(&self) -> Result<Option<RequestPermit>, QueryError> {
+        match &self.request_limiter {
+            Some(limiter) => limiter.acquire().await.map(Some),
+            None => Ok(None),
+        }
+    }
+
     async fn send_request<R: Request>(
         &self,
         request: &R,
         compress: bool,
         tracing: bool,
+        custom_payload: Option<&HashMap<String, Vec<u8>>>,
     ) -> Result<QueryResponse, QueryError> {
-        let compression = if compress {
+        let version = self.config.protocol_version;
+        // Since protocol v5 compression is applied to segments, not frames
+        let compression = if compress && !version.uses_segments() {
             self.config.compression
         } else {
             None
         };
-        let serialized_request = SerializedRequest::make(request, compression, tracing)?;
+        let serialized_request =
+            SerializedRequest::make(request, version, compression, tracing, custom_payload)?;
         let request_id = self.allocate_request_id();
 
         let (response_sender, receiver) = oneshot::channel();
@@ -835,6 +1029,7 @@ impl Connection {
         compression: Option<Compression>,
         features: &ProtocolFeatures,
     ) -> Result<QueryResponse, QueryError> {
+        let version = task_response.params.protocol_version()?;
         let body_with_ext = frame::parse_response_body_extensions(
             task_response.params.flags,
             compression,
@@ -849,12 +1044,13 @@ impl Connection {
         }
 
         let response =
-            Response::deserialize(features, task_response.opcode, &mut &*body_with_ext.body)?;
+            Response::deserialize(features, version, task_response.opcode, body_with_ext.body)?;
 
         Ok(QueryResponse {
             response,
             warnings: body_with_ext.warnings,
             tracing_id: body_with_ext.trace_id,
+            custom_payload: body_with_ext.custom_payload,
         })
     }
 
@@ -864,6 +1060,7 @@ impl Connection {
         receiver: mpsc::Receiver<Task>,
         error_sender: tokio::sync::oneshot::Sender<QueryError>,
         orphan_notification_receiver: mpsc::UnboundedReceiver<RequestId>,
+        segment_framing: Arc<SegmentFraming>,
     ) -> Result<RemoteHandle<()>, std::io::Error> {
         #[cfg(feature = "ssl")]
         if let Some(context) = &config.ssl_context {
@@ -877,6 +1074,7 @@ impl Connection {
                 receiver,
                 error_sender,
                 orphan_notification_receiver,
+                segment_framing,
             )
             .remote_handle();
             tokio::task::spawn(task);
@@ -889,6 +1087,7 @@ impl Connection {
             receiver,
             error_sender,
             orphan_notification_receiver,
+            segment_framing,
         )
         .remote_handle();
         tokio::task::spawn(task);
@@ -901,6 +1100,7 @@ impl Connection {
         receiver: mpsc::Receiver<Task>,
         error_sender: tokio::sync::oneshot::Sender<QueryError>,
         orphan_notification_receiver: mpsc::UnboundedReceiver<RequestId>,
+        segment_framing: Arc<SegmentFraming>,
     ) {
         let (read_half, write_half) = split(stream);
         // Why are using a mutex here?
@@ -921,11 +1121,13 @@ impl Connection {
             BufReader::with_capacity(8192, read_half),
             &handler_map,
             config,
+            &segment_framing,
         );
         let w = Self::writer(
             BufWriter::with_capacity(8192, write_half),
             &handler_map,
             receiver,
+            &segment_framing,
         );
         let o = Self::orphaner(&handler_map, orphan_notification_receiver);
 
@@ -953,9 +1155,22 @@ impl Connection {
         mut read_half: (impl AsyncRead + Unpin),
         handler_map: &StdMutex<ResponseHandlerMap>,
         config: ConnectionConfig,
+        segment_framing: &SegmentFraming,
     ) -> Result<(), QueryError> {
+        let mut segment_decoder: Option<SegmentDecoder> = None;
         loop {
-            let (params, opcode, body) = frame::read_response_frame(&mut read_half).await?;
+            let (params, opcode, body) = match &mut segment_decoder {
+                Some(decoder) => decoder.read_response_frame(&mut read_half).await?,
+                None => {
+                    let frame = frame::read_response_frame(&mut read_half).await?;
+                    segment_framing.on_legacy_frame(frame.1);
+                    if segment_framing.is_active() {
+                        segment_decoder =
+                            Some(SegmentDecoder::new(segment_framing.is_compressed()));
+                    }
+                    frame
+                }
+            };
             let response = TaskResponse {
                 params,
                 opcode,
@@ -1035,11 +1250,19 @@ impl Connection {
         mut write_half: (impl AsyncWrite + Unpin),
         handler_map: &StdMutex<ResponseHandlerMap>,
         mut task_receiver: mpsc::Receiver<Task>,
+        segment_framing: &SegmentFraming,
     ) -> Result<(), QueryError> {
+        let mut segment_encoder: Option<SegmentEncoder> = None;
+        let mut segments = Vec::new();
+
         // When the Connection object is dropped, the sender half
         // of the channel will be dropped, this task will return an error
         // and the whole worker will be stopped
         while let Some(mut task) = task_receiver.recv().await {
+            if segment_encoder.is_none() && segment_framing.is_active() {
+                segment_encoder = Some(SegmentEncoder::new(segment_framing.is_compressed()));
+            }
+
             let mut num_requests = 0;
             let mut total_sent = 0;
             while let Some(stream_id) = Self::alloc_stream_id(handler_map, task.response_handler) {
@@ -1048,7 +1271,14 @@ impl Connection {
                 let req_data: &[u8] = req.get_data();
                 total_sent += req_data.len();
                 num_requests += 1;
-                write_half.write_all(req_data).await?;
+                match &mut segment_encoder {
+                    Some(encoder) => {
+                        encoder.push_frame(req_data, &mut segments)?;
+                        write_half.write_all(&segments).await?;
+                        segments.clear();
+                    }
+                    None => write_half.write_all(req_data).await?,
+                }
                 task = match task_receiver.try_recv() {
                     Ok(t) => t,
                     Err(_) => {
@@ -1063,6 +1293,11 @@ impl Connection {
                     }
                 }
             }
+            if let Some(encoder) = &mut segment_encoder {
+                encoder.flush(&mut segments)?;
+                write_half.write_all(&segments).await?;
+                segments.clear();
+            }
             trace!("Sending {} requests; {} bytes", num_requests, total_sent);
             write_half.flush().await?;
         }
@@ -1157,6 +1392,11 @@ impl Connection {
     pub fn get_connect_address(&self) -> SocketAddr {
         self.connect_address
     }
+
+    /// Returns the protocol version negotiated with the server.
+    pub fn get_protocol_version(&self) -> ProtocolVersion {
+        self.config.protocol_version
+    }
 }
 
 pub async fn open_connection(
@@ -1177,30 +1417,58 @@ pub async fn open_connection(
 pub async fn open_named_connection(
     addr: SocketAddr,
     source_port: Option<u16>,
-    config: ConnectionConfig,
+    mut config: ConnectionConfig,
     driver_name: Option<String>,
     driver_version: Option<String>,
 ) -> Result<(Connection, ErrorReceiver), QueryError> {
     // TODO: shouldn't all this logic be in Connection::new?
-    let (mut connection, error_receiver) =
-        Connection::new(addr, source_port, config.clone()).await?;
-
-    let options_result = connection.get_options().await?;
+    // Start with the protocol version negotiated by the session so far and move
+    // to older ones as long as the server answers OPTIONS with a protocol error.
+    // The server usually closes the connection after such an error,
+    // so each attempt is made on a new one.
+    let negotiated_version = config.negotiated_protocol_version.clone();
+    config.protocol_version = negotiated_version.first_to_try(config.protocol_version);
+    let (mut connection, error_receiver, mut supported) = loop {
+        let (connection, error_receiver) =
+            Connection::new(addr, source_port, config.clone()).await?;
+
+        match connection.get_options().await? {
+            Response::Supported(supported) => {
+                negotiated_version.settle(config.protocol_version);
+                break (connection, error_receiver, supported);
+            }
+            Response::Error(frame::response::Error {
+                error: DbError::ProtocolError,
+                reason,
+            }) => match config.protocol_version.fallback() {
+                Some(older_version) => {
+                    debug!(
+                        "Protocol {} rejected by {}: {}, falling back to {}",
+                        config.protocol_version, addr, reason, older_version
+                    );
+                    negotiated_version.settle(older_version);
+                    if source_port.is_some() {
+                        // The port was just used by the connection closed by the server,
+                        // the caller opens the next connection with the older version
+                        return Err(QueryError::DbError(DbError::ProtocolError, reason));
+                    }
+                    config.protocol_version = older_version;
+                }
+                None => return Err(QueryError::DbError(DbError::ProtocolError, reason)),
+            },
+            _ => {
+                return Err(QueryError::ProtocolError(
+                    "Wrong response to OPTIONS message was received",
+                ));
+            }
+        }
+    };
 
     let shard_aware_port_key = match config.is_ssl() {
         true => "SCYLLA_SHARD_AWARE_PORT_SSL",
         false => "SCYLLA_SHARD_AWARE_PORT",
     };
 
-    let mut supported = match options_result {
-        Response::Supported(supported) => supported,
-        _ => {
-            return Err(QueryError::ProtocolError(
-                "Wrong response to OPTIONS message was received",
-            ));
-        }
-    };
-
     let shard_info = ShardInfo::try_from(&supported.options).ok();
     let supported_compression = supported.options.remove("COMPRESSION").unwrap_or_default();
     let shard_aware_port = supported
@@ -1232,7 +1500,10 @@ pub async fn open_named_connection(
     }
     if let Some(compression) = &config.compression {
         let compression_str = compression.to_string();
-        if supported_compression.iter().any(|c| c == &compression_str) {
+        // Segments used since protocol v5 can only be compressed with LZ4
+        let usable_with_version =
+            !config.protocol_version.uses_segments() || *compression == Compression::Lz4;
+        if usable_with_version && supported_compression.iter().any(|c| c == &compression_str) {
             // Compression is reported to be supported by the server,
             // request it from the server
             options.insert("COMPRESSION".to_string(), compression.to_string());
@@ -1558,29 +1829,34 @@ impl VerifiedKeyspaceName {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use scylla_cql::errors::BadQuery;
     use scylla_cql::frame::protocol_features::{
         LWT_OPTIMIZATION_META_BIT_MASK_KEY, SCYLLA_LWT_ADD_METADATA_MARK_EXTENSION,
     };
-    use scylla_cql::frame::types;
+    use scylla_cql::frame::response::ResponseOpcode;
+    use scylla_cql::frame::{segment, types, ProtocolVersion};
     use scylla_proxy::{
         Condition, Node, Proxy, Reaction, RequestFrame, RequestOpcode, RequestReaction,
         RequestRule, ResponseFrame,
     };
 
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::select;
     use tokio::sync::mpsc;
 
     use super::super::errors::QueryError;
-    use super::ConnectionConfig;
+    use super::{Batch, ConnectionConfig, PreparedStatement, ResultMetadataId};
     use crate::query::Query;
+    use crate::transport::concurrency::{ConcurrencyLimits, RequestLimiter};
     use crate::transport::connection::open_connection;
+    use crate::transport::metrics::Metrics;
     use crate::utils::test_utils::unique_keyspace_name;
     use crate::{IntoTypedRows, SessionBuilder};
     use std::collections::HashMap;
     use std::net::SocketAddr;
     use std::sync::Arc;
+    use std::time::Duration;
 
     // Just like resolve_hostname in session.rs
     async fn resolve_hostname(hostname: &str) -> SocketAddr {
@@ -1791,4 +2067,327 @@ mod tests {
             &lwt_optimisation_entry
         )
     }
+
+    /// Reads a frame sent before the connection switched to segments,
+    /// returns its protocol version, stream, opcode and body
+    async fn read_plain_frame(socket: &mut tokio::net::TcpStream) -> (u8, i16, u8, Vec<u8>) {
+        let mut header = [0u8; 9];
+        socket.read_exact(&mut header).await.unwrap();
+        let length = u32::from_be_bytes(header[5..9].try_into().unwrap()) as usize;
+        let mut body = vec![0u8; length];
+        socket.read_exact(&mut body).await.unwrap();
+        let stream = i16::from_be_bytes([header[2], header[3]]);
+        (header[0], stream, header[4], body)
+    }
+
+    /// Reads a frame carried by self-contained segments
+    async fn read_segmented_frame(socket: &mut tokio::net::TcpStream) -> (i16, u8, Vec<u8>) {
+        let payload = segment::read_segment(socket, false).await.unwrap();
+        let length = u32::from_be_bytes(payload[5..9].try_into().unwrap()) as usize;
+        assert_eq!(payload.len(), 9 + length);
+        (
+            i16::from_be_bytes([payload[2], payload[3]]),
+            payload[4],
+            payload[9..].to_vec(),
+        )
+    }
+
+    fn response_frame(version: u8, stream: i16, opcode: u8, body: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x80 | version, 0x00];
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.push(opcode);
+        frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frame.extend_from_slice(body);
+        frame
+    }
+
+    async fn write_segmented_frame(socket: &mut tokio::net::TcpStream, frame: &[u8]) {
+        let mut encoder = segment::SegmentEncoder::new(false);
+        let mut out = Vec::new();
+        encoder.push_frame(frame, &mut out).unwrap();
+        encoder.flush(&mut out).unwrap();
+        socket.write_all(&out).await.unwrap();
+    }
+
+    /// Body of a ROWS result with a single text column and a single row
+    fn rows_body(new_metadata_id: Option<&[u8]>, value: &str) -> Vec<u8> {
+        let mut body = Vec::new();
+        types::write_int(0x0002, &mut body);
+        // Global table spec, and the changed metadata id if it's given
+        let flags = if new_metadata_id.is_some() {
+            0x0009
+        } else {
+            0x0001
+        };
+        types::write_int(flags, &mut body);
+        types::write_int(1, &mut body);
+        if let Some(id) = new_metadata_id {
+            types::write_short_bytes(id, &mut body).unwrap();
+        }
+        types::write_string("ks", &mut body).unwrap();
+        types::write_string("t", &mut body).unwrap();
+        types::write_string("v", &mut body).unwrap();
+        types::write_short(0x000D, &mut body);
+        types::write_int(1, &mut body);
+        types::write_bytes(value.as_bytes(), &mut body).unwrap();
+        body
+    }
+
+    /// Serves OPTIONS, STARTUP, two PREPAREs and two EXECUTEs with protocol v5.
+    /// Returns the result metadata ids sent in the EXECUTEs.
+    async fn serve_v5_connection(
+        listener: tokio::net::TcpListener,
+        large_value: String,
+    ) -> Vec<Vec<u8>> {
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        let (_, stream, opcode, _) = read_plain_frame(&mut socket).await;
+        assert_eq!(opcode, RequestOpcode::Options as u8);
+        let mut supported = Vec::new();
+        types::write_short(0, &mut supported);
+        let frame = response_frame(5, stream, ResponseOpcode::Supported as u8, &supported);
+        socket.write_all(&frame).await.unwrap();
+
+        let (_, stream, opcode, _) = read_plain_frame(&mut socket).await;
+        assert_eq!(opcode, RequestOpcode::Startup as u8);
+        let frame = response_frame(5, stream, ResponseOpcode::Ready as u8, &[]);
+        socket.write_all(&frame).await.unwrap();
+
+        // The second PREPARE reprepares a statement which has no result metadata id
+        for _ in 0..2 {
+            let (stream, opcode, _) = read_segmented_frame(&mut socket).await;
+            assert_eq!(opcode, RequestOpcode::Prepare as u8);
+            let mut prepared = Vec::new();
+            types::write_int(0x0004, &mut prepared);
+            types::write_short_bytes(b"id", &mut prepared).unwrap();
+            types::write_short_bytes(b"m1", &mut prepared).unwrap();
+            // Prepared metadata without bind markers
+            types::write_int(0, &mut prepared);
+            types::write_int(0, &mut prepared);
+            types::write_int(0, &mut prepared);
+            // Result metadata without column specs
+            types::write_int(0x0004, &mut prepared);
+            types::write_int(1, &mut prepared);
+            let frame = response_frame(5, stream, ResponseOpcode::Result as u8, &prepared);
+            write_segmented_frame(&mut socket, &frame).await;
+        }
+
+        let mut metadata_ids = Vec::new();
+        for (new_metadata_id, value) in [(Some(&b"m2"[..]), large_value.as_str()), (None, "small")]
+        {
+            let (stream, opcode, body) = read_segmented_frame(&mut socket).await;
+            assert_eq!(opcode, RequestOpcode::Execute as u8);
+            let mut body = &body[..];
+            assert_eq!(types::read_short_bytes(&mut body).unwrap(), b"id");
+            metadata_ids.push(types::read_short_bytes(&mut body).unwrap().to_vec());
+
+            let rows = rows_body(new_metadata_id, value);
+            let frame = response_frame(5, stream, ResponseOpcode::Result as u8, &rows);
+            write_segmented_frame(&mut socket, &frame).await;
+        }
+        metadata_ids
+    }
+
+    /// Runs a v5 connection against a mock server. Checks that frames are wrapped in segments,
+    /// also responses which have to be split into multiple segments, that a statement prepared
+    /// without a result metadata id is reprepared and that the result metadata id changed
+    /// by the server is sent in the following requests.
+    #[tokio::test]
+    async fn v5_segments_and_result_metadata_id() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Larger than the maximum payload of a segment
+        let large_value = "a".repeat(200_000);
+        let server = tokio::spawn(serve_v5_connection(listener, large_value.clone()));
+
+        let config = ConnectionConfig {
+            protocol_version: ProtocolVersion::V5,
+            ..Default::default()
+        };
+        let (connection, _) = open_connection(addr, None, config).await.unwrap();
+        assert_eq!(connection.get_protocol_version(), ProtocolVersion::V5);
+
+        let prepared = connection
+            .prepare(&Query::new("SELECT v FROM ks.t"))
+            .await
+            .unwrap();
+        assert_eq!(
+            prepared.get_result_metadata_id().as_deref(),
+            Some(&b"m1"[..])
+        );
+        // Like a statement prepared on a connection using protocol v4
+        let prepared = PreparedStatement::new(
+            prepared.get_id().clone(),
+            ResultMetadataId::default(),
+            false,
+            prepared.get_prepared_metadata().clone(),
+            prepared.get_statement().to_string(),
+            None,
+            Default::default(),
+        );
+
+        for expected in [large_value, "small".to_string()] {
+            let rows = connection
+                .execute(&prepared, (), None)
+                .await
+                .unwrap()
+                .into_query_result()
+                .unwrap()
+                .rows_typed::<(String,)>()
+                .unwrap()
+                .map(|row| row.unwrap().0)
+                .collect::<Vec<_>>();
+            assert_eq!(rows, vec![expected]);
+        }
+        assert_eq!(
+            prepared.get_result_metadata_id().as_deref(),
+            Some(&b"m2"[..])
+        );
+
+        assert_eq!(server.await.unwrap(), vec![b"m1".to_vec(), b"m2".to_vec()]);
+    }
+
+    /// Serves a statement which the server forgets after it's prepared, with protocol v4:
+    /// an EXECUTE and a BATCH are answered with an Unprepared error and succeed once it's reprepared
+    async fn serve_unprepared(listener: tokio::net::TcpListener) {
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        let (_, stream, opcode, _) = read_plain_frame(&mut socket).await;
+        assert_eq!(opcode, RequestOpcode::Options as u8);
+        let mut supported = Vec::new();
+        types::write_short(0, &mut supported);
+        let frame = response_frame(4, stream, ResponseOpcode::Supported as u8, &supported);
+        socket.write_all(&frame).await.unwrap();
+
+        let (_, stream, opcode, _) = read_plain_frame(&mut socket).await;
+        assert_eq!(opcode, RequestOpcode::Startup as u8);
+        let frame = response_frame(4, stream, ResponseOpcode::Ready as u8, &[]);
+        socket.write_all(&frame).await.unwrap();
+
+        let mut prepared = Vec::new();
+        types::write_int(0x0004, &mut prepared);
+        types::write_short_bytes(b"id", &mut prepared).unwrap();
+        // Prepared metadata without bind markers
+        types::write_int(0, &mut prepared);
+        types::write_int(0, &mut prepared);
+        types::write_int(0, &mut prepared);
+        // Result metadata without column specs
+        types::write_int(0x0004, &mut prepared);
+        types::write_int(0, &mut prepared);
+        let mut unprepared = Vec::new();
+        types::write_int(0x2500, &mut unprepared);
+        types::write_string("Unprepared", &mut unprepared).unwrap();
+        types::write_short_bytes(b"id", &mut unprepared).unwrap();
+        let mut void = Vec::new();
+        types::write_int(0x0001, &mut void);
+
+        let prepare = (RequestOpcode::Prepare, ResponseOpcode::Result, &prepared);
+        let exchanges = [
+            prepare,
+            (RequestOpcode::Execute, ResponseOpcode::Error, &unprepared),
+            prepare,
+            (RequestOpcode::Execute, ResponseOpcode::Result, &void),
+            (RequestOpcode::Batch, ResponseOpcode::Error, &unprepared),
+            prepare,
+            (RequestOpcode::Batch, ResponseOpcode::Result, &void),
+        ];
+        for (request_opcode, response_opcode, body) in exchanges {
+            let (_, stream, opcode, _) = read_plain_frame(&mut socket).await;
+            assert_eq!(opcode, request_opcode as u8);
+            let frame = response_frame(4, stream, response_opcode as u8, body);
+            socket.write_all(&frame).await.unwrap();
+        }
+    }
+
+    /// Repreparing a statement doesn't need another request permit, with a limit of one request
+    /// per connection it would wait for the permit of the EXECUTE or BATCH until the queue timeout.
+    #[tokio::test]
+    async fn reprepare_within_request_limit() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve_unprepared(listener));
+
+        let limits = ConcurrencyLimits {
+            max_requests_per_connection: Some(1),
+            queue_timeout: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let limiter = Arc::new(RequestLimiter::new(limits, Arc::new(Metrics::new())));
+        let config = ConnectionConfig {
+            protocol_version: ProtocolVersion::V4,
+            request_limiter: Some(limiter.node_limiter()),
+            ..Default::default()
+        };
+        let (connection, _) = open_connection(addr, None, config).await.unwrap();
+
+        let prepared = connection
+            .prepare(&Query::new("INSERT INTO ks.t (v) VALUES ('a')"))
+            .await
+            .unwrap();
+        connection.execute(&prepared, (), None).await.unwrap();
+
+        let mut batch = Batch::default();
+        batch.append_statement(prepared);
+        connection.batch(&batch, ((),)).await.unwrap();
+
+        server.await.unwrap();
+    }
+
+    /// Accepts `connections` connections, rejecting protocol v5 on each of them.
+    /// Returns the protocol versions of the OPTIONS requests.
+    pub(crate) async fn serve_v4_only(
+        listener: tokio::net::TcpListener,
+        connections: usize,
+    ) -> Vec<u8> {
+        let mut versions = Vec::new();
+        let mut ready_sockets = Vec::new();
+        while ready_sockets.len() < connections {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let (version, stream, opcode, _) = read_plain_frame(&mut socket).await;
+            assert_eq!(opcode, RequestOpcode::Options as u8);
+            versions.push(version);
+
+            if version == 5 {
+                let mut error = Vec::new();
+                types::write_int(0x000A, &mut error);
+                types::write_string("Beta version of the protocol used", &mut error).unwrap();
+                let frame = response_frame(4, stream, ResponseOpcode::Error as u8, &error);
+                socket.write_all(&frame).await.unwrap();
+                continue;
+            }
+
+            let mut supported = Vec::new();
+            types::write_short(0, &mut supported);
+            let frame = response_frame(4, stream, ResponseOpcode::Supported as u8, &supported);
+            socket.write_all(&frame).await.unwrap();
+            let (_, stream, opcode, _) = read_plain_frame(&mut socket).await;
+            assert_eq!(opcode, RequestOpcode::Startup as u8);
+            let frame = response_frame(4, stream, ResponseOpcode::Ready as u8, &[]);
+            socket.write_all(&frame).await.unwrap();
+            ready_sockets.push(socket);
+        }
+        versions
+    }
+
+    /// A connection rejected with protocol v5 falls back to v4,
+    /// the following connections of the session start with v4 right away.
+    #[tokio::test]
+    async fn negotiated_protocol_version_is_shared() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve_v4_only(listener, 2));
+
+        let config = ConnectionConfig {
+            protocol_version: ProtocolVersion::V5,
+            ..Default::default()
+        };
+        let mut connections = Vec::new();
+        for _ in 0..2 {
+            let (connection, _) = open_connection(addr, None, config.clone()).await.unwrap();
+            assert_eq!(connection.get_protocol_version(), ProtocolVersion::V4);
+            connections.push(connection);
+        }
+
+        assert_eq!(server.await.unwrap(), vec![5, 4, 4]);
+    }
 }
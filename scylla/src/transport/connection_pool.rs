@@ -1,6 +1,7 @@
 use crate::routing::{Shard, ShardCount, Sharder, Token};
-use crate::transport::errors::QueryError;
+use crate::transport::errors::{DbError, QueryError};
 use crate::transport::{
+    concurrency::RequestLimiter,
     connection,
     connection::{Connection, ConnectionConfig, ErrorReceiver, VerifiedKeyspaceName},
 };
@@ -48,6 +49,8 @@ pub struct PoolConfig {
     pub pool_size: PoolSize,
     pub can_use_shard_aware_port: bool,
     pub keepalive_interval: Option<Duration>,
+    // Shared by the pools of all nodes in the session, if it has concurrency limits
+    pub(crate) request_limiter: Option<Arc<RequestLimiter>>,
 }
 
 impl Default for PoolConfig {
@@ -57,6 +60,7 @@ impl Default for PoolConfig {
             pool_size: Default::default(),
             can_use_shard_aware_port: true,
             keepalive_interval: None,
+            request_limiter: None,
         }
     }
 }
@@ -72,6 +76,9 @@ enum MaybePoolConnections {
 
     // The pool has some connections which are usable (or will be removed soon)
     Ready(PoolConnections),
+
+    // The pool was closed because the session was shut down, it won't be refilled
+    Closed,
 }
 
 impl std::fmt::Debug for MaybePoolConnections {
@@ -80,6 +87,7 @@ impl std::fmt::Debug for MaybePoolConnections {
             MaybePoolConnections::Initializing => write!(f, "Initializing"),
             MaybePoolConnections::Broken(err) => write!(f, "Broken({:?})", err),
             MaybePoolConnections::Ready(conns) => write!(f, "{:?}", conns),
+            MaybePoolConnections::Closed => write!(f, "Closed"),
         }
     }
 }
@@ -139,6 +147,7 @@ impl std::fmt::Debug for PoolConnections {
 pub struct NodeConnectionPool {
     conns: Arc<ArcSwap<MaybePoolConnections>>,
     use_keyspace_request_sender: mpsc::Sender<UseKeyspaceRequest>,
+    close_request_sender: mpsc::Sender<CloseRequest>,
     _refiller_handle: RemoteHandle<()>,
     _keepaliver_handle: Option<RemoteHandle<()>>,
     pool_updated_notify: Arc<Notify>,
@@ -156,10 +165,17 @@ impl NodeConnectionPool {
     pub fn new(
         address: IpAddr,
         port: u16,
-        pool_config: PoolConfig,
+        mut pool_config: PoolConfig,
         current_keyspace: Option<VerifiedKeyspaceName>,
     ) -> Self {
+        // All connections of the node share its limit
+        pool_config.connection_config.request_limiter = pool_config
+            .request_limiter
+            .as_ref()
+            .map(|limiter| limiter.node_limiter());
+
         let (use_keyspace_request_sender, use_keyspace_request_receiver) = mpsc::channel(1);
+        let (close_request_sender, close_request_receiver) = mpsc::channel(1);
         let pool_updated_notify = Arc::new(Notify::new());
 
         let keepalive_interval = pool_config.keepalive_interval;
@@ -173,7 +189,9 @@ impl NodeConnectionPool {
         );
 
         let conns = refiller.get_shared_connections();
-        let (fut, refiller_handle) = refiller.run(use_keyspace_request_receiver).remote_handle();
+        let (fut, refiller_handle) = refiller
+            .run(use_keyspace_request_receiver, close_request_receiver)
+            .remote_handle();
         tokio::spawn(fut);
 
         let keepaliver_handle = if let Some(interval) = keepalive_interval {
@@ -194,6 +212,7 @@ impl NodeConnectionPool {
         Self {
             conns,
             use_keyspace_request_sender,
+            close_request_sender,
             _refiller_handle: refiller_handle,
             _keepaliver_handle: keepaliver_handle,
             pool_updated_notify,
@@ -298,6 +317,24 @@ impl NodeConnectionPool {
         response_receiver.await.unwrap() // NodePoolRefiller always responds
     }
 
+    // Closes all connections of the pool and stops refilling it.
+    // Returns the number of closed connections, 0 if the pool was already closed.
+    pub async fn close(&self) -> usize {
+        let (response_sender, response_receiver) = tokio::sync::oneshot::channel();
+
+        if self
+            .close_request_sender
+            .send(CloseRequest { response_sender })
+            .await
+            .is_err()
+        {
+            // The refiller stops after closing the pool
+            return 0;
+        }
+
+        response_receiver.await.unwrap_or(0)
+    }
+
     // Waits until the pool becomes initialized.
     // The pool is considered initialized either if the first connection has been
     // established or after first filling ends, whichever comes first.
@@ -360,6 +397,7 @@ impl NodeConnectionPool {
                     "No connections in the pool, pool is still being initialized",
                 ))))
             }
+            MaybePoolConnections::Closed => Err(QueryError::SessionShutDown),
         }
     }
 }
@@ -379,7 +417,7 @@ impl Keepaliver {
         match &*pool {
             Ready(NotSharded(conns)) => conns.clone(),
             Ready(Sharded { connections, .. }) => connections.iter().flatten().cloned().collect(),
-            Initializing => vec![],
+            Initializing | Closed => vec![],
             Broken(err) => {
                 debug!(
                     "Cannot send connection keepalives for node {} as there are \
@@ -403,6 +441,14 @@ impl Keepaliver {
         interval.tick().await;
 
         loop {
+            if let MaybePoolConnections::Closed = **self.connections.load() {
+                debug!(
+                    "Stopping connection keepalives for node {} as its pool was closed",
+                    self.node_address
+                );
+                return;
+            }
+
             let send_keepalives = self.send_keepalives();
 
             tokio::select! {
@@ -535,6 +581,12 @@ struct UseKeyspaceRequest {
     response_sender: tokio::sync::oneshot::Sender<Result<(), QueryError>>,
 }
 
+#[derive(Debug)]
+struct CloseRequest {
+    // Receives the number of closed connections
+    response_sender: tokio::sync::oneshot::Sender<usize>,
+}
+
 impl PoolRefiller {
     pub fn new(
         address: IpAddr,
@@ -581,6 +633,7 @@ impl PoolRefiller {
     pub async fn run(
         mut self,
         mut use_keyspace_request_receiver: mpsc::Receiver<UseKeyspaceRequest>,
+        mut close_request_receiver: mpsc::Receiver<CloseRequest>,
     ) {
         debug!("[{}] Started asynchronous pool worker", self.address);
 
@@ -627,6 +680,14 @@ impl PoolRefiller {
                         return;
                     }
                 }
+
+                Some(req) = close_request_receiver.recv() => {
+                    debug!("[{}] Requested closing the pool", self.address);
+                    let closed_connections = self.close();
+                    // Don't care if nobody waits for the result
+                    let _ = req.response_sender.send(closed_connections);
+                    return;
+                }
             }
             trace!(
                 pool_state = format!("{:?}", ShardedConnectionVectorWrapper(&self.conns)).as_str()
@@ -652,6 +713,23 @@ impl PoolRefiller {
         }
     }
 
+    // Drops all connections of the pool, including the ones being opened, and marks it as closed.
+    // Connections are closed once requests which still use them are done.
+    fn close(&mut self) -> usize {
+        let closed_connections = self.active_connection_count() + self.excess_connections.len();
+        self.conns.iter_mut().for_each(Vec::clear);
+        self.excess_connections.clear();
+        self.ready_connections = FuturesUnordered::new();
+        self.connection_errors = FuturesUnordered::new();
+
+        self.shared_conns
+            .store(Arc::new(MaybePoolConnections::Closed));
+        // Wake up anyone waiting for the pool to be initialized
+        self.pool_updated_notify.notify_waiters();
+
+        closed_connections
+    }
+
     fn is_filling(&self) -> bool {
         !self.ready_connections.is_empty()
     }
@@ -790,6 +868,17 @@ impl PoolRefiller {
                 let shard_id = shard_info.map_or(0, |s| s.shard as usize);
                 self.maybe_reshard(sharder);
 
+                // Open next connections with the negotiated protocol version,
+                // so that they don't have to be rejected by the node first
+                let protocol_version = connection.get_protocol_version();
+                if self.pool_config.connection_config.protocol_version != protocol_version {
+                    debug!(
+                        "[{}] Using negotiated protocol version: {}",
+                        self.address, protocol_version,
+                    );
+                    self.pool_config.connection_config.protocol_version = protocol_version;
+                }
+
                 // Update the shard-aware port
                 if self.shard_aware_port != connection.get_shard_aware_port() {
                     debug!(
@@ -1205,11 +1294,21 @@ async fn open_connection_to_shard_aware_port(
     let source_port_iter = sharder.iter_source_ports_for_shard(shard);
 
     for port in source_port_iter {
+        let version_to_try = connection_config
+            .negotiated_protocol_version
+            .first_to_try(connection_config.protocol_version);
         let connect_result =
             connection::open_connection(address, Some(port), connection_config.clone()).await;
 
         match connect_result {
             Err(err) if err.is_address_unavailable_for_use() => continue, // If we can't use this port, try the next one
+            // The server closed the connection after rejecting the protocol version,
+            // the next port is used to connect with the older version
+            Err(QueryError::DbError(DbError::ProtocolError, _))
+                if version_to_try.fallback().is_some() =>
+            {
+                continue
+            }
             result => return result,
         }
     }
@@ -1225,9 +1324,33 @@ async fn open_connection_to_shard_aware_port(
 mod tests {
     use super::open_connection_to_shard_aware_port;
     use crate::routing::{ShardCount, Sharder};
+    use crate::transport::connection::tests::serve_v4_only;
     use crate::transport::connection::ConnectionConfig;
+    use crate::transport::ProtocolVersion;
     use std::net::{SocketAddr, ToSocketAddrs};
 
+    // A node rejecting the protocol version closes the connection,
+    // the next source port of the shard is used to connect with the older version
+    #[tokio::test]
+    async fn shard_aware_port_protocol_fallback() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve_v4_only(listener, 1));
+
+        let connection_config = ConnectionConfig {
+            protocol_version: ProtocolVersion::V5,
+            ..Default::default()
+        };
+        let sharder = Sharder::new(ShardCount::new(3).unwrap(), 12);
+        let (connection, _) =
+            open_connection_to_shard_aware_port(addr, 0, sharder, &connection_config)
+                .await
+                .unwrap();
+
+        assert_eq!(connection.get_protocol_version(), ProtocolVersion::V4);
+        assert_eq!(server.await.unwrap(), vec![5, 4]);
+    }
+
     // Open many connections to a node
     // Port collision should occur
     // If they are not handled this test will most likely fail
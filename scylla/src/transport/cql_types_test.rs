@@ -1,17 +1,15 @@
 use crate as scylla;
 use crate::cql_to_rust::FromCqlVal;
 use crate::frame::response::result::CqlValue;
-use crate::frame::value::Counter;
 use crate::frame::value::Value;
+use crate::frame::value::{Counter, CqlDecimal, CqlVarint};
 use crate::frame::value::{Date, Time, Timestamp};
 use crate::macros::{FromUserType, IntoUserType};
 use crate::transport::session::IntoTypedRows;
 use crate::transport::session::Session;
 use crate::utils::test_utils::unique_keyspace_name;
 use crate::SessionBuilder;
-use bigdecimal::BigDecimal;
 use chrono::{Duration, NaiveDate};
-use num_bigint::BigInt;
 use std::cmp::PartialEq;
 use std::env;
 use std::fmt::Debug;
@@ -108,36 +106,48 @@ where
     }
 }
 
+const VARINT_TESTS: [&str; 10] = [
+    "0",
+    "1",
+    "127",
+    "128",
+    "129",
+    "-1",
+    "-128",
+    "-129",
+    "123456789012345678901234567890",
+    "-123456789012345678901234567890",
+];
+
 #[tokio::test]
 async fn test_varint() {
-    let tests = [
-        "0",
-        "1",
-        "127",
-        "128",
-        "129",
-        "-1",
-        "-128",
-        "-129",
-        "123456789012345678901234567890",
-        "-123456789012345678901234567890",
-    ];
+    run_tests::<CqlVarint>(&VARINT_TESTS, "varint").await;
+}
 
-    run_tests::<BigInt>(&tests, "varint").await;
+#[cfg(feature = "num-bigint-03")]
+#[tokio::test]
+async fn test_varint_num_bigint_03() {
+    run_tests::<num_bigint_03::BigInt>(&VARINT_TESTS, "varint").await;
 }
 
+const DECIMAL_TESTS: [&str; 6] = [
+    "4.2",
+    "0",
+    "1.999999999999999999999999999999999999999",
+    "997",
+    "123456789012345678901234567890.1234567890",
+    "-123456789012345678901234567890.1234567890",
+];
+
 #[tokio::test]
 async fn test_decimal() {
-    let tests = [
-        "4.2",
-        "0",
-        "1.999999999999999999999999999999999999999",
-        "997",
-        "123456789012345678901234567890.1234567890",
-        "-123456789012345678901234567890.1234567890",
-    ];
+    run_tests::<CqlDecimal>(&DECIMAL_TESTS, "decimal").await;
+}
 
-    run_tests::<BigDecimal>(&tests, "decimal").await;
+#[cfg(feature = "bigdecimal-04")]
+#[tokio::test]
+async fn test_decimal_bigdecimal_04() {
+    run_tests::<bigdecimal_04::BigDecimal>(&DECIMAL_TESTS, "decimal").await;
 }
 
 #[tokio::test]
@@ -1,13 +1,13 @@
 //! Iterators over rows returned by paged queries
 
+use std::collections::HashMap;
 use std::future::Future;
-use std::mem;
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use bytes::Bytes;
+use bytes::{Buf, Bytes};
 use futures::Stream;
 use std::result::Result;
 use thiserror::Error;
@@ -15,12 +15,13 @@ use tokio::sync::mpsc;
 
 use super::errors::QueryError;
 use super::execution_profile::ExecutionProfileInner;
-use crate::cql_to_rust::{FromRow, FromRowError};
+use crate::cql_to_rust::{FromRowError, FromRowWithSpecs, RowTypeCheckError};
 use crate::Session;
 
 use crate::frame::types::LegacyConsistency;
 use crate::frame::{
     response::{
+        deserialize::{DeserializationError, DeserializeRow, FrameSlice, TypedRowsIterator},
         result,
         result::{ColumnSpec, Row, Rows},
         NonErrorResponse,
@@ -37,6 +38,7 @@ use crate::transport::load_balancing::{LoadBalancingPolicy, Statement};
 use crate::transport::metrics::Metrics;
 use crate::transport::node::{Node, TimestampedAverage};
 use crate::transport::retry_policy::{QueryInfo, RetryDecision, RetrySession};
+use crate::transport::shutdown::RequestGuard;
 use tracing::{trace, trace_span, warn, Instrument};
 use uuid::Uuid;
 
@@ -59,15 +61,22 @@ const DEFAULT_ITER_PAGE_SIZE: i32 = 5000;
 /// Iterator over rows returned by paged queries\
 /// Allows to easily access rows without worrying about handling multiple pages
 pub struct RowIterator {
-    current_row_idx: usize,
     current_page: Rows,
+    // Serialized rows of the current page which were not returned yet,
+    // they are deserialized one by one
+    remaining_rows: Bytes,
+    remaining_rows_count: usize,
     page_receiver: mpsc::Receiver<Result<ReceivedPage, QueryError>>,
     tracing_ids: Vec<Uuid>,
+    // Sent by the server with the current page
+    custom_payload: Option<HashMap<String, Vec<u8>>>,
+    received_pages: usize,
 }
 
 struct ReceivedPage {
     pub rows: Rows,
     pub tracing_id: Option<Uuid>,
+    pub custom_payload: Option<HashMap<String, Vec<u8>>>,
 }
 
 pub(crate) struct PreparedIteratorConfig {
@@ -85,45 +94,150 @@ impl Stream for RowIterator {
     type Item = Result<Row, QueryError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut s = self.as_mut();
+        self.as_mut()
+            .poll_next_row::<Row>(cx)
+            .map_err(|err| match err {
+                NextDeserializedRowError::QueryError(err) => err,
+                NextDeserializedRowError::DeserializationError(err) => err.into(),
+            })
+    }
+}
 
-        if s.is_current_page_exhausted() {
-            match Pin::new(&mut s.page_receiver).poll_recv(cx) {
-                Poll::Ready(Some(Ok(received_page))) => {
-                    s.current_page = received_page.rows;
-                    s.current_row_idx = 0;
+impl RowIterator {
+    /// Converts this iterator into an iterator over rows parsed as given type\
+    /// Columns of every page are checked against `RowT` before its rows are parsed.
+    /// If they don't match, each row of the page is returned as
+    /// [`FromRowError::TypeCheckError`] listing the mismatched columns.
+    pub fn into_typed<RowT: FromRowWithSpecs>(self) -> TypedRowIterator<RowT> {
+        TypedRowIterator {
+            row_iterator: self,
+            mapped_page: 0,
+            column_mapping: None,
+        }
+    }
 
-                    if let Some(tracing_id) = received_page.tracing_id {
-                        s.tracing_ids.push(tracing_id);
-                    }
-                }
-                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
-                Poll::Ready(None) => return Poll::Ready(None),
-                Poll::Pending => return Poll::Pending,
-            }
+    /// Converts this iterator into an iterator over rows deserialized as the given type
+    /// directly from the received pages, without creating [`Row`]s first.\
+    /// Rows which were already returned by this iterator are skipped.
+    pub fn into_deserialized<RowT>(self) -> DeserializedRowIterator<RowT>
+    where
+        RowT: for<'frame> DeserializeRow<'frame>,
+    {
+        DeserializedRowIterator {
+            row_iterator: self,
+            phantom_data: Default::default(),
         }
+    }
 
-        let idx = s.current_row_idx;
-        if idx < s.current_page.rows.len() {
-            let row = mem::take(&mut s.current_page.rows[idx]);
-            s.current_row_idx += 1;
-            return Poll::Ready(Some(Ok(row)));
+    // Deserializes the next row of the current page, receiving the next pages if needed
+    fn poll_next_row<RowT>(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<RowT, NextDeserializedRowError>>>
+    where
+        RowT: for<'frame> DeserializeRow<'frame>,
+    {
+        match self.poll_remaining_rows(cx) {
+            Poll::Ready(Some(Ok(()))) => {}
+            Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err.into()))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
         }
 
-        // We probably got a zero-sized page
-        // Yield, but tell that we are ready
-        cx.waker().wake_by_ref();
-        Poll::Pending
+        let mut rows = TypedRowsIterator::<RowT>::new(
+            &self.current_page.metadata.col_specs,
+            self.remaining_rows_count,
+            FrameSlice::new(&self.remaining_rows),
+        );
+        let next_row = rows.next();
+        let consumed = self.remaining_rows.len() - rows.remaining_slice().as_slice().len();
+        self.remaining_rows_count = rows.len();
+        self.remaining_rows.advance(consumed);
+
+        Poll::Ready(next_row.map(|row| row.map_err(Into::into)))
     }
-}
 
-impl RowIterator {
-    /// Converts this iterator into an iterator over rows parsed as given type
-    pub fn into_typed<RowT: FromRow>(self) -> TypedRowIterator<RowT> {
-        TypedRowIterator {
-            row_iterator: self,
-            phantom_data: Default::default(),
+    // Deserializes all rows of the current page which were not returned yet,
+    // receiving the next pages if there are none
+    #[cfg(feature = "arrow")]
+    pub(crate) fn poll_next_page_rows(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Vec<Row>, NextDeserializedRowError>>> {
+        match self.poll_remaining_rows(cx) {
+            Poll::Ready(Some(Ok(()))) => {}
+            Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err.into()))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
         }
+
+        let rows = TypedRowsIterator::<Row>::new(
+            &self.current_page.metadata.col_specs,
+            self.remaining_rows_count,
+            FrameSlice::new(&self.remaining_rows),
+        )
+        .collect::<Result<Vec<_>, _>>();
+        self.remaining_rows_count = 0;
+        self.remaining_rows.clear();
+
+        Poll::Ready(Some(rows.map_err(Into::into)))
+    }
+
+    // Makes sure that there are rows remaining in the current page.
+    // Pages can be empty, so more than one of them may have to be received
+    fn poll_remaining_rows(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<(), QueryError>>> {
+        while self.remaining_rows_count == 0 {
+            match self.poll_next_page(cx) {
+                Poll::Ready(Some(Ok(()))) => {}
+                other => return other,
+            }
+        }
+        Poll::Ready(Some(Ok(())))
+    }
+
+    // Receives the next page and makes it the current one
+    fn poll_next_page(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<(), QueryError>>> {
+        let received_page = match self.page_receiver.poll_recv(cx) {
+            Poll::Ready(Some(Ok(received_page))) => received_page,
+            Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        self.set_current_page(received_page.rows);
+
+        if let Some(tracing_id) = received_page.tracing_id {
+            self.tracing_ids.push(tracing_id);
+        }
+        self.custom_payload = received_page.custom_payload;
+        self.received_pages += 1;
+
+        Poll::Ready(Some(Ok(())))
+    }
+
+    // Spawns a worker fetching pages in the background. It's counted as an in-flight request
+    // until it finishes; if the session is shut down before that, the worker is stopped
+    // and the iterator receives an error, instead of ending as if all pages were fetched.
+    fn spawn_worker(
+        worker_task: impl Future<Output = ()> + Send + 'static,
+        request: RequestGuard,
+        sender: mpsc::Sender<Result<ReceivedPage, QueryError>>,
+    ) {
+        tokio::task::spawn(async move {
+            let aborted = tokio::select! {
+                _ = worker_task => false,
+                _ = request.aborted() => true,
+            };
+            drop(request);
+
+            if aborted {
+                // Ignore sending error, the iterator was dropped
+                let _ = sender.send(Err(QueryError::SessionShutDown)).await;
+            }
+        });
     }
 
     pub(crate) async fn new_for_query(
@@ -132,11 +246,13 @@ impl RowIterator {
         execution_profile: Arc<ExecutionProfileInner>,
         cluster_data: Arc<ClusterData>,
         metrics: Arc<Metrics>,
+        request: RequestGuard,
     ) -> Result<RowIterator, QueryError> {
         if query.get_page_size().is_none() {
             query.set_page_size(DEFAULT_ITER_PAGE_SIZE);
         }
         let (sender, mut receiver) = mpsc::channel(1);
+        let abort_sender = sender.clone();
 
         let consistency = query
             .config
@@ -188,17 +304,18 @@ impl RowIterator {
             let _: PageSendAttemptedProof = worker.work(cluster_data).await;
         };
 
-        tokio::task::spawn(worker_task);
+        Self::spawn_worker(worker_task, request, abort_sender);
 
         // This unwrap is safe because:
         // - The future returned by worker.work sends at least one item
         //   to the channel (the PageSendAttemptedProof helps enforce this)
         // - That future is polled in a tokio::task which isn't going to be
-        //   cancelled
+        //   cancelled, unless the session is shut down and then an error is sent instead
         let pages_received = receiver.recv().await.unwrap()?;
 
         Ok(RowIterator {
-            current_row_idx: 0,
+            remaining_rows: pages_received.rows.raw_rows.as_bytes().clone(),
+            remaining_rows_count: pages_received.rows.raw_rows.rows_count(),
             current_page: pages_received.rows,
             page_receiver: receiver,
             tracing_ids: if let Some(tracing_id) = pages_received.tracing_id {
@@ -206,16 +323,20 @@ impl RowIterator {
             } else {
                 Vec::new()
             },
+            custom_payload: pages_received.custom_payload,
+            received_pages: 1,
         })
     }
 
     pub(crate) async fn new_for_prepared_statement(
         mut config: PreparedIteratorConfig,
+        request: RequestGuard,
     ) -> Result<RowIterator, QueryError> {
         if config.prepared.get_page_size().is_none() {
             config.prepared.set_page_size(DEFAULT_ITER_PAGE_SIZE);
         }
         let (sender, mut receiver) = mpsc::channel(1);
+        let abort_sender = sender.clone();
 
         let consistency = config
             .prepared
@@ -280,17 +401,18 @@ impl RowIterator {
             let _: PageSendAttemptedProof = worker.work(config.cluster_data).await;
         };
 
-        tokio::task::spawn(worker_task);
+        Self::spawn_worker(worker_task, request, abort_sender);
 
         // This unwrap is safe because:
         // - The future returned by worker.work sends at least one item
         //   to the channel (the PageSendAttemptedProof helps enforce this)
         // - That future is polled in a tokio::task which isn't going to be
-        //   cancelled
+        //   cancelled, unless the session is shut down and then an error is sent instead
         let pages_received = receiver.recv().await.unwrap()?;
 
         Ok(RowIterator {
-            current_row_idx: 0,
+            remaining_rows: pages_received.rows.raw_rows.as_bytes().clone(),
+            remaining_rows_count: pages_received.rows.raw_rows.rows_count(),
             current_page: pages_received.rows,
             page_receiver: receiver,
             tracing_ids: if let Some(tracing_id) = pages_received.tracing_id {
@@ -298,6 +420,8 @@ impl RowIterator {
             } else {
                 Vec::new()
             },
+            custom_payload: pages_received.custom_payload,
+            received_pages: 1,
         })
     }
 
@@ -306,13 +430,25 @@ impl RowIterator {
         &self.tracing_ids
     }
 
+    /// Returns the custom payload sent by the server with the page
+    /// which is currently being iterated over
+    pub fn get_current_custom_payload(&self) -> Option<&HashMap<String, Vec<u8>>> {
+        self.custom_payload.as_ref()
+    }
+
     /// Returns specification of row columns
     pub fn get_column_specs(&self) -> &[ColumnSpec] {
         &self.current_page.metadata.col_specs
     }
 
-    fn is_current_page_exhausted(&self) -> bool {
-        self.current_row_idx >= self.current_page.rows.len()
+    fn received_pages_count(&self) -> usize {
+        self.received_pages
+    }
+
+    fn set_current_page(&mut self, page: Rows) {
+        self.remaining_rows = page.raw_rows.as_bytes().clone();
+        self.remaining_rows_count = page.raw_rows.rows_count();
+        self.current_page = page;
     }
 }
 
@@ -487,9 +623,10 @@ where
                                 rows: Rows {
                                     metadata: Default::default(),
                                     rows_count: 0,
-                                    rows: Vec::new(),
+                                    raw_rows: Default::default(),
                                 },
                                 tracing_id: None,
+                                custom_payload: None,
                             }))
                             .await;
                         return proof;
@@ -542,6 +679,7 @@ where
                 Ok(NonErrorQueryResponse {
                     response: NonErrorResponse::Result(result::Result::Rows(mut rows)),
                     tracing_id,
+                    custom_payload,
                     ..
                 }) => {
                     let _ = self.metrics.log_query_latency(elapsed.as_millis() as u64);
@@ -550,7 +688,11 @@ where
 
                     self.paging_state = rows.metadata.paging_state.take();
 
-                    let received_page = ReceivedPage { rows, tracing_id };
+                    let received_page = ReceivedPage {
+                        rows,
+                        tracing_id,
+                        custom_payload,
+                    };
 
                     // Send next page to RowIterator
                     let (proof, res) = self.sender.send(Ok(received_page)).await;
@@ -575,6 +717,7 @@ where
                 Ok(NonErrorQueryResponse {
                     response: NonErrorResponse::Result(_),
                     tracing_id,
+                    custom_payload,
                     ..
                 }) => {
                     // We have most probably sent a modification statement (e.g. INSERT or UPDATE),
@@ -587,9 +730,10 @@ where
                             rows: Rows {
                                 metadata: Default::default(),
                                 rows_count: 0,
-                                rows: Vec::new(),
+                                raw_rows: Default::default(),
                             },
                             tracing_id,
+                            custom_payload,
                         }))
                         .await;
                     return Ok(proof);
@@ -689,17 +833,24 @@ where
 /// Iterator over rows returned by paged queries
 /// where each row is parsed as the given type\
 /// Returned by `RowIterator::into_typed`
-pub struct TypedRowIterator<RowT> {
+pub struct TypedRowIterator<RowT: FromRowWithSpecs> {
     row_iterator: RowIterator,
-    phantom_data: std::marker::PhantomData<RowT>,
+    // Number of the page whose columns were last mapped to RowT, and the result of that mapping
+    mapped_page: usize,
+    column_mapping: Option<Result<RowT::ColumnMapping, RowTypeCheckError>>,
 }
 
-impl<RowT> TypedRowIterator<RowT> {
+impl<RowT: FromRowWithSpecs> TypedRowIterator<RowT> {
     /// If tracing was enabled returns tracing ids of all finished page queries
     pub fn get_tracing_ids(&self) -> &[Uuid] {
         self.row_iterator.get_tracing_ids()
     }
 
+    /// Returns the custom payload sent by the server with the current page
+    pub fn get_current_custom_payload(&self) -> Option<&HashMap<String, Vec<u8>>> {
+        self.row_iterator.get_current_custom_payload()
+    }
+
     /// Returns specification of row columns
     pub fn get_column_specs(&self) -> &[ColumnSpec] {
         self.row_iterator.get_column_specs()
@@ -720,7 +871,7 @@ pub enum NextRowError {
 
 /// Fetching pages is asynchronous so `TypedRowIterator` does not implement the `Iterator` trait.\
 /// Instead it uses the asynchronous `Stream` trait
-impl<RowT: FromRow> Stream for TypedRowIterator<RowT> {
+impl<RowT: FromRowWithSpecs> Stream for TypedRowIterator<RowT> {
     type Item = Result<RowT, NextRowError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
@@ -732,8 +883,19 @@ impl<RowT: FromRow> Stream for TypedRowIterator<RowT> {
                 Poll::Pending => return Poll::Pending,
             };
 
+        // Columns are checked once per page, as they can change between pages
+        // if the schema is altered during the iteration
+        let page = s.row_iterator.received_pages_count();
+        if s.column_mapping.is_none() || s.mapped_page != page {
+            s.column_mapping = Some(RowT::map_columns(s.row_iterator.get_column_specs()));
+            s.mapped_page = page;
+        }
+
         let next_ready: Option<Self::Item> = match next_elem {
-            Some(Ok(next_row)) => Some(RowT::from_row(next_row).map_err(|e| e.into())),
+            Some(Ok(next_row)) => Some(match s.column_mapping.as_ref().unwrap() {
+                Ok(mapping) => RowT::from_row_with_mapping(next_row, mapping).map_err(|e| e.into()),
+                Err(err) => Err(FromRowError::from(err.clone()).into()),
+            }),
             Some(Err(e)) => Some(Err(e.into())),
             None => None,
         };
@@ -743,4 +905,57 @@ impl<RowT: FromRow> Stream for TypedRowIterator<RowT> {
 }
 
 // TypedRowIterator can be moved freely for any RowT so it's Unpin
-impl<RowT> Unpin for TypedRowIterator<RowT> {}
+impl<RowT: FromRowWithSpecs> Unpin for TypedRowIterator<RowT> {}
+
+/// Iterator over rows returned by paged queries
+/// where each row is deserialized directly from the received page\
+/// Returned by `RowIterator::into_deserialized`
+pub struct DeserializedRowIterator<RowT> {
+    row_iterator: RowIterator,
+    phantom_data: std::marker::PhantomData<RowT>,
+}
+
+impl<RowT> DeserializedRowIterator<RowT> {
+    /// If tracing was enabled returns tracing ids of all finished page queries
+    pub fn get_tracing_ids(&self) -> &[Uuid] {
+        self.row_iterator.get_tracing_ids()
+    }
+
+    /// Returns the custom payload sent by the server with the current page
+    pub fn get_current_custom_payload(&self) -> Option<&HashMap<String, Vec<u8>>> {
+        self.row_iterator.get_current_custom_payload()
+    }
+
+    /// Returns specification of row columns
+    pub fn get_column_specs(&self) -> &[ColumnSpec] {
+        self.row_iterator.get_column_specs()
+    }
+}
+
+/// Couldn't get next deserialized row from the iterator
+#[derive(Error, Debug)]
+pub enum NextDeserializedRowError {
+    /// Query to fetch next page has failed
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// Deserializing the row as the given type failed
+    #[error(transparent)]
+    DeserializationError(#[from] DeserializationError),
+}
+
+/// Fetching pages is asynchronous so `DeserializedRowIterator` does not implement the `Iterator` trait.\
+/// Instead it uses the asynchronous `Stream` trait
+impl<RowT> Stream for DeserializedRowIterator<RowT>
+where
+    RowT: for<'frame> DeserializeRow<'frame>,
+{
+    type Item = Result<RowT, NextDeserializedRowError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.as_mut().row_iterator.poll_next_row(cx)
+    }
+}
+
+// DeserializedRowIterator can be moved freely for any RowT so it's Unpin
+impl<RowT> Unpin for DeserializedRowIterator<RowT> {}
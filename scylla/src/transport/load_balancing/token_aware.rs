@@ -198,6 +198,7 @@ mod tests {
 
     use crate::load_balancing::tests::DumbPolicy;
     use crate::load_balancing::RoundRobinPolicy;
+    use crate::routing::TokenRange;
     use crate::transport::load_balancing::tests;
     use crate::transport::topology::Keyspace;
     use crate::transport::topology::Metadata;
@@ -299,6 +300,62 @@ mod tests {
         assert_eq!(plan.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_token_range_replicas() {
+        let cluster = tests::mock_cluster_data_for_token_aware_tests();
+        let range = |start, end| TokenRange::new(Token { value: start }, Token { value: end });
+        let node_ids = |nodes: &[Arc<Node>]| {
+            nodes
+                .iter()
+                .map(|node| node.address.port())
+                .collect::<Vec<_>>()
+        };
+
+        let ranges = cluster
+            .get_token_ranges()
+            .map(|(range, node)| (range, node.address.port()))
+            .collect::<Vec<_>>();
+        let expected_ranges = vec![
+            (range(500, 50), 2),
+            (range(50, 100), 1),
+            (range(100, 150), 2),
+            (range(150, 200), 3),
+            (range(200, 250), 1),
+            (range(250, 300), 2),
+            (range(300, 400), 3),
+            (range(400, 500), 1),
+        ];
+        assert_eq!(ranges, expected_ranges);
+
+        let replicas = cluster
+            .get_token_range_replicas("keyspace_with_simple_strategy_replication_factor_2")
+            .map(|(range, replicas)| (range, node_ids(&replicas)))
+            .collect::<Vec<_>>();
+        let expected_replicas = vec![
+            (range(500, 50), vec![2, 1]),
+            (range(50, 100), vec![1, 2]),
+            (range(100, 150), vec![2, 3]),
+            (range(150, 200), vec![3, 1]),
+            (range(200, 250), vec![1, 2]),
+            (range(250, 300), vec![2, 3]),
+            (range(300, 400), vec![3, 1]),
+            (range(400, 500), vec![1, 2]),
+        ];
+        assert_eq!(replicas, expected_replicas);
+
+        // Every token belongs to exactly one range
+        for value in [i64::MIN, 0, 50, 51, 450, 500, 501, i64::MAX] {
+            let token = Token { value };
+            assert_eq!(
+                ranges
+                    .iter()
+                    .filter(|(range, _)| range.contains(token))
+                    .count(),
+                1
+            );
+        }
+    }
+
     #[tokio::test]
     async fn token_aware_policy_optimises_lwt_routing() {
         let keyspace = Some("keyspace_with_simple_strategy_replication_factor_3");
@@ -35,6 +35,9 @@ pub struct Metrics {
     errors_iter_num: AtomicU64,
     queries_iter_num: AtomicU64,
     retries_num: AtomicU64,
+    queued_requests: AtomicU64,
+    rejected_requests_num: AtomicU64,
+    queue_timeouts_num: AtomicU64,
     histogram: Arc<Mutex<Histogram>>,
 }
 
@@ -46,6 +49,9 @@ impl Metrics {
             errors_iter_num: AtomicU64::new(0),
             queries_iter_num: AtomicU64::new(0),
             retries_num: AtomicU64::new(0),
+            queued_requests: AtomicU64::new(0),
+            rejected_requests_num: AtomicU64::new(0),
+            queue_timeouts_num: AtomicU64::new(0),
             histogram: Arc::new(Mutex::new(Histogram::new())),
         }
     }
@@ -76,6 +82,26 @@ impl Metrics {
         self.retries_num.fetch_add(1, ORDER_TYPE);
     }
 
+    /// Increments the number of requests waiting for the concurrency limits
+    pub(crate) fn inc_queued_requests(&self) {
+        self.queued_requests.fetch_add(1, ORDER_TYPE);
+    }
+
+    /// Decrements the number of requests waiting for the concurrency limits
+    pub(crate) fn dec_queued_requests(&self) {
+        self.queued_requests.fetch_sub(1, ORDER_TYPE);
+    }
+
+    /// Increments counter of requests rejected because the request queue was full
+    pub(crate) fn inc_rejected_requests(&self) {
+        self.rejected_requests_num.fetch_add(1, ORDER_TYPE);
+    }
+
+    /// Increments counter of requests which waited in the request queue for too long
+    pub(crate) fn inc_queue_timeouts(&self) {
+        self.queue_timeouts_num.fetch_add(1, ORDER_TYPE);
+    }
+
     /// Saves to histogram latency of completing single query.
     /// For paged queries it should log latency for every page.
     ///
@@ -127,4 +153,19 @@ impl Metrics {
     pub fn get_retries_num(&self) -> u64 {
         self.retries_num.load(ORDER_TYPE)
     }
+
+    /// Returns the number of requests currently waiting for the concurrency limits
+    pub fn get_queued_requests(&self) -> u64 {
+        self.queued_requests.load(ORDER_TYPE)
+    }
+
+    /// Returns counter of requests rejected because the request queue was full
+    pub fn get_rejected_requests_num(&self) -> u64 {
+        self.rejected_requests_num.load(ORDER_TYPE)
+    }
+
+    /// Returns counter of requests which waited in the request queue for too long
+    pub fn get_queue_timeouts_num(&self) -> u64 {
+        self.queue_timeouts_num.load(ORDER_TYPE)
+    }
 }
@@ -1,5 +1,7 @@
+pub mod bulk_writer;
 pub(crate) mod caching_session;
 mod cluster;
+pub(crate) mod concurrency;
 pub(crate) mod connection;
 mod connection_pool;
 pub mod downgrading_consistency_retry_policy;
@@ -11,13 +13,17 @@ pub(crate) mod metrics;
 mod node;
 pub mod partitioner;
 pub mod query_result;
+#[cfg(feature = "arrow")]
+pub mod record_batch;
 pub mod retry_policy;
 pub mod session;
 pub mod session_builder;
+pub(crate) mod shutdown;
 pub mod speculative_execution;
+pub mod table_scan;
 pub mod topology;
 
-pub use crate::frame::{Authenticator, Compression};
+pub use crate::frame::{Authenticator, Compression, ProtocolVersion};
 pub use execution_profile::ExecutionProfile;
 pub use scylla_cql::errors;
 
@@ -152,6 +152,14 @@ impl Node {
         self.get_pool()?.get_working_connections()
     }
 
+    // Closes the connections of the node's pool, returns how many were closed
+    pub(crate) async fn close_pool(&self) -> usize {
+        match &self.pool {
+            Some(pool) => pool.close().await,
+            None => 0,
+        }
+    }
+
     pub(crate) async fn wait_until_pool_initialized(&self) {
         if let Some(pool) = &self.pool {
             pool.wait_until_initialized().await;
@@ -1,8 +1,14 @@
-use crate::frame::response::cql_to_rust::{FromRow, FromRowError};
+#[cfg(feature = "serde")]
+use crate::frame::response::cql_serde::{self, SerdeDeserializationError};
+use crate::frame::response::cql_to_rust::{FromRowError, FromRowWithSpecs, RowTypeCheckError};
+use crate::frame::response::deserialize::{
+    DeserializationError, DeserializeRow, LazyRow, RawRows, TypedRowsIterator,
+};
 use crate::frame::response::result::ColumnSpec;
 use crate::frame::response::result::Row;
-use crate::transport::session::{IntoTypedRows, TypedRowIter};
+use crate::transport::session::TypedRowIter;
 use bytes::Bytes;
+use std::collections::HashMap;
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -12,12 +18,22 @@ use uuid::Uuid;
 pub struct QueryResult {
     /// Rows returned by the database.\
     /// Queries like `SELECT` will have `Some(Vec)`, while queries like `INSERT` will have `None`.\
-    /// Can contain an empty Vec.
+    /// Can contain an empty Vec.\
+    /// Also `None` if the statement had lazy deserialization enabled, then the rows
+    /// are deserialized from [`raw_rows`](QueryResult::raw_rows) by accessors like
+    /// [`rows()`](QueryResult::rows), or can be read directly through
+    /// [`lazy_rows`](QueryResult::lazy_rows) or [`deserialize_rows`](QueryResult::deserialize_rows).
     pub rows: Option<Vec<Row>>,
+    /// Rows returned by the database in their serialized form.\
+    /// `Some` only if the statement had lazy deserialization enabled and the query returned rows,
+    /// otherwise the rows are in [`rows`](QueryResult::rows) and their bytes are not kept.
+    pub raw_rows: Option<RawRows>,
     /// Warnings returned by the database
     pub warnings: Vec<String>,
     /// CQL Tracing uuid - can only be Some if tracing is enabled for this query
     pub tracing_id: Option<Uuid>,
+    /// Custom payload returned by the server, used by server-side extensions
+    pub custom_payload: Option<HashMap<String, Vec<u8>>>,
     /// Paging state returned from the server
     pub paging_state: Option<Bytes>,
     /// Column specification returned from the server
@@ -25,59 +41,135 @@ pub struct QueryResult {
 }
 
 impl QueryResult {
-    /// Returns the number of received rows.\
+    /// Returns the number of received rows, without deserializing them.\
     /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows).
     pub fn rows_num(&self) -> Result<usize, RowsExpectedError> {
-        match &self.rows {
-            Some(rows) => Ok(rows.len()),
-            None => Err(RowsExpectedError),
+        match (&self.raw_rows, &self.rows) {
+            (Some(raw_rows), _) => Ok(raw_rows.rows_count()),
+            (None, Some(rows)) => Ok(rows.len()),
+            (None, None) => Err(RowsExpectedError),
         }
     }
 
     /// Returns the received rows when present.\
     /// If `QueryResult.rows` is `None`, which means that this query is not supposed to return rows (e.g `INSERT`), returns an error.\
-    /// Can return an empty `Vec`.
-    pub fn rows(self) -> Result<Vec<Row>, RowsExpectedError> {
-        match self.rows {
-            Some(rows) => Ok(rows),
-            None => Err(RowsExpectedError),
-        }
+    /// Can return an empty `Vec`.\
+    /// Rows of a statement with lazy deserialization are deserialized here, which can fail.
+    pub fn rows(mut self) -> Result<Vec<Row>, RowsError> {
+        Ok(self.take_rows()?.ok_or(RowsExpectedError)?)
     }
 
     /// Returns the received rows parsed as the given type.\
-    /// Equal to `rows()?.into_typed()`.\
-    /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows).
-    pub fn rows_typed<RowT: FromRow>(self) -> Result<TypedRowIter<RowT>, RowsExpectedError> {
-        Ok(self.rows()?.into_typed())
+    /// Equal to `rows()?.into_typed()`, but first checks the types of the columns against `RowT`.\
+    /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows),
+    /// or when the columns can't be parsed as `RowT`.
+    pub fn rows_typed<RowT: FromRowWithSpecs>(
+        mut self,
+    ) -> Result<TypedRowIter<RowT>, RowsTypedError> {
+        let column_mapping = match RowT::map_columns(&self.col_specs) {
+            // Without rows there is nothing to convert, so the columns don't matter
+            Err(err) if self.has_rows() => return Err(err.into()),
+            column_mapping => column_mapping,
+        };
+        let rows = self.take_rows()?.ok_or(RowsExpectedError)?;
+        Ok(TypedRowIter::with_column_mapping(rows, column_mapping))
+    }
+
+    /// Returns an iterator which deserializes the received rows as the given type
+    /// directly from the bytes of the response, without creating [`Row`]s first.\
+    /// `RowT` can borrow from the result, e.g. `(&str, &[u8])` avoids copying any values.\
+    /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows),
+    /// or when the statement didn't have lazy deserialization enabled, as then the response bytes are not kept.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use scylla::query::Query;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// let mut query = Query::new("SELECT a, b FROM ks.tab");
+    /// query.set_lazy_deserialization(true);
+    ///
+    /// let result = session.query(query, &[]).await?;
+    /// for row in result.deserialize_rows::<(i32, &str)>()? {
+    ///     let (a, b) = row?;
+    ///     println!("a, b: {}, {}", a, b);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn deserialize_rows<'frame, RowT: DeserializeRow<'frame>>(
+        &'frame self,
+    ) -> Result<TypedRowsIterator<'frame, RowT>, DeserializeRowsError> {
+        match (&self.raw_rows, &self.rows) {
+            (Some(raw_rows), _) => Ok(raw_rows.rows(&self.col_specs)),
+            (None, Some(_)) => Err(DeserializeRowsError::RowsAlreadyDeserialized),
+            (None, None) => Err(RowsExpectedError.into()),
+        }
+    }
+
+    /// Returns an iterator over the received rows, which deserializes
+    /// only the rows and columns that are actually accessed.\
+    /// This is the way to read rows of a statement with lazy deserialization enabled.\
+    /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows),
+    /// or when the statement didn't have lazy deserialization enabled.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use scylla::query::Query;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// let mut query = Query::new("SELECT * FROM ks.tab");
+    /// query.set_lazy_deserialization(true);
+    ///
+    /// let result = session.query(query, &[]).await?;
+    /// println!("Received {} rows", result.rows_num()?);
+    /// for row in result.lazy_rows()? {
+    ///     // Only the column `b` is deserialized
+    ///     let b: &str = row?.column_by_name("b")?;
+    ///     println!("b: {}", b);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lazy_rows(&self) -> Result<TypedRowsIterator<'_, LazyRow<'_>>, DeserializeRowsError> {
+        self.deserialize_rows()
     }
 
     /// Returns `Ok` for a result of a query that shouldn't contain any rows.\
     /// Will return `Ok` for `INSERT` result, but a `SELECT` result, even an empty one, will cause an error.\
     /// Opposite of [`rows()`](QueryResult::rows).
     pub fn result_not_rows(&self) -> Result<(), RowsNotExpectedError> {
-        match self.rows {
-            Some(_) => Err(RowsNotExpectedError),
-            None => Ok(()),
+        if self.rows.is_some() || self.raw_rows.is_some() {
+            Err(RowsNotExpectedError)
+        } else {
+            Ok(())
         }
     }
 
-    /// Returns rows when `QueryResult.rows` is `Some`, otherwise an empty Vec.\
-    /// Equal to `rows().unwrap_or_default()`.
-    pub fn rows_or_empty(self) -> Vec<Row> {
-        self.rows.unwrap_or_default()
+    /// Returns the received rows, or an empty Vec if the query isn't of a type that could return rows.\
+    /// Fails only when rows of a statement with lazy deserialization can't be deserialized.
+    pub fn rows_or_empty(mut self) -> Result<Vec<Row>, DeserializationError> {
+        Ok(self.take_rows()?.unwrap_or_default())
     }
 
     /// Returns rows parsed as the given type.\
-    /// When `QueryResult.rows` is `None`, returns 0 rows.\
-    /// Equal to `rows_or_empty().into_typed::<RowT>()`.
-    pub fn rows_typed_or_empty<RowT: FromRow>(self) -> TypedRowIter<RowT> {
-        self.rows_or_empty().into_typed::<RowT>()
+    /// When the query isn't of a type that could return rows, returns 0 rows.\
+    /// Equal to `rows_or_empty()?.into_typed::<RowT>()`, except that if the columns
+    /// can't be parsed as `RowT`, each row is returned as [`FromRowError::TypeCheckError`].
+    pub fn rows_typed_or_empty<RowT: FromRowWithSpecs>(
+        mut self,
+    ) -> Result<TypedRowIter<RowT>, DeserializationError> {
+        let column_mapping = RowT::map_columns(&self.col_specs);
+        let rows = self.take_rows()?.unwrap_or_default();
+        Ok(TypedRowIter::with_column_mapping(rows, column_mapping))
     }
 
     /// Returns first row from the received rows.\
     /// When the first row is not available, returns an error.
-    pub fn first_row(self) -> Result<Row, FirstRowError> {
-        match self.maybe_first_row()? {
+    pub fn first_row(mut self) -> Result<Row, FirstRowError> {
+        match self.take_first_row()? {
             Some(row) => Ok(row),
             None => Err(FirstRowError::RowsEmpty),
         }
@@ -85,43 +177,111 @@ impl QueryResult {
 
     /// Returns first row from the received rows parsed as the given type.\
     /// When the first row is not available, returns an error.
-    pub fn first_row_typed<RowT: FromRow>(self) -> Result<RowT, FirstRowTypedError> {
-        Ok(self.first_row()?.into_typed()?)
+    pub fn first_row_typed<RowT: FromRowWithSpecs>(mut self) -> Result<RowT, FirstRowTypedError> {
+        match self.take_first_row().map_err(FirstRowError::from)? {
+            Some(row) => Ok(row.into_typed_with_specs(&self.col_specs)?),
+            None => Err(FirstRowTypedError::RowsEmpty),
+        }
     }
 
     /// Returns `Option<RowT>` containing the first of a result.\
     /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows).
-    pub fn maybe_first_row(self) -> Result<Option<Row>, RowsExpectedError> {
-        Ok(self.rows()?.into_iter().next())
+    pub fn maybe_first_row(mut self) -> Result<Option<Row>, RowsError> {
+        self.take_first_row()
     }
 
     /// Returns `Option<RowT>` containing the first of a result.\
     /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows).
-    pub fn maybe_first_row_typed<RowT: FromRow>(
-        self,
+    pub fn maybe_first_row_typed<RowT: FromRowWithSpecs>(
+        mut self,
     ) -> Result<Option<RowT>, MaybeFirstRowTypedError> {
-        match self.maybe_first_row()? {
-            Some(row) => Ok(Some(row.into_typed::<RowT>()?)),
+        match self.take_first_row()? {
+            Some(row) => Ok(Some(row.into_typed_with_specs::<RowT>(&self.col_specs)?)),
             None => Ok(None),
         }
     }
 
     /// Returns the only received row.\
     /// Fails if the result is anything else than a single row.\
-    pub fn single_row(self) -> Result<Row, SingleRowError> {
-        let rows: Vec<Row> = self.rows()?;
+    pub fn single_row(mut self) -> Result<Row, SingleRowError> {
+        self.take_single_row()
+    }
+
+    /// Returns the only received row parsed as the given type.\
+    /// Fails if the result is anything else than a single row.\
+    pub fn single_row_typed<RowT: FromRowWithSpecs>(mut self) -> Result<RowT, SingleRowTypedError> {
+        Ok(self
+            .take_single_row()?
+            .into_typed_with_specs::<RowT>(&self.col_specs)?)
+    }
+
+    fn has_rows(&self) -> bool {
+        self.rows.is_some() || self.raw_rows.is_some()
+    }
+
+    // Takes the first of the received rows, only this row is deserialized
+    // if the statement had lazy deserialization enabled
+    fn take_first_row(&mut self) -> Result<Option<Row>, RowsError> {
+        match (self.rows.take(), &self.raw_rows) {
+            (Some(rows), _) => Ok(rows.into_iter().next()),
+            (None, Some(raw_rows)) => Ok(raw_rows.rows(&self.col_specs).next().transpose()?),
+            (None, None) => Err(RowsExpectedError.into()),
+        }
+    }
 
-        if rows.len() != 1 {
-            return Err(SingleRowError::BadNumberOfRows(rows.len()));
+    fn take_single_row(&mut self) -> Result<Row, SingleRowError> {
+        let rows_num = self.rows_num()?;
+        if rows_num != 1 {
+            return Err(SingleRowError::BadNumberOfRows(rows_num));
         }
 
-        Ok(rows.into_iter().next().unwrap())
+        Ok(self.take_first_row()?.unwrap())
     }
 
-    /// Returns the only received row parsed as the given type.\
-    /// Fails if the result is anything else than a single row.\
-    pub fn single_row_typed<RowT: FromRow>(self) -> Result<RowT, SingleRowTypedError> {
-        Ok(self.single_row()?.into_typed::<RowT>()?)
+    // Takes the received rows, deserializing them from `raw_rows`
+    // if the statement had lazy deserialization enabled
+    fn take_rows(&mut self) -> Result<Option<Vec<Row>>, DeserializationError> {
+        match (self.rows.take(), &self.raw_rows) {
+            (Some(rows), _) => Ok(Some(rows)),
+            (None, Some(raw_rows)) => raw_rows
+                .rows(&self.col_specs)
+                .collect::<Result<_, _>>()
+                .map(Some),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Returns the received rows deserialized using serde as the given type,
+    /// which can be any type implementing [`Deserialize`](serde::Deserialize).\
+    /// Structs are read using column names, tuples are read in the order of columns.\
+    /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// #[derive(serde::Deserialize)]
+    /// struct MyRow {
+    ///     a: i32,
+    ///     b: Option<String>,
+    /// }
+    ///
+    /// let rows: Vec<MyRow> = session
+    ///     .query("SELECT a, b FROM ks.tab", &[])
+    ///     .await?
+    ///     .rows_serde::<MyRow>()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn rows_serde<T: serde::de::DeserializeOwned>(mut self) -> Result<Vec<T>, RowsSerdeError> {
+        let rows = self.take_rows()?.ok_or(RowsExpectedError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| cql_serde::from_row(row, &self.col_specs))
+            .collect::<Result<_, _>>()?)
     }
 
     /// Returns a column specification for a column with given name, or None if not found
@@ -133,20 +293,81 @@ impl QueryResult {
     }
 
     /// This function is used to merge results of multiple paged queries into one.\
-    /// other is the result of a new paged query.\
-    /// It is merged with current result kept in self.\
-    pub(crate) fn merge_with_next_page_res(&mut self, other: QueryResult) {
-        if let Some(other_rows) = other.rows {
-            match &mut self.rows {
-                Some(self_rows) => self_rows.extend(other_rows),
-                None => self.rows = Some(other_rows),
+    /// `pages` are the results of consecutive pages of the query.\
+    /// Rows are concatenated, the rest is taken from the last page.\
+    /// All pages come from the same statement, so either all of them have their rows
+    /// deserialized, or all of them have lazy deserialization enabled.
+    pub(crate) fn merge_pages(pages: Vec<QueryResult>) -> QueryResult {
+        let mut result = QueryResult::default();
+        let mut raw_pages: Vec<RawRows> = Vec::new();
+
+        for page in pages {
+            match (page.rows, page.raw_rows) {
+                (Some(page_rows), _) => match &mut result.rows {
+                    Some(rows) => rows.extend(page_rows),
+                    None => result.rows = Some(page_rows),
+                },
+                (None, Some(page_raw_rows)) => raw_pages.push(page_raw_rows),
+                (None, None) => {}
             }
-        };
 
-        self.warnings.extend(other.warnings);
-        self.tracing_id = other.tracing_id;
-        self.paging_state = other.paging_state;
-        self.col_specs = other.col_specs;
+            result.warnings.extend(page.warnings);
+            result.tracing_id = page.tracing_id;
+            result.custom_payload = page.custom_payload;
+            result.paging_state = page.paging_state;
+            result.col_specs = page.col_specs;
+        }
+
+        // Bytes of deserialized rows aren't kept, only lazy pages have to be concatenated
+        if result.rows.is_none() && !raw_pages.is_empty() {
+            result.raw_rows = Some(RawRows::concat(&raw_pages));
+        }
+        result
+    }
+}
+
+/// Serialized as a struct with fields `rows`, `warnings` and `tracing_id`.\
+/// `rows` is a sequence of maps from column names to values,
+/// or none if the query isn't of a type that could return rows.
+#[cfg(feature = "serde")]
+impl serde::Serialize for QueryResult {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut result = serializer.serialize_struct("QueryResult", 3)?;
+        result.serialize_field("rows", &SerializableRows(self))?;
+        result.serialize_field("warnings", &self.warnings)?;
+        result.serialize_field(
+            "tracing_id",
+            &self.tracing_id.map(|tracing_id| tracing_id.to_string()),
+        )?;
+        result.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+struct SerializableRows<'a>(&'a QueryResult);
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SerializableRows<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeSeq};
+
+        let col_specs = &self.0.col_specs;
+        match (&self.0.rows, &self.0.raw_rows) {
+            (Some(rows), _) => {
+                serializer.collect_seq(rows.iter().map(|row| row.with_names(col_specs)))
+            }
+            (None, Some(raw_rows)) => {
+                let mut seq = serializer.serialize_seq(Some(raw_rows.rows_count()))?;
+                for row in raw_rows.rows::<Row>(col_specs) {
+                    let row = row.map_err(S::Error::custom)?;
+                    seq.serialize_element(&row.with_names(col_specs))?;
+                }
+                seq.end()
+            }
+            (None, None) => serializer.serialize_none(),
+        }
     }
 }
 
@@ -176,7 +397,41 @@ pub struct RowsExpectedError;
 )]
 pub struct RowsNotExpectedError;
 
+/// [`QueryResult::deserialize_rows()`](QueryResult::deserialize_rows) or [`QueryResult::lazy_rows()`](QueryResult::lazy_rows) failed
 #[derive(Debug, Error, PartialEq, Eq)]
+pub enum DeserializeRowsError {
+    /// The function was called on a bad QueryResult.\
+    /// Expected `QueryResult.rows` to be `Some`, but it was `None`.\
+    /// `QueryResult.rows` is `Some` for queries that can return rows (e.g `SELECT`).\
+    /// It is `None` for queries that can't return rows (e.g `INSERT`).
+    #[error(transparent)]
+    RowsExpected(#[from] RowsExpectedError),
+
+    /// The statement didn't have lazy deserialization enabled,
+    /// so the rows were already deserialized into `QueryResult.rows` and their bytes weren't kept
+    #[error(
+        "Rows were already deserialized into QueryResult.rows, \
+         enable lazy deserialization on the statement to read them from the response bytes"
+    )]
+    RowsAlreadyDeserialized,
+}
+
+/// [`QueryResult::rows()`](QueryResult::rows) or [`QueryResult::maybe_first_row()`](QueryResult::maybe_first_row) failed
+#[derive(Debug, Error)]
+pub enum RowsError {
+    /// The function was called on a bad QueryResult.\
+    /// Expected `QueryResult.rows` to be `Some`, but it was `None`.\
+    /// `QueryResult.rows` is `Some` for queries that can return rows (e.g `SELECT`).\
+    /// It is `None` for queries that can't return rows (e.g `INSERT`).
+    #[error(transparent)]
+    RowsExpected(#[from] RowsExpectedError),
+
+    /// Rows of a statement with lazy deserialization could not be deserialized
+    #[error(transparent)]
+    RowsDeserialization(#[from] DeserializationError),
+}
+
+#[derive(Debug, Error)]
 pub enum FirstRowError {
     /// [`QueryResult::first_row()`](QueryResult::first_row) called on a bad QueryResult.\
     /// Expected `QueryResult.rows` to be `Some`, but it was `None`.\
@@ -185,12 +440,55 @@ pub enum FirstRowError {
     #[error(transparent)]
     RowsExpected(#[from] RowsExpectedError),
 
+    /// Rows of a statement with lazy deserialization could not be deserialized
+    #[error(transparent)]
+    RowsDeserialization(#[from] DeserializationError),
+
     /// Rows in `QueryResult` are empty
     #[error("Rows in QueryResult are empty")]
     RowsEmpty,
 }
 
-#[derive(Debug, Error, PartialEq, Eq)]
+/// [`QueryResult::rows_typed()`](QueryResult::rows_typed) failed
+#[derive(Debug, Error)]
+pub enum RowsTypedError {
+    /// [`QueryResult::rows_typed()`](QueryResult::rows_typed) called on a bad QueryResult.\
+    /// Expected `QueryResult.rows` to be `Some`, but it was `None`.\
+    /// `QueryResult.rows` is `Some` for queries that can return rows (e.g `SELECT`).\
+    /// It is `None` for queries that can't return rows (e.g `INSERT`).
+    #[error(transparent)]
+    RowsExpected(#[from] RowsExpectedError),
+
+    /// Rows of a statement with lazy deserialization could not be deserialized
+    #[error(transparent)]
+    RowsDeserialization(#[from] DeserializationError),
+
+    /// Columns of the result can't be parsed as the given type
+    #[error(transparent)]
+    TypeCheckError(#[from] RowTypeCheckError),
+}
+
+/// [`QueryResult::rows_serde()`](QueryResult::rows_serde) failed
+#[cfg(feature = "serde")]
+#[derive(Debug, Error)]
+pub enum RowsSerdeError {
+    /// [`QueryResult::rows_serde()`](QueryResult::rows_serde) called on a bad QueryResult.\
+    /// Expected `QueryResult.rows` to be `Some`, but it was `None`.\
+    /// `QueryResult.rows` is `Some` for queries that can return rows (e.g `SELECT`).\
+    /// It is `None` for queries that can't return rows (e.g `INSERT`).
+    #[error(transparent)]
+    RowsExpected(#[from] RowsExpectedError),
+
+    /// Received rows could not be parsed
+    #[error(transparent)]
+    RowsDeserialization(#[from] DeserializationError),
+
+    /// A row could not be deserialized as the given type
+    #[error(transparent)]
+    SerdeDeserialization(#[from] SerdeDeserializationError),
+}
+
+#[derive(Debug, Error)]
 pub enum FirstRowTypedError {
     /// [`QueryResult::first_row_typed()`](QueryResult::first_row_typed) called on a bad QueryResult.\
     /// Expected `QueryResult.rows` to be `Some`, but it was `None`.\
@@ -199,6 +497,10 @@ pub enum FirstRowTypedError {
     #[error(transparent)]
     RowsExpected(#[from] RowsExpectedError),
 
+    /// Rows of a statement with lazy deserialization could not be deserialized
+    #[error(transparent)]
+    RowsDeserialization(#[from] DeserializationError),
+
     /// Rows in `QueryResult` are empty
     #[error("Rows in QueryResult are empty")]
     RowsEmpty,
@@ -208,7 +510,7 @@ pub enum FirstRowTypedError {
     FromRowError(#[from] FromRowError),
 }
 
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Error)]
 pub enum MaybeFirstRowTypedError {
     /// [`QueryResult::maybe_first_row_typed()`](QueryResult::maybe_first_row_typed) called on a bad QueryResult.\
     /// Expected `QueryResult.rows` to be `Some`, but it was `None`.
@@ -217,12 +519,16 @@ pub enum MaybeFirstRowTypedError {
     #[error(transparent)]
     RowsExpected(#[from] RowsExpectedError),
 
+    /// Rows of a statement with lazy deserialization could not be deserialized
+    #[error(transparent)]
+    RowsDeserialization(#[from] DeserializationError),
+
     /// Parsing row as the given type failed
     #[error(transparent)]
     FromRowError(#[from] FromRowError),
 }
 
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Error)]
 pub enum SingleRowError {
     /// [`QueryResult::single_row()`](QueryResult::single_row) called on a bad QueryResult.\
     /// Expected `QueryResult.rows` to be `Some`, but it was `None`.\
@@ -231,12 +537,16 @@ pub enum SingleRowError {
     #[error(transparent)]
     RowsExpected(#[from] RowsExpectedError),
 
+    /// Rows of a statement with lazy deserialization could not be deserialized
+    #[error(transparent)]
+    RowsDeserialization(#[from] DeserializationError),
+
     /// Expected a single row, found other number of rows
     #[error("Expected a single row, found {0} rows")]
     BadNumberOfRows(usize),
 }
 
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Error)]
 pub enum SingleRowTypedError {
     /// [`QueryResult::single_row_typed()`](QueryResult::single_row_typed) called on a bad QueryResult.\
     /// Expected `QueryResult.rows` to be `Some`, but it was `None`.\
@@ -245,6 +555,10 @@ pub enum SingleRowTypedError {
     #[error(transparent)]
     RowsExpected(#[from] RowsExpectedError),
 
+    /// Rows of a statement with lazy deserialization could not be deserialized
+    #[error(transparent)]
+    RowsDeserialization(#[from] DeserializationError),
+
     /// Expected a single row, found other number of rows
     #[error("Expected a single row, found {0} rows")]
     BadNumberOfRows(usize),
@@ -258,6 +572,7 @@ impl From<FirstRowError> for FirstRowTypedError {
     fn from(err: FirstRowError) -> FirstRowTypedError {
         match err {
             FirstRowError::RowsExpected(e) => FirstRowTypedError::RowsExpected(e),
+            FirstRowError::RowsDeserialization(e) => FirstRowTypedError::RowsDeserialization(e),
             FirstRowError::RowsEmpty => FirstRowTypedError::RowsEmpty,
         }
     }
@@ -267,15 +582,54 @@ impl From<SingleRowError> for SingleRowTypedError {
     fn from(err: SingleRowError) -> SingleRowTypedError {
         match err {
             SingleRowError::RowsExpected(e) => SingleRowTypedError::RowsExpected(e),
+            SingleRowError::RowsDeserialization(e) => SingleRowTypedError::RowsDeserialization(e),
             SingleRowError::BadNumberOfRows(r) => SingleRowTypedError::BadNumberOfRows(r),
         }
     }
 }
 
+impl From<RowsError> for FirstRowError {
+    fn from(err: RowsError) -> FirstRowError {
+        match err {
+            RowsError::RowsExpected(e) => FirstRowError::RowsExpected(e),
+            RowsError::RowsDeserialization(e) => FirstRowError::RowsDeserialization(e),
+        }
+    }
+}
+
+impl From<RowsError> for RowsTypedError {
+    fn from(err: RowsError) -> RowsTypedError {
+        match err {
+            RowsError::RowsExpected(e) => RowsTypedError::RowsExpected(e),
+            RowsError::RowsDeserialization(e) => RowsTypedError::RowsDeserialization(e),
+        }
+    }
+}
+
+impl From<RowsError> for MaybeFirstRowTypedError {
+    fn from(err: RowsError) -> MaybeFirstRowTypedError {
+        match err {
+            RowsError::RowsExpected(e) => MaybeFirstRowTypedError::RowsExpected(e),
+            RowsError::RowsDeserialization(e) => MaybeFirstRowTypedError::RowsDeserialization(e),
+        }
+    }
+}
+
+impl From<RowsError> for SingleRowError {
+    fn from(err: RowsError) -> SingleRowError {
+        match err {
+            RowsError::RowsExpected(e) => SingleRowError::RowsExpected(e),
+            RowsError::RowsDeserialization(e) => SingleRowError::RowsDeserialization(e),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::frame::response::deserialize::RawRows;
     use crate::frame::response::result::{ColumnSpec, ColumnType, CqlValue, Row, TableSpec};
+    use crate::frame::types;
     use std::convert::TryInto;
 
     // Returns specified number of rows, each one containing one int32 value.
@@ -291,6 +645,16 @@ mod tests {
         rows
     }
 
+    // Serialized version of make_rows
+    fn make_raw_rows(rows_num: usize) -> RawRows {
+        let mut raw = Vec::new();
+        for cur_value in 0..rows_num {
+            let int_val: i32 = cur_value.try_into().unwrap();
+            types::write_bytes(&int_val.to_be_bytes(), &mut raw).unwrap();
+        }
+        RawRows::new(rows_num, raw.into())
+    }
+
     // Just like make_rows, but each column has one String value
     // values are "val0", "val1", "val2", ...
     fn make_string_rows(rows_num: usize) -> Vec<Row> {
@@ -317,8 +681,10 @@ mod tests {
 
         QueryResult {
             rows: None,
+            raw_rows: None,
             warnings: vec![],
             tracing_id: None,
+            custom_payload: None,
             paging_state: None,
             col_specs: vec![column_spec],
         }
@@ -330,9 +696,17 @@ mod tests {
         res
     }
 
+    // Result of a statement with lazy deserialization
+    fn make_lazy_rows_query_result(rows_num: usize) -> QueryResult {
+        let mut res = make_not_rows_query_result();
+        res.raw_rows = Some(make_raw_rows(rows_num));
+        res
+    }
+
     fn make_string_rows_query_result(rows_num: usize) -> QueryResult {
         let mut res = make_not_rows_query_result();
         res.rows = Some(make_string_rows(rows_num));
+        res.col_specs[0].typ = ColumnType::Text;
         res
     }
 
@@ -350,10 +724,13 @@ mod tests {
 
     #[test]
     fn rows_test() {
-        assert_eq!(make_not_rows_query_result().rows(), Err(RowsExpectedError));
-        assert_eq!(make_rows_query_result(0).rows(), Ok(vec![]));
-        assert_eq!(make_rows_query_result(1).rows(), Ok(make_rows(1)));
-        assert_eq!(make_rows_query_result(2).rows(), Ok(make_rows(2)));
+        assert!(matches!(
+            make_not_rows_query_result().rows(),
+            Err(RowsError::RowsExpected(RowsExpectedError))
+        ));
+        assert_eq!(make_rows_query_result(0).rows().unwrap(), vec![]);
+        assert_eq!(make_rows_query_result(1).rows().unwrap(), make_rows(1));
+        assert_eq!(make_rows_query_result(2).rows().unwrap(), make_rows(2));
     }
 
     #[test]
@@ -385,6 +762,219 @@ mod tests {
         assert_eq!(rows2, vec![(0,), (1,)]);
     }
 
+    #[test]
+    fn deserialize_rows_test() {
+        assert_eq!(
+            make_not_rows_query_result()
+                .deserialize_rows::<(i32,)>()
+                .err(),
+            Some(DeserializeRowsError::RowsExpected(RowsExpectedError))
+        );
+        assert_eq!(
+            make_rows_query_result(3).deserialize_rows::<(i32,)>().err(),
+            Some(DeserializeRowsError::RowsAlreadyDeserialized)
+        );
+
+        let result = make_lazy_rows_query_result(3);
+        let rows: Vec<(i32,)> = result
+            .deserialize_rows::<(i32,)>()
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows, vec![(0,), (1,), (2,)]);
+
+        let rows: Vec<Row> = result
+            .deserialize_rows::<Row>()
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows, make_rows(3));
+
+        assert!(result
+            .deserialize_rows::<(&str,)>()
+            .unwrap()
+            .all(|r| r.is_err()));
+    }
+
+    #[test]
+    fn rows_typed_type_check_test() {
+        match make_string_rows_query_result(2).rows_typed::<(i32,)>() {
+            Err(RowsTypedError::TypeCheckError(RowTypeCheckError::ColumnTypeMismatch(
+                mismatches,
+            ))) => {
+                assert_eq!(mismatches.len(), 1);
+                assert_eq!(mismatches[0].column, "column0");
+                assert_eq!(mismatches[0].cql_type, ColumnType::Text);
+                assert_eq!(mismatches[0].rust_type, "i32");
+            }
+            res => panic!("Unexpected result: {:?}", res.map(|_| ())),
+        }
+
+        assert!(matches!(
+            make_rows_query_result(1).rows_typed::<(i32, i32)>(),
+            Err(RowsTypedError::TypeCheckError(
+                RowTypeCheckError::WrongRowSize {
+                    expected: 2,
+                    actual: 1
+                }
+            ))
+        ));
+        assert!(matches!(
+            make_string_rows_query_result(1).single_row_typed::<(i32,)>(),
+            Err(SingleRowTypedError::FromRowError(
+                FromRowError::TypeCheckError(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn lazy_rows_test() {
+        assert!(make_not_rows_query_result().lazy_rows().is_err());
+        assert!(make_rows_query_result(3).lazy_rows().is_err());
+
+        let result = make_lazy_rows_query_result(3);
+
+        assert_eq!(result.rows_num(), Ok(3));
+        assert_eq!(result.result_not_rows(), Err(RowsNotExpectedError));
+
+        let values: Vec<i32> = result
+            .lazy_rows()
+            .unwrap()
+            .map(|row| row.unwrap().column_by_name::<i32>("column0").unwrap())
+            .collect();
+        assert_eq!(values, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn lazy_deserialization_accessors_test() {
+        assert_eq!(make_lazy_rows_query_result(2).rows().unwrap(), make_rows(2));
+        assert_eq!(
+            make_lazy_rows_query_result(2).rows_or_empty().unwrap(),
+            make_rows(2)
+        );
+        let rows: Vec<(i32,)> = make_lazy_rows_query_result(2)
+            .rows_typed::<(i32,)>()
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows, vec![(0,), (1,)]);
+        assert_eq!(
+            make_lazy_rows_query_result(2).first_row().unwrap(),
+            make_rows(1).into_iter().next().unwrap()
+        );
+        assert_eq!(
+            make_lazy_rows_query_result(2)
+                .maybe_first_row_typed::<(i32,)>()
+                .unwrap(),
+            Some((0,))
+        );
+        assert_eq!(
+            make_lazy_rows_query_result(1)
+                .single_row_typed::<(i32,)>()
+                .unwrap(),
+            (0,)
+        );
+        assert!(matches!(
+            make_lazy_rows_query_result(2).single_row(),
+            Err(SingleRowError::BadNumberOfRows(2))
+        ));
+        assert!(matches!(
+            make_lazy_rows_query_result(0).first_row(),
+            Err(FirstRowError::RowsEmpty)
+        ));
+
+        // Columns are type checked also when the rows weren't deserialized yet
+        let mut result = make_lazy_rows_query_result(1);
+        result.col_specs[0].typ = ColumnType::Text;
+        assert!(matches!(
+            result.rows_typed::<(i32,)>(),
+            Err(RowsTypedError::TypeCheckError(_))
+        ));
+
+        // Rows which can't be deserialized cause an error, not an empty result
+        let mut result = make_not_rows_query_result();
+        result.raw_rows = Some(RawRows::new(2, make_raw_rows(1).as_bytes().clone()));
+        assert!(matches!(
+            result.rows_or_empty(),
+            Err(DeserializationError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn merge_pages_test() {
+        let mut first_page = make_rows_query_result(1);
+        first_page.paging_state = Some(Bytes::from_static(b"page"));
+        first_page.custom_payload = Some(HashMap::from([("key".to_string(), vec![1])]));
+        let mut last_page = make_rows_query_result(2);
+        last_page.custom_payload = Some(HashMap::from([("key".to_string(), vec![2])]));
+        let result = QueryResult::merge_pages(vec![first_page, last_page]);
+        assert_eq!(result.paging_state, None);
+        assert_eq!(
+            result.custom_payload,
+            Some(HashMap::from([("key".to_string(), vec![2])]))
+        );
+        assert!(result.raw_rows.is_none());
+        let mut expected_rows = make_rows(1);
+        expected_rows.extend(make_rows(2));
+        assert_eq!(result.rows.unwrap(), expected_rows);
+
+        let mut first_lazy_page = make_lazy_rows_query_result(2);
+        first_lazy_page.paging_state = Some(Bytes::from_static(b"page"));
+        let result =
+            QueryResult::merge_pages(vec![first_lazy_page, make_lazy_rows_query_result(3)]);
+        assert_eq!(result.rows_num(), Ok(5));
+        assert_eq!(result.paging_state, None);
+        assert!(result.rows.is_none());
+        let values: Vec<(i32,)> = result
+            .deserialize_rows::<(i32,)>()
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(values, vec![(0,), (1,), (0,), (1,), (2,)]);
+
+        assert!(QueryResult::merge_pages(vec![make_not_rows_query_result()])
+            .result_not_rows()
+            .is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_test() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct MyRow {
+            column0: i32,
+        }
+
+        assert!(matches!(
+            make_not_rows_query_result().rows_serde::<MyRow>(),
+            Err(RowsSerdeError::RowsExpected(_))
+        ));
+        assert_eq!(
+            make_rows_query_result(2).rows_serde::<MyRow>().unwrap(),
+            vec![MyRow { column0: 0 }, MyRow { column0: 1 }]
+        );
+        assert!(matches!(
+            make_string_rows_query_result(1).rows_serde::<MyRow>(),
+            Err(RowsSerdeError::SerdeDeserialization(_))
+        ));
+
+        assert_eq!(
+            serde_json::to_string(&make_rows_query_result(2)).unwrap(),
+            r#"{"rows":[{"column0":0},{"column0":1}],"warnings":[],"tracing_id":null}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&make_not_rows_query_result()).unwrap(),
+            r#"{"rows":null,"warnings":[],"tracing_id":null}"#
+        );
+
+        let result = make_lazy_rows_query_result(1);
+        assert_eq!(
+            serde_json::to_string(&result).unwrap(),
+            r#"{"rows":[{"column0":0}],"warnings":[],"tracing_id":null}"#
+        );
+        assert_eq!(result.rows_serde::<(i32,)>().unwrap(), vec![(0,)]);
+    }
+
     #[test]
     fn result_not_rows_test() {
         assert_eq!(make_not_rows_query_result().result_not_rows(), Ok(()));
@@ -404,16 +994,29 @@ mod tests {
 
     #[test]
     fn rows_or_empty_test() {
-        assert_eq!(make_not_rows_query_result().rows_or_empty(), vec![]);
-        assert_eq!(make_rows_query_result(0).rows_or_empty(), make_rows(0));
-        assert_eq!(make_rows_query_result(1).rows_or_empty(), make_rows(1));
-        assert_eq!(make_rows_query_result(2).rows_or_empty(), make_rows(2));
+        assert_eq!(
+            make_not_rows_query_result().rows_or_empty().unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            make_rows_query_result(0).rows_or_empty().unwrap(),
+            make_rows(0)
+        );
+        assert_eq!(
+            make_rows_query_result(1).rows_or_empty().unwrap(),
+            make_rows(1)
+        );
+        assert_eq!(
+            make_rows_query_result(2).rows_or_empty().unwrap(),
+            make_rows(2)
+        );
     }
 
     #[test]
     fn rows_typed_or_empty() {
         let rows_empty: Vec<(i32,)> = make_not_rows_query_result()
             .rows_typed_or_empty::<(i32,)>()
+            .unwrap()
             .map(|r| r.unwrap())
             .collect();
 
@@ -421,6 +1024,7 @@ mod tests {
 
         let rows0: Vec<(i32,)> = make_rows_query_result(0)
             .rows_typed_or_empty::<(i32,)>()
+            .unwrap()
             .map(|r| r.unwrap())
             .collect();
 
@@ -428,6 +1032,7 @@ mod tests {
 
         let rows1: Vec<(i32,)> = make_rows_query_result(1)
             .rows_typed_or_empty::<(i32,)>()
+            .unwrap()
             .map(|r| r.unwrap())
             .collect();
 
@@ -435,6 +1040,7 @@ mod tests {
 
         let rows2: Vec<(i32,)> = make_rows_query_result(2)
             .rows_typed_or_empty::<(i32,)>()
+            .unwrap()
             .map(|r| r.unwrap())
             .collect();
 
@@ -443,49 +1049,55 @@ mod tests {
 
     #[test]
     fn first_row_test() {
-        assert_eq!(
+        assert!(matches!(
             make_not_rows_query_result().first_row(),
             Err(FirstRowError::RowsExpected(RowsExpectedError))
-        );
-        assert_eq!(
+        ));
+        assert!(matches!(
             make_rows_query_result(0).first_row(),
             Err(FirstRowError::RowsEmpty)
-        );
+        ));
         assert_eq!(
-            make_rows_query_result(1).first_row(),
-            Ok(make_rows(1).into_iter().next().unwrap())
+            make_rows_query_result(1).first_row().unwrap(),
+            make_rows(1).into_iter().next().unwrap()
         );
         assert_eq!(
-            make_rows_query_result(2).first_row(),
-            Ok(make_rows(2).into_iter().next().unwrap())
+            make_rows_query_result(2).first_row().unwrap(),
+            make_rows(2).into_iter().next().unwrap()
         );
         assert_eq!(
-            make_rows_query_result(3).first_row(),
-            Ok(make_rows(3).into_iter().next().unwrap())
+            make_rows_query_result(3).first_row().unwrap(),
+            make_rows(3).into_iter().next().unwrap()
         );
     }
 
     #[test]
     fn first_row_typed_test() {
-        assert_eq!(
+        assert!(matches!(
             make_not_rows_query_result().first_row_typed::<(i32,)>(),
             Err(FirstRowTypedError::RowsExpected(RowsExpectedError))
-        );
-        assert_eq!(
+        ));
+        assert!(matches!(
             make_rows_query_result(0).first_row_typed::<(i32,)>(),
             Err(FirstRowTypedError::RowsEmpty)
-        );
+        ));
         assert_eq!(
-            make_rows_query_result(1).first_row_typed::<(i32,)>(),
-            Ok((0,))
+            make_rows_query_result(1)
+                .first_row_typed::<(i32,)>()
+                .unwrap(),
+            (0,)
         );
         assert_eq!(
-            make_rows_query_result(2).first_row_typed::<(i32,)>(),
-            Ok((0,))
+            make_rows_query_result(2)
+                .first_row_typed::<(i32,)>()
+                .unwrap(),
+            (0,)
         );
         assert_eq!(
-            make_rows_query_result(3).first_row_typed::<(i32,)>(),
-            Ok((0,))
+            make_rows_query_result(3)
+                .first_row_typed::<(i32,)>()
+                .unwrap(),
+            (0,)
         );
 
         assert!(matches!(
@@ -496,50 +1108,58 @@ mod tests {
 
     #[test]
     fn maybe_first_row_test() {
-        assert_eq!(
+        assert!(matches!(
             make_not_rows_query_result().maybe_first_row(),
-            Err(RowsExpectedError)
-        );
-        assert_eq!(make_rows_query_result(0).maybe_first_row(), Ok(None));
+            Err(RowsError::RowsExpected(RowsExpectedError))
+        ));
+        assert_eq!(make_rows_query_result(0).maybe_first_row().unwrap(), None);
         assert_eq!(
-            make_rows_query_result(1).maybe_first_row(),
-            Ok(Some(make_rows(1).into_iter().next().unwrap()))
+            make_rows_query_result(1).maybe_first_row().unwrap(),
+            Some(make_rows(1).into_iter().next().unwrap())
         );
         assert_eq!(
-            make_rows_query_result(2).maybe_first_row(),
-            Ok(Some(make_rows(2).into_iter().next().unwrap()))
+            make_rows_query_result(2).maybe_first_row().unwrap(),
+            Some(make_rows(2).into_iter().next().unwrap())
         );
         assert_eq!(
-            make_rows_query_result(3).maybe_first_row(),
-            Ok(Some(make_rows(3).into_iter().next().unwrap()))
+            make_rows_query_result(3).maybe_first_row().unwrap(),
+            Some(make_rows(3).into_iter().next().unwrap())
         );
     }
 
     #[test]
     fn maybe_first_row_typed_test() {
-        assert_eq!(
+        assert!(matches!(
             make_not_rows_query_result().maybe_first_row_typed::<(i32,)>(),
             Err(MaybeFirstRowTypedError::RowsExpected(RowsExpectedError))
-        );
+        ));
 
         assert_eq!(
-            make_rows_query_result(0).maybe_first_row_typed::<(i32,)>(),
-            Ok(None)
+            make_rows_query_result(0)
+                .maybe_first_row_typed::<(i32,)>()
+                .unwrap(),
+            None
         );
 
         assert_eq!(
-            make_rows_query_result(1).maybe_first_row_typed::<(i32,)>(),
-            Ok(Some((0,)))
+            make_rows_query_result(1)
+                .maybe_first_row_typed::<(i32,)>()
+                .unwrap(),
+            Some((0,))
         );
 
         assert_eq!(
-            make_rows_query_result(2).maybe_first_row_typed::<(i32,)>(),
-            Ok(Some((0,)))
+            make_rows_query_result(2)
+                .maybe_first_row_typed::<(i32,)>()
+                .unwrap(),
+            Some((0,))
         );
 
         assert_eq!(
-            make_rows_query_result(3).maybe_first_row_typed::<(i32,)>(),
-            Ok(Some((0,)))
+            make_rows_query_result(3)
+                .maybe_first_row_typed::<(i32,)>()
+                .unwrap(),
+            Some((0,))
         );
 
         assert!(matches!(
@@ -550,50 +1170,52 @@ mod tests {
 
     #[test]
     fn single_row_test() {
-        assert_eq!(
+        assert!(matches!(
             make_not_rows_query_result().single_row(),
             Err(SingleRowError::RowsExpected(RowsExpectedError))
-        );
-        assert_eq!(
+        ));
+        assert!(matches!(
             make_rows_query_result(0).single_row(),
             Err(SingleRowError::BadNumberOfRows(0))
-        );
+        ));
         assert_eq!(
-            make_rows_query_result(1).single_row(),
-            Ok(make_rows(1).into_iter().next().unwrap())
+            make_rows_query_result(1).single_row().unwrap(),
+            make_rows(1).into_iter().next().unwrap()
         );
-        assert_eq!(
+        assert!(matches!(
             make_rows_query_result(2).single_row(),
             Err(SingleRowError::BadNumberOfRows(2))
-        );
-        assert_eq!(
+        ));
+        assert!(matches!(
             make_rows_query_result(3).single_row(),
             Err(SingleRowError::BadNumberOfRows(3))
-        );
+        ));
     }
 
     #[test]
     fn single_row_typed_test() {
-        assert_eq!(
+        assert!(matches!(
             make_not_rows_query_result().single_row_typed::<(i32,)>(),
             Err(SingleRowTypedError::RowsExpected(RowsExpectedError))
-        );
-        assert_eq!(
+        ));
+        assert!(matches!(
             make_rows_query_result(0).single_row_typed::<(i32,)>(),
             Err(SingleRowTypedError::BadNumberOfRows(0))
-        );
+        ));
         assert_eq!(
-            make_rows_query_result(1).single_row_typed::<(i32,)>(),
-            Ok((0,))
+            make_rows_query_result(1)
+                .single_row_typed::<(i32,)>()
+                .unwrap(),
+            (0,)
         );
-        assert_eq!(
+        assert!(matches!(
             make_rows_query_result(2).single_row_typed::<(i32,)>(),
             Err(SingleRowTypedError::BadNumberOfRows(2))
-        );
-        assert_eq!(
+        ));
+        assert!(matches!(
             make_rows_query_result(3).single_row_typed::<(i32,)>(),
             Err(SingleRowTypedError::BadNumberOfRows(3))
-        );
+        ));
 
         assert!(matches!(
             make_string_rows_query_result(1).single_row_typed::<(i32,)>(),
@@ -0,0 +1,704 @@
+//! Conversion of query results to [Apache Arrow](https://arrow.apache.org) record batches,
+//! enabled by the `arrow` feature.
+//!
+//! CQL types are mapped to Arrow data types as follows:
+//! * `ascii`, `text` - `Utf8`
+//! * `boolean` - `Boolean`
+//! * `blob` - `Binary`
+//! * `tinyint`, `smallint`, `int`, `bigint`, `counter` - `Int8`, `Int16`, `Int32`, `Int64`, `Int64`
+//! * `float`, `double` - `Float32`, `Float64`
+//! * `date` - `Date32`
+//! * `time` - `Time64(Nanosecond)`
+//! * `timestamp` - `Timestamp(Millisecond, "UTC")`
+//! * `duration` - `Interval(MonthDayNano)`
+//! * `uuid`, `timeuuid` - `FixedSizeBinary(16)`
+//! * `decimal`, `varint`, `inet` - `Utf8`, as their text representation
+//! * `list`, `set` - `List`
+//! * `vector` - `FixedSizeList`
+//! * `map` - `Map`
+//! * user defined types - `Struct` with the fields of the type
+//! * `tuple` - `Struct` with fields named `0`, `1`, `2`, ...
+//!
+//! Empty values are converted to nulls, except for map keys, which can't be null in Arrow.
+//! A map with an empty key fails the conversion.\
+//! Custom types are not supported.\
+//! Note that the Arrow crates require a newer Rust version than the rest of the driver.
+
+use super::iterator::{NextDeserializedRowError, RowIterator};
+use super::query_result::{QueryResult, RowsExpectedError};
+use crate::frame::response::deserialize::DeserializationError;
+use crate::frame::response::result::{ColumnSpec, ColumnType, CqlValue, Row};
+use crate::transport::errors::QueryError;
+use arrow_array::types::IntervalMonthDayNanoType;
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Date32Array, FixedSizeBinaryArray, FixedSizeListArray,
+    Float32Array, Float64Array, Int16Array, Int32Array, Int64Array, Int8Array, ListArray, MapArray,
+    PrimitiveArray, RecordBatch, RecordBatchOptions, StringArray, StructArray,
+    Time64NanosecondArray, TimestampMillisecondArray,
+};
+use arrow_buffer::{IntervalMonthDayNano, NullBuffer, OffsetBuffer, ScalarBuffer};
+use arrow_schema::{ArrowError, DataType, Field, Fields, IntervalUnit, Schema, TimeUnit};
+use futures::Stream;
+use std::convert::TryFrom;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use thiserror::Error;
+
+/// Error returned when rows can't be converted to an Arrow [`RecordBatch`]
+#[derive(Error, Debug)]
+pub enum ArrowConversionError {
+    /// The column type has no Arrow equivalent
+    #[error("Column type {0:?} can't be converted to an Arrow data type")]
+    UnsupportedType(ColumnType),
+
+    /// A value in the rows doesn't match the type of its column
+    #[error("Value {value:?} doesn't match column type {typ:?}")]
+    TypeMismatch {
+        typ: ColumnType,
+        value: Box<CqlValue>,
+    },
+
+    /// A map has an empty key, which has no Arrow representation, as map keys can't be null
+    #[error("Map of type {0:?} has an empty key, which can't be converted to Arrow")]
+    EmptyMapKey(ColumnType),
+
+    /// A row has a different number of columns than the column specification
+    #[error("Row has {actual} columns, expected {expected}")]
+    WrongRowSize { expected: usize, actual: usize },
+
+    /// Building the Arrow arrays failed
+    #[error(transparent)]
+    ArrowError(#[from] ArrowError),
+
+    /// Called on a result of a query that doesn't return rows
+    #[error(transparent)]
+    RowsExpected(#[from] RowsExpectedError),
+
+    /// Received rows could not be parsed
+    #[error(transparent)]
+    DeserializationError(#[from] DeserializationError),
+
+    /// Query to fetch the next page has failed
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+}
+
+impl From<NextDeserializedRowError> for ArrowConversionError {
+    fn from(err: NextDeserializedRowError) -> Self {
+        match err {
+            NextDeserializedRowError::QueryError(err) => err.into(),
+            NextDeserializedRowError::DeserializationError(err) => err.into(),
+        }
+    }
+}
+
+/// Returns the Arrow schema matching the given columns
+pub fn arrow_schema(col_specs: &[ColumnSpec]) -> Result<Schema, ArrowConversionError> {
+    let fields = col_specs
+        .iter()
+        .map(|spec| Ok(Field::new(&spec.name, arrow_data_type(&spec.typ)?, true)))
+        .collect::<Result<Vec<_>, ArrowConversionError>>()?;
+    Ok(Schema::new(fields))
+}
+
+/// Returns the Arrow data type used to represent values of the given CQL type
+pub fn arrow_data_type(typ: &ColumnType) -> Result<DataType, ArrowConversionError> {
+    Ok(match typ {
+        ColumnType::Custom(_) => return Err(ArrowConversionError::UnsupportedType(typ.clone())),
+        ColumnType::Ascii | ColumnType::Text => DataType::Utf8,
+        ColumnType::Boolean => DataType::Boolean,
+        ColumnType::Blob => DataType::Binary,
+        ColumnType::Counter | ColumnType::BigInt => DataType::Int64,
+        ColumnType::Date => DataType::Date32,
+        ColumnType::Decimal | ColumnType::Varint | ColumnType::Inet => DataType::Utf8,
+        ColumnType::Double => DataType::Float64,
+        ColumnType::Duration => DataType::Interval(IntervalUnit::MonthDayNano),
+        ColumnType::Float => DataType::Float32,
+        ColumnType::Int => DataType::Int32,
+        ColumnType::Timestamp => DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        ColumnType::SmallInt => DataType::Int16,
+        ColumnType::TinyInt => DataType::Int8,
+        ColumnType::Time => DataType::Time64(TimeUnit::Nanosecond),
+        ColumnType::Timeuuid | ColumnType::Uuid => DataType::FixedSizeBinary(16),
+        ColumnType::List(elem) | ColumnType::Set(elem) => DataType::List(Arc::new(
+            Field::new_list_field(arrow_data_type(elem)?, true),
+        )),
+        ColumnType::Vector {
+            element,
+            dimensions,
+        } => DataType::FixedSizeList(
+            Arc::new(Field::new_list_field(arrow_data_type(element)?, true)),
+            *dimensions as i32,
+        ),
+        ColumnType::Map(key, value) => {
+            DataType::Map(Arc::new(map_entries_field(key, value)?), false)
+        }
+        ColumnType::UserDefinedType { field_types, .. } => DataType::Struct(struct_fields(
+            field_types.iter().map(|(n, t)| (n.as_str(), t)),
+        )?),
+        ColumnType::Tuple(types) => DataType::Struct(tuple_fields(types)?),
+    })
+}
+
+fn struct_fields<'a>(
+    fields: impl Iterator<Item = (&'a str, &'a ColumnType)>,
+) -> Result<Fields, ArrowConversionError> {
+    fields
+        .map(|(name, typ)| Ok(Field::new(name, arrow_data_type(typ)?, true)))
+        .collect()
+}
+
+fn tuple_fields(types: &[ColumnType]) -> Result<Fields, ArrowConversionError> {
+    let names: Vec<String> = (0..types.len()).map(|i| i.to_string()).collect();
+    struct_fields(names.iter().map(String::as_str).zip(types))
+}
+
+fn map_entries_field(key: &ColumnType, value: &ColumnType) -> Result<Field, ArrowConversionError> {
+    let fields = Fields::from(vec![
+        Field::new("keys", arrow_data_type(key)?, false),
+        Field::new("values", arrow_data_type(value)?, true),
+    ]);
+    Ok(Field::new("entries", DataType::Struct(fields), false))
+}
+
+/// Converts the rows to a [`RecordBatch`] with the schema returned by [`arrow_schema`]
+pub fn rows_to_record_batch(
+    col_specs: &[ColumnSpec],
+    rows: &[Row],
+) -> Result<RecordBatch, ArrowConversionError> {
+    if let Some(row) = rows.iter().find(|row| row.columns.len() != col_specs.len()) {
+        return Err(ArrowConversionError::WrongRowSize {
+            expected: col_specs.len(),
+            actual: row.columns.len(),
+        });
+    }
+
+    let columns = col_specs
+        .iter()
+        .enumerate()
+        .map(|(i, spec)| {
+            let values: Vec<Option<&CqlValue>> =
+                rows.iter().map(|row| row.columns[i].as_ref()).collect();
+            build_array(&spec.typ, &values)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+    Ok(RecordBatch::try_new_with_options(
+        Arc::new(arrow_schema(col_specs)?),
+        columns,
+        &options,
+    )?)
+}
+
+fn mismatch(typ: &ColumnType, value: &CqlValue) -> ArrowConversionError {
+    ArrowConversionError::TypeMismatch {
+        typ: typ.clone(),
+        value: Box::new(value.clone()),
+    }
+}
+
+fn null_buffer(validity: Vec<bool>) -> Option<NullBuffer> {
+    if validity.iter().all(|valid| *valid) {
+        None
+    } else {
+        Some(NullBuffer::from(validity))
+    }
+}
+
+fn offset_buffer(offsets: Vec<usize>) -> Result<OffsetBuffer<i32>, ArrowConversionError> {
+    let offsets = offsets
+        .into_iter()
+        .map(|offset| {
+            i32::try_from(offset)
+                .map_err(|_| ArrowError::ComputeError("Too many collection elements".to_string()))
+        })
+        .collect::<Result<Vec<i32>, _>>()?;
+    Ok(OffsetBuffer::new(ScalarBuffer::from(offsets)))
+}
+
+// Collects values of a primitive type into an array, failing on values of other types
+macro_rules! primitive_array {
+    ($array:ty, $typ:expr, $values:expr, $pattern:pat => $value:expr) => {
+        $values
+            .iter()
+            .map(|value| match value {
+                None => Ok(None),
+                Some($pattern) => Ok(Some($value)),
+                Some(other) => Err(mismatch($typ, other)),
+            })
+            .collect::<Result<$array, ArrowConversionError>>()?
+    };
+}
+
+// Builds an array of the given type, `None` is a null value
+fn build_array(
+    typ: &ColumnType,
+    values: &[Option<&CqlValue>],
+) -> Result<ArrayRef, ArrowConversionError> {
+    // Empty values are represented as nulls
+    let values: Vec<Option<&CqlValue>> = values
+        .iter()
+        .map(|value| value.filter(|value| !matches!(value, CqlValue::Empty)))
+        .collect();
+
+    Ok(match typ {
+        ColumnType::Custom(_) => return Err(ArrowConversionError::UnsupportedType(typ.clone())),
+        ColumnType::Ascii | ColumnType::Text => Arc::new(primitive_array!(
+            StringArray, typ, values,
+            CqlValue::Ascii(s) | CqlValue::Text(s) => s.as_str()
+        )),
+        ColumnType::Boolean => Arc::new(primitive_array!(
+            BooleanArray, typ, values, CqlValue::Boolean(b) => *b
+        )),
+        ColumnType::Blob => Arc::new(primitive_array!(
+            BinaryArray, typ, values, CqlValue::Blob(b) => b.as_slice()
+        )),
+        ColumnType::Counter => Arc::new(primitive_array!(
+            Int64Array, typ, values, CqlValue::Counter(c) => c.0
+        )),
+        ColumnType::BigInt => Arc::new(primitive_array!(
+            Int64Array, typ, values, CqlValue::BigInt(i) => *i
+        )),
+        ColumnType::Date => Arc::new(primitive_array!(
+            // Days since unix epoch, which is 2^31 in CQL
+            Date32Array, typ, values, CqlValue::Date(d) => (*d as i64 - (1 << 31)) as i32
+        )),
+        ColumnType::Decimal => Arc::new(primitive_array!(
+            StringArray, typ, values, CqlValue::Decimal(d) => d.to_string()
+        )),
+        ColumnType::Varint => Arc::new(primitive_array!(
+            StringArray, typ, values, CqlValue::Varint(v) => v.to_string()
+        )),
+        ColumnType::Inet => Arc::new(primitive_array!(
+            StringArray, typ, values, CqlValue::Inet(i) => i.to_string()
+        )),
+        ColumnType::Double => Arc::new(primitive_array!(
+            Float64Array, typ, values, CqlValue::Double(d) => *d
+        )),
+        ColumnType::Float => Arc::new(primitive_array!(
+            Float32Array, typ, values, CqlValue::Float(f) => *f
+        )),
+        ColumnType::Int => Arc::new(primitive_array!(
+            Int32Array, typ, values, CqlValue::Int(i) => *i
+        )),
+        ColumnType::SmallInt => Arc::new(primitive_array!(
+            Int16Array, typ, values, CqlValue::SmallInt(i) => *i
+        )),
+        ColumnType::TinyInt => Arc::new(primitive_array!(
+            Int8Array, typ, values, CqlValue::TinyInt(i) => *i
+        )),
+        ColumnType::Duration => Arc::new(primitive_array!(
+            PrimitiveArray<IntervalMonthDayNanoType>, typ, values,
+            CqlValue::Duration(d) => IntervalMonthDayNano::new(d.months, d.days, d.nanoseconds)
+        )),
+        ColumnType::Timestamp => Arc::new(
+            primitive_array!(
+                TimestampMillisecondArray, typ, values,
+                CqlValue::Timestamp(t) => t.num_milliseconds()
+            )
+            .with_timezone("UTC"),
+        ),
+        ColumnType::Time => Arc::new(primitive_array!(
+            Time64NanosecondArray, typ, values,
+            CqlValue::Time(t) => t
+                .num_nanoseconds()
+                .ok_or_else(|| ArrowError::ComputeError("Time value out of range".to_string()))?
+        )),
+        ColumnType::Timeuuid | ColumnType::Uuid => {
+            let uuids = values
+                .iter()
+                .map(|value| match value {
+                    None => Ok(None),
+                    Some(CqlValue::Uuid(u)) => Ok(Some(*u.as_bytes())),
+                    Some(CqlValue::Timeuuid(u)) => Ok(Some(*u.as_bytes())),
+                    Some(other) => Err(mismatch(typ, other)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Arc::new(FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                uuids.into_iter(),
+                16,
+            )?)
+        }
+        ColumnType::List(elem) | ColumnType::Set(elem) => {
+            let mut offsets = vec![0];
+            let mut elements = Vec::new();
+            let mut validity = Vec::with_capacity(values.len());
+            for value in &values {
+                match value {
+                    None => validity.push(false),
+                    Some(CqlValue::List(v) | CqlValue::Set(v)) => {
+                        elements.extend(v.iter().map(Some));
+                        validity.push(true);
+                    }
+                    Some(other) => return Err(mismatch(typ, other)),
+                }
+                offsets.push(elements.len());
+            }
+
+            let elements = build_array(elem, &elements)?;
+            Arc::new(ListArray::try_new(
+                Arc::new(Field::new_list_field(elements.data_type().clone(), true)),
+                offset_buffer(offsets)?,
+                elements,
+                null_buffer(validity),
+            )?)
+        }
+        ColumnType::Vector {
+            element,
+            dimensions,
+        } => {
+            let dimensions = *dimensions as usize;
+            let mut elements = Vec::with_capacity(values.len() * dimensions);
+            let mut validity = Vec::with_capacity(values.len());
+            for value in &values {
+                match value {
+                    None => {
+                        // Null vectors still take space in the elements array
+                        elements.resize(elements.len() + dimensions, None);
+                        validity.push(false);
+                    }
+                    Some(CqlValue::Vector(v)) if v.len() == dimensions => {
+                        elements.extend(v.iter().map(Some));
+                        validity.push(true);
+                    }
+                    Some(other) => return Err(mismatch(typ, other)),
+                }
+            }
+
+            let elements = build_array(element, &elements)?;
+            Arc::new(FixedSizeListArray::try_new_with_length(
+                Arc::new(Field::new_list_field(elements.data_type().clone(), true)),
+                dimensions as i32,
+                elements,
+                null_buffer(validity),
+                values.len(),
+            )?)
+        }
+        ColumnType::Map(key_type, value_type) => {
+            let mut offsets = vec![0];
+            let mut keys = Vec::new();
+            let mut map_values = Vec::new();
+            let mut validity = Vec::with_capacity(values.len());
+            for value in &values {
+                match value {
+                    None => validity.push(false),
+                    Some(CqlValue::Map(m)) => {
+                        for (k, v) in m {
+                            if matches!(k, CqlValue::Empty) {
+                                return Err(ArrowConversionError::EmptyMapKey(typ.clone()));
+                            }
+                            keys.push(Some(k));
+                            map_values.push(Some(v));
+                        }
+                        validity.push(true);
+                    }
+                    Some(other) => return Err(mismatch(typ, other)),
+                }
+                offsets.push(keys.len());
+            }
+
+            let entries_field = map_entries_field(key_type, value_type)?;
+            let entries_fields = match entries_field.data_type() {
+                DataType::Struct(fields) => fields.clone(),
+                _ => unreachable!("Map entries are always a struct"),
+            };
+            let entries = StructArray::try_new(
+                entries_fields,
+                vec![
+                    build_array(key_type, &keys)?,
+                    build_array(value_type, &map_values)?,
+                ],
+                None,
+            )?;
+            Arc::new(MapArray::try_new(
+                Arc::new(entries_field),
+                offset_buffer(offsets)?,
+                entries,
+                null_buffer(validity),
+                false,
+            )?)
+        }
+        ColumnType::UserDefinedType { field_types, .. } => {
+            let fields = struct_fields(field_types.iter().map(|(n, t)| (n.as_str(), t)))?;
+            build_struct_array(
+                typ,
+                fields,
+                field_types.iter().map(|(_, t)| t),
+                &values,
+                |value| match value {
+                    CqlValue::UserDefinedType { fields, .. } => {
+                        Some(fields.iter().map(|(_, v)| v.as_ref()).collect())
+                    }
+                    _ => None,
+                },
+            )?
+        }
+        ColumnType::Tuple(types) => build_struct_array(
+            typ,
+            tuple_fields(types)?,
+            types.iter(),
+            &values,
+            |value| match value {
+                CqlValue::Tuple(t) => Some(t.iter().map(Option::as_ref).collect()),
+                _ => None,
+            },
+        )?,
+    })
+}
+
+// Builds a struct array from UDTs or tuples, `get_fields` returns the field values of a value.
+// Missing trailing fields are nulls.
+fn build_struct_array<'a>(
+    typ: &ColumnType,
+    fields: Fields,
+    field_types: impl Iterator<Item = &'a ColumnType>,
+    values: &[Option<&CqlValue>],
+    get_fields: impl Fn(&CqlValue) -> Option<Vec<Option<&CqlValue>>>,
+) -> Result<ArrayRef, ArrowConversionError> {
+    let mut validity = Vec::with_capacity(values.len());
+    let mut rows_fields = Vec::with_capacity(values.len());
+    for value in values {
+        match value {
+            None => {
+                validity.push(false);
+                rows_fields.push(Vec::new());
+            }
+            Some(value) => {
+                rows_fields.push(get_fields(value).ok_or_else(|| mismatch(typ, value))?);
+                validity.push(true);
+            }
+        }
+    }
+
+    let children = field_types
+        .enumerate()
+        .map(|(i, field_type)| {
+            let field_values: Vec<Option<&CqlValue>> = rows_fields
+                .iter()
+                .map(|row_fields| row_fields.get(i).copied().flatten())
+                .collect();
+            build_array(field_type, &field_values)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Arc::new(StructArray::try_new_with_length(
+        fields,
+        children,
+        null_buffer(validity),
+        values.len(),
+    )?))
+}
+
+impl QueryResult {
+    /// Converts the received rows to an Arrow [`RecordBatch`].\
+    /// The schema of the batch is built from the column specifications, see [`arrow_schema`].\
+    /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows).
+    pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowConversionError> {
+        match (&self.rows, &self.raw_rows) {
+            (Some(rows), _) => rows_to_record_batch(&self.col_specs, rows),
+            (None, Some(raw_rows)) => {
+                let rows = raw_rows
+                    .rows::<Row>(&self.col_specs)
+                    .collect::<Result<Vec<_>, _>>()?;
+                rows_to_record_batch(&self.col_specs, &rows)
+            }
+            (None, None) => Err(RowsExpectedError.into()),
+        }
+    }
+}
+
+impl RowIterator {
+    /// Converts this iterator into a stream of Arrow [`RecordBatch`]es, one for every received page.\
+    /// Rows which were already returned by this iterator are skipped, empty pages are skipped.
+    pub fn into_record_batches(self) -> RecordBatchStream {
+        RecordBatchStream { row_iterator: self }
+    }
+}
+
+/// Stream of Arrow [`RecordBatch`]es, one for every page of a paged query\
+/// Returned by [`RowIterator::into_record_batches`]
+pub struct RecordBatchStream {
+    row_iterator: RowIterator,
+}
+
+impl RecordBatchStream {
+    /// Returns specification of columns of the current page
+    pub fn get_column_specs(&self) -> &[ColumnSpec] {
+        self.row_iterator.get_column_specs()
+    }
+}
+
+impl Stream for RecordBatchStream {
+    type Item = Result<RecordBatch, ArrowConversionError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let rows = match self.row_iterator.poll_next_page_rows(cx) {
+            Poll::Ready(Some(Ok(rows))) => rows,
+            Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err.into()))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        Poll::Ready(Some(rows_to_record_batch(
+            self.row_iterator.get_column_specs(),
+            &rows,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{arrow_schema, rows_to_record_batch, ArrowConversionError};
+    use crate::frame::response::result::{ColumnSpec, ColumnType, CqlValue, Row, TableSpec};
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float32Type, Int32Type};
+    use arrow_array::Array;
+    use arrow_schema::DataType;
+
+    fn col_spec(name: &str, typ: ColumnType) -> ColumnSpec {
+        ColumnSpec {
+            table_spec: TableSpec {
+                ks_name: "ks".to_string(),
+                table_name: "tab".to_string(),
+            },
+            name: name.to_string(),
+            typ,
+        }
+    }
+
+    #[test]
+    fn rows_to_record_batch_test() {
+        let udt = ColumnType::UserDefinedType {
+            type_name: "udt".to_string(),
+            keyspace: "ks".to_string(),
+            field_types: vec![
+                ("x".to_string(), ColumnType::Int),
+                ("y".to_string(), ColumnType::Text),
+            ],
+        };
+        let col_specs = vec![
+            col_spec("a", ColumnType::Int),
+            col_spec("b", ColumnType::List(Box::new(ColumnType::Text))),
+            col_spec(
+                "c",
+                ColumnType::Map(Box::new(ColumnType::Text), Box::new(ColumnType::Int)),
+            ),
+            col_spec("d", udt),
+            col_spec(
+                "e",
+                ColumnType::Vector {
+                    element: Box::new(ColumnType::Float),
+                    dimensions: 2,
+                },
+            ),
+        ];
+        let rows = vec![
+            Row {
+                columns: vec![
+                    Some(CqlValue::Int(1)),
+                    Some(CqlValue::List(vec![
+                        CqlValue::Text("p".to_string()),
+                        CqlValue::Text("q".to_string()),
+                    ])),
+                    Some(CqlValue::Map(vec![(
+                        CqlValue::Text("k".to_string()),
+                        CqlValue::Int(2),
+                    )])),
+                    Some(CqlValue::UserDefinedType {
+                        keyspace: "ks".to_string(),
+                        type_name: "udt".to_string(),
+                        // The last field is missing
+                        fields: vec![("x".to_string(), Some(CqlValue::Int(3)))],
+                    }),
+                    Some(CqlValue::Vector(vec![
+                        CqlValue::Float(0.5),
+                        CqlValue::Float(1.5),
+                    ])),
+                ],
+            },
+            Row {
+                columns: vec![None, None, None, None, None],
+            },
+        ];
+
+        let batch = rows_to_record_batch(&col_specs, &rows).unwrap();
+        assert_eq!(batch.schema().as_ref(), &arrow_schema(&col_specs).unwrap());
+        assert_eq!(batch.num_rows(), 2);
+
+        let a = batch.column(0).as_primitive::<Int32Type>();
+        assert_eq!(a.value(0), 1);
+        assert!(a.is_null(1));
+
+        let b = batch.column(1).as_list::<i32>();
+        assert_eq!(b.value(0).as_string::<i32>().value(1), "q");
+        assert!(b.is_null(1));
+
+        let c = batch.column(2).as_map();
+        assert_eq!(c.keys().as_string::<i32>().value(0), "k");
+        assert_eq!(c.values().as_primitive::<Int32Type>().value(0), 2);
+        assert!(c.is_null(1));
+
+        let d = batch.column(3).as_struct();
+        assert_eq!(d.column(0).as_primitive::<Int32Type>().value(0), 3);
+        assert!(d.column(1).is_null(0));
+        assert!(d.is_null(1));
+
+        let e = batch.column(4).as_fixed_size_list();
+        assert_eq!(
+            e.value(0).as_primitive::<Float32Type>().values(),
+            &[0.5, 1.5]
+        );
+        assert!(e.is_null(1));
+        assert!(matches!(
+            batch.schema().field(4).data_type(),
+            DataType::FixedSizeList(_, 2)
+        ));
+    }
+
+    #[test]
+    fn rows_to_record_batch_errors() {
+        let col_specs = vec![col_spec("a", ColumnType::Int)];
+
+        let rows = vec![Row {
+            columns: vec![Some(CqlValue::Text("a".to_string()))],
+        }];
+        assert!(matches!(
+            rows_to_record_batch(&col_specs, &rows),
+            Err(ArrowConversionError::TypeMismatch { .. })
+        ));
+
+        let rows = vec![Row {
+            columns: vec![None, None],
+        }];
+        assert!(matches!(
+            rows_to_record_batch(&col_specs, &rows),
+            Err(ArrowConversionError::WrongRowSize {
+                expected: 1,
+                actual: 2
+            })
+        ));
+
+        let col_specs = vec![col_spec(
+            "a",
+            ColumnType::Map(Box::new(ColumnType::Int), Box::new(ColumnType::Int)),
+        )];
+        let rows = vec![Row {
+            columns: vec![Some(CqlValue::Map(vec![
+                (CqlValue::Int(1), CqlValue::Int(1)),
+                (CqlValue::Empty, CqlValue::Int(2)),
+            ]))],
+        }];
+        assert!(matches!(
+            rows_to_record_batch(&col_specs, &rows),
+            Err(ArrowConversionError::EmptyMapKey(_))
+        ));
+
+        let col_specs = vec![col_spec("a", ColumnType::Custom("Foo".to_string()))];
+        assert!(matches!(
+            arrow_schema(&col_specs),
+            Err(ArrowConversionError::UnsupportedType(_))
+        ));
+    }
+}
@@ -28,7 +28,7 @@ use super::errors::{BadQuery, NewSessionError, QueryError};
 use super::execution_profile::{ExecutionProfile, ExecutionProfileHandle, ExecutionProfileInner};
 use super::partitioner::PartitionerName;
 use super::topology::UntranslatedPeer;
-use crate::cql_to_rust::FromRow;
+use crate::cql_to_rust::{FromRow, FromRowWithSpecs, RowTypeCheckError};
 use crate::frame::response::cql_to_rust::FromRowError;
 use crate::frame::response::result;
 use crate::frame::value::{
@@ -40,6 +40,7 @@ use crate::routing::Token;
 use crate::statement::{Consistency, SerialConsistency};
 use crate::tracing::{GetTracingConfig, TracingEvent, TracingInfo};
 use crate::transport::cluster::{Cluster, ClusterData, ClusterNeatDebug};
+use crate::transport::concurrency::RequestLimiter;
 use crate::transport::connection::{Connection, ConnectionConfig, VerifiedKeyspaceName};
 use crate::transport::connection_pool::PoolConfig;
 use crate::transport::host_filter::HostFilter;
@@ -49,14 +50,18 @@ use crate::transport::metrics::Metrics;
 use crate::transport::node::Node;
 use crate::transport::query_result::QueryResult;
 use crate::transport::retry_policy::{QueryInfo, RetryDecision, RetrySession};
+use crate::transport::shutdown::{RequestKind, RequestTracker};
 use crate::transport::speculative_execution;
-use crate::transport::Compression;
+use crate::transport::table_scan::TableScan;
+use crate::transport::{Compression, ProtocolVersion};
 use crate::{
     batch::{Batch, BatchStatement},
     statement::StatementConfig,
 };
 
+pub use crate::transport::concurrency::ConcurrencyLimits;
 pub use crate::transport::connection_pool::PoolSize;
+pub use crate::transport::shutdown::ShutdownReport;
 
 use crate::authentication::AuthenticatorProvider;
 #[cfg(feature = "ssl")]
@@ -117,6 +122,8 @@ pub struct Session {
     metrics: Arc<Metrics>,
     auto_await_schema_agreement_timeout: Option<Duration>,
     refresh_metadata_on_auto_schema_agreement: bool,
+    protocol_version: ProtocolVersion,
+    request_tracker: Arc<RequestTracker>,
 }
 
 /// This implementation deliberately omits some details from Cluster in order
@@ -131,6 +138,7 @@ impl std::fmt::Debug for Session {
             )
             .field("schema_agreement_interval", &self.schema_agreement_interval)
             .field("metrics", &self.metrics)
+            .field("protocol_version", &self.protocol_version)
             .field(
                 "auto_await_schema_agreement_timeout",
                 &self.auto_await_schema_agreement_timeout,
@@ -152,6 +160,11 @@ pub struct SessionConfig {
     /// Preferred compression algorithm to use on connections.
     /// If it's not supported by database server Session will fall back to no compression.
     pub compression: Option<Compression>,
+
+    /// The highest version of the CQL native protocol to use on connections.
+    /// Connections fall back to older versions if the server does not support it.
+    /// The default is [`ProtocolVersion::V5`].
+    pub protocol_version: ProtocolVersion,
     pub tcp_nodelay: bool,
 
     pub default_execution_profile_handle: ExecutionProfileHandle,
@@ -199,6 +212,10 @@ pub struct SessionConfig {
     /// If true, full schema metadata is fetched after successfully reaching a schema agreement.
     /// It is true by default but can be disabled if successive schema-altering statements should be performed.
     pub refresh_metadata_on_auto_schema_agreement: bool,
+
+    /// Limits of the number of requests in flight per connection, per node and per session.
+    /// There are no limits by default.
+    pub concurrency_limits: ConcurrencyLimits,
 }
 
 /// Describes database server known on Session startup.
@@ -223,6 +240,7 @@ impl SessionConfig {
         SessionConfig {
             known_nodes: Vec::new(),
             compression: None,
+            protocol_version: ProtocolVersion::default(),
             tcp_nodelay: true,
             schema_agreement_interval: Duration::from_millis(200),
             default_execution_profile_handle: ExecutionProfile::new_from_inner(Default::default())
@@ -242,6 +260,7 @@ impl SessionConfig {
             address_translator: None,
             host_filter: None,
             refresh_metadata_on_auto_schema_agreement: true,
+            concurrency_limits: Default::default(),
         }
     }
 
@@ -304,12 +323,19 @@ impl SessionConfig {
     }
 
     /// Creates a PoolConfig which can be used to create NodeConnectionPools
-    fn get_pool_config(&self) -> PoolConfig {
+    fn get_pool_config(&self, metrics: &Arc<Metrics>) -> PoolConfig {
+        let request_limiter = self.concurrency_limits.is_limited().then(|| {
+            Arc::new(RequestLimiter::new(
+                self.concurrency_limits.clone(),
+                metrics.clone(),
+            ))
+        });
         PoolConfig {
             connection_config: self.get_connection_config(),
             pool_size: self.connection_pool_size.clone(),
             can_use_shard_aware_port: !self.disallow_shard_aware_port,
             keepalive_interval: self.keepalive_interval,
+            request_limiter,
         }
     }
 
@@ -317,6 +343,7 @@ impl SessionConfig {
     fn get_connection_config(&self) -> ConnectionConfig {
         ConnectionConfig {
             compression: self.compression,
+            protocol_version: self.protocol_version,
             tcp_nodelay: self.tcp_nodelay,
             #[cfg(feature = "ssl")]
             ssl_context: self.ssl_context.clone(),
@@ -324,6 +351,8 @@ impl SessionConfig {
             connect_timeout: self.connect_timeout,
             event_sender: None,
             default_consistency: self.default_execution_profile_handle.access().consistency,
+            request_limiter: None,
+            negotiated_protocol_version: Default::default(),
         }
     }
 }
@@ -347,23 +376,41 @@ impl IntoTypedRows for Vec<result::Row> {
     fn into_typed<RowT: FromRow>(self) -> TypedRowIter<RowT> {
         TypedRowIter {
             row_iter: self.into_iter(),
-            phantom_data: Default::default(),
+            column_mapping: Ok(()),
         }
     }
 }
 
 /// Iterator over rows parsed as the given type\
 /// Returned by `rows.into_typed::<(...)>()`
-pub struct TypedRowIter<RowT: FromRow> {
+pub struct TypedRowIter<RowT: FromRowWithSpecs> {
     row_iter: std::vec::IntoIter<result::Row>,
-    phantom_data: std::marker::PhantomData<RowT>,
+    // Computed once from the column specs when created from a QueryResult.
+    // If the columns don't match RowT, each row is returned as the error.
+    column_mapping: Result<RowT::ColumnMapping, RowTypeCheckError>,
+}
+
+impl<RowT: FromRowWithSpecs> TypedRowIter<RowT> {
+    pub(crate) fn with_column_mapping(
+        rows: Vec<result::Row>,
+        column_mapping: Result<RowT::ColumnMapping, RowTypeCheckError>,
+    ) -> Self {
+        TypedRowIter {
+            row_iter: rows.into_iter(),
+            column_mapping,
+        }
+    }
 }
 
-impl<RowT: FromRow> Iterator for TypedRowIter<RowT> {
+impl<RowT: FromRowWithSpecs> Iterator for TypedRowIter<RowT> {
     type Item = Result<RowT, FromRowError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.row_iter.next().map(RowT::from_row)
+        let row = self.row_iter.next()?;
+        Some(match &self.column_mapping {
+            Ok(mapping) => RowT::from_row_with_mapping(row, mapping),
+            Err(err) => Err(err.clone().into()),
+        })
     }
 }
 
@@ -420,9 +467,10 @@ impl Session {
 
         node_addresses.extend(resolved);
 
+        let metrics = Arc::new(Metrics::new());
         let cluster = Cluster::new(
             &node_addresses,
-            config.get_pool_config(),
+            config.get_pool_config(&metrics),
             config.keyspaces_to_fetch,
             config.fetch_schema_metadata,
             &config.address_translator,
@@ -430,16 +478,26 @@ impl Session {
         )
         .await?;
 
+        // Nodes may differ in the versions they support, report the lowest one
+        let protocol_version = cluster
+            .get_working_connections()
+            .await
+            .ok()
+            .and_then(|connections| connections.iter().map(|c| c.get_protocol_version()).min())
+            .unwrap_or(config.protocol_version);
+
         let default_execution_profile_handle = config.default_execution_profile_handle;
 
         let session = Session {
             cluster,
             default_execution_profile_handle,
             schema_agreement_interval: config.schema_agreement_interval,
-            metrics: Arc::new(Metrics::new()),
+            metrics,
             auto_await_schema_agreement_timeout: config.auto_await_schema_agreement_timeout,
             refresh_metadata_on_auto_schema_agreement: config
                 .refresh_metadata_on_auto_schema_agreement,
+            protocol_version,
+            request_tracker: Arc::new(RequestTracker::default()),
         };
 
         if let Some(keyspace_name) = config.used_keyspace {
@@ -559,6 +617,7 @@ impl Session {
                 response: NonErrorResponse::Result(result::Result::Void),
                 tracing_id: None,
                 warnings: Vec::new(),
+                custom_payload: None,
             },
             RunQueryResult::Completed(response) => response,
         };
@@ -567,7 +626,7 @@ impl Session {
         self.handle_auto_await_schema_agreement(&query.contents, &response)
             .await?;
 
-        response.into_query_result()
+        response.into_query_result_with_lazy_rows(query.get_lazy_deserialization())
     }
 
     async fn handle_set_keyspace_response(
@@ -660,6 +719,8 @@ impl Session {
             .unwrap_or_else(|| self.get_default_execution_profile_handle())
             .access();
 
+        let request = self.request_tracker.start(RequestKind::Iterator)?;
+
         let span = trace_span!("Request", query = query.contents.as_str());
         RowIterator::new_for_query(
             query,
@@ -667,6 +728,7 @@ impl Session {
             execution_profile,
             self.cluster.get_data(),
             self.metrics.clone(),
+            request,
         )
         .instrument(span)
         .await
@@ -708,8 +770,15 @@ impl Session {
     /// # }
     /// ```
     pub async fn prepare(&self, query: impl Into<Query>) -> Result<PreparedStatement, QueryError> {
-        let query = query.into();
+        self.request_tracker
+            .run(self.prepare_on_all_connections(query.into()))
+            .await
+    }
 
+    async fn prepare_on_all_connections(
+        &self,
+        query: Query,
+    ) -> Result<PreparedStatement, QueryError> {
         let connections = self.cluster.get_working_connections().await?;
 
         // Prepare statements on all connections concurrently
@@ -827,11 +896,23 @@ impl Session {
         values: impl ValueList,
         paging_state: Option<Bytes>,
     ) -> Result<QueryResult, QueryError> {
-        let serialized_values = values.serialized()?;
-        let values_ref = &serialized_values;
-        let paging_state_ref = &paging_state;
-
+        let serialized_values = prepared.serialize_values(&values)?;
         let token = self.calculate_token(prepared, &serialized_values)?;
+        self.execute_paged_for_token(prepared, &serialized_values, paging_state, token)
+            .await
+    }
+
+    /// Same as [`execute_paged`](Session::execute_paged), but the statement is routed
+    /// to the replicas owning `token` instead of the token of the bound partition key
+    pub(crate) async fn execute_paged_for_token(
+        &self,
+        prepared: &PreparedStatement,
+        serialized_values: &SerializedValues,
+        paging_state: Option<Bytes>,
+        token: Option<Token>,
+    ) -> Result<QueryResult, QueryError> {
+        let values_ref = serialized_values;
+        let paging_state_ref = &paging_state;
 
         let statement_info = Statement {
             token,
@@ -882,6 +963,7 @@ impl Session {
                 response: NonErrorResponse::Result(result::Result::Void),
                 tracing_id: None,
                 warnings: Vec::new(),
+                custom_payload: None,
             },
             RunQueryResult::Completed(response) => response,
         };
@@ -890,7 +972,7 @@ impl Session {
         self.handle_auto_await_schema_agreement(prepared.get_statement(), &response)
             .await?;
 
-        response.into_query_result()
+        response.into_query_result_with_lazy_rows(prepared.get_lazy_deserialization())
     }
 
     /// Run a prepared query with paging\
@@ -940,7 +1022,7 @@ impl Session {
         values: impl ValueList,
     ) -> Result<RowIterator, QueryError> {
         let prepared = prepared.into();
-        let serialized_values = values.serialized()?;
+        let serialized_values = prepared.serialize_values(&values)?;
 
         let token = self.calculate_token(&prepared, &serialized_values)?;
 
@@ -949,22 +1031,65 @@ impl Session {
             .unwrap_or_else(|| self.get_default_execution_profile_handle())
             .access();
 
+        let request = self.request_tracker.start(RequestKind::Iterator)?;
+
         let span = trace_span!(
             "Request",
             prepared_id = format!("{:X}", prepared.get_id()).as_str()
         );
-        RowIterator::new_for_prepared_statement(PreparedIteratorConfig {
-            prepared,
-            values: serialized_values.into_owned(),
-            token,
-            execution_profile,
-            cluster_data: self.cluster.get_data(),
-            metrics: self.metrics.clone(),
-        })
+        RowIterator::new_for_prepared_statement(
+            PreparedIteratorConfig {
+                prepared,
+                values: serialized_values.into_owned(),
+                token,
+                execution_profile,
+                cluster_data: self.cluster.get_data(),
+                metrics: self.metrics.clone(),
+            },
+            request,
+        )
         .instrument(span)
         .await
     }
 
+    /// Reads all rows of a table, querying its token ranges in parallel.\
+    /// The token ring is split into ranges owned by a single replica and shard,
+    /// each range is read with a separate query routed to its owner.
+    /// Rows of all the ranges are merged into one stream, see [`TableScan`] for the details.
+    ///
+    /// # Arguments
+    /// * `keyspace` - name of the keyspace, the table must be in the cluster metadata
+    /// * `table` - name of the table
+    /// * `columns` - names of the selected columns, all columns if empty
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use futures::stream::StreamExt;
+    ///
+    /// let mut scan = session
+    ///     .scan_table("ks", "tab", &["a", "b"])
+    ///     .await?
+    ///     .parallelism(32);
+    ///
+    /// while let Some(row) = scan.next().await {
+    ///     let (a, b): (i32, i32) = row?.into_typed()?;
+    ///     println!("a, b: {}, {}", a, b);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn scan_table(
+        &self,
+        keyspace: &str,
+        table: &str,
+        columns: &[&str],
+    ) -> Result<TableScan<'_>, QueryError> {
+        TableScan::new(self, keyspace, table, columns).await
+    }
+
     /// Perform a batch query\
     /// Batch contains many `simple` or `prepared` queries which are executed at once\
     /// Batch doesn't return any rows
@@ -1010,6 +1135,25 @@ impl Session {
         batch: &Batch,
         values: impl BatchValues,
     ) -> Result<QueryResult, QueryError> {
+        // Values bound to prepared statements are type checked, same as in `execute`.
+        // The iterator can't live across an await, see `BatchValues::BatchValuesIter`
+        {
+            let mut values_iter = values.batch_values_iter();
+            for statement in &batch.statements {
+                let type_check_result = match statement {
+                    BatchStatement::PreparedStatement(ps) => {
+                        values_iter.type_check_next(&ps.get_prepared_metadata().col_specs)
+                    }
+                    BatchStatement::Query(_) => values_iter.skip_next().map(|()| Ok(())),
+                };
+                match type_check_result {
+                    Some(result) => result.map_err(BadQuery::from)?,
+                    // The number of values is checked when serializing the batch
+                    None => break,
+                }
+            }
+        }
+
         // Shard-awareness behavior for batch will be to pick shard based on first batch statement's shard
         // If users batch statements by shard, they will be rewarded with full shard awareness
 
@@ -1166,7 +1310,9 @@ impl Session {
         // To avoid any possible CQL injections it's good to verify that the name is valid
         let verified_ks_name = VerifiedKeyspaceName::new(keyspace_name.into(), case_sensitive)?;
 
-        self.cluster.use_keyspace(verified_ks_name).await?;
+        self.request_tracker
+            .run(self.cluster.use_keyspace(verified_ks_name))
+            .await?;
 
         Ok(())
     }
@@ -1177,7 +1323,46 @@ impl Session {
     /// Normally this is not needed,
     /// the driver should automatically detect all metadata changes in the cluster
     pub async fn refresh_metadata(&self) -> Result<(), QueryError> {
-        self.cluster.refresh_metadata().await
+        self.request_tracker
+            .run(self.cluster.refresh_metadata())
+            .await
+    }
+
+    /// Shuts the session down gracefully.\
+    /// New requests fail with [`QueryError::SessionShutDown`] right away, the in-flight ones,
+    /// including the workers of [`RowIterator`]s, are given `timeout` to finish.
+    /// Requests still running after that are aborted, then all pooled connections are closed
+    /// and the background tasks of the session stop.
+    ///
+    /// Returns how many requests were aborted and how many connections were closed.
+    /// Calling `shutdown` again does nothing.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    ///
+    /// let report = session.shutdown(Duration::from_secs(10)).await;
+    /// if !report.is_clean() {
+    ///     println!(
+    ///         "Aborted {} requests and {} iterators",
+    ///         report.aborted_requests, report.aborted_iterators
+    ///     );
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        let (aborted_requests, aborted_iterators) = self.request_tracker.shut_down(timeout).await;
+        let closed_connections = self.cluster.shutdown().await;
+
+        ShutdownReport {
+            aborted_requests,
+            aborted_iterators,
+            closed_connections,
+        }
     }
 
     /// Access metrics collected by the driver\
@@ -1187,6 +1372,13 @@ impl Session {
         self.metrics.clone()
     }
 
+    /// Returns the version of the CQL native protocol negotiated with the cluster
+    /// when the session was created.\
+    /// If nodes support different versions, the lowest of them is returned.
+    pub fn get_protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
     /// Access cluster data collected by the driver\
     /// Driver collects various information about network topology or schema.
     /// They can be read using this method
@@ -1321,6 +1513,9 @@ impl Session {
                 Err(err) => match err {
                     // "fast" errors, i.e. ones that are returned quickly after the query begins
                     QueryError::BadQuery(_)
+                    | QueryError::SessionShutDown
+                    | QueryError::RequestQueueFull
+                    | QueryError::RequestQueueTimeout
                     | QueryError::TooManyOrphanedStreamIds(_)
                     | QueryError::UnableToAllocStreamId
                     | QueryError::DbError(DbError::IsBootstrapping, _)
@@ -1335,6 +1530,9 @@ impl Session {
                     | QueryError::ProtocolError(_)
                     | QueryError::TimeoutError
                     | QueryError::RequestTimeout(_) => true,
+
+                    // `QueryError` is non-exhaustive, errors not known here don't skew the measurements
+                    _ => false,
                 },
             }
     }
@@ -1358,6 +1556,29 @@ impl Session {
         choose_connection: impl Fn(Arc<Node>) -> ConnFut,
         do_query: impl Fn(Arc<Connection>, Consistency, &ExecutionProfileInner) -> QueryFut,
     ) -> Result<RunQueryResult<ResT>, QueryError>
+    where
+        ConnFut: Future<Output = Result<Arc<Connection>, QueryError>>,
+        QueryFut: Future<Output = Result<ResT, QueryError>>,
+        ResT: AllowedRunQueryResTType,
+    {
+        // The request is tracked, so that `shutdown` waits for it or aborts it
+        self.request_tracker
+            .run(self.run_query_on_plan(
+                statement_info,
+                statement_config,
+                choose_connection,
+                do_query,
+            ))
+            .await
+    }
+
+    async fn run_query_on_plan<'a, ConnFut, QueryFut, ResT>(
+        &'a self,
+        statement_info: Statement<'a>,
+        statement_config: &'a StatementConfig,
+        choose_connection: impl Fn(Arc<Node>) -> ConnFut,
+        do_query: impl Fn(Arc<Connection>, Consistency, &ExecutionProfileInner) -> QueryFut,
+    ) -> Result<RunQueryResult<ResT>, QueryError>
     where
         ConnFut: Future<Output = Result<Arc<Connection>, QueryError>>,
         QueryFut: Future<Output = Result<ResT, QueryError>>,
@@ -1677,14 +1898,18 @@ impl Session {
     }
 
     pub async fn check_schema_agreement(&self) -> Result<bool, QueryError> {
-        let connections = self.cluster.get_working_connections().await?;
+        self.request_tracker
+            .run(async {
+                let connections = self.cluster.get_working_connections().await?;
 
-        let handles = connections.iter().map(|c| c.fetch_schema_version());
-        let versions = try_join_all(handles).await?;
+                let handles = connections.iter().map(|c| c.fetch_schema_version());
+                let versions = try_join_all(handles).await?;
 
-        let local_version: Uuid = versions[0];
-        let in_agreement = versions.into_iter().all(|v| v == local_version);
-        Ok(in_agreement)
+                let local_version: Uuid = versions[0];
+                let in_agreement = versions.into_iter().all(|v| v == local_version);
+                Ok(in_agreement)
+            })
+            .await
     }
 
     pub async fn fetch_schema_version(&self) -> Result<Uuid, QueryError> {
@@ -1718,7 +1943,7 @@ impl Session {
     }
 }
 
-fn calculate_partition_key(
+pub(crate) fn calculate_partition_key(
     stmt: &PreparedStatement,
     values: &SerializedValues,
 ) -> Result<Bytes, QueryError> {
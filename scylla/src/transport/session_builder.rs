@@ -2,8 +2,8 @@
 
 use super::errors::NewSessionError;
 use super::execution_profile::ExecutionProfileHandle;
-use super::session::{AddressTranslator, Session, SessionConfig};
-use super::Compression;
+use super::session::{AddressTranslator, ConcurrencyLimits, Session, SessionConfig};
+use super::{Compression, ProtocolVersion};
 use crate::transport::connection_pool::PoolSize;
 use crate::transport::host_filter::HostFilter;
 use std::net::SocketAddr;
@@ -146,6 +146,33 @@ impl SessionBuilder {
         self
     }
 
+    /// Set the highest version of the CQL native protocol used on connections.
+    /// The default is [`ProtocolVersion::V5`]. If a node rejects the version,
+    /// the connection falls back to older versions. The version negotiated by one
+    /// connection is used by the following ones, so that every new connection doesn't
+    /// have to be rejected first. The version in use can be checked
+    /// with [`Session::get_protocol_version`].
+    /// Protocol v5 requires Cassandra 4.0 or newer; with it,
+    /// frames are protected by checksums and compression is limited to LZ4.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::ProtocolVersion;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .protocol_version(ProtocolVersion::V4)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn protocol_version(mut self, protocol_version: ProtocolVersion) -> Self {
+        self.config.protocol_version = protocol_version;
+        self
+    }
+
     /// Set the nodelay TCP flag.
     /// The default is true.
     ///
@@ -671,6 +698,34 @@ impl SessionBuilder {
         self.config.refresh_metadata_on_auto_schema_agreement = refresh_metadata;
         self
     }
+
+    /// Limits the number of requests in flight per connection, per node and per session.
+    /// Requests over the limits wait in a bounded queue instead of failing,
+    /// see [`ConcurrencyLimits`].
+    /// There are no limits by default.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::session::ConcurrencyLimits;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .concurrency_limits(ConcurrencyLimits {
+    ///         max_requests_per_connection: Some(1024),
+    ///         queue_timeout: Duration::from_millis(500),
+    ///         ..Default::default()
+    ///     })
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.config.concurrency_limits = limits;
+        self
+    }
 }
 
 /// Creates a [`SessionBuilder`] with default configuration, same as [`SessionBuilder::new`]
@@ -688,8 +743,8 @@ mod tests {
     use super::SessionBuilder;
     use crate::load_balancing::LatencyAwarePolicy;
     use crate::transport::execution_profile::{defaults, ExecutionProfile};
-    use crate::transport::session::KnownNode;
-    use crate::transport::Compression;
+    use crate::transport::session::{ConcurrencyLimits, KnownNode};
+    use crate::transport::{Compression, ProtocolVersion};
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
     use std::sync::Arc;
     use std::time::Duration;
@@ -773,6 +828,15 @@ mod tests {
         assert_eq!(builder.config.compression, None);
     }
 
+    #[test]
+    fn protocol_version() {
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.protocol_version, ProtocolVersion::V5);
+
+        builder = builder.protocol_version(ProtocolVersion::V4);
+        assert_eq!(builder.config.protocol_version, ProtocolVersion::V4);
+    }
+
     #[test]
     fn tcp_nodelay() {
         let mut builder = SessionBuilder::new();
@@ -889,6 +953,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn concurrency_limits() {
+        let mut builder = SessionBuilder::new();
+        assert!(!builder.config.concurrency_limits.is_limited());
+
+        let limits = ConcurrencyLimits {
+            max_requests_per_node: Some(100),
+            max_queued_requests: 10,
+            ..Default::default()
+        };
+        builder = builder.concurrency_limits(limits.clone());
+        assert!(builder.config.concurrency_limits.is_limited());
+        assert_eq!(builder.config.concurrency_limits, limits);
+    }
+
     #[test]
     fn all_features() {
         let mut builder = SessionBuilder::new();
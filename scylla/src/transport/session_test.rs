@@ -2680,3 +2680,217 @@ async fn test_iter_methods_with_modification_statements() {
         .unwrap();
     row_iterator.next().await.ok_or(()).unwrap_err(); // assert empty
 }
+
+#[tokio::test]
+async fn test_table_mapper() {
+    use crate::statement::table::{Table, TableMapper};
+
+    #[derive(scylla::FromRow, scylla::macros::Table, PartialEq, Debug)]
+    #[table = "events"]
+    struct Event {
+        #[partition_key]
+        user_id: i32,
+        #[clustering_key]
+        at: i64,
+        payload: Option<String>,
+    }
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session.use_keyspace(&ks, false).await.unwrap();
+    session
+        .query(
+            "CREATE TABLE IF NOT EXISTS events (user_id int, at bigint, payload text, primary key (user_id, at))",
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let mapper = TableMapper::<Event>::prepare(&session).await.unwrap();
+    let mut event = Event {
+        user_id: 1,
+        at: 2,
+        payload: Some("abc".to_string()),
+    };
+    mapper.insert(&session, &event).await.unwrap();
+    assert_eq!(
+        mapper.get(&session, &event.key()).await.unwrap(),
+        Some(Event {
+            user_id: 1,
+            at: 2,
+            payload: Some("abc".to_string()),
+        })
+    );
+
+    event.payload = None;
+    mapper.update(&session, &event).await.unwrap();
+    assert_eq!(
+        mapper.get(&session, &event.key()).await.unwrap(),
+        Some(event)
+    );
+
+    let key = EventKey { user_id: 1, at: 2 };
+    mapper.delete(&session, &key).await.unwrap();
+    assert_eq!(mapper.get(&session, &key).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_session_shutdown() {
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let mut rows = session
+        .query_iter("SELECT host_id FROM system.peers", &[])
+        .await
+        .unwrap();
+    while rows.try_next().await.unwrap().is_some() {}
+
+    let report = session.shutdown(Duration::from_secs(5)).await;
+    assert!(report.is_clean());
+    assert!(report.closed_connections > 0);
+
+    assert_matches!(
+        session.query("SELECT host_id FROM system.local", &[]).await,
+        Err(QueryError::SessionShutDown)
+    );
+    assert_matches!(
+        session.prepare("SELECT host_id FROM system.local").await,
+        Err(QueryError::SessionShutDown)
+    );
+
+    // The connections are already closed
+    let report = session.shutdown(Duration::from_secs(5)).await;
+    assert_eq!(report.closed_connections, 0);
+}
+
+#[tokio::test]
+async fn test_bulk_writer() {
+    use crate::bulk_writer::BulkWriter;
+    use crate::frame::response::result::CqlValue;
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session.use_keyspace(&ks, false).await.unwrap();
+    session
+        .query(
+            "CREATE TABLE IF NOT EXISTS t (a int, b int, c text, primary key (a, b))",
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let prepared = session
+        .prepare("INSERT INTO t (a, b, c) VALUES (?, ?, ?)")
+        .await
+        .unwrap();
+    let mut writer = BulkWriter::new(prepared);
+    writer.set_concurrency(8);
+    writer.set_chunk_size(32);
+    writer.set_max_batch_size(4);
+
+    let rows = futures::stream::iter((0..100).map(|i: i32| {
+        let mut values = vec![CqlValue::Int(i % 10), CqlValue::Int(i)];
+        // The row without the last value fails, the others are written
+        if i != 5 {
+            values.push(CqlValue::Text(i.to_string()));
+        }
+        values
+    }));
+    let report = writer.write(&session, rows).await;
+
+    assert_eq!(report.rows_written, 99);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].row, 5);
+    assert_matches!(report.failures[0].error, QueryError::BadQuery(_));
+    assert!(report.batches > 0);
+    assert!(report.requests < 99);
+
+    let count: i64 = session
+        .query("SELECT COUNT(*) FROM t", &[])
+        .await
+        .unwrap()
+        .single_row_typed::<(i64,)>()
+        .unwrap()
+        .0;
+    assert_eq!(count, 99);
+}
+
+#[tokio::test]
+async fn test_scan_table() {
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}.t (a int, b int, c text, primary key (a, b))",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    let insert = session
+        .prepare(format!(
+            "INSERT INTO {}.t (a, b, c) VALUES (?, ?, 'abc')",
+            ks
+        ))
+        .await
+        .unwrap();
+    for i in 0..200 {
+        session.execute(&insert, (i % 50, i)).await.unwrap();
+    }
+    session.refresh_metadata().await.unwrap();
+
+    let expected: BTreeSet<(i32, i32)> = (0..200).map(|i| (i % 50, i)).collect();
+    let read_rows = |rows: Vec<Result<Row, QueryError>>| -> Vec<(i32, i32)> {
+        rows.into_iter()
+            .map(|row| row.unwrap().into_typed::<(i32, i32)>().unwrap())
+            .collect()
+    };
+
+    let scan = session
+        .scan_table(&ks, "t", &["a", "b"])
+        .await
+        .unwrap()
+        .parallelism(4)
+        .page_size(7);
+    let rows = read_rows(scan.collect().await);
+    assert_eq!(rows.len(), 200);
+    assert_eq!(rows.into_iter().collect::<BTreeSet<_>>(), expected);
+
+    // Read a part of the table and resume from a checkpoint
+    let mut scan = session
+        .scan_table(&ks, "t", &["a", "b"])
+        .await
+        .unwrap()
+        .page_size(7);
+    let mut rows = read_rows((&mut scan).take(90).collect().await);
+    let checkpoint = scan.checkpoint();
+    assert!(!checkpoint.is_finished());
+    drop(scan);
+
+    let mut scan = session
+        .scan_table(&ks, "t", &["a", "b"])
+        .await
+        .unwrap()
+        .page_size(7)
+        .resume_from(checkpoint);
+    rows.extend(read_rows((&mut scan).collect().await));
+    assert!(scan.checkpoint().is_finished());
+    assert_eq!(rows.len(), 200);
+    assert_eq!(rows.into_iter().collect::<BTreeSet<_>>(), expected);
+
+    assert_matches!(
+        session.scan_table(&ks, "no_such_table", &[]).await.err(),
+        Some(QueryError::BadQuery(BadQuery::Other(_)))
+    );
+}
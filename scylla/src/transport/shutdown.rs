@@ -0,0 +1,219 @@
+//! Tracking of in-flight requests, used to shut a [`Session`](crate::Session) down gracefully
+
+use crate::transport::errors::QueryError;
+use futures::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::debug;
+
+/// Summary of [`Session::shutdown`](crate::Session::shutdown)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ShutdownReport {
+    /// Requests which didn't finish before the timeout, they failed with
+    /// [`QueryError::SessionShutDown`]
+    pub aborted_requests: usize,
+    /// Row iterators which were still fetching pages when the timeout elapsed,
+    /// their next page is [`QueryError::SessionShutDown`]
+    pub aborted_iterators: usize,
+    /// Pooled connections which were closed
+    pub closed_connections: usize,
+}
+
+impl ShutdownReport {
+    /// Whether all in-flight requests finished before the timeout
+    pub fn is_clean(&self) -> bool {
+        self.aborted_requests == 0 && self.aborted_iterators == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum RequestKind {
+    /// A single request, e.g. a query or a prepare
+    Request,
+    /// A worker of a `RowIterator`, fetching pages in the background
+    Iterator,
+}
+
+/// Counts the requests in flight, rejects new ones once the session is shut down
+/// and aborts the remaining ones when the shutdown timeout elapses.
+#[derive(Default)]
+pub(crate) struct RequestTracker {
+    shut_down: AtomicBool,
+    aborted: AtomicBool,
+    in_flight_requests: AtomicUsize,
+    in_flight_iterators: AtomicUsize,
+    // Notified whenever the last in-flight request finishes
+    drained: Notify,
+    // Notified when the remaining requests should be aborted
+    abort: Notify,
+}
+
+impl RequestTracker {
+    /// Registers a new request, fails if the session is shut down
+    pub(crate) fn start(self: &Arc<Self>, kind: RequestKind) -> Result<RequestGuard, QueryError> {
+        // The counter is incremented before checking the flag, so that either the request
+        // sees the flag or the shutdown sees the request and waits for it
+        self.counter(kind).fetch_add(1, Ordering::SeqCst);
+        let guard = RequestGuard {
+            tracker: self.clone(),
+            kind,
+        };
+        if self.shut_down.load(Ordering::SeqCst) {
+            return Err(QueryError::SessionShutDown);
+        }
+        Ok(guard)
+    }
+
+    /// Runs a request, it fails with `SessionShutDown` if it's started after
+    /// the shutdown or is still running when the shutdown timeout elapses
+    pub(crate) async fn run<T>(
+        self: &Arc<Self>,
+        request: impl Future<Output = Result<T, QueryError>>,
+    ) -> Result<T, QueryError> {
+        let guard = self.start(RequestKind::Request)?;
+        tokio::select! {
+            result = request => result,
+            _ = guard.aborted() => Err(QueryError::SessionShutDown),
+        }
+    }
+
+    /// Stops accepting new requests and waits until the in-flight ones finish.
+    /// Requests still running after `timeout` are aborted.\
+    /// Returns the numbers of aborted requests and iterators.
+    pub(crate) async fn shut_down(&self, timeout: Duration) -> (usize, usize) {
+        self.shut_down.store(true, Ordering::SeqCst);
+        if tokio::time::timeout(timeout, self.wait_until_drained())
+            .await
+            .is_ok()
+        {
+            return (0, 0);
+        }
+
+        let aborted_requests = self.in_flight_requests.load(Ordering::SeqCst);
+        let aborted_iterators = self.in_flight_iterators.load(Ordering::SeqCst);
+        debug!(
+            "Shutdown timeout elapsed, aborting {} requests and {} iterators",
+            aborted_requests, aborted_iterators
+        );
+        self.aborted.store(true, Ordering::SeqCst);
+        self.abort.notify_waiters();
+
+        // Aborted requests drop their guards right away
+        self.wait_until_drained().await;
+        (aborted_requests, aborted_iterators)
+    }
+
+    async fn wait_until_drained(&self) {
+        loop {
+            // Register for the notification first so that it isn't missed
+            let drained = self.drained.notified();
+            if self.in_flight_requests.load(Ordering::SeqCst) == 0
+                && self.in_flight_iterators.load(Ordering::SeqCst) == 0
+            {
+                return;
+            }
+            drained.await;
+        }
+    }
+
+    fn counter(&self, kind: RequestKind) -> &AtomicUsize {
+        match kind {
+            RequestKind::Request => &self.in_flight_requests,
+            RequestKind::Iterator => &self.in_flight_iterators,
+        }
+    }
+}
+
+/// Keeps a request counted as in flight until it's dropped
+pub(crate) struct RequestGuard {
+    tracker: Arc<RequestTracker>,
+    kind: RequestKind,
+}
+
+impl RequestGuard {
+    /// Completes when the request should be aborted because the session was shut down
+    pub(crate) async fn aborted(&self) {
+        loop {
+            let abort = self.tracker.abort.notified();
+            if self.tracker.aborted.load(Ordering::SeqCst) {
+                return;
+            }
+            abort.await;
+        }
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        if self
+            .tracker
+            .counter(self.kind)
+            .fetch_sub(1, Ordering::SeqCst)
+            == 1
+        {
+            self.tracker.drained.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RequestKind, RequestTracker};
+    use crate::transport::errors::QueryError;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn shutdown_waits_for_requests() {
+        let tracker = Arc::new(RequestTracker::default());
+        let guard = tracker.start(RequestKind::Request).unwrap();
+
+        let mut shutdown = tokio::spawn({
+            let tracker = tracker.clone();
+            async move { tracker.shut_down(Duration::from_secs(10)).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // New requests are rejected while the old ones are drained
+        assert!(matches!(
+            tracker.start(RequestKind::Request),
+            Err(QueryError::SessionShutDown)
+        ));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), &mut shutdown)
+                .await
+                .is_err()
+        );
+
+        drop(guard);
+        assert_eq!(shutdown.await.unwrap(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn shutdown_aborts_requests_after_timeout() {
+        let tracker = Arc::new(RequestTracker::default());
+        let request = tokio::spawn({
+            let tracker = tracker.clone();
+            async move {
+                tracker
+                    .run(async {
+                        futures::future::pending::<()>().await;
+                        Ok(())
+                    })
+                    .await
+            }
+        });
+        let iterator = tracker.start(RequestKind::Iterator).unwrap();
+        let iterator_worker = tokio::spawn(async move { iterator.aborted().await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(tracker.shut_down(Duration::from_millis(10)).await, (1, 1));
+        assert!(matches!(
+            request.await.unwrap(),
+            Err(QueryError::SessionShutDown)
+        ));
+        iterator_worker.await.unwrap();
+    }
+}
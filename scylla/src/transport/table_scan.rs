@@ -0,0 +1,496 @@
+//! Reading a whole table in parallel, see [`Session::scan_table`]
+
+use crate::frame::response::cql_literal::quote_identifier;
+use crate::frame::response::result::Row;
+use crate::frame::value::ValueList;
+use crate::prepared_statement::PreparedStatement;
+use crate::routing::{Sharder, Token, TokenRange};
+use crate::transport::errors::{BadQuery, DbError, QueryError};
+use crate::transport::session::Session;
+use crate::QueryResult;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{FuturesUnordered, Stream, StreamExt};
+use std::collections::{BTreeMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Rows of a table, read by [`Session::scan_table`].
+///
+/// The token ring is split into ranges owned by a single replica and shard.
+/// Each range is read with a `token(pk) > ? AND token(pk) <= ?` query routed to its owner,
+/// [`parallelism`](TableScan::parallelism) ranges are read at the same time.
+/// Rows of different ranges are interleaved, rows of a single range come in the token order.
+///
+/// A page of a range which fails with a transient error, e.g. a timeout or an unavailable replica,
+/// is retried [`max_range_retries`](TableScan::max_range_retries) times, after a
+/// [`retry_delay`](TableScan::retry_delay) which doubles with each retry.
+/// If it still fails, or fails with another error, the stream returns the error and ends.
+/// The scan can be resumed later from its [`checkpoint`](TableScan::checkpoint).
+pub struct TableScan<'a> {
+    session: &'a Session,
+    statement: Arc<PreparedStatement>,
+    parallelism: usize,
+    max_range_retries: usize,
+    retry_delay: Duration,
+
+    // Ranges which weren't started yet, with their positions in the scan
+    pending: VecDeque<(usize, ScanPosition)>,
+    // Ranges being read
+    active: BTreeMap<usize, ActiveRange>,
+    fetching: FuturesUnordered<BoxFuture<'a, (usize, Result<QueryResult, QueryError>)>>,
+    current_page: Option<CurrentPage>,
+    failed: bool,
+}
+
+/// Where the scan of a single token range stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanPosition {
+    pub range: TokenRange,
+    /// Paging state of the page being read, `None` for the first page
+    pub paging_state: Option<Bytes>,
+    /// Number of rows of the page which were already returned
+    pub skip_rows: usize,
+}
+
+/// Ranges of a [`TableScan`] which weren't read completely.\
+/// Scanning the same table with [`TableScan::resume_from`] returns only the rows
+/// which weren't returned before the checkpoint was taken.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanCheckpoint {
+    pub positions: Vec<ScanPosition>,
+}
+
+impl ScanCheckpoint {
+    /// Checks if the whole table was read
+    pub fn is_finished(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+struct ActiveRange {
+    position: ScanPosition,
+    retries: usize,
+}
+
+struct CurrentPage {
+    range: usize,
+    rows: std::vec::IntoIter<Row>,
+    next_paging_state: Option<Bytes>,
+}
+
+impl<'a> TableScan<'a> {
+    pub(crate) async fn new(
+        session: &'a Session,
+        keyspace: &str,
+        table: &str,
+        columns: &[&str],
+    ) -> Result<TableScan<'a>, QueryError> {
+        let cluster_data = session.get_cluster_data();
+        let table_metadata = cluster_data
+            .get_keyspace_info()
+            .get(keyspace)
+            .and_then(|keyspace| keyspace.tables.get(table))
+            .ok_or_else(|| {
+                BadQuery::Other(format!(
+                    "Table {}.{} not found in the cluster metadata",
+                    keyspace, table
+                ))
+            })?;
+
+        let selected = if columns.is_empty() {
+            "*".to_string()
+        } else {
+            join_identifiers(columns.iter().copied())
+        };
+        let partition_key =
+            join_identifiers(table_metadata.partition_key.iter().map(String::as_str));
+        let query = format!(
+            "SELECT {} FROM {}.{} WHERE token({}) > ? AND token({}) <= ?",
+            selected,
+            quote_identifier(keyspace),
+            quote_identifier(table),
+            partition_key,
+            partition_key
+        );
+        let statement = session.prepare(query).await?;
+
+        let ring: Vec<(TokenRange, Option<Sharder>)> = cluster_data
+            .get_token_ranges()
+            .map(|(range, node)| (range, node.sharder()))
+            .collect();
+        let pending = scan_ranges(ring)
+            .into_iter()
+            .map(|range| ScanPosition {
+                range,
+                paging_state: None,
+                skip_rows: 0,
+            })
+            .enumerate()
+            .collect();
+
+        Ok(TableScan {
+            session,
+            statement: Arc::new(statement),
+            parallelism: 16,
+            max_range_retries: 3,
+            retry_delay: Duration::from_millis(100),
+            pending,
+            active: BTreeMap::new(),
+            fetching: FuturesUnordered::new(),
+            current_page: None,
+            failed: false,
+        })
+    }
+
+    /// Sets the number of token ranges read at the same time, 16 by default
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        assert!(parallelism > 0, "parallelism must be larger than 0");
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Sets how many times a failed page of a token range is retried, 3 by default
+    pub fn max_range_retries(mut self, max_range_retries: usize) -> Self {
+        self.max_range_retries = max_range_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry of a failed page, doubled with each following retry,
+    /// 100 ms by default
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Sets the number of rows in each page
+    pub fn page_size(mut self, page_size: i32) -> Self {
+        self.statement_mut().set_page_size(page_size);
+        self
+    }
+
+    /// Reads only the rows which weren't read before the `checkpoint` was taken,
+    /// instead of the whole table
+    pub fn resume_from(mut self, checkpoint: ScanCheckpoint) -> Self {
+        self.pending = checkpoint.positions.into_iter().enumerate().collect();
+        self
+    }
+
+    /// The statement reading a token range, e.g. to set its consistency
+    pub fn statement_mut(&mut self) -> &mut PreparedStatement {
+        Arc::make_mut(&mut self.statement)
+    }
+
+    /// Ranges which weren't read completely, taking the rows already returned into account
+    pub fn checkpoint(&self) -> ScanCheckpoint {
+        let mut positions: Vec<(usize, &ScanPosition)> = self
+            .active
+            .iter()
+            .map(|(index, active)| (*index, &active.position))
+            .chain(
+                self.pending
+                    .iter()
+                    .map(|(index, position)| (*index, position)),
+            )
+            .collect();
+        positions.sort_by_key(|(index, _)| *index);
+
+        ScanCheckpoint {
+            positions: positions
+                .into_iter()
+                .map(|(_, position)| position.clone())
+                .collect(),
+        }
+    }
+
+    fn start_ranges(&mut self) {
+        while self.active.len() < self.parallelism {
+            let (index, position) = match self.pending.pop_front() {
+                Some(pending) => pending,
+                None => return,
+            };
+            self.active.insert(
+                index,
+                ActiveRange {
+                    position,
+                    retries: 0,
+                },
+            );
+            self.fetch_page(index);
+        }
+    }
+
+    fn fetch_page(&mut self, index: usize) {
+        let active = &self.active[&index];
+        let position = &active.position;
+        let delay = match active.retries {
+            0 => None,
+            retries => Some(
+                self.retry_delay
+                    .saturating_mul(1 << (retries - 1).min(16) as u32),
+            ),
+        };
+        let session = self.session;
+        let statement = self.statement.clone();
+        let range = position.range;
+        let paging_state = position.paging_state.clone();
+
+        self.fetching.push(Box::pin(async move {
+            if let Some(delay) = delay {
+                tokio::time::sleep(delay).await;
+            }
+            let result = match (range.start.value, range.end.value).serialized() {
+                // The range is routed to the owner of its last token
+                Ok(values) => {
+                    session
+                        .execute_paged_for_token(&statement, &values, paging_state, Some(range.end))
+                        .await
+                }
+                Err(err) => Err(err.into()),
+            };
+            (index, result)
+        }));
+    }
+
+    fn page_fetched(
+        &mut self,
+        index: usize,
+        result: Result<QueryResult, QueryError>,
+    ) -> Result<(), QueryError> {
+        let active = match self.active.get_mut(&index) {
+            Some(active) => active,
+            None => return Ok(()),
+        };
+        match result {
+            Ok(result) => {
+                active.retries = 0;
+                let next_paging_state = result.paging_state.clone();
+                let mut rows = result.rows_or_empty()?;
+                // Rows returned before the scan was resumed
+                rows.drain(..active.position.skip_rows.min(rows.len()));
+                self.current_page = Some(CurrentPage {
+                    range: index,
+                    rows: rows.into_iter(),
+                    next_paging_state,
+                });
+                Ok(())
+            }
+            Err(err) if is_transient(&err) && active.retries < self.max_range_retries => {
+                active.retries += 1;
+                self.fetch_page(index);
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn page_finished(&mut self, page: CurrentPage) {
+        match page.next_paging_state {
+            Some(paging_state) => {
+                if let Some(active) = self.active.get_mut(&page.range) {
+                    active.position.paging_state = Some(paging_state);
+                    active.position.skip_rows = 0;
+                    self.fetch_page(page.range);
+                }
+            }
+            None => {
+                self.active.remove(&page.range);
+            }
+        }
+    }
+}
+
+impl Stream for TableScan<'_> {
+    type Item = Result<Row, QueryError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let scan = &mut *self;
+        loop {
+            if let Some(page) = &mut scan.current_page {
+                if let Some(row) = page.rows.next() {
+                    if let Some(active) = scan.active.get_mut(&page.range) {
+                        active.position.skip_rows += 1;
+                    }
+                    return Poll::Ready(Some(Ok(row)));
+                }
+                if let Some(page) = scan.current_page.take() {
+                    scan.page_finished(page);
+                }
+                continue;
+            }
+            if scan.failed {
+                return Poll::Ready(None);
+            }
+
+            scan.start_ranges();
+            match scan.fetching.poll_next_unpin(cx) {
+                Poll::Ready(Some((index, result))) => {
+                    if let Err(err) = scan.page_fetched(index, result) {
+                        // The unfinished ranges stay in the checkpoint
+                        scan.failed = true;
+                        scan.fetching = FuturesUnordered::new();
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Splits the ranges of the token ring into ranges which don't wrap around
+/// and are owned by a single shard
+fn scan_ranges(ring: Vec<(TokenRange, Option<Sharder>)>) -> Vec<TokenRange> {
+    ring.into_iter()
+        .flat_map(|(range, sharder)| {
+            range
+                .unwrap()
+                .into_iter()
+                .flat_map(move |part| split_by_shard(part, sharder.as_ref()))
+        })
+        .collect()
+}
+
+/// Splits the range into ranges belonging to a single shard
+fn split_by_shard(range: TokenRange, sharder: Option<&Sharder>) -> Vec<TokenRange> {
+    let sharder = match sharder {
+        Some(sharder) => sharder,
+        None => return vec![range],
+    };
+    let mut ranges = Vec::new();
+    let mut start = range.start;
+    loop {
+        let first_token = Token {
+            value: start.value + 1,
+        };
+        let end = match sharder.next_shard_boundary(first_token) {
+            Some(boundary) if boundary <= range.end => Token {
+                value: boundary.value - 1,
+            },
+            _ => range.end,
+        };
+        ranges.push(TokenRange::new(start, end));
+        if end == range.end {
+            return ranges;
+        }
+        start = end;
+    }
+}
+
+/// Errors after which reading the same page again may succeed.
+/// The session's retry policy has already decided not to retry them right away.
+fn is_transient(err: &QueryError) -> bool {
+    matches!(
+        err,
+        QueryError::IoError(_)
+            | QueryError::TimeoutError
+            | QueryError::RequestTimeout(_)
+            | QueryError::TooManyOrphanedStreamIds(_)
+            | QueryError::UnableToAllocStreamId
+            | QueryError::RequestQueueFull
+            | QueryError::RequestQueueTimeout
+            | QueryError::DbError(
+                DbError::Unavailable { .. }
+                    | DbError::Overloaded
+                    | DbError::IsBootstrapping
+                    | DbError::ReadTimeout { .. }
+                    | DbError::ReadFailure { .. }
+                    | DbError::ServerError,
+                _
+            )
+    )
+}
+
+fn join_identifiers<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    names.map(quote_identifier).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_transient, scan_ranges, split_by_shard};
+    use crate::frame::types::{Consistency, LegacyConsistency};
+    use crate::routing::{ShardCount, Sharder, Token, TokenRange};
+    use crate::transport::errors::{BadQuery, DbError, QueryError};
+
+    fn token(value: i64) -> Token {
+        Token { value }
+    }
+
+    #[test]
+    fn scan_ranges_cover_ring() {
+        let ring = [
+            (TokenRange::new(token(i64::MAX), token(-100)), None),
+            (TokenRange::new(token(-100), token(0)), None),
+            (TokenRange::new(token(0), token(i64::MAX)), None),
+        ];
+        assert_eq!(
+            scan_ranges(ring.to_vec()),
+            vec![
+                TokenRange::new(token(i64::MIN), token(-100)),
+                TokenRange::new(token(-100), token(0)),
+                TokenRange::new(token(0), token(i64::MAX)),
+            ]
+        );
+
+        let ring = [(TokenRange::new(token(5), token(5)), None)];
+        assert_eq!(
+            scan_ranges(ring.to_vec()),
+            vec![
+                TokenRange::new(token(5), token(i64::MAX)),
+                TokenRange::new(token(i64::MIN), token(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_ranges_by_shard() {
+        let sharder = Sharder::new(ShardCount::new(4).unwrap(), 12);
+        // A whole cycle of tokens, split evenly between the 4 shards
+        let range = TokenRange::new(token(-1), token((1 << 52) - 1));
+        let ranges = split_by_shard(range, Some(&sharder));
+
+        assert_eq!(ranges.len(), 4);
+        assert_eq!(ranges.first().unwrap().start, range.start);
+        assert_eq!(ranges.last().unwrap().end, range.end);
+        for (range, next) in ranges.iter().zip(ranges.iter().skip(1)) {
+            assert_eq!(range.end, next.start);
+        }
+        for range in ranges {
+            let first_token = token(range.start.value + 1);
+            assert_eq!(sharder.shard_of(first_token), sharder.shard_of(range.end));
+        }
+
+        assert_eq!(split_by_shard(range, None), vec![range]);
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        let unavailable = DbError::Unavailable {
+            consistency: LegacyConsistency::Regular(Consistency::Quorum),
+            required: 2,
+            alive: 1,
+        };
+        assert!(is_transient(&QueryError::DbError(
+            unavailable,
+            String::new()
+        )));
+        assert!(is_transient(&QueryError::TimeoutError));
+        assert!(is_transient(&QueryError::RequestQueueTimeout));
+
+        assert!(!is_transient(&QueryError::DbError(
+            DbError::Invalid,
+            String::new()
+        )));
+        assert!(!is_transient(&QueryError::DbError(
+            DbError::Unauthorized,
+            String::new()
+        )));
+        assert!(!is_transient(&QueryError::BadQuery(BadQuery::Other(
+            String::new()
+        ))));
+        assert!(!is_transient(&QueryError::SessionShutDown));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::frame::response::cql_literal::quote_identifier;
 use crate::frame::response::event::Event;
 use crate::routing::Token;
 use crate::statement::query::Query;
@@ -95,14 +96,26 @@ pub struct MaterializedView {
 pub struct Column {
     pub type_: CqlType,
     pub kind: ColumnKind,
+    /// The order of rows by the column, set for columns of the clustering key
+    pub clustering_order: Option<ClusteringOrder>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum CqlType {
     Native(NativeType),
-    Collection { frozen: bool, type_: CollectionType },
+    Collection {
+        frozen: bool,
+        type_: CollectionType,
+    },
     Tuple(Vec<CqlType>),
-    UserDefinedType { frozen: bool, name: String },
+    UserDefinedType {
+        frozen: bool,
+        name: String,
+    },
+    Vector {
+        type_: Box<CqlType>,
+        dimensions: u16,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, EnumString)]
@@ -146,6 +159,13 @@ pub enum ColumnKind {
     PartitionKey,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum ClusteringOrder {
+    Asc,
+    Desc,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[allow(clippy::enum_variant_names)]
 pub enum Strategy {
@@ -220,6 +240,282 @@ impl Metadata {
     }
 }
 
+impl Keyspace {
+    /// Describes the keyspace as CQL statements creating it, its user defined types,
+    /// tables and materialized views.\
+    /// The output can be used as a schema file of the `query!` macro.
+    pub fn to_schema_cql(&self, name: &str) -> String {
+        let keyspace = quote_identifier(name);
+        let mut cql = format!(
+            "CREATE KEYSPACE {} WITH replication = {};\n",
+            keyspace,
+            strategy_to_cql(&self.strategy)
+        );
+
+        // A type has to be created after the types used by its fields
+        let mut pending: Vec<_> = self
+            .user_defined_types
+            .iter()
+            .sorted_by_key(|(type_name, _)| *type_name)
+            .collect();
+        while !pending.is_empty() {
+            let uses_pending = |fields: &[(String, CqlType)]| {
+                let mut used = Vec::new();
+                for (_, field_type) in fields {
+                    used_user_types(field_type, &mut used);
+                }
+                used.iter()
+                    .any(|used| pending.iter().any(|(type_name, _)| type_name == used))
+            };
+            let next = pending
+                .iter()
+                .position(|(_, fields)| !uses_pending(fields))
+                .unwrap_or(0);
+            let (type_name, fields) = pending.remove(next);
+            let fields = fields
+                .iter()
+                .map(|(field_name, field_type)| {
+                    format!(
+                        "    {} {}",
+                        quote_identifier(field_name),
+                        cql_type_to_cql(field_type)
+                    )
+                })
+                .join(",\n");
+            cql += &format!(
+                "\nCREATE TYPE {}.{} (\n{}\n);\n",
+                keyspace,
+                quote_identifier(type_name),
+                fields
+            );
+        }
+
+        for (table_name, table) in self
+            .tables
+            .iter()
+            .sorted_by_key(|(table_name, _)| *table_name)
+        {
+            let columns = table_columns(table).map(|(column_name, column)| {
+                let static_ = if column.kind == ColumnKind::Static {
+                    " static"
+                } else {
+                    ""
+                };
+                format!(
+                    "    {} {}{}",
+                    quote_identifier(column_name),
+                    cql_type_to_cql(&column.type_),
+                    static_
+                )
+            });
+            let primary_key = format!("    PRIMARY KEY {}", primary_key_to_cql(table));
+            cql += &format!(
+                "\nCREATE TABLE {}.{} (\n{}\n){};\n",
+                keyspace,
+                quote_identifier(table_name),
+                columns.chain(std::iter::once(primary_key)).join(",\n"),
+                clustering_order_to_cql(table)
+            );
+        }
+
+        for (view_name, view) in self.views.iter().sorted_by_key(|(view_name, _)| *view_name) {
+            let table = &view.view_metadata;
+            let columns = table_columns(table)
+                .map(|(column_name, _)| quote_identifier(column_name))
+                .join(", ");
+            let key_not_null = table
+                .partition_key
+                .iter()
+                .chain(&table.clustering_key)
+                .map(|column_name| format!("{} IS NOT NULL", quote_identifier(column_name)))
+                .join(" AND ");
+            cql += &format!(
+                "\nCREATE MATERIALIZED VIEW {ks}.{} AS\n    SELECT {} FROM {ks}.{}\n    WHERE {}\n    PRIMARY KEY {}{};\n",
+                quote_identifier(view_name),
+                columns,
+                quote_identifier(&view.base_table_name),
+                key_not_null,
+                primary_key_to_cql(table),
+                clustering_order_to_cql(table),
+                ks = keyspace,
+            );
+        }
+        cql
+    }
+}
+
+/// Columns in the order of `SELECT *`: partition key, clustering key,
+/// then static and regular columns sorted by name
+fn table_columns(table: &Table) -> impl Iterator<Item = (&String, &Column)> {
+    let key_columns = table
+        .partition_key
+        .iter()
+        .chain(&table.clustering_key)
+        .filter_map(|name| table.columns.get_key_value(name));
+    let other_columns = table
+        .columns
+        .iter()
+        .filter(|(_, column)| matches!(column.kind, ColumnKind::Static | ColumnKind::Regular))
+        .sorted_by_key(|(name, column)| (column.kind != ColumnKind::Static, *name));
+    key_columns.chain(other_columns)
+}
+
+fn primary_key_to_cql(table: &Table) -> String {
+    let partition_key = table
+        .partition_key
+        .iter()
+        .map(|name| quote_identifier(name))
+        .join(", ");
+    let clustering_key = table
+        .clustering_key
+        .iter()
+        .map(|name| format!(", {}", quote_identifier(name)))
+        .join("");
+    format!("(({}){})", partition_key, clustering_key)
+}
+
+/// The `WITH CLUSTERING ORDER BY (...)` clause on its own line, empty for tables without a clustering key
+fn clustering_order_to_cql(table: &Table) -> String {
+    if table.clustering_key.is_empty() {
+        return String::new();
+    }
+    let orders = table
+        .clustering_key
+        .iter()
+        .map(|name| {
+            let order = match table
+                .columns
+                .get(name)
+                .and_then(|column| column.clustering_order)
+            {
+                Some(ClusteringOrder::Desc) => "DESC",
+                Some(ClusteringOrder::Asc) | None => "ASC",
+            };
+            format!("{} {}", quote_identifier(name), order)
+        })
+        .join(", ");
+    format!("\nWITH CLUSTERING ORDER BY ({})", orders)
+}
+
+fn strategy_to_cql(strategy: &Strategy) -> String {
+    let class = |name: &str| ("class".to_string(), name.to_string());
+    let options: Vec<(String, String)> = match strategy {
+        Strategy::SimpleStrategy { replication_factor } => vec![
+            class("SimpleStrategy"),
+            (
+                "replication_factor".to_string(),
+                replication_factor.to_string(),
+            ),
+        ],
+        Strategy::NetworkTopologyStrategy {
+            datacenter_repfactors,
+        } => std::iter::once(class("NetworkTopologyStrategy"))
+            .chain(
+                datacenter_repfactors
+                    .iter()
+                    .sorted()
+                    .map(|(datacenter, factor)| (datacenter.clone(), factor.to_string())),
+            )
+            .collect(),
+        Strategy::LocalStrategy => vec![class("LocalStrategy")],
+        Strategy::Other { name, data } => std::iter::once(class(name))
+            .chain(data.iter().sorted().map(|(k, v)| (k.clone(), v.clone())))
+            .collect(),
+    };
+    let options = options
+        .iter()
+        .map(|(key, value)| {
+            format!(
+                "'{}': '{}'",
+                key.replace('\'', "''"),
+                value.replace('\'', "''")
+            )
+        })
+        .join(", ");
+    format!("{{{}}}", options)
+}
+
+fn cql_type_to_cql(type_: &CqlType) -> String {
+    let freeze = |type_: String, frozen: bool| {
+        if frozen {
+            format!("frozen<{}>", type_)
+        } else {
+            type_
+        }
+    };
+    match type_ {
+        CqlType::Native(native) => native_type_to_cql(native).to_string(),
+        CqlType::Collection { frozen, type_ } => {
+            let collection = match type_ {
+                CollectionType::List(element) => format!("list<{}>", cql_type_to_cql(element)),
+                CollectionType::Set(element) => format!("set<{}>", cql_type_to_cql(element)),
+                CollectionType::Map(key, value) => {
+                    format!("map<{}, {}>", cql_type_to_cql(key), cql_type_to_cql(value))
+                }
+            };
+            freeze(collection, *frozen)
+        }
+        CqlType::Tuple(elements) => {
+            format!("tuple<{}>", elements.iter().map(cql_type_to_cql).join(", "))
+        }
+        CqlType::UserDefinedType { frozen, name } => {
+            // Names of types may be qualified with a keyspace
+            let name = name.split('.').map(quote_identifier).join(".");
+            freeze(name, *frozen)
+        }
+        CqlType::Vector { type_, dimensions } => {
+            format!("vector<{}, {}>", cql_type_to_cql(type_), dimensions)
+        }
+    }
+}
+
+fn native_type_to_cql(native: &NativeType) -> &'static str {
+    match native {
+        NativeType::Ascii => "ascii",
+        NativeType::Boolean => "boolean",
+        NativeType::Blob => "blob",
+        NativeType::Counter => "counter",
+        NativeType::Date => "date",
+        NativeType::Decimal => "decimal",
+        NativeType::Double => "double",
+        NativeType::Duration => "duration",
+        NativeType::Float => "float",
+        NativeType::Int => "int",
+        NativeType::BigInt => "bigint",
+        NativeType::Text => "text",
+        NativeType::Timestamp => "timestamp",
+        NativeType::Inet => "inet",
+        NativeType::SmallInt => "smallint",
+        NativeType::TinyInt => "tinyint",
+        NativeType::Time => "time",
+        NativeType::Timeuuid => "timeuuid",
+        NativeType::Uuid => "uuid",
+        NativeType::Varint => "varint",
+    }
+}
+
+fn used_user_types<'a>(type_: &'a CqlType, used: &mut Vec<&'a str>) {
+    match type_ {
+        CqlType::Native(_) => {}
+        CqlType::Collection { type_, .. } => match type_ {
+            CollectionType::List(element) | CollectionType::Set(element) => {
+                used_user_types(element, used)
+            }
+            CollectionType::Map(key, value) => {
+                used_user_types(key, used);
+                used_user_types(value, used);
+            }
+        },
+        CqlType::Tuple(elements) => {
+            for element in elements {
+                used_user_types(element, used);
+            }
+        }
+        CqlType::UserDefinedType { name, .. } => used.push(name),
+        CqlType::Vector { type_, .. } => used_user_types(type_, used),
+    }
+}
+
 impl MetadataReader {
     /// Creates new MetadataReader, which connects to known_peers in the background
     #[allow(clippy::too_many_arguments)]
@@ -441,6 +737,9 @@ impl MetadataReader {
             // The shard-aware port won't be used with PerHost pool size anyway,
             // so explicitly disable it here
             can_use_shard_aware_port: false,
+
+            // Requests of the control connection aren't limited
+            request_limiter: None,
         };
 
         NodeConnectionPool::new(addr.ip(), addr.port(), pool_config, None)
@@ -803,7 +1102,7 @@ async fn query_tables_schema(
     const THRIFT_EMPTY_TYPE: &str = "empty";
 
     let rows = query_filter_keyspace_name(conn,
-        "select keyspace_name, table_name, column_name, kind, position, type from system_schema.columns", keyspaces_to_fetch
+        "select keyspace_name, table_name, column_name, kind, position, type, clustering_order from system_schema.columns", keyspaces_to_fetch
     )
         .await?
         .rows
@@ -813,9 +1112,9 @@ async fn query_tables_schema(
 
     let mut tables_schema = HashMap::with_capacity(rows.len());
 
-    for row in rows.into_typed::<(String, String, String, String, i32, String)>() {
-        let (keyspace_name, table_name, column_name, kind, position, type_) =
-            row.map_err(|_| {
+    for row in rows.into_typed::<(String, String, String, String, i32, String, String)>() {
+        let (keyspace_name, table_name, column_name, kind, position, type_, clustering_order) = row
+            .map_err(|_| {
                 QueryError::ProtocolError("system_schema.columns has invalid column type")
             })?;
 
@@ -835,6 +1134,15 @@ async fn query_tables_schema(
             // FIXME: The correct error type is QueryError:ProtocolError but at the moment it accepts only &'static str
             .map_err(|_| QueryError::InvalidMessage(format!("invalid column kind {}", kind)))?;
 
+        // Columns outside of the clustering key have the order `none`
+        let clustering_order = if kind == ColumnKind::Clustering {
+            Some(ClusteringOrder::from_str(&clustering_order).map_err(|_| {
+                QueryError::InvalidMessage(format!("invalid clustering order {}", clustering_order))
+            })?)
+        } else {
+            None
+        };
+
         if kind == ColumnKind::PartitionKey || kind == ColumnKind::Clustering {
             let key_map = if kind == ColumnKind::PartitionKey {
                 entry.1.borrow_mut()
@@ -849,6 +1157,7 @@ async fn query_tables_schema(
             Column {
                 type_: cql_type,
                 kind,
+                clustering_order,
             },
         );
     }
@@ -962,6 +1271,21 @@ fn parse_cql_type(p: ParserState) -> ParseResult<(CqlType, ParserState)> {
         })?;
 
         Ok((CqlType::Tuple(types), p))
+    } else if let Ok(p) = p.accept("vector<") {
+        let (inner_type, p) = parse_cql_type(p)?;
+        let p = p.accept(",")?.skip_white();
+        let (dimensions, p) = p.take_while(|c| c.is_ascii_digit());
+        let dimensions = dimensions
+            .parse()
+            .map_err(|_| p.error(ParseErrorCause::Other("invalid vector dimensions")))?;
+        let p = p.accept(">")?;
+
+        let typ = CqlType::Vector {
+            type_: Box::new(inner_type),
+            dimensions,
+        };
+
+        Ok((typ, p))
     } else if let Ok((typ, p)) = parse_native_type(p) {
         Ok((CqlType::Native(typ), p))
     } else if let Ok((name, p)) = parse_user_defined_type(p) {
@@ -1151,6 +1475,13 @@ mod tests {
                     name: "ks.my_udt".to_string(),
                 },
             ),
+            (
+                "vector<float, 768>",
+                CqlType::Vector {
+                    type_: Box::new(CqlType::Native(NativeType::Float)),
+                    dimensions: 768,
+                },
+            ),
             (
                 "map<text, frozen<map<text, text>>>",
                 CqlType::Collection {
@@ -1248,4 +1579,95 @@ mod tests {
             assert_eq!(parsed, expected);
         }
     }
+
+    #[test]
+    fn test_keyspace_to_schema_cql() {
+        let column = |type_: &str, kind: ColumnKind| Column {
+            type_: map_string_to_cql_type(type_).unwrap(),
+            kind,
+            clustering_order: None,
+        };
+        let clustering = |type_: &str, order: ClusteringOrder| Column {
+            clustering_order: Some(order),
+            ..column(type_, ColumnKind::Clustering)
+        };
+        let table = Table {
+            columns: HashMap::from([
+                ("id".to_string(), column("uuid", ColumnKind::PartitionKey)),
+                (
+                    "at".to_string(),
+                    clustering("timestamp", ClusteringOrder::Desc),
+                ),
+                (
+                    "seq".to_string(),
+                    clustering("bigint", ClusteringOrder::Asc),
+                ),
+                ("Name".to_string(), column("text", ColumnKind::Regular)),
+                (
+                    "owner".to_string(),
+                    column("frozen<person>", ColumnKind::Regular),
+                ),
+                ("region".to_string(), column("text", ColumnKind::Static)),
+                ("tags".to_string(), column("set<text>", ColumnKind::Regular)),
+            ]),
+            partition_key: vec!["id".to_string()],
+            clustering_key: vec!["at".to_string(), "seq".to_string()],
+            partitioner: None,
+        };
+        let keyspace = Keyspace {
+            strategy: Strategy::NetworkTopologyStrategy {
+                datacenter_repfactors: HashMap::from([
+                    ("dc2".to_string(), 1),
+                    ("dc1".to_string(), 3),
+                ]),
+            },
+            tables: HashMap::from([("events".to_string(), table)]),
+            views: HashMap::new(),
+            user_defined_types: HashMap::from([
+                (
+                    "person".to_string(),
+                    vec![
+                        ("name".to_string(), map_string_to_cql_type("text").unwrap()),
+                        (
+                            "address".to_string(),
+                            map_string_to_cql_type("frozen<address>").unwrap(),
+                        ),
+                    ],
+                ),
+                (
+                    "address".to_string(),
+                    vec![(
+                        "street".to_string(),
+                        map_string_to_cql_type("text").unwrap(),
+                    )],
+                ),
+            ]),
+        };
+
+        let expected = "\
+CREATE KEYSPACE ks WITH replication = {'class': 'NetworkTopologyStrategy', 'dc1': '3', 'dc2': '1'};
+
+CREATE TYPE ks.address (
+    street text
+);
+
+CREATE TYPE ks.person (
+    name text,
+    address frozen<address>
+);
+
+CREATE TABLE ks.events (
+    id uuid,
+    at timestamp,
+    seq bigint,
+    region text static,
+    \"Name\" text,
+    owner frozen<person>,
+    tags set<text>,
+    PRIMARY KEY ((id), at, seq)
+)
+WITH CLUSTERING ORDER BY (at DESC, seq ASC);
+";
+        assert_eq!(keyspace.to_schema_cql("ks"), expected);
+    }
 }
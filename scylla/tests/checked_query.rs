@@ -0,0 +1,176 @@
+// The statements are only checked, their rows are never read
+#![allow(dead_code)]
+
+use chrono::NaiveDate;
+use scylla::frame::value::Counter;
+use scylla::macros::{CqlEnum, FromRow, FromUserType, IntoUserType};
+use scylla::{query, query_as};
+use uuid::Uuid;
+
+#[derive(CqlEnum, Debug, PartialEq)]
+enum Status {
+    Active,
+    Banned,
+}
+
+#[derive(FromUserType, IntoUserType, Debug, PartialEq)]
+struct Address {
+    street: String,
+    city: String,
+}
+
+#[derive(FromRow)]
+struct User {
+    id: Uuid,
+    name: String,
+    status: Status,
+    address: Option<Address>,
+}
+
+#[test]
+fn checked_modifications() {
+    let id = Uuid::nil();
+    let address = Address {
+        street: "Main".to_string(),
+        city: "Warsaw".to_string(),
+    };
+    let insert = query!(
+        schema = "tests/checked_query_schema.cql",
+        "INSERT INTO ks.users (id, name, status, address) VALUES (?, ?, ?, ?) USING TTL ?",
+        id,
+        "Ann",
+        Status::Active,
+        &address,
+        3600,
+    );
+    assert_eq!(
+        insert.query().contents,
+        "INSERT INTO ks.users (id, name, status, address) VALUES (?, ?, ?, ?) USING TTL ?"
+    );
+    let (_, values) = insert.into_parts();
+    assert_eq!(values.2, Status::Active);
+
+    let update = query!(
+        schema = "tests/checked_query_schema.cql",
+        "UPDATE users SET emails = emails + ?, status = ? WHERE id = ? IF EXISTS",
+        vec!["ann@example.com".to_string()],
+        Some(Status::Banned),
+        id,
+    );
+    assert_eq!(update.values().1, Some(Status::Banned));
+
+    let count = query!(
+        schema = "tests/checked_query_schema.cql",
+        "UPDATE visits SET count = count + ? WHERE id = ? AND day = ?",
+        Counter(1),
+        id,
+        NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+    );
+    assert_eq!(count.values().0, Counter(1));
+}
+
+#[test]
+fn checked_selects() {
+    let id = Uuid::nil();
+    let users = query_as!(
+        User,
+        schema = "tests/checked_query_schema.cql",
+        "SELECT id, name, status, address FROM ks.users WHERE id IN ?",
+        vec![id],
+    );
+    assert_eq!(users.values().0, [id]);
+
+    let names = query_as!(
+        (String, Option<std::collections::HashSet<String>>, i64),
+        schema = "tests/checked_query_schema.cql",
+        "SELECT name, emails, writetime(name) FROM ks.users WHERE token(id) > ? LIMIT ?",
+        i64::MIN,
+        10,
+    );
+    assert_eq!(names.values(), &(i64::MIN, 10));
+
+    // Read by position, the names of the fields don't matter
+    #[derive(FromRow)]
+    struct Renamed {
+        user_name: String,
+        user_status: Option<Status>,
+    }
+    query_as!(
+        Renamed,
+        schema = "tests/checked_query_schema.cql",
+        "SELECT name, status FROM ks.users WHERE id = ?",
+        id,
+    );
+
+    #[derive(FromRow)]
+    struct Written<T> {
+        name: T,
+        written_at: i64,
+    }
+    query_as!(
+        Written<String>,
+        schema = "tests/checked_query_schema.cql",
+        "SELECT name, writetime(name) FROM ks.users WHERE id = ?",
+        id,
+    );
+
+    // Read by name, in any order
+    #[derive(FromRow)]
+    #[scylla(match_by_name)]
+    struct ByName {
+        id: Uuid,
+        #[scylla(rename = "name")]
+        user_name: String,
+        emails: Option<Vec<String>>,
+    }
+    query_as!(
+        ByName,
+        schema = "tests/checked_query_schema.cql",
+        "SELECT emails, name, id FROM ks.users WHERE id = ?",
+        id,
+    );
+
+    // Flattened fields are read the way of the outer struct
+    #[derive(FromRow)]
+    struct Contact {
+        name: String,
+        emails: Option<Vec<String>>,
+    }
+    #[derive(FromRow)]
+    #[scylla(match_by_name)]
+    struct UserContact {
+        #[scylla(flatten)]
+        contact: Contact,
+        id: Uuid,
+        #[scylla(skip)]
+        cached: bool,
+        #[scylla(default)]
+        status: Option<Status>,
+    }
+    query_as!(
+        UserContact,
+        schema = "tests/checked_query_schema.cql",
+        "SELECT id, emails, name FROM ks.users WHERE id = ?",
+        id,
+    );
+    #[derive(FromRow)]
+    struct WithContact {
+        id: Uuid,
+        #[scylla(flatten)]
+        contact: Contact,
+    }
+    query_as!(
+        WithContact,
+        schema = "tests/checked_query_schema.cql",
+        "SELECT id, name, emails FROM ks.users WHERE id = ?",
+        id,
+    );
+
+    let all = query!(
+        schema = "tests/checked_query_schema.cql",
+        "SELECT * FROM ks.visits WHERE id = ? AND day > ?",
+        id,
+        scylla::frame::value::Date(0),
+    );
+    assert!(all.query().contents.starts_with("SELECT *"));
+}
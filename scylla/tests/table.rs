@@ -0,0 +1,123 @@
+use scylla::frame::response::cql_literal::quote_identifier;
+use scylla::frame::value::{SerializedValues, ValueList};
+use scylla::macros::{FromRow, Table};
+use scylla::Table as _;
+
+#[derive(FromRow, Table, Debug, PartialEq)]
+#[table = "ks.events"]
+#[scylla(match_by_name)]
+struct Event {
+    #[partition_key]
+    user_id: i32,
+    #[clustering_key]
+    at: i64,
+    #[partition_key]
+    #[scylla(rename = "Day")]
+    day: i32,
+    payload: Option<String>,
+}
+
+#[derive(FromRow, Table)]
+#[table = "tags"]
+struct Tag {
+    #[partition_key]
+    name: String,
+}
+
+// Columns named like CQL keywords have to be quoted
+#[derive(FromRow, Table)]
+#[table = "ks.items"]
+struct Item {
+    #[partition_key]
+    key: i32,
+    #[clustering_key]
+    order: i32,
+    token: String,
+    from: String,
+    name: String,
+}
+
+fn event() -> Event {
+    Event {
+        user_id: 1,
+        at: 2,
+        day: 3,
+        payload: Some("abc".to_string()),
+    }
+}
+
+fn serialized(values: impl ValueList) -> SerializedValues {
+    values.serialized().unwrap().into_owned()
+}
+
+#[test]
+fn table_statements() {
+    assert_eq!(Event::NAME, "ks.events");
+    assert_eq!(
+        Event::INSERT,
+        "INSERT INTO ks.events (user_id, at, \"Day\", payload) VALUES (?, ?, ?, ?)"
+    );
+    assert_eq!(
+        Event::SELECT,
+        "SELECT user_id, at, \"Day\", payload FROM ks.events WHERE user_id = ? AND \"Day\" = ? AND at = ?"
+    );
+    assert_eq!(
+        Event::UPDATE,
+        "UPDATE ks.events SET payload = ? WHERE user_id = ? AND \"Day\" = ? AND at = ?"
+    );
+    assert_eq!(
+        Event::DELETE,
+        "DELETE FROM ks.events WHERE user_id = ? AND \"Day\" = ? AND at = ?"
+    );
+
+    assert_eq!(Tag::UPDATE, "INSERT INTO tags (name) VALUES (?)");
+    assert_eq!(Tag::DELETE, "DELETE FROM tags WHERE name = ?");
+}
+
+#[test]
+fn table_keyword_columns() {
+    assert_eq!(
+        Item::INSERT,
+        "INSERT INTO ks.items (\"key\", \"order\", \"token\", \"from\", name) VALUES (?, ?, ?, ?, ?)"
+    );
+    // Names are quoted like the driver quotes them
+    let columns = ["key", "order", "token", "from", "name"]
+        .iter()
+        .map(|name| quote_identifier(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    assert!(Item::INSERT.contains(&columns));
+}
+
+#[test]
+fn table_values() {
+    let event = event();
+    let key = event.key();
+    assert_eq!(
+        key,
+        EventKey {
+            user_id: 1,
+            day: 3,
+            at: 2
+        }
+    );
+    assert_eq!(serialized(&key), serialized((1, 3, 2_i64)));
+
+    assert_eq!(
+        event.insert_values().unwrap().into_owned(),
+        serialized((1, 2_i64, 3, "abc"))
+    );
+    assert_eq!(
+        event.update_values().unwrap().into_owned(),
+        serialized(("abc", 1, 3, 2_i64))
+    );
+
+    let tag = Tag {
+        name: "rust".to_string(),
+    };
+    assert_eq!(tag.key().name, "rust");
+    assert_eq!(
+        tag.update_values().unwrap().into_owned(),
+        serialized(("rust",))
+    );
+}